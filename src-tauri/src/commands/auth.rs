@@ -1,11 +1,11 @@
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 
 use crate::services::{
     auth::{AuthService, SetPasswordsPayload},
-    SecurityService,
+    permission::{emit_permission_changes, window_session_key},
 };
 use crate::state::AppState;
 
@@ -92,6 +92,9 @@ pub struct AuthStatusResponse {
     pub has_admin_password: bool,
     pub has_points_password: bool,
     pub has_recovery_string: bool,
+    pub failed_attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockout_remaining_seconds: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,15 +161,18 @@ pub struct OAuthAuthorizationUrlResponse {
     pub code_verifier: String,
 }
 
-fn get_iv_hex() -> String {
-    SecurityService::generate_iv_hex()
+fn flush_permission_changes(state: &Arc<RwLock<AppState>>) {
+    let state_guard = state.read();
+    let changes = state_guard.permissions.write().take_changes();
+    emit_permission_changes(&state_guard.app_handle, &changes);
 }
 
 #[tauri::command]
 pub async fn auth_get_status(
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<AuthStatusResponse>, String> {
+    let session = window_session_key(webview.label());
     let state_guard = state.read();
     let db_conn = state_guard.db.read().clone();
     let mut settings = state_guard.settings.write();
@@ -174,13 +180,15 @@ pub async fn auth_get_status(
     settings.initialize().await?;
     let mut permissions = state_guard.permissions.write();
 
-    let status = AuthService::get_status(&settings, sender_id, &mut permissions);
+    let status = AuthService::get_status(&settings, &session, &session, &mut permissions);
 
     let response = AuthStatusResponse {
         permission: status.permission,
         has_admin_password: status.has_admin_password,
         has_points_password: status.has_points_password,
         has_recovery_string: status.has_recovery_string,
+        failed_attempts: status.failed_attempts,
+        lockout_remaining_seconds: status.lockout_remaining_seconds,
     };
 
     Ok(IpcResponse::success(response))
@@ -189,32 +197,20 @@ pub async fn auth_get_status(
 #[tauri::command]
 pub async fn auth_login(
     password: String,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<LoginResponse>, String> {
-    let sender = sender_id.unwrap_or(0);
-
-    let iv_hex = get_iv_hex();
+    let session = window_session_key(webview.label());
 
-    let result = {
+    {
         let state_guard = state.read();
         let db_conn = state_guard.db.read().clone();
         let mut settings = state_guard.settings.write();
         settings.attach_db(db_conn);
         settings.initialize().await?;
-        let security = state_guard.security.read();
-        let mut permissions = state_guard.permissions.write();
-
-        AuthService::login(
-            &mut settings,
-            &security,
-            &mut permissions,
-            sender,
-            &password,
-            &iv_hex,
-        )
-        .await
-    };
+    }
+    let result = AuthService::login(state.inner(), &session, &session, &password).await;
+    flush_permission_changes(&state);
 
     if result.success {
         Ok(IpcResponse::success(LoginResponse {
@@ -229,23 +225,28 @@ pub async fn auth_login(
 
 #[tauri::command]
 pub async fn auth_logout(
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<LoginResponse>, String> {
-    let default_permission = {
+    auth_lock(webview, state).await
+}
+
+/// 锁定当前窗口的权限会话，回落到默认级别
+#[tauri::command]
+pub async fn auth_lock(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<LoginResponse>, String> {
+    let session = window_session_key(webview.label());
+    let level = {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-
-        if let Some(id) = sender_id {
-            let level = AuthService::logout(&mut permissions, id);
-            level.as_str().to_string()
-        } else {
-            permissions.get_default_permission().as_str().to_string()
-        }
+        AuthService::logout(&mut permissions, &session)
     };
+    flush_permission_changes(&state);
 
     Ok(IpcResponse::success(LoginResponse {
-        permission: default_permission,
+        permission: level.as_str().to_string(),
     }))
 }
 
@@ -253,37 +254,25 @@ pub async fn auth_logout(
 pub async fn auth_set_passwords(
     admin_password: Option<String>,
     points_password: Option<String>,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SetPasswordsResponse>, String> {
-    let sender = sender_id.unwrap_or(0);
-
-    let iv_hex = get_iv_hex();
+    let session = window_session_key(webview.label());
 
     let payload = SetPasswordsPayload {
         admin_password,
         points_password,
     };
 
-    let result = {
+    {
         let state_guard = state.read();
         let db_conn = state_guard.db.read().clone();
         let mut settings = state_guard.settings.write();
         settings.attach_db(db_conn);
         settings.initialize().await?;
-        let security = state_guard.security.read();
-        let mut permissions = state_guard.permissions.write();
-
-        AuthService::set_passwords(
-            &mut settings,
-            &security,
-            &mut permissions,
-            sender,
-            payload,
-            &iv_hex,
-        )
-        .await
-    };
+    }
+    let result = AuthService::set_passwords(state.inner(), &session, payload).await;
+    flush_permission_changes(&state);
 
     if result.success {
         Ok(IpcResponse::success(SetPasswordsResponse {
@@ -300,25 +289,20 @@ pub async fn auth_set_passwords(
 
 #[tauri::command]
 pub async fn auth_generate_recovery(
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SetPasswordsResponse>, String> {
-    let sender = sender_id.unwrap_or(0);
+    let session = window_session_key(webview.label());
 
-    let iv_hex = get_iv_hex();
-
-    let result = {
+    {
         let state_guard = state.read();
         let db_conn = state_guard.db.read().clone();
         let mut settings = state_guard.settings.write();
         settings.attach_db(db_conn);
         settings.initialize().await?;
-        let security = state_guard.security.read();
-        let mut permissions = state_guard.permissions.write();
-
-        AuthService::generate_recovery(&mut settings, &security, &mut permissions, sender, &iv_hex)
-            .await
-    };
+    }
+    let result = AuthService::generate_recovery(state.inner(), &session).await;
+    flush_permission_changes(&state);
 
     if result.success {
         Ok(IpcResponse::success(SetPasswordsResponse {
@@ -336,32 +320,20 @@ pub async fn auth_generate_recovery(
 #[tauri::command]
 pub async fn auth_reset_by_recovery(
     recovery_string: String,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SetPasswordsResponse>, String> {
-    let sender = sender_id.unwrap_or(0);
-
-    let iv_hex = get_iv_hex();
+    let session = window_session_key(webview.label());
 
-    let result = {
+    {
         let state_guard = state.read();
         let db_conn = state_guard.db.read().clone();
         let mut settings = state_guard.settings.write();
         settings.attach_db(db_conn);
         settings.initialize().await?;
-        let security = state_guard.security.read();
-        let mut permissions = state_guard.permissions.write();
-
-        AuthService::reset_by_recovery(
-            &mut settings,
-            &security,
-            &mut permissions,
-            sender,
-            &recovery_string,
-            &iv_hex,
-        )
-        .await
-    };
+    }
+    let result = AuthService::reset_by_recovery(state.inner(), &session, &recovery_string).await;
+    flush_permission_changes(&state);
 
     if result.success {
         Ok(IpcResponse::success(SetPasswordsResponse {
@@ -378,10 +350,10 @@ pub async fn auth_reset_by_recovery(
 
 #[tauri::command]
 pub async fn auth_clear_all(
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
-    let sender = sender_id.unwrap_or(0);
+    let session = window_session_key(webview.label());
 
     let result = {
         let state_guard = state.read();
@@ -391,8 +363,9 @@ pub async fn auth_clear_all(
         settings.initialize().await?;
         let mut permissions = state_guard.permissions.write();
 
-        AuthService::clear_all(&mut settings, &mut permissions, sender).await
+        AuthService::clear_all(&mut settings, &mut permissions, &session).await
     };
+    flush_permission_changes(&state);
 
     match result {
         Ok(()) => Ok(IpcResponse::success(())),
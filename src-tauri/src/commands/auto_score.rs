@@ -2,13 +2,14 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, State, Webview};
 
 use crate::services::{
-    apply_offline_backfill, query_execution_batches, rollback_execution_batch, AutoScoreAction,
-    AutoScoreBackfillItem, AutoScoreBackfillResult, AutoScoreExecutionBatch,
-    AutoScoreExecutionConfig, AutoScoreFilterConfig, AutoScoreRule, AutoScoreService,
-    AutoScoreTrigger, PermissionLevel, SettingsKey, SettingsValue,
+    apply_offline_backfill, preview_rule, query_execution_batches, rollback_execution_batch,
+    window_session_key, AutoScoreAction, AutoScoreBackfillItem, AutoScoreBackfillResult,
+    AutoScoreBatchPage, AutoScoreBatchPageParams, AutoScoreExecutionBatch,
+    AutoScoreExecutionConfig, AutoScoreFilterConfig, AutoScoreRule, AutoScoreRulePreview,
+    AutoScoreService, AutoScoreTrigger, PermissionLevel, SettingsKey, SettingsValue,
 };
 use crate::state::AppState;
 
@@ -16,10 +17,10 @@ use super::response::IpcResponse;
 
 fn check_admin_permission(
     permissions: &mut crate::services::PermissionService,
-    sender_id: Option<u32>,
+    webview: &Webview,
 ) -> bool {
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::Admin)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::Admin)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,6 +74,15 @@ pub struct ApplyBackfillParams {
     pub items: Vec<AutoScoreBackfillItem>,
 }
 
+/// 预览已保存的规则只需 ruleId；预览编辑中的规则时传 rule，带上 ruleId 可沿用该规则的冷却与每日上限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewRuleParams {
+    #[serde(rename = "ruleId", default)]
+    pub rule_id: Option<i32>,
+    #[serde(default)]
+    pub rule: Option<CreateAutoScoreRule>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutoScoreStatus {
     pub enabled: bool,
@@ -156,7 +166,9 @@ fn replace_cached_rules(state: &Arc<RwLock<AppState>>, rules: Vec<AutoScoreRule>
     auto_score_service.replace_rules(rules);
 }
 
-async fn sync_cached_rules(state: &Arc<RwLock<AppState>>) -> Result<Vec<AutoScoreRule>, String> {
+pub async fn sync_cached_rules(
+    state: &Arc<RwLock<AppState>>,
+) -> Result<Vec<AutoScoreRule>, String> {
     let rules = load_rules_from_settings(state).await?;
     replace_cached_rules(state, rules.clone());
     Ok(rules)
@@ -181,13 +193,13 @@ fn emit_auto_score_status_changed(app_handle: &AppHandle, rules: &[AutoScoreRule
 
 #[tauri::command]
 pub async fn auto_score_get_rules(
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<Vec<AutoScoreRule>>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -199,14 +211,14 @@ pub async fn auto_score_get_rules(
 #[tauri::command]
 pub async fn auto_score_add_rule(
     rule: CreateAutoScoreRule,
-    sender_id: Option<u32>,
+    webview: Webview,
     app_handle: AppHandle,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<i32>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -231,14 +243,14 @@ pub async fn auto_score_add_rule(
 #[tauri::command]
 pub async fn auto_score_update_rule(
     rule: UpdateAutoScoreRule,
-    sender_id: Option<u32>,
+    webview: Webview,
     app_handle: AppHandle,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<bool>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -262,14 +274,14 @@ pub async fn auto_score_update_rule(
 #[tauri::command]
 pub async fn auto_score_delete_rule(
     rule_id: i32,
-    sender_id: Option<u32>,
+    webview: Webview,
     app_handle: AppHandle,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<bool>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -293,43 +305,56 @@ pub async fn auto_score_delete_rule(
 #[tauri::command]
 pub async fn auto_score_toggle_rule(
     params: ToggleRuleParams,
-    sender_id: Option<u32>,
+    webview: Webview,
     app_handle: AppHandle,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<bool>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
 
-    let current_rules = sync_cached_rules(state.inner()).await?;
+    match set_rule_enabled(state.inner(), &app_handle, params.rule_id, params.enabled).await? {
+        Ok(_) => Ok(IpcResponse::success(true)),
+        Err(message) => Ok(IpcResponse::error(&message)),
+    }
+}
+
+/// 启用或停用单条规则并通知前端；外层错误为存储失败，内层为规则校验失败
+pub async fn set_rule_enabled(
+    state: &Arc<RwLock<AppState>>,
+    app_handle: &AppHandle,
+    rule_id: i32,
+    enabled: bool,
+) -> Result<Result<Vec<AutoScoreRule>, String>, String> {
+    let current_rules = sync_cached_rules(state).await?;
     let mut working_service = AutoScoreService::from_rules(current_rules);
-    if let Err(message) = working_service.toggle_rule(params.rule_id, params.enabled) {
-        return Ok(IpcResponse::error(&message));
+    if let Err(message) = working_service.toggle_rule(rule_id, enabled) {
+        return Ok(Err(message));
     }
 
     let next_rules = working_service.into_rules();
-    persist_rules_to_settings(state.inner(), &next_rules).await?;
-    replace_cached_rules(state.inner(), next_rules);
+    persist_rules_to_settings(state, &next_rules).await?;
+    replace_cached_rules(state, next_rules);
     let rules = state.read().auto_score.read().get_rules().to_vec();
-    emit_auto_score_status_changed(&app_handle, &rules);
-    emit_rules_changed(&app_handle, state.inner());
+    emit_auto_score_status_changed(app_handle, &rules);
+    emit_rules_changed(app_handle, state);
 
-    Ok(IpcResponse::success(true))
+    Ok(Ok(rules))
 }
 
 #[tauri::command]
 pub async fn auto_score_get_status(
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<AutoScoreStatus>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -342,14 +367,14 @@ pub async fn auto_score_get_status(
 #[tauri::command]
 pub async fn auto_score_sort_rules(
     rule_ids: Vec<i32>,
-    sender_id: Option<u32>,
+    webview: Webview,
     app_handle: AppHandle,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<bool>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -370,31 +395,39 @@ pub async fn auto_score_sort_rules(
 
 #[tauri::command]
 pub async fn auto_score_query_batches(
-    sender_id: Option<u32>,
+    params: Option<AutoScoreBatchPageParams>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
-) -> Result<IpcResponse<Vec<AutoScoreExecutionBatch>>, String> {
+) -> Result<IpcResponse<AutoScoreBatchPage>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
 
-    let batches = query_execution_batches(state.inner()).await?;
-    Ok(IpcResponse::success(batches))
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    match query_execution_batches(&conn, &params.unwrap_or_default()).await {
+        Ok(page) => Ok(IpcResponse::success(page)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
 }
 
 #[tauri::command]
 pub async fn auto_score_rollback_batch(
     params: RollbackBatchParams,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<AutoScoreExecutionBatch>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -406,14 +439,14 @@ pub async fn auto_score_rollback_batch(
 #[tauri::command]
 pub async fn auto_score_apply_backfill(
     params: ApplyBackfillParams,
-    sender_id: Option<u32>,
+    webview: Webview,
     app_handle: AppHandle,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<AutoScoreBackfillResult>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -424,3 +457,38 @@ pub async fn auto_score_apply_backfill(
     emit_rules_changed(&app_handle, state.inner());
     Ok(IpcResponse::success(result))
 }
+
+#[tauri::command]
+pub async fn auto_score_preview_rule(
+    params: PreviewRuleParams,
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<AutoScoreRulePreview>, String> {
+    {
+        let state_guard = state.read();
+        let mut permissions = state_guard.permissions.write();
+        if !check_admin_permission(&mut permissions, &webview) {
+            return Ok(IpcResponse::error("Permission denied: admin required"));
+        }
+    }
+
+    let current_rules = sync_cached_rules(state.inner()).await?;
+    let saved_rule = params
+        .rule_id
+        .and_then(|rule_id| current_rules.into_iter().find(|rule| rule.id == rule_id));
+    let rule = match (params.rule, saved_rule) {
+        (Some(draft), saved_rule) => {
+            let mut rule = build_rule_from_create(draft);
+            rule.id = params.rule_id.unwrap_or(0);
+            rule.last_executed = saved_rule.and_then(|saved| saved.last_executed);
+            rule
+        }
+        (None, Some(saved_rule)) => saved_rule,
+        (None, None) => return Ok(IpcResponse::error("Rule not found")),
+    };
+
+    match preview_rule(state.inner(), rule).await {
+        Ok(preview) => Ok(IpcResponse::success(preview)),
+        Err(message) => Ok(IpcResponse::error(&message)),
+    }
+}
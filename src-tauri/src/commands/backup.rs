@@ -0,0 +1,97 @@
+use parking_lot::RwLock;
+use serde_json::json;
+use std::sync::Arc;
+use tauri::{State, Webview};
+
+use crate::services::backup::{
+    create_backup, delete_backup, list_backups, restore_backup, BackupEntry, BackupKind,
+};
+use crate::services::permission::{window_session_key, PermissionLevel};
+use crate::services::{BusPayload, EventSource};
+use crate::state::AppState;
+
+use super::response::IpcResponse;
+
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> Result<(), String> {
+    let state_guard = state.read();
+    let mut permissions = state_guard.permissions.write();
+    let session = window_session_key(webview.label());
+    if !permissions.require_permission(&session, PermissionLevel::Admin) {
+        return Err("Permission denied: Admin required".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn backup_list(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<Vec<BackupEntry>>, String> {
+    check_admin_permission(&state, &webview)?;
+
+    match list_backups(state.inner()).await {
+        Ok(entries) => Ok(IpcResponse::success(entries)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
+#[tauri::command]
+pub async fn backup_create(
+    reason: Option<String>,
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<BackupEntry>, String> {
+    check_admin_permission(&state, &webview)?;
+
+    let reason = reason
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .unwrap_or_else(|| "manual".to_string());
+    match create_backup(state.inner(), BackupKind::Manual, &reason).await {
+        Ok(entry) => Ok(IpcResponse::success(entry)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
+#[tauri::command]
+pub async fn backup_restore(
+    id: String,
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<BackupEntry>, String> {
+    check_admin_permission(&state, &webview)?;
+
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+
+    match restore_backup(state.inner(), &id).await {
+        Ok(entry) => {
+            {
+                let state_guard = state.read();
+                state_guard.logger.read().info_with_meta(
+                    "Backup restored",
+                    json!({ "id": entry.id, "created_at": entry.created_at }),
+                );
+                state_guard
+                    .event_bus
+                    .publish(EventSource::Backup, BusPayload::DataReset);
+            }
+            Ok(IpcResponse::success(entry))
+        }
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
+#[tauri::command]
+pub async fn backup_delete(
+    id: String,
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<()>, String> {
+    check_admin_permission(&state, &webview)?;
+
+    match delete_backup(state.inner(), &id).await {
+        Ok(()) => Ok(IpcResponse::success_empty()),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
@@ -5,11 +5,11 @@ use serde_json::{Map, Value as JsonValue};
 use sqlx::{Column, Row, SqlitePool};
 use std::collections::HashSet;
 use std::sync::Arc;
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, Manager, State, Webview};
 
 use crate::db::sqlite_connection_url;
 use crate::services::settings::{SettingsKey, SettingsValue};
-use crate::services::PermissionLevel;
+use crate::services::{window_session_key, PermissionLevel};
 use crate::state::AppState;
 
 use super::response::IpcResponse;
@@ -28,21 +28,21 @@ enum QueryBackend {
 
 fn check_permission(
     state: &Arc<RwLock<AppState>>,
-    sender_id: Option<u32>,
+    webview: &Webview,
     level: PermissionLevel,
 ) -> bool {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, level)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, level)
 }
 
-fn check_view_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
-    check_permission(state, sender_id, PermissionLevel::View)
+fn check_view_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
+    check_permission(state, webview, PermissionLevel::View)
 }
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
-    check_permission(state, sender_id, PermissionLevel::Admin)
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
+    check_permission(state, webview, PermissionLevel::Admin)
 }
 
 fn contains_forbidden_keyword(sql: &str) -> bool {
@@ -300,10 +300,10 @@ async fn query_postgres(sql: &str, pg_url: &str) -> Result<Vec<JsonValue>, Strin
 
 #[tauri::command]
 pub async fn board_get_configs(
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<JsonValue>, String> {
-    if !check_view_permission(&state, sender_id) {
+    if !check_view_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: view required"));
     }
 
@@ -335,10 +335,10 @@ pub async fn board_get_configs(
 #[tauri::command]
 pub async fn board_save_configs(
     configs: JsonValue,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -363,10 +363,10 @@ pub async fn board_save_configs(
 #[tauri::command]
 pub async fn board_query_sql(
     params: BoardSqlQueryParams,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<Vec<JsonValue>>, String> {
-    if !check_view_permission(&state, sender_id) {
+    if !check_view_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: view required"));
     }
 
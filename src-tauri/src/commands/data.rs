@@ -1,17 +1,24 @@
 use parking_lot::RwLock;
+use serde_json::json;
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 
-use crate::services::permission::PermissionLevel;
+use crate::services::backup::backup_before_operation;
+use crate::services::data::{
+    export_database, import_database, DataService, ImportMode, ImportResult,
+};
+use crate::services::permission::{window_session_key, PermissionLevel};
+use crate::services::{BusPayload, EventSource};
 use crate::state::AppState;
 
+use super::database::realtime_dual_write_sync_if_legacy;
 use super::response::IpcResponse;
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> Result<(), String> {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let sender_id = 0;
-    if !permissions.require_permission(sender_id, PermissionLevel::Admin) {
+    let session = window_session_key(webview.label());
+    if !permissions.require_permission(&session, PermissionLevel::Admin) {
         return Err("Permission denied: Admin required".to_string());
     }
     Ok(())
@@ -19,21 +26,27 @@ fn check_admin_permission(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
 
 #[tauri::command]
 pub async fn data_export_json(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<String>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
-    let state_guard = state.read();
-    let settings = state_guard.settings.read();
-    let settings_value = settings.get_all();
-    let settings_json = serde_json::to_value(settings_value)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-
-    let data_service = state_guard.data.read();
-    let students: Vec<crate::services::data::StudentExport> = Vec::new();
-    let events: Vec<crate::services::data::EventExport> = Vec::new();
+    let (settings_json, db_conn) = {
+        let state_guard = state.read();
+        let settings = state_guard.settings.read();
+        let settings_json = serde_json::to_value(settings.get_all())
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        let db_conn = state_guard.db.read().clone();
+        (settings_json, db_conn)
+    };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
 
-    let export_data = data_service.export_json(settings_json, students, events);
+    let export_data = match export_database(&conn, settings_json).await {
+        Ok(data) => data,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
 
     let json_string = serde_json::to_string_pretty(&export_data)
         .map_err(|e| format!("Failed to serialize export data: {}", e))?;
@@ -44,19 +57,46 @@ pub async fn data_export_json(
 #[tauri::command]
 pub async fn data_import_json(
     json_text: String,
+    mode: Option<ImportMode>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
-) -> Result<IpcResponse<()>, String> {
-    check_admin_permission(&state)?;
+) -> Result<IpcResponse<ImportResult>, String> {
+    check_admin_permission(&state, &webview)?;
 
-    let state_guard = state.read();
-    let mut data_service = state_guard.data.write();
-    let result = data_service.import_json(&json_text);
-
-    if result.success {
-        Ok(IpcResponse::success_empty())
-    } else {
-        Ok(IpcResponse::error(
-            result.message.as_deref().unwrap_or("Import failed"),
-        ))
+    let data = match DataService::validate_import_data(&json_text) {
+        Ok(data) => data,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
+    let mode = mode.unwrap_or_default();
+    if mode == ImportMode::Replace {
+        if let Err(e) = backup_before_operation(state.inner(), "data_import_replace").await {
+            return Ok(IpcResponse::error(&e));
+        }
+    }
+
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    match import_database(&conn, data, mode).await {
+        Ok(result) => {
+            {
+                let state_guard = state.read();
+                state_guard.logger.read().info_with_meta(
+                    "Data imported from JSON",
+                    json!({ "mode": result.mode, "tables": result.tables }),
+                );
+            }
+            realtime_dual_write_sync_if_legacy(state.inner()).await?;
+            state
+                .read()
+                .event_bus
+                .publish(EventSource::Desktop, BusPayload::DataReset);
+            Ok(IpcResponse::success(result))
+        }
+        Err(e) => Ok(IpcResponse::error(&e)),
     }
 }
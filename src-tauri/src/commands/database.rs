@@ -2,20 +2,24 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, Manager, State};
+use tauri::{AppHandle, Emitter, Manager, State, Webview};
 use tokio::time::{timeout, Duration};
 
 use crate::db::connection::DatabaseType;
 use crate::db::connection::{create_postgres_connection, create_sqlite_connection};
 use crate::db::entities::{
-    reasons, reward_redemptions, reward_settings, score_events, student_tags, students, tags,
+    reason_categories, reasons, reward_redemptions, reward_settings, score_events, student_tags,
+    students, tags,
 };
-use crate::db::migration::run_migration;
+use crate::db::migration::{check_migration_status, run_migration, Migration, MigrationStatus};
+use crate::services::backup::backup_before_operation;
 use crate::services::logger::LogLevel;
-use crate::services::permission::PermissionLevel;
+use crate::services::permission::{window_session_key, PermissionLevel};
 use crate::services::settings::{SettingsKey, SettingsValue};
 use crate::state::AppState;
-use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set,
+};
 
 use super::response::IpcResponse;
 
@@ -94,6 +98,7 @@ pub enum ConflictStrategy {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct StudentNormalized {
+    uuid: String,
     name: String,
     group_name: Option<String>,
     score: i32,
@@ -110,6 +115,19 @@ struct ReasonNormalized {
     category: String,
     delta: i32,
     is_system: i32,
+    sort_order: i32,
+    is_archived: i32,
+    updated_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReasonCategoryNormalized {
+    name: String,
+    sort_order: i32,
+    color: Option<String>,
+    icon: Option<String>,
+    is_archived: i32,
+    created_at: String,
     updated_at: String,
 }
 
@@ -123,6 +141,7 @@ struct TagNormalized {
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct EventNormalized {
     uuid: String,
+    student_uuid: Option<String>,
     student_name: String,
     reason_content: String,
     delta: i32,
@@ -135,6 +154,12 @@ struct EventNormalized {
 struct RewardSettingNormalized {
     name: String,
     cost_points: i32,
+    stock: Option<i32>,
+    per_student_limit: Option<i32>,
+    per_period_limit: Option<i32>,
+    active_from: Option<String>,
+    active_until: Option<String>,
+    requires_approval: i32,
     created_at: String,
     updated_at: String,
 }
@@ -142,16 +167,23 @@ struct RewardSettingNormalized {
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct RewardRedemptionNormalized {
     uuid: String,
+    student_uuid: Option<String>,
     student_name: String,
     reward_id: i32,
     reward_name: String,
     cost_points: i32,
     redeemed_at: String,
+    status: String,
+    reviewed_at: Option<String>,
+    review_note: Option<String>,
+    cancelled_at: Option<String>,
+    cancelled_by: Option<String>,
+    cancel_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct StudentTagPair {
-    student_name: String,
+    student_uuid: String,
     tag_name: String,
 }
 
@@ -196,8 +228,9 @@ async fn load_students(
     let mut map = std::collections::HashMap::new();
     for row in rows {
         map.insert(
-            row.name.clone(),
+            row.uuid.clone(),
             StudentNormalized {
+                uuid: row.uuid,
                 name: row.name,
                 group_name: row.group_name,
                 score: row.score,
@@ -228,6 +261,33 @@ async fn load_reasons(
                 category: row.category,
                 delta: row.delta,
                 is_system: row.is_system,
+                sort_order: row.sort_order,
+                is_archived: row.is_archived,
+                updated_at: row.updated_at,
+            },
+        );
+    }
+    Ok(map)
+}
+
+async fn load_reason_categories(
+    conn: &sea_orm::DatabaseConnection,
+) -> Result<std::collections::HashMap<String, ReasonCategoryNormalized>, String> {
+    let rows = reason_categories::Entity::find()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut map = std::collections::HashMap::new();
+    for row in rows {
+        map.insert(
+            row.name.clone(),
+            ReasonCategoryNormalized {
+                name: row.name,
+                sort_order: row.sort_order,
+                color: row.color,
+                icon: row.icon,
+                is_archived: row.is_archived,
+                created_at: row.created_at,
                 updated_at: row.updated_at,
             },
         );
@@ -263,12 +323,16 @@ async fn load_events(
         .all(conn)
         .await
         .map_err(|e| e.to_string())?;
+    let student_uuids = load_student_uuid_map(conn).await?;
     let mut map = std::collections::HashMap::new();
     for row in rows {
         map.insert(
             row.uuid.clone(),
             EventNormalized {
                 uuid: row.uuid,
+                student_uuid: row
+                    .student_id
+                    .and_then(|id| student_uuids.get(&id).cloned()),
                 student_name: row.student_name,
                 reason_content: row.reason_content,
                 delta: row.delta,
@@ -295,6 +359,12 @@ async fn load_reward_settings(
             RewardSettingNormalized {
                 name: row.name,
                 cost_points: row.cost_points,
+                stock: row.stock,
+                per_student_limit: row.per_student_limit,
+                per_period_limit: row.per_period_limit,
+                active_from: row.active_from,
+                active_until: row.active_until,
+                requires_approval: row.requires_approval,
                 created_at: row.created_at,
                 updated_at: row.updated_at,
             },
@@ -310,23 +380,82 @@ async fn load_reward_redemptions(
         .all(conn)
         .await
         .map_err(|e| e.to_string())?;
+    let student_uuids = load_student_uuid_map(conn).await?;
     let mut map = std::collections::HashMap::new();
     for row in rows {
         map.insert(
             row.uuid.clone(),
             RewardRedemptionNormalized {
                 uuid: row.uuid,
+                student_uuid: row
+                    .student_id
+                    .and_then(|id| student_uuids.get(&id).cloned()),
                 student_name: row.student_name,
                 reward_id: row.reward_id,
                 reward_name: row.reward_name,
                 cost_points: row.cost_points,
                 redeemed_at: row.redeemed_at,
+                status: row.status,
+                reviewed_at: row.reviewed_at,
+                review_note: row.review_note,
+                cancelled_at: row.cancelled_at,
+                cancelled_by: row.cancelled_by,
+                cancel_reason: row.cancel_reason,
             },
         );
     }
     Ok(map)
 }
 
+async fn load_student_uuid_map(
+    conn: &sea_orm::DatabaseConnection,
+) -> Result<std::collections::HashMap<i32, String>, String> {
+    let rows = students::Entity::find()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().map(|s| (s.id, s.uuid)).collect())
+}
+
+async fn student_uuid_of(
+    conn: &sea_orm::DatabaseConnection,
+    student_id: Option<i32>,
+) -> Result<Option<String>, String> {
+    let Some(student_id) = student_id else {
+        return Ok(None);
+    };
+    let row = students::Entity::find_by_id(student_id)
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(row.map(|s| s.uuid))
+}
+
+// 优先按 uuid 定位学生，旧数据没有 uuid 时退回按姓名
+async fn resolve_student_id(
+    conn: &sea_orm::DatabaseConnection,
+    student_uuid: Option<&str>,
+    student_name: &str,
+) -> Result<Option<i32>, String> {
+    if let Some(student_uuid) = student_uuid {
+        let row = students::Entity::find()
+            .filter(students::Column::Uuid.eq(student_uuid))
+            .one(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(row) = row {
+            return Ok(Some(row.id));
+        }
+    }
+    let row = students::Entity::find()
+        .filter(students::Column::Name.eq(student_name))
+        .order_by_asc(students::Column::Id)
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(row.map(|s| s.id))
+}
+
 async fn load_student_tag_pairs(
     conn: &sea_orm::DatabaseConnection,
 ) -> Result<std::collections::HashSet<StudentTagPair>, String> {
@@ -343,19 +472,19 @@ async fn load_student_tag_pairs(
         .await
         .map_err(|e| e.to_string())?;
 
-    let student_name_map: std::collections::HashMap<i32, String> =
-        student_rows.into_iter().map(|s| (s.id, s.name)).collect();
+    let student_uuid_map: std::collections::HashMap<i32, String> =
+        student_rows.into_iter().map(|s| (s.id, s.uuid)).collect();
     let tag_name_map: std::collections::HashMap<i32, String> =
         tag_rows.into_iter().map(|t| (t.id, t.name)).collect();
 
     let mut pairs = std::collections::HashSet::new();
     for rel in link_rows {
-        if let (Some(student_name), Some(tag_name)) = (
-            student_name_map.get(&rel.student_id),
+        if let (Some(student_uuid), Some(tag_name)) = (
+            student_uuid_map.get(&rel.student_id),
             tag_name_map.get(&rel.tag_id),
         ) {
             pairs.insert(StudentTagPair {
-                student_name: student_name.clone(),
+                student_uuid: student_uuid.clone(),
                 tag_name: tag_name.clone(),
             });
         }
@@ -396,7 +525,7 @@ async fn upsert_student(
     data: &StudentNormalized,
 ) -> Result<bool, String> {
     let existing = students::Entity::find()
-        .filter(students::Column::Name.eq(&data.name))
+        .filter(students::Column::Uuid.eq(&data.uuid))
         .one(conn)
         .await
         .map_err(|e| e.to_string())?;
@@ -404,6 +533,7 @@ async fn upsert_student(
     match existing {
         Some(row) => {
             let normalized_current = StudentNormalized {
+                uuid: row.uuid.clone(),
                 name: row.name.clone(),
                 group_name: row.group_name.clone(),
                 score: row.score,
@@ -417,6 +547,7 @@ async fn upsert_student(
                 return Ok(false);
             }
             let mut active: students::ActiveModel = row.into();
+            active.name = Set(data.name.clone());
             active.score = Set(data.score);
             active.group_name = Set(data.group_name.clone());
             active.reward_points = Set(data.reward_points);
@@ -430,6 +561,7 @@ async fn upsert_student(
         None => {
             students::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
+                uuid: Set(data.uuid.clone()),
                 name: Set(data.name.clone()),
                 group_name: Set(data.group_name.clone()),
                 score: Set(data.score),
@@ -463,26 +595,92 @@ async fn upsert_reason(
                 category: row.category.clone(),
                 delta: row.delta,
                 is_system: row.is_system,
+                sort_order: row.sort_order,
+                is_archived: row.is_archived,
                 updated_at: row.updated_at.clone(),
             };
             if normalized_current == *data {
                 return Ok(false);
             }
+            // 两端分类 id 不同，按名称对应
+            let category_id = crate::services::reason::ensure_category(conn, &data.category)
+                .await?
+                .map(|c| c.id);
             let mut active: reasons::ActiveModel = row.into();
             active.category = Set(data.category.clone());
+            active.category_id = Set(category_id);
             active.delta = Set(data.delta);
             active.is_system = Set(data.is_system);
+            active.sort_order = Set(data.sort_order);
+            active.is_archived = Set(data.is_archived);
             active.updated_at = Set(data.updated_at.clone());
             active.update(conn).await.map_err(|e| e.to_string())?;
             Ok(true)
         }
         None => {
+            let category_id = crate::services::reason::ensure_category(conn, &data.category)
+                .await?
+                .map(|c| c.id);
             reasons::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
                 content: Set(data.content.clone()),
                 category: Set(data.category.clone()),
+                category_id: Set(category_id),
                 delta: Set(data.delta),
                 is_system: Set(data.is_system),
+                sort_order: Set(data.sort_order),
+                is_archived: Set(data.is_archived),
+                updated_at: Set(data.updated_at.clone()),
+            }
+            .insert(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+            Ok(true)
+        }
+    }
+}
+
+async fn upsert_reason_category(
+    conn: &sea_orm::DatabaseConnection,
+    data: &ReasonCategoryNormalized,
+) -> Result<bool, String> {
+    let existing = reason_categories::Entity::find()
+        .filter(reason_categories::Column::Name.eq(&data.name))
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    match existing {
+        Some(row) => {
+            let normalized_current = ReasonCategoryNormalized {
+                name: row.name.clone(),
+                sort_order: row.sort_order,
+                color: row.color.clone(),
+                icon: row.icon.clone(),
+                is_archived: row.is_archived,
+                created_at: row.created_at.clone(),
+                updated_at: row.updated_at.clone(),
+            };
+            if normalized_current == *data {
+                return Ok(false);
+            }
+            let mut active: reason_categories::ActiveModel = row.into();
+            active.sort_order = Set(data.sort_order);
+            active.color = Set(data.color.clone());
+            active.icon = Set(data.icon.clone());
+            active.is_archived = Set(data.is_archived);
+            active.updated_at = Set(data.updated_at.clone());
+            active.update(conn).await.map_err(|e| e.to_string())?;
+            Ok(true)
+        }
+        None => {
+            reason_categories::ActiveModel {
+                id: sea_orm::ActiveValue::NotSet,
+                name: Set(data.name.clone()),
+                sort_order: Set(data.sort_order),
+                color: Set(data.color.clone()),
+                icon: Set(data.icon.clone()),
+                is_archived: Set(data.is_archived),
+                created_at: Set(data.created_at.clone()),
                 updated_at: Set(data.updated_at.clone()),
             }
             .insert(conn)
@@ -546,6 +744,7 @@ async fn upsert_event(
         Some(row) => {
             let normalized_current = EventNormalized {
                 uuid: row.uuid.clone(),
+                student_uuid: student_uuid_of(conn, row.student_id).await?,
                 student_name: row.student_name.clone(),
                 reason_content: row.reason_content.clone(),
                 delta: row.delta,
@@ -556,7 +755,10 @@ async fn upsert_event(
             if normalized_current == *data {
                 return Ok(false);
             }
+            let student_id =
+                resolve_student_id(conn, data.student_uuid.as_deref(), &data.student_name).await?;
             let mut active: score_events::ActiveModel = row.into();
+            active.student_id = Set(student_id);
             active.student_name = Set(data.student_name.clone());
             active.reason_content = Set(data.reason_content.clone());
             active.delta = Set(data.delta);
@@ -567,9 +769,12 @@ async fn upsert_event(
             Ok(true)
         }
         None => {
+            let student_id =
+                resolve_student_id(conn, data.student_uuid.as_deref(), &data.student_name).await?;
             score_events::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
                 uuid: Set(data.uuid.clone()),
+                student_id: Set(student_id),
                 student_name: Set(data.student_name.clone()),
                 reason_content: Set(data.reason_content.clone()),
                 delta: Set(data.delta),
@@ -600,6 +805,12 @@ async fn upsert_reward_setting(
             let normalized_current = RewardSettingNormalized {
                 name: row.name.clone(),
                 cost_points: row.cost_points,
+                stock: row.stock,
+                per_student_limit: row.per_student_limit,
+                per_period_limit: row.per_period_limit,
+                active_from: row.active_from.clone(),
+                active_until: row.active_until.clone(),
+                requires_approval: row.requires_approval,
                 created_at: row.created_at.clone(),
                 updated_at: row.updated_at.clone(),
             };
@@ -608,6 +819,12 @@ async fn upsert_reward_setting(
             }
             let mut active: reward_settings::ActiveModel = row.into();
             active.cost_points = Set(data.cost_points);
+            active.stock = Set(data.stock);
+            active.per_student_limit = Set(data.per_student_limit);
+            active.per_period_limit = Set(data.per_period_limit);
+            active.active_from = Set(data.active_from.clone());
+            active.active_until = Set(data.active_until.clone());
+            active.requires_approval = Set(data.requires_approval);
             active.created_at = Set(data.created_at.clone());
             active.updated_at = Set(data.updated_at.clone());
             active.update(conn).await.map_err(|e| e.to_string())?;
@@ -618,6 +835,12 @@ async fn upsert_reward_setting(
                 id: sea_orm::ActiveValue::NotSet,
                 name: Set(data.name.clone()),
                 cost_points: Set(data.cost_points),
+                stock: Set(data.stock),
+                per_student_limit: Set(data.per_student_limit),
+                per_period_limit: Set(data.per_period_limit),
+                active_from: Set(data.active_from.clone()),
+                active_until: Set(data.active_until.clone()),
+                requires_approval: Set(data.requires_approval),
                 created_at: Set(data.created_at.clone()),
                 updated_at: Set(data.updated_at.clone()),
             }
@@ -642,33 +865,58 @@ async fn upsert_reward_redemption(
         Some(row) => {
             let normalized_current = RewardRedemptionNormalized {
                 uuid: row.uuid.clone(),
+                student_uuid: student_uuid_of(conn, row.student_id).await?,
                 student_name: row.student_name.clone(),
                 reward_id: row.reward_id,
                 reward_name: row.reward_name.clone(),
                 cost_points: row.cost_points,
                 redeemed_at: row.redeemed_at.clone(),
+                status: row.status.clone(),
+                reviewed_at: row.reviewed_at.clone(),
+                review_note: row.review_note.clone(),
+                cancelled_at: row.cancelled_at.clone(),
+                cancelled_by: row.cancelled_by.clone(),
+                cancel_reason: row.cancel_reason.clone(),
             };
             if normalized_current == *data {
                 return Ok(false);
             }
+            let student_id =
+                resolve_student_id(conn, data.student_uuid.as_deref(), &data.student_name).await?;
             let mut active: reward_redemptions::ActiveModel = row.into();
+            active.student_id = Set(student_id);
             active.student_name = Set(data.student_name.clone());
             active.reward_id = Set(data.reward_id);
             active.reward_name = Set(data.reward_name.clone());
             active.cost_points = Set(data.cost_points);
             active.redeemed_at = Set(data.redeemed_at.clone());
+            active.status = Set(data.status.clone());
+            active.reviewed_at = Set(data.reviewed_at.clone());
+            active.review_note = Set(data.review_note.clone());
+            active.cancelled_at = Set(data.cancelled_at.clone());
+            active.cancelled_by = Set(data.cancelled_by.clone());
+            active.cancel_reason = Set(data.cancel_reason.clone());
             active.update(conn).await.map_err(|e| e.to_string())?;
             Ok(true)
         }
         None => {
+            let student_id =
+                resolve_student_id(conn, data.student_uuid.as_deref(), &data.student_name).await?;
             reward_redemptions::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
                 uuid: Set(data.uuid.clone()),
+                student_id: Set(student_id),
                 student_name: Set(data.student_name.clone()),
                 reward_id: Set(data.reward_id),
                 reward_name: Set(data.reward_name.clone()),
                 cost_points: Set(data.cost_points),
                 redeemed_at: Set(data.redeemed_at.clone()),
+                status: Set(data.status.clone()),
+                reviewed_at: Set(data.reviewed_at.clone()),
+                review_note: Set(data.review_note.clone()),
+                cancelled_at: Set(data.cancelled_at.clone()),
+                cancelled_by: Set(data.cancelled_by.clone()),
+                cancel_reason: Set(data.cancel_reason.clone()),
             }
             .insert(conn)
             .await
@@ -683,7 +931,7 @@ async fn ensure_student_tag_pair(
     pair: &StudentTagPair,
 ) -> Result<bool, String> {
     let student_row = students::Entity::find()
-        .filter(students::Column::Name.eq(&pair.student_name))
+        .filter(students::Column::Uuid.eq(&pair.student_uuid))
         .one(conn)
         .await
         .map_err(|e| e.to_string())?;
@@ -803,6 +1051,11 @@ pub async fn realtime_dual_write_sync(app_state: &Arc<RwLock<AppState>>) -> Resu
         let _ = upsert_student(&local_conn, student).await?;
     }
 
+    let remote_categories = load_reason_categories(&remote_conn).await?;
+    for category in remote_categories.values() {
+        let _ = upsert_reason_category(&local_conn, category).await?;
+    }
+
     let remote_reasons = load_reasons(&remote_conn).await?;
     for reason in remote_reasons.values() {
         let _ = upsert_reason(&local_conn, reason).await?;
@@ -888,6 +1141,8 @@ async fn db_sync_apply_internal(
 
     let local_students = load_students(&local_conn).await?;
     let remote_students = load_students(&remote_conn).await?;
+    let local_categories = load_reason_categories(&local_conn).await?;
+    let remote_categories = load_reason_categories(&remote_conn).await?;
     let local_reasons = load_reasons(&local_conn).await?;
     let remote_reasons = load_reasons(&remote_conn).await?;
     let local_tags = load_tags(&local_conn).await?;
@@ -939,6 +1194,38 @@ async fn db_sync_apply_internal(
         }
     }
 
+    // 分类先于理由同步，理由按名称关联分类
+    let category_keys: std::collections::HashSet<String> = local_categories
+        .keys()
+        .chain(remote_categories.keys())
+        .cloned()
+        .collect();
+    for key in category_keys {
+        match (local_categories.get(&key), remote_categories.get(&key)) {
+            (Some(local), Some(remote)) if local != remote => {
+                let changed = if strategy == ConflictStrategy::KeepLocal {
+                    upsert_reason_category(&remote_conn, local).await?
+                } else {
+                    upsert_reason_category(&local_conn, remote).await?
+                };
+                if changed {
+                    resolved_conflicts += 1;
+                }
+            }
+            (Some(local), None) => {
+                if upsert_reason_category(&remote_conn, local).await? {
+                    synced_records += 1;
+                }
+            }
+            (None, Some(remote)) => {
+                if upsert_reason_category(&local_conn, remote).await? {
+                    synced_records += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
     let reason_keys: std::collections::HashSet<String> = local_reasons
         .keys()
         .chain(remote_reasons.keys())
@@ -1119,14 +1406,14 @@ async fn db_sync_apply_internal(
     })
 }
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> Result<(), String> {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let sender_id = 0;
-    if !permissions.require_permission(sender_id, PermissionLevel::Admin) {
+    let session = window_session_key(webview.label());
+    if !permissions.require_permission(&session, PermissionLevel::Admin) {
         state_guard.logger.read().warn_with_meta(
             "数据库切换被拒绝：当前进程没有管理员权限",
-            json!({ "sender_id": sender_id, "required": "admin" }),
+            json!({ "session": session, "required": "admin" }),
         );
         return Err("Permission denied: Admin required".to_string());
     }
@@ -1213,9 +1500,10 @@ pub async fn db_test_connection(
 pub async fn db_switch_connection(
     connection_string: String,
     app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SwitchConnectionResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
     if connection_string.starts_with("postgres://")
         || connection_string.starts_with("postgresql://")
@@ -1407,6 +1695,7 @@ pub async fn db_switch_connection(
 /// 将当前业务连接切换到启动时缓存的本地 SQLite，并关闭旧的 PostgreSQL 连接池。
 #[tauri::command]
 pub async fn db_use_local_sqlite(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SwitchConnectionResult>, String> {
     {
@@ -1416,7 +1705,7 @@ pub async fn db_use_local_sqlite(
             json!({ "command": "db_use_local_sqlite" }),
         );
     }
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
     let local_conn = {
         let state_guard = state.read();
@@ -1547,12 +1836,36 @@ pub async fn db_get_status(
     }))
 }
 
+/// 当前连接的结构版本，供界面提示待执行或高于程序版本的迁移
+#[tauri::command]
+pub async fn db_migration_status(
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<MigrationStatus>, String> {
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+    let db_type = match conn.get_database_backend() {
+        sea_orm::DatabaseBackend::Postgres => DatabaseType::PostgreSQL,
+        _ => DatabaseType::SQLite,
+    };
+
+    match check_migration_status(&conn, db_type).await {
+        Ok(status) => Ok(IpcResponse::success(status)),
+        Err(e) => Ok(IpcResponse::error(&format!(
+            "Failed to check migration status: {}",
+            e
+        ))),
+    }
+}
+
 #[tauri::command]
 pub async fn db_sync_preview(
     app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<DbSyncPreviewResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
     let app_state = state.inner().clone();
     let Some((local_conn, remote_conn)) =
@@ -1570,6 +1883,8 @@ pub async fn db_sync_preview(
 
     let local_students = load_students(&local_conn).await?;
     let remote_students = load_students(&remote_conn).await?;
+    let local_categories = load_reason_categories(&local_conn).await?;
+    let remote_categories = load_reason_categories(&remote_conn).await?;
     let local_reasons = load_reasons(&local_conn).await?;
     let remote_reasons = load_reasons(&remote_conn).await?;
     let local_tags = load_tags(&local_conn).await?;
@@ -1585,6 +1900,8 @@ pub async fn db_sync_preview(
 
     let (stu_local_only, stu_remote_only, stu_conflicts) =
         compare_maps("students", &local_students, &remote_students);
+    let (cat_local_only, cat_remote_only, cat_conflicts) =
+        compare_maps("reason_categories", &local_categories, &remote_categories);
     let (rea_local_only, rea_remote_only, rea_conflicts) =
         compare_maps("reasons", &local_reasons, &remote_reasons);
     let (tag_local_only, tag_remote_only, tag_conflicts) =
@@ -1603,12 +1920,14 @@ pub async fn db_sync_preview(
     );
 
     let mut local_only = stu_local_only
+        + cat_local_only
         + rea_local_only
         + tag_local_only
         + evt_local_only
         + reward_local_only
         + redeem_local_only;
     let mut remote_only = stu_remote_only
+        + cat_remote_only
         + rea_remote_only
         + tag_remote_only
         + evt_remote_only
@@ -1643,6 +1962,22 @@ pub async fn db_sync_preview(
             ),
         });
     }
+    for (table, key) in cat_conflicts {
+        let local = local_categories.get(&key).expect("local category exists");
+        let remote = remote_categories.get(&key).expect("remote category exists");
+        conflicts.push(DbSyncConflict {
+            table,
+            key,
+            local_summary: format!(
+                "sort_order={}, archived={}, updated_at={}",
+                local.sort_order, local.is_archived, local.updated_at
+            ),
+            remote_summary: format!(
+                "sort_order={}, archived={}, updated_at={}",
+                remote.sort_order, remote.is_archived, remote.updated_at
+            ),
+        });
+    }
     for (table, key) in rea_conflicts {
         let local = local_reasons.get(&key).expect("local reason exists");
         let remote = remote_reasons.get(&key).expect("remote reason exists");
@@ -1690,12 +2025,12 @@ pub async fn db_sync_preview(
             table,
             key,
             local_summary: format!(
-                "cost_points={}, updated_at={}",
-                local.cost_points, local.updated_at
+                "cost_points={}, stock={:?}, updated_at={}",
+                local.cost_points, local.stock, local.updated_at
             ),
             remote_summary: format!(
-                "cost_points={}, updated_at={}",
-                remote.cost_points, remote.updated_at
+                "cost_points={}, stock={:?}, updated_at={}",
+                remote.cost_points, remote.stock, remote.updated_at
             ),
         });
     }
@@ -1710,12 +2045,20 @@ pub async fn db_sync_preview(
             table,
             key,
             local_summary: format!(
-                "student={}, reward={}, cost={}, redeemed_at={}",
-                local.student_name, local.reward_name, local.cost_points, local.redeemed_at
+                "student={}, reward={}, cost={}, status={}, redeemed_at={}",
+                local.student_name,
+                local.reward_name,
+                local.cost_points,
+                local.status,
+                local.redeemed_at
             ),
             remote_summary: format!(
-                "student={}, reward={}, cost={}, redeemed_at={}",
-                remote.student_name, remote.reward_name, remote.cost_points, remote.redeemed_at
+                "student={}, reward={}, cost={}, status={}, redeemed_at={}",
+                remote.student_name,
+                remote.reward_name,
+                remote.cost_points,
+                remote.status,
+                remote.redeemed_at
             ),
         });
     }
@@ -1735,18 +2078,38 @@ pub async fn db_sync_preview(
 pub async fn db_sync_apply(
     strategy: ConflictStrategy,
     app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<DbSyncApplyResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
+    if let Err(e) = backup_before_operation(state.inner(), "db_sync_apply").await {
+        return Ok(IpcResponse::error(&e));
+    }
     let result = db_sync_apply_internal(strategy, app_handle, state.inner().clone()).await?;
     Ok(IpcResponse::success(result))
 }
 
+/// 清空并重建当前数据库；重置前先做操作前备份，备份失败则不重置
+pub async fn reset_current_database(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
+    backup_before_operation(state, "reset_database").await?;
+    let conn = state
+        .read()
+        .db
+        .read()
+        .clone()
+        .ok_or_else(|| "Database not connected".to_string())?;
+    let sqlite = conn.get_database_backend() == sea_orm::DbBackend::Sqlite;
+    Migration::reset_database(&conn, sqlite)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn db_sync(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SyncResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
     let state_guard = state.read();
     let db_guard = state_guard.db.read();
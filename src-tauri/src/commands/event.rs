@@ -1,17 +1,22 @@
-use chrono::{Datelike, Duration, Timelike, Utc};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use parking_lot::RwLock;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
-    TransactionTrait,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Statement,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use tauri::State;
-use uuid::Uuid;
+use tauri::{State, Webview};
 
-use crate::db::entities::{score_events, students};
-use crate::services::PermissionLevel;
+use crate::db::bind_statement;
+use crate::db::entities::{reason_categories, reasons, score_events, students};
+use crate::services::score::{self, StudentSelector};
+use crate::services::{
+    window_session_key, BusPayload, EventSource, PermissionLevel, AUTO_SCORE_REASON_PREFIX,
+};
 use crate::state::AppState;
 
 use super::database::realtime_dual_write_sync_if_legacy;
@@ -21,6 +26,7 @@ use super::response::IpcResponse;
 pub struct ScoreEvent {
     pub id: i32,
     pub uuid: String,
+    pub student_id: Option<i32>,
     pub student_name: String,
     pub reason_content: String,
     pub delta: i32,
@@ -30,9 +36,28 @@ pub struct ScoreEvent {
     pub settlement_id: Option<i32>,
 }
 
+impl From<score_events::Model> for ScoreEvent {
+    fn from(model: score_events::Model) -> Self {
+        Self {
+            id: model.id,
+            uuid: model.uuid,
+            student_id: model.student_id,
+            student_name: model.student_name,
+            reason_content: model.reason_content,
+            delta: model.delta,
+            val_prev: model.val_prev,
+            val_curr: model.val_curr,
+            event_time: model.event_time,
+            settlement_id: model.settlement_id,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateScoreEvent {
-    #[serde(alias = "studentName")]
+    #[serde(default, alias = "studentId")]
+    pub student_id: Option<i32>,
+    #[serde(default, alias = "studentName")]
     pub student_name: String,
     #[serde(alias = "reasonContent")]
     pub reason_content: String,
@@ -41,14 +66,104 @@ pub struct CreateScoreEvent {
     pub operation_id: Option<String>,
 }
 
+/// 批量加减分：按学生 ID、姓名、分组或标签选取，结果取并集
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateScoreEventBatch {
+    #[serde(flatten)]
+    pub selector: StudentSelector,
+    #[serde(alias = "reasonContent")]
+    pub reason_content: String,
+    pub delta: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventBatchResult {
+    pub uuids: Vec<String>,
+    pub events: Vec<ScoreEvent>,
+}
+
+impl From<Vec<score_events::Model>> for EventBatchResult {
+    fn from(created: Vec<score_events::Model>) -> Self {
+        Self {
+            uuids: created.iter().map(|event| event.uuid.clone()).collect(),
+            events: created.into_iter().map(ScoreEvent::from).collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryEventParams {
     pub limit: Option<i32>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeltaSign {
+    Positive,
+    Negative,
+    Zero,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementScope {
+    /// 当前结算周期内（未结算）的记录
+    #[default]
+    Unsettled,
+    /// 已归入任意历史结算的记录
+    Settled,
+    Any,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventPageParams {
+    pub limit: Option<i32>,
+    pub cursor: Option<String>,
+    #[serde(default, alias = "studentIds")]
+    pub student_ids: Vec<i32>,
+    #[serde(default, alias = "studentNames")]
+    pub student_names: Vec<String>,
+    #[serde(alias = "reasonContent")]
+    pub reason_content: Option<String>,
+    /// 分类名称，按当前分类表解析；仅在未指定 reason_category_id 时使用
+    #[serde(alias = "reasonCategory")]
+    pub reason_category: Option<String>,
+    #[serde(alias = "reasonCategoryId")]
+    pub reason_category_id: Option<i32>,
+    #[serde(alias = "deltaSign")]
+    pub delta_sign: Option<DeltaSign>,
+    #[serde(alias = "startTime")]
+    pub start_time: Option<String>,
+    #[serde(alias = "endTime")]
+    pub end_time: Option<String>,
+    #[serde(default, alias = "settlementScope")]
+    pub settlement_scope: SettlementScope,
+    /// 指定后只返回该次结算的记录，优先于 settlement_scope
+    #[serde(alias = "settlementId")]
+    pub settlement_id: Option<i32>,
+    /// true 只看自动化加分，false 排除自动化加分
+    #[serde(alias = "autoScore")]
+    pub auto_score: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPage {
+    pub items: Vec<ScoreEvent>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EventCursor {
+    event_time: String,
+    id: i32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryByStudentParams {
-    #[serde(alias = "studentName")]
+    #[serde(default, alias = "studentId")]
+    pub student_id: Option<i32>,
+    #[serde(default, alias = "studentName")]
     pub student_name: String,
     pub limit: Option<i32>,
     #[serde(alias = "startTime")]
@@ -59,25 +174,96 @@ pub struct QueryByStudentParams {
 pub struct LeaderboardRow {
     pub id: i32,
     pub name: String,
+    pub group_name: Option<String>,
     pub score: i32,
     pub range_change: i64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardGroupRow {
+    /// 分组名或标签名，未分组/无标签的学生归入 None
+    pub name: Option<String>,
+    pub student_count: usize,
+    pub total_score: i64,
+    pub total_range_change: i64,
+    pub average_score: f64,
+    pub average_range_change: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LeaderboardResult {
     pub start_time: String,
+    pub end_time: Option<String>,
     pub rows: Vec<LeaderboardRow>,
+    pub groups: Option<Vec<LeaderboardGroupRow>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReasonStatsParams {
+    /// today / week / month，未指定 start 时作为起点；指定 settlement_id 时忽略时间范围
+    #[serde(default)]
+    pub range: String,
+    #[serde(default, alias = "startTime")]
+    pub start: Option<String>,
+    #[serde(default, alias = "endTime")]
+    pub end: Option<String>,
+    #[serde(default, alias = "settlementId")]
+    pub settlement_id: Option<i32>,
+    #[serde(default, alias = "groupName")]
+    pub group_name: Option<String>,
+    #[serde(default, alias = "tagId")]
+    pub tag_id: Option<i32>,
+    /// 只返回使用次数最多的前 N 条，未使用的理由不受影响
+    #[serde(default)]
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReasonStatRow {
+    /// 自动化加分或已删除理由的记录没有对应的 reasons 行
+    pub reason_id: Option<i32>,
+    pub reason_content: String,
+    pub category: Option<String>,
+    pub count: i64,
+    pub total_delta: i64,
+    pub distinct_students: i64,
+    pub last_used_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReasonStatsResult {
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub settlement_id: Option<i32>,
+    pub rows: Vec<ReasonStatRow>,
+    /// 期间内未被使用过的理由（不含已归档），last_used_at 为历史上最后一次使用时间
+    pub unused: Vec<ReasonStatRow>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardGroupBy {
+    Group,
+    Tag,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LeaderboardParams {
+    #[serde(default)]
     pub range: String,
+    #[serde(default, alias = "startTime")]
+    pub start: Option<String>,
+    #[serde(default, alias = "endTime")]
+    pub end: Option<String>,
+    #[serde(default, alias = "groupBy")]
+    pub group_by: Option<LeaderboardGroupBy>,
 }
 
-fn check_points_permission(state: &Arc<RwLock<AppState>>) -> bool {
+fn check_points_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
+    let session = window_session_key(webview.label());
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    permissions.require_permission(0, PermissionLevel::Points)
+    permissions.require_permission(&session, PermissionLevel::Points)
 }
 
 #[tauri::command]
@@ -105,6 +291,7 @@ pub async fn event_query(
                     .map(|e| ScoreEvent {
                         id: e.id,
                         uuid: e.uuid,
+                        student_id: e.student_id,
                         student_name: e.student_name,
                         reason_content: e.reason_content,
                         delta: e.delta,
@@ -126,166 +313,430 @@ pub async fn event_query(
     }
 }
 
+fn encode_event_cursor(event: &ScoreEvent) -> String {
+    let raw = serde_json::to_vec(&EventCursor {
+        event_time: event.event_time.clone(),
+        id: event.id,
+    })
+    .unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+fn decode_event_cursor(cursor: &str) -> Result<EventCursor, String> {
+    URL_SAFE_NO_PAD
+        .decode(cursor.trim())
+        .ok()
+        .and_then(|raw| serde_json::from_slice::<EventCursor>(&raw).ok())
+        .ok_or_else(|| "Invalid cursor".to_string())
+}
+
+/// SQLite 中的记录时间既有 RFC3339 也有列默认值写入的 `YYYY-MM-DD HH:MM:SS`，
+/// 统一经 julianday 比较
+fn event_time_expr(backend: DbBackend, column: &str) -> String {
+    match backend {
+        DbBackend::Sqlite => format!("julianday({})", column),
+        _ => column.to_string(),
+    }
+}
+
+fn event_time_cmp(backend: DbBackend, op: &str) -> String {
+    format!(
+        "{} {} {}",
+        event_time_expr(backend, "e.event_time"),
+        op,
+        event_time_expr(backend, "?")
+    )
+}
+
+/// 转义 LIKE 通配符，配合 `ESCAPE '\'` 使用
+fn escape_like(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for ch in term.chars() {
+        if matches!(ch, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+fn build_event_filters(
+    backend: DbBackend,
+    params: &EventPageParams,
+) -> Result<(Vec<String>, Vec<sea_orm::Value>), String> {
+    let mut conditions = Vec::new();
+    let mut values: Vec<sea_orm::Value> = Vec::new();
+
+    // 按学生 ID 与姓名取并集；姓名先解析为当前学生，未关联学生的旧记录再按快照姓名匹配
+    let names = params
+        .student_names
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    let mut student_conditions = Vec::new();
+    if !params.student_ids.is_empty() {
+        student_conditions.push(format!(
+            "e.student_id IN ({})",
+            vec!["?"; params.student_ids.len()].join(", ")
+        ));
+        values.extend(params.student_ids.iter().map(|id| (*id).into()));
+    }
+    if !names.is_empty() {
+        let placeholders = vec!["?"; names.len()].join(", ");
+        student_conditions.push(format!(
+            "e.student_id IN (SELECT s.id FROM students s WHERE s.name IN ({}))",
+            placeholders
+        ));
+        student_conditions.push(format!(
+            "(e.student_id IS NULL AND e.student_name IN ({}))",
+            placeholders
+        ));
+        values.extend(names.iter().map(|name| name.to_string().into()));
+        values.extend(names.iter().map(|name| name.to_string().into()));
+    }
+    if !student_conditions.is_empty() {
+        conditions.push(format!("({})", student_conditions.join(" OR ")));
+    }
+
+    if let Some(content) = params
+        .reason_content
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        conditions.push("e.reason_content LIKE ? ESCAPE '\\'".to_string());
+        values.push(format!("%{}%", escape_like(content)).into());
+    }
+
+    // 按分类 ID 匹配，未关联分类的旧理由再按分类名快照匹配
+    let category_name = params
+        .reason_category
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    match (params.reason_category_id, category_name) {
+        (Some(category_id), _) => {
+            conditions.push(
+                "e.reason_content IN (SELECT r.content FROM reasons r WHERE r.category_id = ? \
+                 OR (r.category_id IS NULL AND r.category = \
+                 (SELECT c.name FROM reason_categories c WHERE c.id = ?)))"
+                    .to_string(),
+            );
+            values.push(category_id.into());
+            values.push(category_id.into());
+        }
+        (None, Some(category)) => {
+            conditions.push(
+                "e.reason_content IN (SELECT r.content FROM reasons r WHERE r.category_id IN \
+                 (SELECT c.id FROM reason_categories c WHERE c.name = ?) \
+                 OR (r.category_id IS NULL AND r.category = ?))"
+                    .to_string(),
+            );
+            values.push(category.to_string().into());
+            values.push(category.to_string().into());
+        }
+        (None, None) => {}
+    }
+
+    match params.delta_sign {
+        Some(DeltaSign::Positive) => conditions.push("e.delta > 0".to_string()),
+        Some(DeltaSign::Negative) => conditions.push("e.delta < 0".to_string()),
+        Some(DeltaSign::Zero) => conditions.push("e.delta = 0".to_string()),
+        None => {}
+    }
+
+    if let Some(start_time) = params
+        .start_time
+        .as_deref()
+        .filter(|v| !v.trim().is_empty())
+    {
+        conditions.push(event_time_cmp(backend, ">="));
+        values.push(format_utc(parse_time_bound(start_time, false)?).into());
+    }
+    if let Some(end_time) = params.end_time.as_deref().filter(|v| !v.trim().is_empty()) {
+        conditions.push(event_time_cmp(backend, "<"));
+        values.push(format_utc(parse_time_bound(end_time, true)?).into());
+    }
+
+    match (params.settlement_id, params.settlement_scope) {
+        (Some(settlement_id), _) => {
+            conditions.push("e.settlement_id = ?".to_string());
+            values.push(settlement_id.into());
+        }
+        (None, SettlementScope::Unsettled) => {
+            conditions.push("e.settlement_id IS NULL".to_string())
+        }
+        (None, SettlementScope::Settled) => {
+            conditions.push("e.settlement_id IS NOT NULL".to_string())
+        }
+        (None, SettlementScope::Any) => {}
+    }
+
+    if let Some(auto_score) = params.auto_score {
+        conditions.push(if auto_score {
+            "e.reason_content LIKE ? ESCAPE '\\'".to_string()
+        } else {
+            "e.reason_content NOT LIKE ? ESCAPE '\\'".to_string()
+        });
+        values.push(format!("{}#%", escape_like(AUTO_SCORE_REASON_PREFIX)).into());
+    }
+
+    Ok((conditions, values))
+}
+
+fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    }
+}
+
+pub async fn query_events_page(
+    conn: &DatabaseConnection,
+    params: &EventPageParams,
+) -> Result<EventPage, String> {
+    let backend = conn.get_database_backend();
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+    let (conditions, values) = build_event_filters(backend, params)?;
+
+    let count_sql = format!(
+        "SELECT COUNT(*) AS total FROM score_events e{}",
+        where_clause(&conditions)
+    );
+    let total = conn
+        .query_one(bind_statement(backend, &count_sql, values.clone()))
+        .await
+        .map_err(|e| format!("Failed to count events: {}", e))?
+        .map(|row| row.try_get::<i64>("", "total"))
+        .transpose()
+        .map_err(|e| e.to_string())?
+        .unwrap_or(0);
+
+    let mut page_conditions = conditions;
+    let mut page_values = values;
+    if let Some(cursor) = params.cursor.as_deref().filter(|v| !v.trim().is_empty()) {
+        let cursor = decode_event_cursor(cursor)?;
+        let time = event_time_expr(backend, "e.event_time");
+        let bound = event_time_expr(backend, "?");
+        page_conditions.push(format!(
+            "({time} < {bound} OR ({time} = {bound} AND e.id < ?))",
+            time = time,
+            bound = bound
+        ));
+        page_values.push(cursor.event_time.clone().into());
+        page_values.push(cursor.event_time.into());
+        page_values.push(cursor.id.into());
+    }
+    // 多取一条用于判断是否还有下一页
+    page_values.push((limit + 1).into());
+
+    let page_sql = format!(
+        "SELECT e.id AS id, e.uuid AS uuid, e.student_id AS student_id, \
+         e.student_name AS student_name, e.reason_content AS reason_content, \
+         e.delta AS delta, e.val_prev AS val_prev, e.val_curr AS val_curr, \
+         e.event_time AS event_time, e.settlement_id AS settlement_id \
+         FROM score_events e{} ORDER BY {} DESC, e.id DESC LIMIT ?",
+        where_clause(&page_conditions),
+        event_time_expr(backend, "e.event_time")
+    );
+    let mut items = conn
+        .query_all(bind_statement(backend, &page_sql, page_values))
+        .await
+        .map_err(|e| format!("Failed to query events: {}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok(ScoreEvent {
+                id: row.try_get("", "id")?,
+                uuid: row.try_get("", "uuid")?,
+                student_id: row.try_get("", "student_id")?,
+                student_name: row.try_get("", "student_name")?,
+                reason_content: row.try_get("", "reason_content")?,
+                delta: row.try_get("", "delta")?,
+                val_prev: row.try_get("", "val_prev")?,
+                val_curr: row.try_get("", "val_curr")?,
+                event_time: row.try_get("", "event_time")?,
+                settlement_id: row.try_get("", "settlement_id")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sea_orm::DbErr>>()
+        .map_err(|e| format!("Failed to read events: {}", e))?;
+
+    let next_cursor = if items.len() > limit as usize {
+        items.truncate(limit as usize);
+        items.last().map(encode_event_cursor)
+    } else {
+        None
+    };
+
+    Ok(EventPage {
+        items,
+        total,
+        next_cursor,
+    })
+}
+
+#[tauri::command]
+pub async fn event_query_page(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    params: Option<EventPageParams>,
+) -> Result<IpcResponse<EventPage>, String> {
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    match query_events_page(&conn, &params.unwrap_or_default()).await {
+        Ok(page) => Ok(IpcResponse::success(page)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
 #[tauri::command]
 pub async fn event_create(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
     data: CreateScoreEvent,
 ) -> Result<IpcResponse<i32>, String> {
-    if !check_points_permission(&state) {
+    if !check_points_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: points required"));
     }
 
     let student_name = data.student_name.trim();
-    if student_name.is_empty() {
+    if data.student_id.is_none() && student_name.is_empty() {
         return Ok(IpcResponse::error("Student name cannot be empty"));
     }
 
     let local_write_lock = { state.read().local_write_lock.clone() };
     let _write_guard = local_write_lock.lock().await;
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-
-    if let Some(conn) = db_guard.as_ref() {
-        let student = students::Entity::find()
-            .filter(students::Column::Name.eq(student_name))
-            .one(conn)
-            .await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
 
-        match student {
-            Ok(Some(student)) => {
-                let val_prev = student.score;
-                let val_curr = val_prev + data.delta;
-                let reward_points_next = student.reward_points + data.delta;
-                let now = chrono::Utc::now()
-                    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-                    .to_string();
-                let uuid = data
-                    .operation_id
-                    .as_deref()
-                    .and_then(|value| Uuid::parse_str(value.trim()).ok())
-                    .map(|value| value.to_string())
-                    .unwrap_or_else(|| Uuid::new_v4().to_string());
-
-                let txn = conn.begin().await.map_err(|e| e.to_string())?;
-
-                let new_event = score_events::ActiveModel {
-                    id: sea_orm::ActiveValue::NotSet,
-                    uuid: Set(uuid),
-                    student_name: Set(student_name.to_string()),
-                    reason_content: Set(data.reason_content.trim().to_string()),
-                    delta: Set(data.delta),
-                    val_prev: Set(val_prev),
-                    val_curr: Set(val_curr),
-                    event_time: Set(now.clone()),
-                    settlement_id: Set(None),
-                };
-
-                let inserted = new_event.insert(&txn).await.map_err(|e| e.to_string())?;
-
-                let mut active: students::ActiveModel = student.into();
-                active.score = Set(val_curr);
-                active.reward_points = Set(reward_points_next);
-                active.updated_at = Set(now);
-                active.update(&txn).await.map_err(|e| e.to_string())?;
-
-                txn.commit().await.map_err(|e| e.to_string())?;
-                {
-                    let state_guard = state.read();
-                    let logger = state_guard.logger.read();
-                    logger.error_with_meta(
-                        "event_create:committed",
-                        json!({
-                            "student_name": student_name,
-                            "delta": data.delta,
-                            "val_prev": val_prev,
-                            "val_curr": val_curr,
-                        }),
-                    );
-                }
-                realtime_dual_write_sync_if_legacy(state.inner()).await?;
-                {
-                    let state_guard = state.read();
-                    let logger = state_guard.logger.read();
-                    logger.error_with_meta(
-                        "event_create:sync_done",
-                        json!({
-                            "student_name": student_name,
-                            "delta": data.delta,
-                            "val_curr": val_curr,
-                        }),
-                    );
-                }
-                Ok(IpcResponse::success(inserted.id))
-            }
-            Ok(None) => Ok(IpcResponse::error("Student not found")),
-            Err(e) => Ok(IpcResponse::error(&format!("Database error: {}", e))),
-        }
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
+    let inserted = match score::add_score(
+        &conn,
+        data.student_id,
+        student_name,
+        data.delta,
+        &data.reason_content,
+        data.operation_id.as_deref(),
+    )
+    .await
+    {
+        Ok(inserted) => inserted,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
+    {
+        let state_guard = state.read();
+        let logger = state_guard.logger.read();
+        logger.error_with_meta(
+            "event_create:committed",
+            json!({
+                "student_name": inserted.student_name,
+                "delta": inserted.delta,
+                "val_prev": inserted.val_prev,
+                "val_curr": inserted.val_curr,
+            }),
+        );
     }
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    state.read().event_bus.publish(
+        EventSource::Desktop,
+        BusPayload::ScoreEventsCreated {
+            events: vec![inserted.clone()],
+        },
+    );
+    {
+        let state_guard = state.read();
+        let logger = state_guard.logger.read();
+        logger.error_with_meta(
+            "event_create:sync_done",
+            json!({
+                "student_name": inserted.student_name,
+                "delta": inserted.delta,
+                "val_curr": inserted.val_curr,
+            }),
+        );
+    }
+    Ok(IpcResponse::success(inserted.id))
 }
 
 #[tauri::command]
-pub async fn event_delete(
+pub async fn event_create_batch(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
-    uuid: String,
-) -> Result<IpcResponse<()>, String> {
-    if !check_points_permission(&state) {
+    data: CreateScoreEventBatch,
+) -> Result<IpcResponse<EventBatchResult>, String> {
+    if !check_points_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: points required"));
     }
 
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
 
-    if let Some(conn) = db_guard.as_ref() {
-        let event = score_events::Entity::find()
-            .filter(score_events::Column::Uuid.eq(uuid.trim()))
-            .one(conn)
-            .await;
+    let created =
+        match score::add_score_batch(&conn, &data.selector, data.delta, &data.reason_content).await
+        {
+            Ok(created) => created,
+            Err(e) => return Ok(IpcResponse::error(&e)),
+        };
+    {
+        let state_guard = state.read();
+        state_guard.logger.read().info_with_meta(
+            "event_create_batch:committed",
+            json!({
+                "count": created.len(),
+                "delta": data.delta,
+                "reason_content": data.reason_content.trim(),
+            }),
+        );
+    }
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    state.read().event_bus.publish(
+        EventSource::Desktop,
+        BusPayload::ScoreEventsCreated {
+            events: created.clone(),
+        },
+    );
 
-        match event {
-            Ok(Some(event)) => {
-                if event.settlement_id.is_some() {
-                    return Ok(IpcResponse::error("该记录已结算，无法撤销"));
-                }
+    Ok(IpcResponse::success(EventBatchResult::from(created)))
+}
 
-                let txn = conn.begin().await.map_err(|e| e.to_string())?;
-
-                let student = students::Entity::find()
-                    .filter(students::Column::Name.eq(&event.student_name))
-                    .one(&txn)
-                    .await
-                    .map_err(|e| e.to_string())?;
-
-                if let Some(student) = student {
-                    let new_score = student.score - event.delta;
-                    let new_reward_points = student.reward_points - event.delta;
-                    let now = chrono::Utc::now()
-                        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-                        .to_string();
-
-                    let mut active: students::ActiveModel = student.into();
-                    active.score = Set(new_score);
-                    active.reward_points = Set(new_reward_points);
-                    active.updated_at = Set(now);
-                    active.update(&txn).await.map_err(|e| e.to_string())?;
-                }
+#[tauri::command]
+pub async fn event_delete(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+    uuid: String,
+) -> Result<IpcResponse<()>, String> {
+    if !check_points_permission(&state, &webview) {
+        return Ok(IpcResponse::error("Permission denied: points required"));
+    }
 
-                score_events::Entity::delete_by_id(event.id)
-                    .exec(&txn)
-                    .await
-                    .map_err(|e| e.to_string())?;
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
 
-                txn.commit().await.map_err(|e| e.to_string())?;
-                realtime_dual_write_sync_if_legacy(state.inner()).await?;
-                Ok(IpcResponse::success_empty())
-            }
-            Ok(None) => Ok(IpcResponse::error("Event not found")),
-            Err(e) => Ok(IpcResponse::error(&format!("Database error: {}", e))),
-        }
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
-    }
+    let event = match score::revert_score_event(&conn, &uuid).await {
+        Ok(event) => event,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    state.read().event_bus.publish(
+        EventSource::Desktop,
+        BusPayload::ScoreEventsDeleted {
+            events: vec![event],
+        },
+    );
+    Ok(IpcResponse::success_empty())
 }
 
 #[tauri::command]
@@ -299,13 +750,28 @@ pub async fn event_query_by_student(
     let limit = params.limit.unwrap_or(50);
     let student_name = params.student_name.trim();
 
-    if student_name.is_empty() {
+    if params.student_id.is_none() && student_name.is_empty() {
         return Ok(IpcResponse::success(vec![]));
     }
 
     if let Some(conn) = db_guard.as_ref() {
+        let student = match students::find_by_reference(conn, params.student_id, student_name).await
+        {
+            Ok(student) => student,
+            Err(e) => {
+                return Ok(IpcResponse::error(&format!(
+                    "Failed to query events: {}",
+                    e
+                )))
+            }
+        };
+        // 找不到学生时按快照姓名查询，兼容已删除学生的历史记录
+        let student_filter = match student {
+            Some(student) => score_events::Column::StudentId.eq(student.id),
+            None => score_events::Column::StudentName.eq(student_name),
+        };
         let mut query = score_events::Entity::find()
-            .filter(score_events::Column::StudentName.eq(student_name))
+            .filter(student_filter)
             .filter(score_events::Column::SettlementId.is_null())
             .order_by_desc(score_events::Column::EventTime)
             .limit(limit as u64);
@@ -323,6 +789,7 @@ pub async fn event_query_by_student(
                     .map(|e| ScoreEvent {
                         id: e.id,
                         uuid: e.uuid,
+                        student_id: e.student_id,
                         student_name: e.student_name,
                         reason_content: e.reason_content,
                         delta: e.delta,
@@ -344,99 +811,624 @@ pub async fn event_query_by_student(
     }
 }
 
+fn format_utc(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+fn local_day_start(date: NaiveDate) -> DateTime<Utc> {
+    let naive = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
+}
+
+/// 解析时间边界：RFC3339 原样使用，不带时区的时间和日期按本地时区理解；
+/// 作为结束时间的纯日期包含当天，即取次日零点
+fn parse_time_bound(value: &str, is_end: bool) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+            return Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|time| time.with_timezone(&Utc))
+                .ok_or_else(|| format!("Invalid local time: {}", value));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if is_end {
+            date.succ_opt().unwrap_or(date)
+        } else {
+            date
+        };
+        return Ok(local_day_start(date));
+    }
+    Err(format!("Invalid time: {}", value))
+}
+
+/// 解析 range 预设（today / week / month）与可选的自定义起止时间
+fn resolve_time_range(
+    range: &str,
+    start: Option<&str>,
+    end: Option<&str>,
+) -> Result<(DateTime<Utc>, Option<DateTime<Utc>>), String> {
+    let today = Local::now().date_naive();
+    let preset_start = match range {
+        "week" => today - Duration::days(today.weekday().num_days_from_monday() as i64),
+        "month" => today.with_day(1).unwrap_or(today),
+        _ => today,
+    };
+
+    let start = match start.filter(|v| !v.trim().is_empty()) {
+        Some(value) => parse_time_bound(value, false)?,
+        None => local_day_start(preset_start),
+    };
+    let end = match end.filter(|v| !v.trim().is_empty()) {
+        Some(value) => Some(parse_time_bound(value, true)?),
+        None => None,
+    };
+    if let Some(end) = end {
+        if end <= start {
+            return Err("End time must be after start time".to_string());
+        }
+    }
+    Ok((start, end))
+}
+
+fn aggregate_leaderboard_groups(
+    rows: &[LeaderboardRow],
+    keys_by_student: &HashMap<i32, Vec<Option<String>>>,
+) -> Vec<LeaderboardGroupRow> {
+    let mut groups: BTreeMap<Option<String>, (usize, i64, i64)> = BTreeMap::new();
+    for row in rows {
+        let keys = keys_by_student
+            .get(&row.id)
+            .cloned()
+            .unwrap_or_else(|| vec![None]);
+        for key in keys {
+            let entry = groups.entry(key).or_insert((0, 0, 0));
+            entry.0 += 1;
+            entry.1 += row.score as i64;
+            entry.2 += row.range_change;
+        }
+    }
+
+    let mut result = groups
+        .into_iter()
+        .map(
+            |(name, (student_count, total_score, total_range_change))| LeaderboardGroupRow {
+                name,
+                student_count,
+                total_score,
+                total_range_change,
+                average_score: total_score as f64 / student_count as f64,
+                average_range_change: total_range_change as f64 / student_count as f64,
+            },
+        )
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| {
+        b.average_score
+            .total_cmp(&a.average_score)
+            .then(b.total_range_change.cmp(&a.total_range_change))
+            .then(a.name.cmp(&b.name))
+    });
+    result
+}
+
+pub async fn query_leaderboard(
+    conn: &DatabaseConnection,
+    params: &LeaderboardParams,
+) -> Result<LeaderboardResult, String> {
+    let (start, end) = resolve_time_range(
+        &params.range,
+        params.start.as_deref(),
+        params.end.as_deref(),
+    )?;
+    let start_time = format_utc(start);
+    let end_time = end.map(format_utc);
+    let backend = conn.get_database_backend();
+
+    let mut join_conditions = vec![
+        "e.student_id = s.id".to_string(),
+        "e.settlement_id IS NULL".to_string(),
+        event_time_cmp(backend, ">="),
+    ];
+    let mut values: Vec<sea_orm::Value> = vec![start_time.clone().into()];
+    if let Some(end_time) = &end_time {
+        join_conditions.push(event_time_cmp(backend, "<"));
+        values.push(end_time.clone().into());
+    }
+
+    let sql = format!(
+        "SELECT s.id AS id, s.name AS name, s.group_name AS group_name, s.score AS score, \
+         COALESCE(SUM(e.delta), 0) AS range_change \
+         FROM students s LEFT JOIN score_events e ON {} \
+         GROUP BY s.id, s.name, s.group_name, s.score \
+         ORDER BY s.score DESC, range_change DESC, s.name ASC",
+        join_conditions.join(" AND ")
+    );
+
+    let rows = conn
+        .query_all(bind_statement(backend, &sql, values))
+        .await
+        .map_err(|e| format!("Failed to query leaderboard: {}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok(LeaderboardRow {
+                id: row.try_get("", "id")?,
+                name: row.try_get("", "name")?,
+                group_name: row.try_get("", "group_name")?,
+                score: row.try_get::<Option<i32>>("", "score")?.unwrap_or(0),
+                range_change: row.try_get("", "range_change")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sea_orm::DbErr>>()
+        .map_err(|e| format!("Failed to read leaderboard: {}", e))?;
+
+    let groups = match params.group_by {
+        None => None,
+        Some(LeaderboardGroupBy::Group) => {
+            let keys = rows
+                .iter()
+                .map(|row| {
+                    let group = row
+                        .group_name
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(ToString::to_string);
+                    (row.id, vec![group])
+                })
+                .collect::<HashMap<_, _>>();
+            Some(aggregate_leaderboard_groups(&rows, &keys))
+        }
+        Some(LeaderboardGroupBy::Tag) => {
+            let tag_rows = conn
+                .query_all(Statement::from_string(
+                    backend,
+                    "SELECT st.student_id AS student_id, t.name AS tag_name \
+                     FROM student_tags st JOIN tags t ON t.id = st.tag_id",
+                ))
+                .await
+                .map_err(|e| format!("Failed to query student tags: {}", e))?;
+            let mut keys: HashMap<i32, Vec<Option<String>>> = HashMap::new();
+            for row in tag_rows {
+                let student_id: i32 = row.try_get("", "student_id").map_err(|e| e.to_string())?;
+                let tag_name: String = row.try_get("", "tag_name").map_err(|e| e.to_string())?;
+                keys.entry(student_id).or_default().push(Some(tag_name));
+            }
+            Some(aggregate_leaderboard_groups(&rows, &keys))
+        }
+    };
+
+    Ok(LeaderboardResult {
+        start_time,
+        end_time,
+        rows,
+        groups,
+    })
+}
+
 #[tauri::command]
 pub async fn leaderboard_query(
     state: State<'_, Arc<RwLock<AppState>>>,
     params: LeaderboardParams,
 ) -> Result<IpcResponse<LeaderboardResult>, String> {
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
 
-    if let Some(conn) = db_guard.as_ref() {
-        let now = Utc::now();
-        let start = match params.range.as_str() {
-            "today" => {
-                let mut s = now;
-                s = s.with_hour(0).unwrap_or(s);
-                s = s.with_minute(0).unwrap_or(s);
-                s = s.with_second(0).unwrap_or(s);
-                s = s.with_nanosecond(0).unwrap_or(s);
-                s
-            }
-            "week" => {
-                let mut s = now;
-                let day = s.weekday().num_days_from_monday() as i64;
-                s = s - Duration::days(day);
-                s = s.with_hour(0).unwrap_or(s);
-                s = s.with_minute(0).unwrap_or(s);
-                s = s.with_second(0).unwrap_or(s);
-                s = s.with_nanosecond(0).unwrap_or(s);
-                s
-            }
-            "month" => {
-                let s = now.with_day0(0).unwrap_or(now);
-                let mut s = s.with_hour(0).unwrap_or(s);
-                s = s.with_minute(0).unwrap_or(s);
-                s = s.with_second(0).unwrap_or(s);
-                s = s.with_nanosecond(0).unwrap_or(s);
-                s
+    match query_leaderboard(&conn, &params).await {
+        Ok(result) => Ok(IpcResponse::success(result)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
+pub async fn query_reason_stats(
+    conn: &DatabaseConnection,
+    params: &ReasonStatsParams,
+) -> Result<ReasonStatsResult, String> {
+    let backend = conn.get_database_backend();
+    let mut conditions = Vec::new();
+    let mut values: Vec<sea_orm::Value> = Vec::new();
+
+    let (start_time, end_time) = match params.settlement_id {
+        Some(settlement_id) => {
+            conditions.push("e.settlement_id = ?".to_string());
+            values.push(settlement_id.into());
+            (None, None)
+        }
+        None => {
+            let (start, end) = resolve_time_range(
+                &params.range,
+                params.start.as_deref(),
+                params.end.as_deref(),
+            )?;
+            let start_time = format_utc(start);
+            let end_time = end.map(format_utc);
+            conditions.push(event_time_cmp(backend, ">="));
+            values.push(start_time.clone().into());
+            if let Some(end_time) = &end_time {
+                conditions.push(event_time_cmp(backend, "<"));
+                values.push(end_time.clone().into());
             }
-            _ => {
-                let mut s = now;
-                s = s.with_hour(0).unwrap_or(s);
-                s = s.with_minute(0).unwrap_or(s);
-                s = s.with_second(0).unwrap_or(s);
-                s = s.with_nanosecond(0).unwrap_or(s);
-                s
+            (Some(start_time), end_time)
+        }
+    };
+
+    if let Some(group_name) = params
+        .group_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        conditions.push(
+            "e.student_id IN (SELECT s.id FROM students s WHERE s.group_name = ?)".to_string(),
+        );
+        values.push(group_name.to_string().into());
+    }
+    if let Some(tag_id) = params.tag_id {
+        conditions.push(
+            "e.student_id IN (SELECT st.student_id FROM student_tags st WHERE st.tag_id = ?)"
+                .to_string(),
+        );
+        values.push(tag_id.into());
+    }
+
+    // 学生被删除后 student_id 为空，按姓名计入不同学生数
+    let sql = format!(
+        "SELECT e.reason_content AS reason_content, COUNT(*) AS count, \
+         COALESCE(SUM(e.delta), 0) AS total_delta, \
+         COUNT(DISTINCT COALESCE(CAST(e.student_id AS TEXT), e.student_name)) AS distinct_students, \
+         MAX(e.event_time) AS last_used_at \
+         FROM score_events e{} GROUP BY e.reason_content \
+         ORDER BY count DESC, reason_content ASC",
+        where_clause(&conditions)
+    );
+    let used = conn
+        .query_all(bind_statement(backend, &sql, values))
+        .await
+        .map_err(|e| format!("Failed to query reason stats: {}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok((
+                row.try_get::<String>("", "reason_content")?,
+                row.try_get::<i64>("", "count")?,
+                row.try_get::<i64>("", "total_delta")?,
+                row.try_get::<i64>("", "distinct_students")?,
+                row.try_get::<Option<String>>("", "last_used_at")?,
+            ))
+        })
+        .collect::<Result<Vec<_>, sea_orm::DbErr>>()
+        .map_err(|e| format!("Failed to read reason stats: {}", e))?;
+
+    let reason_rows = reasons::Entity::find()
+        .order_by_asc(reasons::Column::Id)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let by_content = reason_rows
+        .iter()
+        .map(|r| (r.content.as_str(), r))
+        .collect::<HashMap<_, _>>();
+    let category_names = reason_categories::Entity::find()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|c| (c.id, c.name))
+        .collect::<HashMap<_, _>>();
+
+    let mut rows = used
+        .iter()
+        .map(
+            |(content, count, total_delta, distinct_students, last_used_at)| {
+                let reason = by_content.get(content.as_str());
+                ReasonStatRow {
+                    reason_id: reason.map(|r| r.id),
+                    reason_content: content.clone(),
+                    category: reason.and_then(|r| reason_category_name(r, &category_names)),
+                    count: *count,
+                    total_delta: *total_delta,
+                    distinct_students: *distinct_students,
+                    last_used_at: last_used_at.clone(),
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    if let Some(limit) = params.limit.filter(|limit| *limit > 0) {
+        rows.truncate(limit as usize);
+    }
+
+    let used_contents = used
+        .iter()
+        .map(|(content, ..)| content.as_str())
+        .collect::<std::collections::HashSet<_>>();
+    let unused_reasons = reason_rows
+        .iter()
+        .filter(|r| r.is_archived == 0 && !used_contents.contains(r.content.as_str()))
+        .collect::<Vec<_>>();
+
+    let mut last_used: HashMap<String, String> = HashMap::new();
+    if !unused_reasons.is_empty() {
+        let placeholders = vec!["?"; unused_reasons.len()].join(", ");
+        let sql = format!(
+            "SELECT reason_content, MAX(event_time) AS last_used_at FROM score_events \
+             WHERE reason_content IN ({}) GROUP BY reason_content",
+            placeholders
+        );
+        let values = unused_reasons
+            .iter()
+            .map(|r| r.content.clone().into())
+            .collect::<Vec<sea_orm::Value>>();
+        for row in conn
+            .query_all(bind_statement(backend, &sql, values))
+            .await
+            .map_err(|e| format!("Failed to query reason stats: {}", e))?
+        {
+            let content: String = row
+                .try_get("", "reason_content")
+                .map_err(|e| e.to_string())?;
+            let time: Option<String> =
+                row.try_get("", "last_used_at").map_err(|e| e.to_string())?;
+            if let Some(time) = time {
+                last_used.insert(content, time);
             }
+        }
+    }
+    let unused = unused_reasons
+        .into_iter()
+        .map(|r| ReasonStatRow {
+            reason_id: Some(r.id),
+            reason_content: r.content.clone(),
+            category: reason_category_name(r, &category_names),
+            count: 0,
+            total_delta: 0,
+            distinct_students: 0,
+            last_used_at: last_used.remove(&r.content),
+        })
+        .collect();
+
+    Ok(ReasonStatsResult {
+        start_time,
+        end_time,
+        settlement_id: params.settlement_id,
+        rows,
+        unused,
+    })
+}
+
+/// 理由所属分类名：按 category_id 关联分类表，仅 category_id 为空的旧理由沿用名称快照
+fn reason_category_name(
+    reason: &reasons::Model,
+    category_names: &HashMap<i32, String>,
+) -> Option<String> {
+    match reason.category_id {
+        Some(id) => category_names.get(&id).cloned(),
+        None => Some(reason.category.clone()),
+    }
+}
+
+#[tauri::command]
+pub async fn reason_stats_query(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    params: Option<ReasonStatsParams>,
+) -> Result<IpcResponse<ReasonStatsResult>, String> {
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    match query_reason_stats(&conn, &params.unwrap_or_default()).await {
+        Ok(result) => Ok(IpcResponse::success(result)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        aggregate_leaderboard_groups, build_event_filters, escape_like, format_utc,
+        parse_time_bound, reason_category_name, resolve_time_range, EventPageParams,
+        LeaderboardRow,
+    };
+    use crate::db::entities::reasons;
+    use chrono::{DateTime, Local, TimeZone, Utc};
+    use sea_orm::DbBackend;
+    use std::collections::HashMap;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Local
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn row(id: i32, score: i32, range_change: i64) -> LeaderboardRow {
+        LeaderboardRow {
+            id,
+            name: format!("s{}", id),
+            group_name: None,
+            score,
+            range_change,
+        }
+    }
+
+    #[test]
+    fn date_only_bounds_use_local_day_edges() {
+        assert_eq!(
+            parse_time_bound("2024-03-10", false).unwrap(),
+            local(2024, 3, 10, 0, 0)
+        );
+        // 结束日期包含当天
+        assert_eq!(
+            parse_time_bound("2024-03-10", true).unwrap(),
+            local(2024, 3, 11, 0, 0)
+        );
+        assert_eq!(
+            parse_time_bound("2024-12-31", true).unwrap(),
+            local(2025, 1, 1, 0, 0)
+        );
+    }
+
+    #[test]
+    fn bounds_with_timezone_are_kept_exact() {
+        let expected = Utc.with_ymd_and_hms(2024, 3, 10, 4, 30, 0).unwrap();
+        assert_eq!(
+            parse_time_bound("2024-03-10T12:30:00+08:00", false).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_time_bound("2024-03-10T04:30:00Z", true).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn bounds_without_timezone_use_local_time() {
+        for value in [
+            "2024-03-10T08:15:00",
+            "2024-03-10 08:15:00",
+            "2024-03-10T08:15",
+        ] {
+            assert_eq!(
+                parse_time_bound(value, false).unwrap(),
+                local(2024, 3, 10, 8, 15)
+            );
+            // 带时刻的结束时间不再顺延
+            assert_eq!(
+                parse_time_bound(value, true).unwrap(),
+                local(2024, 3, 10, 8, 15)
+            );
+        }
+        assert!(parse_time_bound("10/03/2024", false).is_err());
+    }
+
+    #[test]
+    fn resolve_time_range_covers_whole_end_day() {
+        let (start, end) =
+            resolve_time_range("today", Some("2024-03-10"), Some("2024-03-10")).unwrap();
+        assert_eq!(start, local(2024, 3, 10, 0, 0));
+        assert_eq!(end, Some(local(2024, 3, 11, 0, 0)));
+
+        let (start, end) = resolve_time_range("month", Some("2024-03-10"), Some(" ")).unwrap();
+        assert_eq!(start, local(2024, 3, 10, 0, 0));
+        assert_eq!(end, None);
+
+        assert!(resolve_time_range("", Some("2024-03-10"), Some("2024-03-09")).is_err());
+        assert!(resolve_time_range(
+            "",
+            Some("2024-03-10T08:00:00Z"),
+            Some("2024-03-10T08:00:00Z")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn students_with_multiple_tags_count_in_each_group() {
+        let rows = vec![row(1, 10, 2), row(2, 20, -4), row(3, 5, 0)];
+        let keys = HashMap::from([
+            (1, vec![Some("a".to_string()), Some("b".to_string())]),
+            (2, vec![Some("b".to_string())]),
+        ]);
+        let groups = aggregate_leaderboard_groups(&rows, &keys);
+
+        let find = |name: Option<&str>| {
+            groups
+                .iter()
+                .find(|group| group.name.as_deref() == name)
+                .unwrap()
         };
+        let a = find(Some("a"));
+        assert_eq!(
+            (a.student_count, a.total_score, a.total_range_change),
+            (1, 10, 2)
+        );
+        let b = find(Some("b"));
+        assert_eq!(
+            (b.student_count, b.total_score, b.total_range_change),
+            (2, 30, -2)
+        );
+        assert_eq!(b.average_score, 15.0);
+        assert_eq!(b.average_range_change, -1.0);
+        // 没有标签的学生归入 None
+        let none = find(None);
+        assert_eq!((none.student_count, none.total_score), (1, 5));
 
-        let start_time = start.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let order = groups
+            .iter()
+            .map(|group| group.name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![Some("b"), Some("a"), None]);
+    }
 
-        let student_results = students::Entity::find()
-            .order_by_desc(students::Column::Score)
-            .all(conn)
-            .await;
+    #[test]
+    fn like_terms_escape_wildcards() {
+        assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+        assert_eq!(escape_like("普通"), "普通");
+    }
 
-        match student_results {
-            Ok(student_models) => {
-                let mut rows: Vec<LeaderboardRow> = student_models
-                    .into_iter()
-                    .map(|s| LeaderboardRow {
-                        id: s.id,
-                        name: s.name,
-                        score: s.score,
-                        range_change: 0,
-                    })
-                    .collect();
+    #[test]
+    fn event_time_filters_are_normalized() {
+        let params = EventPageParams {
+            start_time: Some("2024-03-10T12:00:00+08:00".to_string()),
+            end_time: Some("2024-03-10".to_string()),
+            ..Default::default()
+        };
+        let (conditions, values) = build_event_filters(DbBackend::Sqlite, &params).unwrap();
+        assert!(conditions.contains(&"julianday(e.event_time) >= julianday(?)".to_string()));
+        assert!(conditions.contains(&"julianday(e.event_time) < julianday(?)".to_string()));
+        assert_eq!(values[0], "2024-03-10T04:00:00.000Z".into());
+        assert_eq!(values[1], format_utc(local(2024, 3, 11, 0, 0)).into());
 
-                for row in &mut rows {
-                    let events = score_events::Entity::find()
-                        .filter(score_events::Column::StudentName.eq(&row.name))
-                        .filter(score_events::Column::SettlementId.is_null())
-                        .filter(score_events::Column::EventTime.gte(&start_time))
-                        .all(conn)
-                        .await
-                        .unwrap_or_default();
+        let (conditions, _) = build_event_filters(DbBackend::Postgres, &params).unwrap();
+        assert!(conditions.contains(&"e.event_time >= ?".to_string()));
 
-                    row.range_change = events.iter().map(|e| e.delta as i64).sum();
-                }
+        let invalid = EventPageParams {
+            start_time: Some("yesterday".to_string()),
+            ..Default::default()
+        };
+        assert!(build_event_filters(DbBackend::Sqlite, &invalid).is_err());
+    }
 
-                rows.sort_by(|a, b| {
-                    b.score
-                        .cmp(&a.score)
-                        .then(b.range_change.cmp(&a.range_change))
-                        .then(a.name.cmp(&b.name))
-                });
+    #[test]
+    fn reason_category_prefers_category_table() {
+        let reason = |category: &str, category_id: Option<i32>| reasons::Model {
+            id: 1,
+            content: "上课发言".to_string(),
+            category: category.to_string(),
+            category_id,
+            delta: 1,
+            is_system: 0,
+            sort_order: 0,
+            is_archived: 0,
+            updated_at: String::new(),
+        };
+        let names = HashMap::from([(7, "课堂表现".to_string())]);
 
-                Ok(IpcResponse::success(LeaderboardResult { start_time, rows }))
-            }
-            Err(e) => Ok(IpcResponse::error(&format!(
-                "Failed to query leaderboard: {}",
-                e
-            ))),
+        let cases = [
+            (
+                "renamed category",
+                reason("旧名称", Some(7)),
+                Some("课堂表现"),
+            ),
+            ("legacy reason", reason("其他", None), Some("其他")),
+            ("missing category row", reason("旧名称", Some(9)), None),
+        ];
+        for (name, reason, expected) in cases {
+            assert_eq!(
+                reason_category_name(&reason, &names).as_deref(),
+                expected,
+                "case: {}",
+                name
+            );
         }
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
     }
 }
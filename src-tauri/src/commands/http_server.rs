@@ -1,6 +1,6 @@
 use axum::{
     body::Body,
-    extract::{Path, Query, State as AxumState},
+    extract::{ConnectInfo, Path, Query, Request, State as AxumState},
     http::{
         header::{
             ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
@@ -9,38 +9,68 @@ use axum::{
         },
         HeaderMap, HeaderValue, Response, StatusCode, Uri,
     },
-    routing::{delete, get},
+    middleware::{self, Next},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, StreamExt};
 use local_ip_address::list_afinet_netifas;
 use parking_lot::RwLock;
 use rand::RngCore;
-use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
-    TransactionTrait,
-};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::{Path as FsPath, PathBuf};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, Manager, State};
-use tokio::sync::{oneshot, Mutex};
+use tauri::{AppHandle, Manager, State, Webview};
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use crate::db::entities::{reasons, reward_settings, score_events, students};
-use crate::services::permission::PermissionLevel;
+use crate::db::entities::{reward_settings, score_events, students, tags};
+use crate::db::repositories::{SettlementLeaderboard, SettlementRepository};
+use crate::models::SettlementSummary;
+use crate::services::bus::BusEvent;
+use crate::services::permission::{
+    emit_permission_changes, lan_client_key, lan_session_key, window_session_key, PermissionLevel,
+    PermissionService,
+};
+use crate::services::profile::{self, ProfileBucket, StudentProfile, StudentProfileParams};
+use crate::services::reason::{self, ReasonFilter};
+use crate::services::{reward, score, tag, AuthService, BusPayload, EventSource};
 use crate::state::AppState;
 
+use super::auth::{AuthStatusResponse, LoginResponse};
 use super::database::realtime_dual_write_sync_if_legacy;
-use super::response::IpcResponse;
+use super::event::{
+    query_leaderboard, CreateScoreEventBatch, EventBatchResult, LeaderboardParams,
+    LeaderboardResult,
+};
+use super::response::{IpcResponse, TagResponse};
+use super::reward::{
+    QueryRewardRedemptionsParams, RedeemRewardData, RedeemRewardResult, RewardRedemptionDto,
+};
 
 const DEFAULT_STATIC_PORT: u16 = 45739;
 const DEFAULT_API_PORT: u16 = 45740;
 const LAN_COOKIE_NAME: &str = "secscore_lan_token";
 const LAN_TRUSTED_TOKENS_KEY: &str = "lan_trusted_tokens";
+const LAN_DEVICES_KEY: &str = "lan_devices";
+// 最近访问时间的落盘间隔，避免每个请求都写设置表
+const LAN_DEVICE_SEEN_PERSIST_SECONDS: i64 = 300;
+// 领取后从未访问过接口的积分级别设备上限，超出时淘汰最早的
+const LAN_PENDING_DEVICE_LIMIT: usize = 20;
+// 领取后超过该时长仍未使用的设备视为废弃
+const LAN_PENDING_DEVICE_TTL_HOURS: i64 = 24;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpServerConfig {
@@ -94,15 +124,104 @@ pub struct HttpServerStatus {
     pub token: Option<String>,
 }
 
+/// 通过分享链接登记的局域网设备，`token` 即浏览器 cookie 中的凭据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LanDevice {
+    id: String,
+    token: String,
+    name: String,
+    level: String,
+    created_at: String,
+    #[serde(default)]
+    last_seen_at: Option<String>,
+    #[serde(default)]
+    last_ip: Option<String>,
+    #[serde(default)]
+    expires_at: Option<String>,
+}
+
+impl LanDevice {
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::from_str(&self.level).unwrap_or(PermissionLevel::Points)
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .is_some_and(|expires_at| expires_at.with_timezone(&Utc) <= now)
+    }
+
+    /// 领取令牌后尚未访问过接口，访问时间仍等于登记时间
+    fn is_unused(&self) -> bool {
+        self.last_seen_at.as_deref() == Some(self.created_at.as_str())
+    }
+
+    fn is_pending(&self) -> bool {
+        self.is_unused() && self.permission() == PermissionLevel::Points
+    }
+
+    fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        self.is_unused()
+            && DateTime::parse_from_rfc3339(&self.created_at).is_ok_and(|created_at| {
+                now - created_at.with_timezone(&Utc)
+                    >= chrono::Duration::hours(LAN_PENDING_DEVICE_TTL_HOURS)
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanDeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub level: String,
+    pub created_at: String,
+    pub last_seen_at: Option<String>,
+    pub last_ip: Option<String>,
+    pub expires_at: Option<String>,
+    pub is_expired: bool,
+}
+
+impl From<&LanDevice> for LanDeviceInfo {
+    fn from(device: &LanDevice) -> Self {
+        Self {
+            id: device.id.clone(),
+            name: device.name.clone(),
+            level: device.level.clone(),
+            created_at: device.created_at.clone(),
+            last_seen_at: device.last_seen_at.clone(),
+            last_ip: device.last_ip.clone(),
+            expires_at: device.expires_at.clone(),
+            is_expired: device.is_expired(Utc::now()),
+        }
+    }
+}
+
+/// 修改设备名称、权限级别或有效期；`expires_at` 传空字符串表示取消有效期
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateLanDeviceData {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default, alias = "expiresAt")]
+    pub expires_at: Option<String>,
+}
+
 struct HttpServerState {
     pub is_running: bool,
     pub config: HttpServerConfig,
     pub url: Option<String>,
     pub api_url: Option<String>,
     pub token: Option<String>,
-    pub trusted_tokens: HashSet<String>,
+    // 以 cookie 令牌为键
+    pub devices: HashMap<String, LanDevice>,
     pub static_shutdown_tx: Option<oneshot::Sender<()>>,
     pub api_shutdown_tx: Option<oneshot::Sender<()>>,
+    // 停止服务时关闭所有事件流，否则长连接会阻塞优雅关闭
+    pub stream_shutdown: CancellationToken,
+    // 以设备 id 为键，设备被修改或撤销时关闭其事件流
+    pub device_streams: HashMap<String, CancellationToken>,
 }
 
 impl Default for HttpServerState {
@@ -113,9 +232,11 @@ impl Default for HttpServerState {
             url: None,
             api_url: None,
             token: None,
-            trusted_tokens: HashSet::new(),
+            devices: HashMap::new(),
             static_shutdown_tx: None,
             api_shutdown_tx: None,
+            stream_shutdown: CancellationToken::new(),
+            device_streams: HashMap::new(),
         }
     }
 }
@@ -136,6 +257,7 @@ struct LanApiState {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanStudent {
     pub id: i32,
+    pub uuid: String,
     pub name: String,
     pub group_name: Option<String>,
     pub score: i32,
@@ -148,6 +270,7 @@ pub struct LanReason {
     pub id: i32,
     pub content: String,
     pub category: String,
+    pub category_id: Option<i32>,
     pub delta: i32,
 }
 
@@ -162,6 +285,7 @@ pub struct LanRewardSetting {
 pub struct LanScoreEvent {
     pub id: i32,
     pub uuid: String,
+    pub student_id: Option<i32>,
     pub student_name: String,
     pub reason_content: String,
     pub delta: i32,
@@ -176,20 +300,49 @@ struct LanQueryEventParams {
     limit: Option<i32>,
 }
 
+#[derive(Debug, Deserialize)]
+struct LanStudentProfileParams {
+    #[serde(default)]
+    bucket: ProfileBucket,
+    start_date: Option<String>,
+    end_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanQueryReasonParams {
+    set: Option<String>,
+    category_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanLoginRequest {
+    password: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct LanCreateScoreEvent {
-    #[serde(alias = "studentName")]
+    #[serde(default, alias = "studentId")]
+    student_id: Option<i32>,
+    #[serde(default, alias = "studentName")]
     student_name: String,
     #[serde(alias = "reasonContent")]
     reason_content: String,
     delta: i32,
+    #[serde(default, alias = "operationId")]
+    operation_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanUpdateStudentTags {
+    #[serde(default, alias = "tagIds")]
+    tag_ids: Vec<i32>,
 }
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> Result<(), String> {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let sender_id = 0;
-    if !permissions.require_permission(sender_id, PermissionLevel::Admin) {
+    let session = window_session_key(webview.label());
+    if !permissions.require_permission(&session, PermissionLevel::Admin) {
         return Err("Permission denied: Admin required".to_string());
     }
     Ok(())
@@ -312,6 +465,10 @@ fn clone_db_conn(app_state: &Arc<RwLock<AppState>>) -> Option<sea_orm::DatabaseC
     db_conn
 }
 
+fn now_iso() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
 fn parse_trusted_tokens(raw: &str) -> HashSet<String> {
     serde_json::from_str::<Vec<String>>(raw)
         .unwrap_or_default()
@@ -320,30 +477,85 @@ fn parse_trusted_tokens(raw: &str) -> HashSet<String> {
         .collect()
 }
 
-async fn load_trusted_tokens(app_state: &Arc<RwLock<AppState>>) -> HashSet<String> {
-    let state_guard = app_state.read();
-    let db_conn = state_guard.db.read().clone();
-    let mut settings = state_guard.settings.write();
-    settings.attach_db(db_conn);
-    if settings.initialize().await.is_err() {
-        return HashSet::new();
+/// 读取已登记的设备；旧版只保存了令牌列表，首次读取时转换为积分级别的设备
+async fn load_devices(app_state: &Arc<RwLock<AppState>>) -> HashMap<String, LanDevice> {
+    let (devices_raw, legacy_raw) = {
+        let state_guard = app_state.read();
+        let db_conn = state_guard.db.read().clone();
+        let mut settings = state_guard.settings.write();
+        settings.attach_db(db_conn);
+        if settings.initialize().await.is_err() {
+            return HashMap::new();
+        }
+        (
+            settings.get_raw(LAN_DEVICES_KEY),
+            settings.get_raw(LAN_TRUSTED_TOKENS_KEY),
+        )
+    };
+
+    let mut devices = serde_json::from_str::<Vec<LanDevice>>(&devices_raw)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|device| (device.token.clone(), device))
+        .collect::<HashMap<_, _>>();
+
+    let legacy = parse_trusted_tokens(&legacy_raw);
+    if legacy.is_empty() {
+        return devices;
+    }
+    let now = now_iso();
+    let mut legacy = legacy.into_iter().collect::<Vec<_>>();
+    legacy.sort();
+    for token in legacy {
+        if devices.contains_key(&token) {
+            continue;
+        }
+        let name = format!("局域网设备 {}", devices.len() + 1);
+        devices.insert(
+            token.clone(),
+            LanDevice {
+                id: Uuid::new_v4().to_string(),
+                token,
+                name,
+                level: PermissionLevel::Points.as_str().to_string(),
+                created_at: now.clone(),
+                last_seen_at: None,
+                last_ip: None,
+                expires_at: None,
+            },
+        );
     }
-    parse_trusted_tokens(&settings.get_raw(LAN_TRUSTED_TOKENS_KEY))
+    if let Err(error) = save_devices(app_state, &devices).await {
+        eprintln!("Failed to migrate LAN trusted tokens: {}", error);
+        return devices;
+    }
+    if let Err(error) = save_setting_raw(app_state, LAN_TRUSTED_TOKENS_KEY, "[]").await {
+        eprintln!("Failed to clear legacy LAN trusted tokens: {}", error);
+    }
+    devices
 }
 
-async fn save_trusted_tokens(
+async fn save_devices(
     app_state: &Arc<RwLock<AppState>>,
-    tokens: &HashSet<String>,
+    devices: &HashMap<String, LanDevice>,
 ) -> Result<(), String> {
-    let mut values = tokens.iter().cloned().collect::<Vec<_>>();
-    values.sort();
+    let mut values = devices.values().cloned().collect::<Vec<_>>();
+    values.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
     let raw = serde_json::to_string(&values).map_err(|e| e.to_string())?;
+    save_setting_raw(app_state, LAN_DEVICES_KEY, &raw).await
+}
+
+async fn save_setting_raw(
+    app_state: &Arc<RwLock<AppState>>,
+    key: &str,
+    raw: &str,
+) -> Result<(), String> {
     let state_guard = app_state.read();
     let db_conn = state_guard.db.read().clone();
     let mut settings = state_guard.settings.write();
     settings.attach_db(db_conn);
     settings.initialize().await.map_err(|e| e.to_string())?;
-    settings.set_raw(LAN_TRUSTED_TOKENS_KEY, &raw).await
+    settings.set_raw(key, raw).await
 }
 
 async fn current_token(server_state: &Arc<Mutex<HttpServerState>>) -> Option<String> {
@@ -351,25 +563,125 @@ async fn current_token(server_state: &Arc<Mutex<HttpServerState>>) -> Option<Str
     state.token.clone()
 }
 
-async fn issue_trusted_browser_token(state: &LanStaticState) -> String {
-    let trusted_token = generate_token();
+/// 清理已过期与长期未使用的设备，并为新设备腾出待用名额
+fn prune_devices(devices: &mut HashMap<String, LanDevice>, now: DateTime<Utc>) {
+    devices.retain(|_, device| !device.is_expired(now) && !device.is_stale(now));
+    let mut pending = devices
+        .values()
+        .filter(|device| device.is_pending())
+        .map(|device| (device.created_at.clone(), device.token.clone()))
+        .collect::<Vec<_>>();
+    if pending.len() < LAN_PENDING_DEVICE_LIMIT {
+        return;
+    }
+    pending.sort();
+    for (_, token) in pending.drain(..=pending.len() - LAN_PENDING_DEVICE_LIMIT) {
+        devices.remove(&token);
+    }
+}
+
+/// 分享链接校验通过后为浏览器登记设备，默认为积分级别；
+/// 浏览器已持有有效 cookie 时沿用原设备
+async fn issue_device_token(state: &LanStaticState, headers: &HeaderMap, ip: IpAddr) -> String {
+    if let Some(device) = authorized_device(headers, &state.server_state).await {
+        touch_device(&state.app_state, &state.server_state, &device.token, ip).await;
+        return device.token;
+    }
+
+    let token = generate_token();
+    let now = Utc::now();
+    let now_text = now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
     let snapshot = {
         let mut server_state = state.server_state.lock().await;
-        server_state.trusted_tokens.insert(trusted_token.clone());
-        server_state.trusted_tokens.clone()
+        prune_devices(&mut server_state.devices, now);
+        let name = format!("局域网设备 {}", server_state.devices.len() + 1);
+        server_state.devices.insert(
+            token.clone(),
+            LanDevice {
+                id: Uuid::new_v4().to_string(),
+                token: token.clone(),
+                name,
+                level: PermissionLevel::Points.as_str().to_string(),
+                created_at: now_text.clone(),
+                last_seen_at: Some(now_text),
+                last_ip: Some(ip.to_string()),
+                expires_at: None,
+            },
+        );
+        server_state.devices.clone()
     };
-    if let Err(error) = save_trusted_tokens(&state.app_state, &snapshot).await {
-        eprintln!("Failed to persist LAN trusted token: {}", error);
+    if let Err(error) = save_devices(&state.app_state, &snapshot).await {
+        eprintln!("Failed to persist LAN device: {}", error);
     }
-    trusted_token
+    token
 }
 
-async fn is_authorized(headers: &HeaderMap, server_state: &Arc<Mutex<HttpServerState>>) -> bool {
-    let Some(cookie_token) = token_from_cookie(headers) else {
-        return false;
-    };
+/// 按 cookie 找到未过期的设备
+async fn authorized_device(
+    headers: &HeaderMap,
+    server_state: &Arc<Mutex<HttpServerState>>,
+) -> Option<LanDevice> {
+    let cookie_token = token_from_cookie(headers)?;
+    active_device(server_state, &cookie_token).await
+}
+
+async fn active_device(
+    server_state: &Arc<Mutex<HttpServerState>>,
+    token: &str,
+) -> Option<LanDevice> {
     let state = server_state.lock().await;
-    state.trusted_tokens.contains(&cookie_token)
+    state
+        .devices
+        .get(token)
+        .filter(|device| !device.is_expired(Utc::now()))
+        .cloned()
+}
+
+/// 记录设备最近一次访问的时间与 IP
+async fn touch_device(
+    app_state: &Arc<RwLock<AppState>>,
+    server_state: &Arc<Mutex<HttpServerState>>,
+    token: &str,
+    ip: IpAddr,
+) {
+    let now = Utc::now();
+    let snapshot = {
+        let mut state = server_state.lock().await;
+        let Some(device) = state.devices.get_mut(token) else {
+            return;
+        };
+        let ip = ip.to_string();
+        let stale = device
+            .last_seen_at
+            .as_deref()
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map_or(true, |seen| {
+                (now - seen.with_timezone(&Utc)).num_seconds() >= LAN_DEVICE_SEEN_PERSIST_SECONDS
+            });
+        let ip_changed = device.last_ip.as_deref() != Some(ip.as_str());
+        let first_use = device.is_unused();
+        device.last_seen_at = Some(now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        device.last_ip = Some(ip);
+        if !stale && !ip_changed && !first_use {
+            return;
+        }
+        state.devices.clone()
+    };
+    if let Err(error) = save_devices(app_state, &snapshot).await {
+        eprintln!("Failed to persist LAN device: {}", error);
+    }
+}
+
+async fn track_api_device(
+    AxumState(state): AxumState<LanApiState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response<Body> {
+    if let Some(token) = token_from_cookie(request.headers()) {
+        touch_device(&state.app_state, &state.server_state, &token, addr.ip()).await;
+    }
+    next.run(request).await
 }
 
 fn html_response(status: StatusCode, body: &str) -> Response<Body> {
@@ -426,6 +738,7 @@ fn safe_static_path(dist_dir: &FsPath, uri_path: &str) -> PathBuf {
 
 async fn static_handler(
     AxumState(state): AxumState<LanStaticState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
     uri: Uri,
 ) -> Response<Body> {
@@ -446,8 +759,8 @@ async fn static_handler(
 
     if let Some(token) = query_params.get("token") {
         if current_token(&state.server_state).await.as_deref() == Some(token.as_str()) {
-            let trusted_token = issue_trusted_browser_token(&state).await;
-            return redirect_with_cookie(clean_uri_without_token(&uri), trusted_token);
+            let device_token = issue_device_token(&state, &headers, addr.ip()).await;
+            return redirect_with_cookie(clean_uri_without_token(&uri), device_token);
         }
         return html_response(
             StatusCode::UNAUTHORIZED,
@@ -455,7 +768,10 @@ async fn static_handler(
         );
     }
 
-    if !is_authorized(&headers, &state.server_state).await {
+    if authorized_device(&headers, &state.server_state)
+        .await
+        .is_none()
+    {
         return html_response(
             StatusCode::UNAUTHORIZED,
             "请从 SecScore 主程序复制最新的局域网访问链接打开。",
@@ -515,7 +831,7 @@ async fn api_options(headers: HeaderMap) -> Response<Body> {
         )
         .header(
             ACCESS_CONTROL_ALLOW_METHODS,
-            HeaderValue::from_static("GET,POST,DELETE,OPTIONS"),
+            HeaderValue::from_static("GET,POST,PUT,DELETE,OPTIONS"),
         )
         .header(
             ACCESS_CONTROL_ALLOW_HEADERS,
@@ -528,23 +844,149 @@ async fn api_options(headers: HeaderMap) -> Response<Body> {
 async fn require_api_auth(
     headers: &HeaderMap,
     server_state: &Arc<Mutex<HttpServerState>>,
-) -> Option<Response<Body>> {
-    if is_authorized(headers, server_state).await {
-        None
-    } else {
-        Some(with_cors(
+) -> Result<LanDevice, Response<Body>> {
+    authorized_device(headers, server_state)
+        .await
+        .ok_or_else(|| {
+            with_cors(
+                headers,
+                StatusCode::UNAUTHORIZED,
+                &IpcResponse::<()>::error("LAN token invalid"),
+            )
+        })
+}
+
+/// 令牌校验通过后，把设备会话按设备的权限级别登记，再检查所需级别
+async fn require_api_permission(
+    headers: &HeaderMap,
+    state: &LanApiState,
+    level: PermissionLevel,
+) -> Result<String, Response<Body>> {
+    let device = require_api_auth(headers, &state.server_state).await?;
+    let granted = {
+        let state_guard = state.app_state.read();
+        let mut permissions = state_guard.permissions.write();
+        grant_device_session(&mut permissions, &device, level)
+    };
+    granted.ok_or_else(|| {
+        with_cors(
             headers,
-            StatusCode::UNAUTHORIZED,
-            &IpcResponse::<()>::error("LAN token invalid"),
-        ))
+            StatusCode::FORBIDDEN,
+            &IpcResponse::<()>::error("Permission denied"),
+        )
+    })
+}
+
+/// 以设备当前级别为保底登记会话，达到所需级别时返回会话键
+fn grant_device_session(
+    permissions: &mut PermissionService,
+    device: &LanDevice,
+    level: PermissionLevel,
+) -> Option<String> {
+    let session = lan_session_key(&device.id);
+    permissions.bind_session(&session, device.permission());
+    permissions
+        .require_permission(&session, level)
+        .then_some(session)
+}
+
+fn flush_permission_changes(app_state: &Arc<RwLock<AppState>>) {
+    let state_guard = app_state.read();
+    let changes = state_guard.permissions.write().take_changes();
+    emit_permission_changes(&state_guard.app_handle, &changes);
+}
+
+async fn lan_auth_status(
+    AxumState(state): AxumState<LanApiState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let session = match require_api_permission(&headers, &state, PermissionLevel::View).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let status = {
+        let state_guard = state.app_state.read();
+        let settings = state_guard.settings.read();
+        let mut permissions = state_guard.permissions.write();
+        let lockout_key = lan_client_key(&addr.ip().to_string());
+        AuthService::get_status(&settings, &session, &lockout_key, &mut permissions)
+    };
+    with_cors(
+        &headers,
+        StatusCode::OK,
+        &IpcResponse::success(AuthStatusResponse {
+            permission: status.permission,
+            has_admin_password: status.has_admin_password,
+            has_points_password: status.has_points_password,
+            has_recovery_string: status.has_recovery_string,
+            failed_attempts: status.failed_attempts,
+            lockout_remaining_seconds: status.lockout_remaining_seconds,
+        }),
+    )
+}
+
+async fn lan_auth_login(
+    AxumState(state): AxumState<LanApiState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(data): Json<LanLoginRequest>,
+) -> Response<Body> {
+    let session = match require_api_permission(&headers, &state, PermissionLevel::View).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let lockout_key = lan_client_key(&addr.ip().to_string());
+    let result = AuthService::login(&state.app_state, &session, &lockout_key, &data.password).await;
+    flush_permission_changes(&state.app_state);
+
+    if result.success {
+        with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::success(LoginResponse {
+                permission: result.permission.unwrap_or_else(|| "view".to_string()),
+            }),
+        )
+    } else {
+        with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<()>::error(
+                &result.message.unwrap_or_else(|| "Login failed".to_string()),
+            ),
+        )
     }
 }
 
+async fn lan_auth_lock(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let session = match require_api_permission(&headers, &state, PermissionLevel::View).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let level = {
+        let state_guard = state.app_state.read();
+        let mut permissions = state_guard.permissions.write();
+        permissions.lock(&session)
+    };
+    flush_permission_changes(&state.app_state);
+    with_cors(
+        &headers,
+        StatusCode::OK,
+        &IpcResponse::success(LoginResponse {
+            permission: level.as_str().to_string(),
+        }),
+    )
+}
+
 async fn lan_students(
     AxumState(state): AxumState<LanApiState>,
     headers: HeaderMap,
 ) -> Response<Body> {
-    if let Some(response) = require_api_auth(&headers, &state.server_state).await {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
         return response;
     }
     let db_conn = clone_db_conn(&state.app_state);
@@ -566,6 +1008,7 @@ async fn lan_students(
                 .into_iter()
                 .map(|row| LanStudent {
                     id: row.id,
+                    uuid: row.uuid,
                     name: row.name,
                     group_name: row.group_name,
                     score: row.score,
@@ -583,11 +1026,47 @@ async fn lan_students(
     }
 }
 
+async fn lan_student_profile(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Query(params): Query<LanStudentProfileParams>,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
+        return response;
+    }
+    let db_conn = clone_db_conn(&state.app_state);
+    let Some(conn) = db_conn else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<StudentProfile>::error("Database not connected"),
+        );
+    };
+
+    let params = StudentProfileParams {
+        student_id: Some(id),
+        student_name: String::new(),
+        bucket: params.bucket,
+        start_date: params.start_date,
+        end_date: params.end_date,
+    };
+    match profile::build_student_profile(&conn, &params).await {
+        Ok(data) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(data)),
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<StudentProfile>::error(&e),
+        ),
+    }
+}
+
 async fn lan_reasons(
     AxumState(state): AxumState<LanApiState>,
     headers: HeaderMap,
+    Query(params): Query<LanQueryReasonParams>,
 ) -> Response<Body> {
-    if let Some(response) = require_api_auth(&headers, &state.server_state).await {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
         return response;
     }
     let db_conn = clone_db_conn(&state.app_state);
@@ -599,11 +1078,13 @@ async fn lan_reasons(
         );
     };
 
-    match reasons::Entity::find()
-        .order_by_asc(reasons::Column::Id)
-        .all(&conn)
-        .await
-    {
+    // 归档的理由不在局域网页面展示；?set= 按看板快捷组取用
+    let filter = ReasonFilter {
+        include_archived: false,
+        category_id: params.category_id,
+        set: params.set,
+    };
+    match reason::query_reasons(&conn, &filter).await {
         Ok(rows) => {
             let data = rows
                 .into_iter()
@@ -611,6 +1092,7 @@ async fn lan_reasons(
                     id: row.id,
                     content: row.content,
                     category: row.category,
+                    category_id: row.category_id,
                     delta: row.delta,
                 })
                 .collect::<Vec<_>>();
@@ -619,7 +1101,7 @@ async fn lan_reasons(
         Err(e) => with_cors(
             &headers,
             StatusCode::OK,
-            &IpcResponse::<Vec<LanReason>>::error(&e.to_string()),
+            &IpcResponse::<Vec<LanReason>>::error(&e),
         ),
     }
 }
@@ -628,7 +1110,7 @@ async fn lan_rewards(
     AxumState(state): AxumState<LanApiState>,
     headers: HeaderMap,
 ) -> Response<Body> {
-    if let Some(response) = require_api_auth(&headers, &state.server_state).await {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
         return response;
     }
     let db_conn = clone_db_conn(&state.app_state);
@@ -669,7 +1151,7 @@ async fn lan_events(
     headers: HeaderMap,
     Query(params): Query<LanQueryEventParams>,
 ) -> Response<Body> {
-    if let Some(response) = require_api_auth(&headers, &state.server_state).await {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
         return response;
     }
     let db_conn = clone_db_conn(&state.app_state);
@@ -695,6 +1177,7 @@ async fn lan_events(
                 .map(|row| LanScoreEvent {
                     id: row.id,
                     uuid: row.uuid,
+                    student_id: row.student_id,
                     student_name: row.student_name,
                     reason_content: row.reason_content,
                     delta: row.delta,
@@ -714,85 +1197,101 @@ async fn lan_events(
     }
 }
 
+/// 局域网写入后发布到事件总线，桌面端与其他局域网页面都会收到
+fn publish_lan_change(app_state: &Arc<RwLock<AppState>>, payload: BusPayload) {
+    app_state
+        .read()
+        .event_bus
+        .publish(EventSource::Lan, payload);
+}
+
 async fn lan_create_event(
     AxumState(state): AxumState<LanApiState>,
     headers: HeaderMap,
     Json(data): Json<LanCreateScoreEvent>,
 ) -> Response<Body> {
-    if let Some(response) = require_api_auth(&headers, &state.server_state).await {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::Points).await {
         return response;
     }
-    let student_name = data.student_name.trim();
-    if student_name.is_empty() {
+    let db_conn = clone_db_conn(&state.app_state);
+    let Some(conn) = db_conn else {
         return with_cors(
             &headers,
             StatusCode::OK,
-            &IpcResponse::<i32>::error("Student name cannot be empty"),
+            &IpcResponse::<i32>::error("Database not connected"),
+        );
+    };
+
+    let result = async {
+        let local_write_lock = { state.app_state.read().local_write_lock.clone() };
+        let _write_guard = local_write_lock.lock().await;
+        let inserted = score::add_score(
+            &conn,
+            data.student_id,
+            &data.student_name,
+            data.delta,
+            &data.reason_content,
+            data.operation_id.as_deref(),
+        )
+        .await?;
+        realtime_dual_write_sync_if_legacy(&state.app_state).await?;
+        let id = inserted.id;
+        publish_lan_change(
+            &state.app_state,
+            BusPayload::ScoreEventsCreated {
+                events: vec![inserted],
+            },
         );
+        Ok::<i32, String>(id)
+    }
+    .await;
+
+    match result {
+        Ok(id) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(id)),
+        Err(e) => with_cors(&headers, StatusCode::OK, &IpcResponse::<i32>::error(&e)),
+    }
+}
+
+async fn lan_create_event_batch(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+    Json(data): Json<CreateScoreEventBatch>,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::Points).await {
+        return response;
     }
     let db_conn = clone_db_conn(&state.app_state);
     let Some(conn) = db_conn else {
         return with_cors(
             &headers,
             StatusCode::OK,
-            &IpcResponse::<i32>::error("Database not connected"),
+            &IpcResponse::<EventBatchResult>::error("Database not connected"),
         );
     };
 
     let result = async {
-        let student = students::Entity::find()
-            .filter(students::Column::Name.eq(student_name))
-            .one(&conn)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| "Student not found".to_string())?;
-
-        let val_prev = student.score;
-        let val_curr = val_prev + data.delta;
-        let reward_points_next = student.reward_points + data.delta;
-        let now = chrono::Utc::now()
-            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-            .to_string();
-
-        let txn = conn.begin().await.map_err(|e| e.to_string())?;
-        let new_event = score_events::ActiveModel {
-            id: sea_orm::ActiveValue::NotSet,
-            uuid: Set(Uuid::new_v4().to_string()),
-            student_name: Set(student_name.to_string()),
-            reason_content: Set(data.reason_content.trim().to_string()),
-            delta: Set(data.delta),
-            val_prev: Set(val_prev),
-            val_curr: Set(val_curr),
-            event_time: Set(now.clone()),
-            settlement_id: Set(None),
-        };
-        let inserted = new_event.insert(&txn).await.map_err(|e| e.to_string())?;
-
-        let mut active: students::ActiveModel = student.into();
-        active.score = Set(val_curr);
-        active.reward_points = Set(reward_points_next);
-        active.updated_at = Set(now);
-        active.update(&txn).await.map_err(|e| e.to_string())?;
-
-        txn.commit().await.map_err(|e| e.to_string())?;
+        let local_write_lock = { state.app_state.read().local_write_lock.clone() };
+        let _write_guard = local_write_lock.lock().await;
+        let created =
+            score::add_score_batch(&conn, &data.selector, data.delta, &data.reason_content).await?;
         realtime_dual_write_sync_if_legacy(&state.app_state).await?;
-        {
-            let state_guard = state.app_state.read();
-            let _ = state_guard.app_handle.emit(
-                "ss:data-updated",
-                json!({
-                    "category": "events",
-                    "source": "lan"
-                }),
-            );
-        }
-        Ok::<i32, String>(inserted.id)
+        publish_lan_change(
+            &state.app_state,
+            BusPayload::ScoreEventsCreated {
+                events: created.clone(),
+            },
+        );
+        Ok::<EventBatchResult, String>(EventBatchResult::from(created))
     }
     .await;
 
     match result {
-        Ok(id) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(id)),
-        Err(e) => with_cors(&headers, StatusCode::OK, &IpcResponse::<i32>::error(&e)),
+        Ok(data) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(data)),
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<EventBatchResult>::error(&e),
+        ),
     }
 }
 
@@ -801,7 +1300,7 @@ async fn lan_delete_event(
     headers: HeaderMap,
     Path(uuid): Path<String>,
 ) -> Response<Body> {
-    if let Some(response) = require_api_auth(&headers, &state.server_state).await {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::Points).await {
         return response;
     }
     let db_conn = clone_db_conn(&state.app_state);
@@ -814,52 +1313,16 @@ async fn lan_delete_event(
     };
 
     let result = async {
-        let event = score_events::Entity::find()
-            .filter(score_events::Column::Uuid.eq(uuid.trim()))
-            .one(&conn)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| "Event not found".to_string())?;
-
-        if event.settlement_id.is_some() {
-            return Err("该记录已结算，无法撤销".to_string());
-        }
-
-        let txn = conn.begin().await.map_err(|e| e.to_string())?;
-        if let Some(student) = students::Entity::find()
-            .filter(students::Column::Name.eq(&event.student_name))
-            .one(&txn)
-            .await
-            .map_err(|e| e.to_string())?
-        {
-            let next_score = student.score - event.delta;
-            let next_reward_points = student.reward_points - event.delta;
-            let now = chrono::Utc::now()
-                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-                .to_string();
-            let mut active: students::ActiveModel = student.into();
-            active.score = Set(next_score);
-            active.reward_points = Set(next_reward_points);
-            active.updated_at = Set(now);
-            active.update(&txn).await.map_err(|e| e.to_string())?;
-        }
-
-        score_events::Entity::delete_by_id(event.id)
-            .exec(&txn)
-            .await
-            .map_err(|e| e.to_string())?;
-        txn.commit().await.map_err(|e| e.to_string())?;
+        let local_write_lock = { state.app_state.read().local_write_lock.clone() };
+        let _write_guard = local_write_lock.lock().await;
+        let event = score::revert_score_event(&conn, &uuid).await?;
         realtime_dual_write_sync_if_legacy(&state.app_state).await?;
-        {
-            let state_guard = state.app_state.read();
-            let _ = state_guard.app_handle.emit(
-                "ss:data-updated",
-                json!({
-                    "category": "events",
-                    "source": "lan"
-                }),
-            );
-        }
+        publish_lan_change(
+            &state.app_state,
+            BusPayload::ScoreEventsDeleted {
+                events: vec![event],
+            },
+        );
         Ok::<(), String>(())
     }
     .await;
@@ -870,26 +1333,341 @@ async fn lan_delete_event(
     }
 }
 
-async fn api_not_found(headers: HeaderMap) -> Response<Body> {
-    with_cors(
-        &headers,
-        StatusCode::NOT_FOUND,
-        &IpcResponse::<()>::error("Not found"),
-    )
-}
-
-async fn start_http_server_inner(
-    config: Option<HttpServerConfig>,
-    app_handle: AppHandle,
-    app_state: Arc<RwLock<AppState>>,
-    refresh_if_running: bool,
-) -> Result<IpcResponse<HttpServerStartResult>, String> {
-    let mut server_state = HTTP_SERVER_STATE.lock().await;
+async fn lan_redeem_reward(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+    Json(data): Json<RedeemRewardData>,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::Points).await {
+        return response;
+    }
+    let db_conn = clone_db_conn(&state.app_state);
+    let Some(conn) = db_conn else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<RedeemRewardResult>::error("Database not connected"),
+        );
+    };
 
-    if server_state.is_running {
-        if refresh_if_running {
-            server_state.token = Some(generate_token());
-            let token = server_state.token.clone().unwrap_or_default();
+    let result = async {
+        let local_write_lock = { state.app_state.read().local_write_lock.clone() };
+        let _write_guard = local_write_lock.lock().await;
+        let (redemption, remaining) = reward::redeem(
+            &conn,
+            data.student_id,
+            &data.student_name,
+            data.reward_id,
+            data.operation_id.as_deref(),
+        )
+        .await?;
+        realtime_dual_write_sync_if_legacy(&state.app_state).await?;
+        let result = RedeemRewardResult {
+            redemption_id: redemption.id,
+            remaining_reward_points: remaining,
+            status: redemption.status.clone(),
+        };
+        publish_lan_change(
+            &state.app_state,
+            BusPayload::RedemptionChanged {
+                redemption: Box::new(redemption),
+            },
+        );
+        Ok::<RedeemRewardResult, String>(result)
+    }
+    .await;
+
+    match result {
+        Ok(data) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(data)),
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<RedeemRewardResult>::error(&e),
+        ),
+    }
+}
+
+async fn lan_redemptions(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+    Query(params): Query<QueryRewardRedemptionsParams>,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
+        return response;
+    }
+    let db_conn = clone_db_conn(&state.app_state);
+    let Some(conn) = db_conn else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<Vec<RewardRedemptionDto>>::error("Database not connected"),
+        );
+    };
+
+    match reward::query_redemptions(&conn, params.status, params.limit.unwrap_or(100)).await {
+        Ok(rows) => {
+            let data = rows
+                .into_iter()
+                .map(RewardRedemptionDto::from)
+                .collect::<Vec<_>>();
+            with_cors(&headers, StatusCode::OK, &IpcResponse::success(data))
+        }
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<Vec<RewardRedemptionDto>>::error(&e),
+        ),
+    }
+}
+
+async fn lan_tags(AxumState(state): AxumState<LanApiState>, headers: HeaderMap) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
+        return response;
+    }
+    let db_conn = clone_db_conn(&state.app_state);
+    let Some(conn) = db_conn else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<Vec<TagResponse>>::error("Database not connected"),
+        );
+    };
+
+    match tags::Entity::find()
+        .order_by_asc(tags::Column::CreatedAt)
+        .all(&conn)
+        .await
+    {
+        Ok(rows) => {
+            let data = rows
+                .into_iter()
+                .map(|row| TagResponse {
+                    id: row.id,
+                    name: row.name,
+                })
+                .collect::<Vec<_>>();
+            with_cors(&headers, StatusCode::OK, &IpcResponse::success(data))
+        }
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<Vec<TagResponse>>::error(&e.to_string()),
+        ),
+    }
+}
+
+async fn lan_update_student_tags(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(data): Json<LanUpdateStudentTags>,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::Admin).await {
+        return response;
+    }
+    let db_conn = clone_db_conn(&state.app_state);
+    let Some(conn) = db_conn else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<()>::error("Database not connected"),
+        );
+    };
+
+    let result = async {
+        let local_write_lock = { state.app_state.read().local_write_lock.clone() };
+        let _write_guard = local_write_lock.lock().await;
+        let added_tag_ids = tag::set_student_tags(&conn, id, data.tag_ids).await?;
+        realtime_dual_write_sync_if_legacy(&state.app_state).await?;
+        publish_lan_change(
+            &state.app_state,
+            BusPayload::StudentTagsUpdated {
+                student_id: id,
+                added_tag_ids,
+            },
+        );
+        Ok::<(), String>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => with_cors(&headers, StatusCode::OK, &IpcResponse::success_empty()),
+        Err(e) => with_cors(&headers, StatusCode::OK, &IpcResponse::<()>::error(&e)),
+    }
+}
+
+async fn lan_leaderboard(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+    Query(params): Query<LeaderboardParams>,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
+        return response;
+    }
+    let db_conn = clone_db_conn(&state.app_state);
+    let Some(conn) = db_conn else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<LeaderboardResult>::error("Database not connected"),
+        );
+    };
+
+    match query_leaderboard(&conn, &params).await {
+        Ok(data) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(data)),
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<LeaderboardResult>::error(&e),
+        ),
+    }
+}
+
+async fn lan_settlements(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
+        return response;
+    }
+    let Some(repo) = clone_db_conn(&state.app_state)
+        .as_ref()
+        .and_then(SettlementRepository::from_connection)
+    else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::success(Vec::<SettlementSummary>::new()),
+        );
+    };
+
+    match repo.find_all().await {
+        Ok(data) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(data)),
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<Vec<SettlementSummary>>::error(&e.to_string()),
+        ),
+    }
+}
+
+async fn lan_settlement_leaderboard(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Response<Body> {
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
+        return response;
+    }
+    let Some(repo) = clone_db_conn(&state.app_state)
+        .as_ref()
+        .and_then(SettlementRepository::from_connection)
+    else {
+        return with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<SettlementLeaderboard>::error("Database not connected"),
+        );
+    };
+
+    match repo.get_leaderboard(id).await {
+        Ok(data) => with_cors(&headers, StatusCode::OK, &IpcResponse::success(data)),
+        Err(e) => with_cors(
+            &headers,
+            StatusCode::OK,
+            &IpcResponse::<SettlementLeaderboard>::error(&e.to_string()),
+        ),
+    }
+}
+
+/// 推送事件总线上的变更，大屏等页面无需轮询。
+/// 服务停止或设备被修改、撤销时关闭连接，每条事件推送前重新校验设备
+/// 设备的总线事件流：服务停止、设备被修改或撤销后结束；
+/// 消费过慢被跳过的事件直接丢弃，客户端收到下一条后自行刷新
+fn device_event_stream(
+    receiver: broadcast::Receiver<BusEvent>,
+    closed: CancellationToken,
+    server_state: Arc<Mutex<HttpServerState>>,
+    token: String,
+) -> impl Stream<Item = BusEvent> {
+    BroadcastStream::new(receiver)
+        .take_until(closed.cancelled_owned())
+        .then(move |item| {
+            let server_state = server_state.clone();
+            let token = token.clone();
+            async move { active_device(&server_state, &token).await.map(|_| item) }
+        })
+        .take_while(|item| futures_util::future::ready(item.is_some()))
+        .filter_map(|item| async move { item?.ok() })
+}
+
+async fn lan_stream(
+    AxumState(state): AxumState<LanApiState>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let device = match require_api_auth(&headers, &state.server_state).await {
+        Ok(device) => device,
+        Err(response) => return response,
+    };
+    if let Err(response) = require_api_permission(&headers, &state, PermissionLevel::View).await {
+        return response;
+    }
+    let closed = {
+        let mut server_state = state.server_state.lock().await;
+        let shutdown = server_state.stream_shutdown.clone();
+        server_state
+            .device_streams
+            .entry(device.id.clone())
+            .or_insert_with(|| shutdown.child_token())
+            .clone()
+    };
+    let receiver = { state.app_state.read().event_bus.subscribe() };
+    let stream = device_event_stream(
+        receiver,
+        closed,
+        state.server_state.clone(),
+        device.token.clone(),
+    )
+    .filter_map(|event| async move {
+        let data = serde_json::to_string(&event).ok()?;
+        Some(Ok::<_, Infallible>(
+            SseEvent::default().event(event.payload.kind()).data(data),
+        ))
+    });
+
+    let mut response = Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, cors_origin(&headers));
+    response_headers.insert(
+        ACCESS_CONTROL_ALLOW_CREDENTIALS,
+        HeaderValue::from_static("true"),
+    );
+    response_headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+async fn api_not_found(headers: HeaderMap) -> Response<Body> {
+    with_cors(
+        &headers,
+        StatusCode::NOT_FOUND,
+        &IpcResponse::<()>::error("Not found"),
+    )
+}
+
+async fn start_http_server_inner(
+    config: Option<HttpServerConfig>,
+    app_handle: AppHandle,
+    app_state: Arc<RwLock<AppState>>,
+    refresh_if_running: bool,
+) -> Result<IpcResponse<HttpServerStartResult>, String> {
+    let mut server_state = HTTP_SERVER_STATE.lock().await;
+
+    if server_state.is_running {
+        if refresh_if_running {
+            server_state.token = Some(generate_token());
+            let token = server_state.token.clone().unwrap_or_default();
             let share_urls = build_share_urls(server_state.config.port, &token);
             let share_url = share_urls
                 .first()
@@ -937,7 +1715,7 @@ async fn start_http_server_inner(
         .local_addr()
         .map_err(|e| format!("Failed to get LAN API addr: {}", e))?;
     let dist_dir = resolve_dist_dir(&app_handle)?;
-    let trusted_tokens = load_trusted_tokens(&app_state).await;
+    let devices = load_devices(&app_state).await;
 
     let token = generate_token();
     let share_urls = build_share_urls(static_local_addr.port(), &token);
@@ -966,35 +1744,87 @@ async fn start_http_server_inner(
         .fallback(static_handler)
         .with_state(static_state);
     let api_router = Router::new()
+        .route(
+            "/api/auth/status",
+            get(lan_auth_status).options(api_options),
+        )
+        .route("/api/auth/login", post(lan_auth_login).options(api_options))
+        .route("/api/auth/lock", post(lan_auth_lock).options(api_options))
         .route("/api/students", get(lan_students).options(api_options))
+        .route(
+            "/api/students/:id/profile",
+            get(lan_student_profile).options(api_options),
+        )
+        .route(
+            "/api/students/:id/tags",
+            put(lan_update_student_tags).options(api_options),
+        )
+        .route("/api/tags", get(lan_tags).options(api_options))
         .route("/api/reasons", get(lan_reasons).options(api_options))
         .route("/api/rewards", get(lan_rewards).options(api_options))
+        .route(
+            "/api/rewards/redeem",
+            post(lan_redeem_reward).options(api_options),
+        )
+        .route(
+            "/api/rewards/redemptions",
+            get(lan_redemptions).options(api_options),
+        )
         .route(
             "/api/events",
             get(lan_events).post(lan_create_event).options(api_options),
         )
+        .route(
+            "/api/events/batch",
+            post(lan_create_event_batch).options(api_options),
+        )
         .route(
             "/api/events/:uuid",
             delete(lan_delete_event).options(api_options),
         )
+        .route(
+            "/api/leaderboard",
+            get(lan_leaderboard).options(api_options),
+        )
+        .route(
+            "/api/settlements",
+            get(lan_settlements).options(api_options),
+        )
+        .route(
+            "/api/settlements/:id/leaderboard",
+            get(lan_settlement_leaderboard).options(api_options),
+        )
+        .route("/api/stream", get(lan_stream).options(api_options))
         .fallback(api_not_found)
+        .layer(middleware::from_fn_with_state(
+            api_state.clone(),
+            track_api_device,
+        ))
         .with_state(api_state);
 
     let (static_shutdown_tx, static_shutdown_rx) = oneshot::channel::<()>();
     let (api_shutdown_tx, api_shutdown_rx) = oneshot::channel::<()>();
+    let stream_shutdown = CancellationToken::new();
 
     tauri::async_runtime::spawn(async move {
-        let server =
-            axum::serve(static_listener, static_router).with_graceful_shutdown(async move {
-                let _ = static_shutdown_rx.await;
-            });
+        let server = axum::serve(
+            static_listener,
+            static_router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
+            let _ = static_shutdown_rx.await;
+        });
         if let Err(e) = server.await {
             eprintln!("LAN static server error: {}", e);
         }
     });
 
     tauri::async_runtime::spawn(async move {
-        let server = axum::serve(api_listener, api_router).with_graceful_shutdown(async move {
+        let server = axum::serve(
+            api_listener,
+            api_router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
             let _ = api_shutdown_rx.await;
         });
         if let Err(e) = server.await {
@@ -1010,9 +1840,11 @@ async fn start_http_server_inner(
     server_state.url = Some(url.clone());
     server_state.api_url = Some(api_url.clone());
     server_state.token = Some(token.clone());
-    server_state.trusted_tokens = trusted_tokens;
+    server_state.devices = devices;
     server_state.static_shutdown_tx = Some(static_shutdown_tx);
     server_state.api_shutdown_tx = Some(api_shutdown_tx);
+    server_state.stream_shutdown = stream_shutdown;
+    server_state.device_streams.clear();
 
     Ok(IpcResponse::success(HttpServerStartResult {
         url,
@@ -1028,26 +1860,29 @@ async fn start_http_server_inner(
 pub async fn http_server_start(
     config: Option<HttpServerConfig>,
     app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<HttpServerStartResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
     start_http_server_inner(config, app_handle, state.inner().clone(), false).await
 }
 
 #[tauri::command]
 pub async fn http_server_refresh_token(
     app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<HttpServerStartResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
     start_http_server_inner(None, app_handle, state.inner().clone(), true).await
 }
 
 #[tauri::command]
 pub async fn http_server_stop(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
     let mut server_state = HTTP_SERVER_STATE.lock().await;
 
@@ -1055,6 +1890,9 @@ pub async fn http_server_stop(
         return Ok(IpcResponse::success_empty());
     }
 
+    // 先关闭事件流，优雅关闭才不会一直等待长连接
+    server_state.stream_shutdown.cancel();
+    server_state.device_streams.clear();
     if let Some(tx) = server_state.static_shutdown_tx.take() {
         let _ = tx.send(());
     }
@@ -1095,6 +1933,137 @@ pub async fn http_server_status(
     Ok(IpcResponse::success(status))
 }
 
+#[tauri::command]
+pub async fn http_server_device_list(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<Vec<LanDeviceInfo>>, String> {
+    check_admin_permission(&state, &webview)?;
+
+    let server_state = HTTP_SERVER_STATE.lock().await;
+    let devices = if server_state.is_running {
+        server_state.devices.clone()
+    } else {
+        drop(server_state);
+        load_devices(state.inner()).await
+    };
+    let mut data = devices
+        .values()
+        .map(LanDeviceInfo::from)
+        .collect::<Vec<_>>();
+    data.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+    Ok(IpcResponse::success(data))
+}
+
+/// 修改设备后清除其会话，下次请求按新的级别重新登记
+async fn mutate_device<F>(
+    app_state: &Arc<RwLock<AppState>>,
+    id: &str,
+    mutate: F,
+) -> Result<Option<LanDevice>, String>
+where
+    F: FnOnce(&mut HashMap<String, LanDevice>, &str) -> Option<LanDevice>,
+{
+    let mut server_state = HTTP_SERVER_STATE.lock().await;
+    if !server_state.is_running {
+        server_state.devices = load_devices(app_state).await;
+    }
+    let token = server_state
+        .devices
+        .values()
+        .find(|device| device.id == id)
+        .map(|device| device.token.clone());
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let device = mutate(&mut server_state.devices, &token);
+    if let Some(closed) = server_state.device_streams.remove(id) {
+        closed.cancel();
+    }
+    let snapshot = server_state.devices.clone();
+    drop(server_state);
+
+    save_devices(app_state, &snapshot).await?;
+    {
+        let state_guard = app_state.read();
+        state_guard
+            .permissions
+            .write()
+            .clear_permission(&lan_session_key(id));
+    }
+    Ok(device)
+}
+
+#[tauri::command]
+pub async fn http_server_device_update(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+    data: UpdateLanDeviceData,
+) -> Result<IpcResponse<LanDeviceInfo>, String> {
+    check_admin_permission(&state, &webview)?;
+
+    let name = data.name.as_deref().map(str::trim);
+    if name.is_some_and(str::is_empty) {
+        return Ok(IpcResponse::error("Device name cannot be empty"));
+    }
+    let level = match data.level.as_deref().map(str::trim) {
+        Some(value) => match PermissionLevel::from_str(value) {
+            Some(level) => Some(level),
+            None => return Ok(IpcResponse::error("Invalid permission level")),
+        },
+        None => None,
+    };
+    let expires_at = match data.expires_at.as_deref().map(str::trim) {
+        Some("") => Some(None),
+        Some(value) => match DateTime::parse_from_rfc3339(value) {
+            Ok(parsed) => Some(Some(
+                parsed
+                    .with_timezone(&Utc)
+                    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                    .to_string(),
+            )),
+            Err(_) => return Ok(IpcResponse::error("Invalid expiry time")),
+        },
+        None => None,
+    };
+
+    let updated = mutate_device(state.inner(), &id, |devices, token| {
+        let device = devices.get_mut(token)?;
+        if let Some(name) = name {
+            device.name = name.to_string();
+        }
+        if let Some(level) = level {
+            device.level = level.as_str().to_string();
+        }
+        if let Some(expires_at) = expires_at {
+            device.expires_at = expires_at;
+        }
+        Some(device.clone())
+    })
+    .await?;
+
+    match updated {
+        Some(device) => Ok(IpcResponse::success(LanDeviceInfo::from(&device))),
+        None => Ok(IpcResponse::error("Device not found")),
+    }
+}
+
+#[tauri::command]
+pub async fn http_server_device_revoke(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+    id: String,
+) -> Result<IpcResponse<()>, String> {
+    check_admin_permission(&state, &webview)?;
+
+    let revoked = mutate_device(state.inner(), &id, |devices, token| devices.remove(token)).await?;
+    if revoked.is_none() {
+        return Ok(IpcResponse::error("Device not found"));
+    }
+    Ok(IpcResponse::success_empty())
+}
+
 pub async fn http_server_start_from_settings(
     app_handle: AppHandle,
     app_state: Arc<RwLock<AppState>>,
@@ -1108,3 +2077,298 @@ pub async fn http_server_start_from_settings(
             .unwrap_or_else(|| "Failed to start LAN server".to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::EventBus;
+
+    fn device(token: &str, level: PermissionLevel, created_at: &str, used: bool) -> LanDevice {
+        LanDevice {
+            id: token.to_string(),
+            token: token.to_string(),
+            name: token.to_string(),
+            level: level.as_str().to_string(),
+            created_at: created_at.to_string(),
+            last_seen_at: Some(if used {
+                "2026-01-02T00:00:00.000Z".to_string()
+            } else {
+                created_at.to_string()
+            }),
+            last_ip: None,
+            expires_at: None,
+        }
+    }
+
+    fn insert(devices: &mut HashMap<String, LanDevice>, device: LanDevice) {
+        devices.insert(device.token.clone(), device);
+    }
+
+    #[test]
+    fn prune_drops_expired_and_stale_devices() {
+        let now = DateTime::parse_from_rfc3339("2026-01-03T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut devices = HashMap::new();
+        insert(
+            &mut devices,
+            device(
+                "stale",
+                PermissionLevel::Points,
+                "2026-01-01T00:00:00.000Z",
+                false,
+            ),
+        );
+        insert(
+            &mut devices,
+            device(
+                "fresh",
+                PermissionLevel::Points,
+                "2026-01-02T12:00:00.000Z",
+                false,
+            ),
+        );
+        insert(
+            &mut devices,
+            device(
+                "used",
+                PermissionLevel::Points,
+                "2026-01-01T00:00:00.000Z",
+                true,
+            ),
+        );
+        let mut expired = device(
+            "expired",
+            PermissionLevel::Admin,
+            "2026-01-01T00:00:00.000Z",
+            true,
+        );
+        expired.expires_at = Some("2026-01-02T00:00:00Z".to_string());
+        insert(&mut devices, expired);
+
+        prune_devices(&mut devices, now);
+
+        let mut left = devices.keys().cloned().collect::<Vec<_>>();
+        left.sort();
+        assert_eq!(left, vec!["fresh".to_string(), "used".to_string()]);
+    }
+
+    #[test]
+    fn prune_caps_pending_points_devices() {
+        let now = DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut devices = HashMap::new();
+        for i in 0..LAN_PENDING_DEVICE_LIMIT + 3 {
+            let created_at = format!("2026-01-01T10:{:02}:00.000Z", i);
+            insert(
+                &mut devices,
+                device(
+                    &format!("p{:02}", i),
+                    PermissionLevel::Points,
+                    &created_at,
+                    false,
+                ),
+            );
+        }
+        // 已提升级别的未使用设备不占待用名额
+        insert(
+            &mut devices,
+            device(
+                "admin",
+                PermissionLevel::Admin,
+                "2026-01-01T09:00:00.000Z",
+                false,
+            ),
+        );
+
+        prune_devices(&mut devices, now);
+
+        let pending = devices.values().filter(|d| d.is_pending()).count();
+        assert_eq!(pending, LAN_PENDING_DEVICE_LIMIT - 1);
+        assert!(devices.contains_key("admin"));
+        assert!(!devices.contains_key("p00"));
+        assert!(!devices.contains_key("p03"));
+        assert!(devices.contains_key("p04"));
+    }
+
+    fn cookie_headers(token: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            headers.insert(
+                COOKIE,
+                HeaderValue::from_str(&format!("other=1; {}={}", LAN_COOKIE_NAME, token)).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn api_auth_requires_a_known_unexpired_device() {
+        let mut expired = device(
+            "expired",
+            PermissionLevel::Admin,
+            "2026-01-01T00:00:00.000Z",
+            true,
+        );
+        expired.expires_at = Some("2026-01-02T00:00:00Z".to_string());
+        let mut server_state = HttpServerState::default();
+        for device in [
+            device(
+                "valid",
+                PermissionLevel::Points,
+                "2026-01-01T00:00:00.000Z",
+                true,
+            ),
+            expired,
+        ] {
+            server_state.devices.insert(device.token.clone(), device);
+        }
+        let server_state = Arc::new(Mutex::new(server_state));
+
+        let cases = [
+            ("no cookie", None, None),
+            ("unknown token", Some("forged"), None),
+            ("expired device", Some("expired"), None),
+            ("valid device", Some("valid"), Some("valid")),
+        ];
+        for (name, token, expected) in cases {
+            let result = require_api_auth(&cookie_headers(token), &server_state).await;
+            match expected {
+                Some(id) => assert_eq!(result.unwrap().id, id, "case: {}", name),
+                None => assert_eq!(
+                    result.unwrap_err().status(),
+                    StatusCode::UNAUTHORIZED,
+                    "case: {}",
+                    name
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn device_level_gates_api_permissions() {
+        use PermissionLevel::*;
+
+        let cases = [
+            (View, View, true),
+            (View, Points, false),
+            (View, Admin, false),
+            (Points, View, true),
+            (Points, Points, true),
+            (Points, Admin, false),
+            (Admin, Points, true),
+            (Admin, Admin, true),
+        ];
+        for (device_level, required, granted) in cases {
+            // 设了口令后桌面端默认只读，局域网设备仍按自身级别放行
+            let mut permissions = PermissionService::new();
+            permissions.update_password_status(true, true);
+            let lan = device("d", device_level, "2026-01-01T00:00:00.000Z", true);
+            assert_eq!(
+                grant_device_session(&mut permissions, &lan, required),
+                granted.then(|| lan_session_key("d")),
+                "device {} requires {}",
+                device_level.as_str(),
+                required.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn demoted_device_loses_admin_endpoints() {
+        let mut permissions = PermissionService::new();
+        permissions.update_password_status(true, true);
+        let mut lan = device(
+            "d",
+            PermissionLevel::Admin,
+            "2026-01-01T00:00:00.000Z",
+            true,
+        );
+        assert!(grant_device_session(&mut permissions, &lan, PermissionLevel::Admin).is_some());
+
+        lan.level = PermissionLevel::Points.as_str().to_string();
+        assert!(grant_device_session(&mut permissions, &lan, PermissionLevel::Admin).is_none());
+        assert!(grant_device_session(&mut permissions, &lan, PermissionLevel::Points).is_some());
+
+        // 积分设备登录管理员口令后可访问管理接口，锁定后回落
+        let session = lan_session_key("d");
+        permissions.set_permission(&session, PermissionLevel::Admin);
+        assert!(grant_device_session(&mut permissions, &lan, PermissionLevel::Admin).is_some());
+        permissions.lock(&session);
+        assert!(grant_device_session(&mut permissions, &lan, PermissionLevel::Admin).is_none());
+    }
+
+    fn stream_fixture(
+        bus: &EventBus,
+    ) -> (
+        Arc<Mutex<HttpServerState>>,
+        CancellationToken,
+        impl Stream<Item = BusEvent>,
+    ) {
+        let mut server_state = HttpServerState::default();
+        let lan = device(
+            "d",
+            PermissionLevel::Points,
+            "2026-01-01T00:00:00.000Z",
+            true,
+        );
+        server_state.devices.insert(lan.token.clone(), lan);
+        let closed = server_state.stream_shutdown.child_token();
+        let server_state = Arc::new(Mutex::new(server_state));
+        let stream = device_event_stream(
+            bus.subscribe(),
+            closed.clone(),
+            server_state.clone(),
+            "d".to_string(),
+        );
+        (server_state, closed, stream)
+    }
+
+    #[tokio::test]
+    async fn device_stream_forwards_bus_events() {
+        let bus = EventBus::new();
+        let (_server_state, _closed, stream) = stream_fixture(&bus);
+        let mut stream = Box::pin(stream);
+
+        bus.publish(
+            EventSource::Desktop,
+            BusPayload::StudentsUpdated {
+                student_ids: vec![1],
+            },
+        );
+        bus.publish(EventSource::Mcp, BusPayload::DataReset);
+
+        let first = stream.next().await.unwrap();
+        assert_eq!(first.source, EventSource::Desktop);
+        assert_eq!(first.payload.kind(), "students_updated");
+        let second = stream.next().await.unwrap();
+        assert_eq!(second.source, EventSource::Mcp);
+        assert_eq!(second.payload.kind(), "data_reset");
+    }
+
+    #[tokio::test]
+    async fn device_stream_closes_on_cancel_and_revoke() {
+        // 服务停止：父令牌取消后流立即结束
+        let bus = EventBus::new();
+        let (server_state, _closed, stream) = stream_fixture(&bus);
+        let mut stream = Box::pin(stream);
+        server_state.lock().await.stream_shutdown.cancel();
+        assert!(stream.next().await.is_none());
+
+        // 单个设备被修改：设备令牌取消
+        let bus = EventBus::new();
+        let (_server_state, closed, stream) = stream_fixture(&bus);
+        let mut stream = Box::pin(stream);
+        closed.cancel();
+        assert!(stream.next().await.is_none());
+
+        // 设备被撤销：下一条事件到达时发现设备已不存在
+        let bus = EventBus::new();
+        let (server_state, _closed, stream) = stream_fixture(&bus);
+        let mut stream = Box::pin(stream);
+        server_state.lock().await.devices.remove("d");
+        bus.publish(EventSource::Lan, BusPayload::DataReset);
+        assert!(stream.next().await.is_none());
+    }
+}
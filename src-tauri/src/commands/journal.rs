@@ -0,0 +1,134 @@
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use tauri::{State, Webview};
+
+use crate::services::journal::{query_journal, redo_operations, undo_operations};
+use crate::services::{window_session_key, BusPayload, EventSource, JournalEntry, PermissionLevel};
+use crate::state::AppState;
+
+use super::database::realtime_dual_write_sync_if_legacy;
+use super::response::IpcResponse;
+
+const MAX_JOURNAL_STEPS: u64 = 50;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JournalStepParams {
+    #[serde(default)]
+    pub count: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JournalQueryParams {
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+// 返回 (是否有积分权限, 是否有管理员权限)
+fn journal_permissions(state: &Arc<RwLock<AppState>>, webview: &Webview) -> (bool, bool) {
+    let state_guard = state.read();
+    let mut permissions = state_guard.permissions.write();
+    let session = window_session_key(webview.label());
+    (
+        permissions.require_permission(&session, PermissionLevel::Points),
+        permissions.require_permission(&session, PermissionLevel::Admin),
+    )
+}
+
+#[tauri::command]
+pub async fn journal_query(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    params: Option<JournalQueryParams>,
+) -> Result<IpcResponse<Vec<JournalEntry>>, String> {
+    let (can_points, _) = journal_permissions(&state, &webview);
+    if !can_points {
+        return Ok(IpcResponse::error("Permission denied: points required"));
+    }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let limit = params.and_then(|p| p.limit).unwrap_or(50).clamp(1, 200);
+    match query_journal(&conn, limit).await {
+        Ok(entries) => Ok(IpcResponse::success(entries)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
+#[tauri::command]
+pub async fn journal_undo(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    params: Option<JournalStepParams>,
+) -> Result<IpcResponse<Vec<JournalEntry>>, String> {
+    journal_step(&state, &webview, params, true).await
+}
+
+#[tauri::command]
+pub async fn journal_redo(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    params: Option<JournalStepParams>,
+) -> Result<IpcResponse<Vec<JournalEntry>>, String> {
+    journal_step(&state, &webview, params, false).await
+}
+
+async fn journal_step(
+    state: &State<'_, Arc<RwLock<AppState>>>,
+    webview: &Webview,
+    params: Option<JournalStepParams>,
+    undo: bool,
+) -> Result<IpcResponse<Vec<JournalEntry>>, String> {
+    let (can_points, can_admin) = journal_permissions(state, webview);
+    if !can_points {
+        return Ok(IpcResponse::error("Permission denied: points required"));
+    }
+
+    let count = params
+        .and_then(|p| p.count)
+        .unwrap_or(1)
+        .clamp(1, MAX_JOURNAL_STEPS);
+
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let result = if undo {
+        undo_operations(&conn, count, can_admin).await
+    } else {
+        redo_operations(&conn, count, can_admin).await
+    };
+
+    match result {
+        Ok(entries) => {
+            {
+                let state_guard = state.read();
+                state_guard.logger.read().info_with_meta(
+                    if undo {
+                        "Journal operations undone"
+                    } else {
+                        "Journal operations redone"
+                    },
+                    json!({
+                        "count": entries.len(),
+                        "ids": entries.iter().map(|e| e.id).collect::<Vec<_>>(),
+                    }),
+                );
+            }
+            realtime_dual_write_sync_if_legacy(state.inner()).await?;
+            state
+                .read()
+                .event_bus
+                .publish(EventSource::Desktop, BusPayload::DataReset);
+            Ok(IpcResponse::success(entries))
+        }
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
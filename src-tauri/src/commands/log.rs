@@ -1,10 +1,10 @@
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 
 use crate::services::logger::LogLevel;
-use crate::services::permission::PermissionLevel;
+use crate::services::permission::{window_session_key, PermissionLevel};
 use crate::state::AppState;
 
 use super::response::IpcResponse;
@@ -17,11 +17,11 @@ pub struct LogWritePayload {
     pub meta: Option<serde_json::Value>,
 }
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> Result<(), String> {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let sender_id = 0;
-    if !permissions.require_permission(sender_id, PermissionLevel::Admin) {
+    let session = window_session_key(webview.label());
+    if !permissions.require_permission(&session, PermissionLevel::Admin) {
         return Err("Permission denied: Admin required".to_string());
     }
     Ok(())
@@ -41,8 +41,11 @@ pub async fn log_query(
 }
 
 #[tauri::command]
-pub async fn log_clear(state: State<'_, Arc<RwLock<AppState>>>) -> Result<IpcResponse<()>, String> {
-    check_admin_permission(&state)?;
+pub async fn log_clear(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<()>, String> {
+    check_admin_permission(&state, &webview)?;
 
     let state_guard = state.read();
     let logger = state_guard.logger.read();
@@ -54,9 +57,10 @@ pub async fn log_clear(state: State<'_, Arc<RwLock<AppState>>>) -> Result<IpcRes
 #[tauri::command]
 pub async fn log_set_level(
     level: String,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
     let log_level =
         LogLevel::from_str(&level).ok_or_else(|| format!("Invalid log level: {}", level))?;
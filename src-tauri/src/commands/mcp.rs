@@ -1,10 +1,16 @@
+use axum::extract::ConnectInfo;
+use axum::http::request::Parts;
 use axum::Router;
 use parking_lot::RwLock;
 use rmcp::{
     handler::server::{router::tool::ToolRouter, wrapper::Parameters},
     model::{
-        CallToolResult, Content, Implementation, InitializeResult, ProtocolVersion,
-        ServerCapabilities, ServerInfo,
+        AnnotateAble, CallToolResult, Content, GetPromptRequestParams, GetPromptResult,
+        Implementation, InitializeResult, ListPromptsResult, ListResourceTemplatesResult,
+        ListResourcesResult, PaginatedRequestParams, Prompt, PromptArgument, PromptMessage,
+        PromptMessageRole, ProtocolVersion, RawResource, RawResourceTemplate,
+        ReadResourceRequestParams, ReadResourceResult, ResourceContents, ServerCapabilities,
+        ServerInfo,
     },
     schemars,
     service::RequestContext,
@@ -14,46 +20,78 @@ use rmcp::{
     },
     ErrorData as McpError, RoleServer, ServerHandler,
 };
-use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
-    TransactionTrait,
-};
 use sea_orm::prelude::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::fs;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::{Emitter, State};
+use tauri::{State, Webview};
 use tokio::sync::{oneshot, Mutex};
 use tokio_util::sync::CancellationToken;
-use uuid::Uuid;
 
-use crate::db::entities::{score_events, students};
-use crate::services::permission::PermissionLevel;
+use crate::db::entities::{reward_settings, score_events, students, tags};
+use crate::db::repositories::{SettlementLeaderboard, SettlementRepository};
+use crate::models::SettlementSummary;
+use crate::services::permission::{
+    emit_permission_changes, mcp_client_key, mcp_session_key, window_session_key, PermissionLevel,
+};
+use crate::services::profile::{
+    build_student_profile, ProfileBucket, StudentProfile, StudentProfileParams,
+};
+use crate::services::reason::{self, ReasonFilter};
+use crate::services::{reward, score, tag, AuthService};
+use crate::services::{AutoScoreRule, BusPayload, EventSource};
 use crate::state::AppState;
 
+use super::auto_score::{set_rule_enabled, sync_cached_rules};
 use super::database::realtime_dual_write_sync_if_legacy;
+use super::event::{
+    query_events_page, query_leaderboard, EventPage, EventPageParams, LeaderboardGroupBy,
+    LeaderboardParams, LeaderboardResult, SettlementScope,
+};
+use super::reason::{create_reason, CreateReason, Reason};
 use super::response::IpcResponse;
+use super::reward::RedeemRewardResult;
 
 const SECAGENT_SERVER_NAME: &str = "secscore";
 const SECAGENT_SERVER_URL: &str = "http://127.0.0.1:3901/mcp";
 const SECAGENT_SKILL_DIR: &str = "secscore";
 const SECAGENT_SKILL: &str = r#"---
 name: SecScore
-description: 查询学生、调整积分和撤销积分操作。
+description: 查询学生、理由、积分记录和排行，调整积分、兑换奖品和管理标签。
 ---
 # SecScore
 
-SecScore 提供学生查询、积分变更和撤销工具。涉及写入时，先确认学生身份和变更原因；完成后用中文简洁说明真实结果。
+SecScore 提供学生查询、积分变更、撤销、兑换、标签和自动加分管理工具。涉及写入时，先确认学生身份和变更原因；完成后用中文简洁说明真实结果。
 
 ## 工具
 
+- `secscore__login`：用 6 位数字口令提升本会话权限。参数：`password`（字符串）。提示权限不足时先向用户索取口令再调用。
 - `secscore__list_students`：列出学生。参数：`limit`（可选，整数）。
 - `secscore__find_students`：按姓名或关键词查找学生。参数：`query`（字符串）、`limit`（可选，整数）。
 - `secscore__add_score`：给学生增加或扣减积分。参数：`student_id`（可选整数）、`student_name`（可选字符串，用于二次确认）、`delta`（整数，负数表示扣分）、`reason_content`（可选字符串）。
 - `secscore__undo_score`：撤销一条积分操作。参数：`event_uuid`（字符串）、`student_id`（整数）。只能撤销真实存在且允许撤销的记录。
+- `secscore__student_profile`：学生概览。参数：`student_id` 或 `student_name`、`bucket`（可选，day/week）、`start_date`、`end_date`（可选，YYYY-MM-DD）。
+- `secscore__list_reasons`：列出加减分理由。参数：`category_id`、`set`、`include_archived`（均可选）。
+- `secscore__create_reason`：新建理由（管理员）。参数：`content`、`delta`、`category` 或 `category_id`。
+- `secscore__query_events`：查询积分记录。参数：`start_time`、`end_time`、`student_id`、`student_name`、`reason_content`、`unsettled_only`、`limit`、`cursor`（均可选）。
+- `secscore__leaderboard`：积分排行。参数：`range`（today/week/month）、`start_time`、`end_time`、`group_by`（group/tag）（均可选）。
+- `secscore__list_rewards`：列出奖品。参数：`include_inactive`（可选）。
+- `secscore__redeem_reward`：兑换奖品。参数：`student_id` 或 `student_name`、`reward_id`。
+- `secscore__list_tags`：列出标签。
+- `secscore__set_student_tags`：整体替换学生标签（管理员）。参数：`student_id` 或 `student_name`、`tag_ids`。
+- `secscore__list_settlements`：列出历次结算。
+- `secscore__settlement_leaderboard`：某次结算的排行。参数：`settlement_id`。
+- `secscore__list_auto_score_rules`：列出自动加分规则（管理员）。
+- `secscore__toggle_auto_score_rule`：启用或停用自动加分规则（管理员）。参数：`rule_id`、`enabled`。
+
+## 资源与提示词
+
+- 资源：`secscore://students`、`secscore://reasons`、`secscore://tags`、`secscore://rewards`、`secscore://settlements`、`secscore://leaderboard/{today|week|month}`、`secscore://students/{id}/profile`。
+- 提示词：`weekly_report`、`student_review`、`record_behavior`。
 
 优先使用查询工具确认学生，再执行写入；不要臆造学生 ID、事件 UUID 或操作结果。
 "#;
@@ -61,7 +99,13 @@ const SECAGENT_MCP: &str = r#"{
   "name": "secscore",
   "transport": "http",
   "url": "http://127.0.0.1:3901/mcp",
-  "tools": ["list_students", "find_students", "add_score", "undo_score"]
+  "tools": [
+    "login", "list_students", "find_students", "add_score", "undo_score", "student_profile",
+    "list_reasons", "create_reason", "query_events", "leaderboard",
+    "list_rewards", "redeem_reward", "list_tags", "set_student_tags",
+    "list_settlements", "settlement_leaderboard",
+    "list_auto_score_rules", "toggle_auto_score_rule"
+  ]
 }
 "#;
 
@@ -259,6 +303,22 @@ struct ListStudentsResult {
     students: Vec<StudentListItem>,
 }
 
+#[derive(Debug, Deserialize, Default, schemars::JsonSchema)]
+struct StudentProfileArgs {
+    #[serde(default)]
+    student_id: Option<i32>,
+    #[serde(default)]
+    student_name: Option<String>,
+    /// day 或 week，默认 day
+    #[serde(default)]
+    bucket: Option<String>,
+    /// YYYY-MM-DD，含当天
+    #[serde(default)]
+    start_date: Option<String>,
+    #[serde(default)]
+    end_date: Option<String>,
+}
+
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 struct UndoScoreArgs {
     event_uuid: String,
@@ -274,13 +334,175 @@ struct UndoScoreResult {
     val_curr: i32,
 }
 
+#[derive(Debug, Deserialize, Default, schemars::JsonSchema)]
+struct ListReasonsArgs {
+    #[serde(default)]
+    category_id: Option<i32>,
+    /// 快捷组 ID 或名称
+    #[serde(default)]
+    set: Option<String>,
+    #[serde(default)]
+    include_archived: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListReasonsResult {
+    total: usize,
+    reasons: Vec<Reason>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct CreateReasonArgs {
+    content: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    category_id: Option<i32>,
+    delta: i32,
+}
+
+#[derive(Debug, Deserialize, Default, schemars::JsonSchema)]
+struct QueryEventsArgs {
+    /// RFC3339 或 YYYY-MM-DD，含起点
+    #[serde(default)]
+    start_time: Option<String>,
+    /// RFC3339 或 YYYY-MM-DD，含当天
+    #[serde(default)]
+    end_time: Option<String>,
+    #[serde(default)]
+    student_id: Option<i32>,
+    #[serde(default)]
+    student_name: Option<String>,
+    #[serde(default)]
+    reason_content: Option<String>,
+    /// true 时只查当前结算周期内的记录
+    #[serde(default)]
+    unsettled_only: Option<bool>,
+    #[serde(default)]
+    limit: Option<i32>,
+    /// 上一页返回的 next_cursor
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default, schemars::JsonSchema)]
+struct LeaderboardArgs {
+    /// today、week 或 month，默认 today
+    #[serde(default)]
+    range: Option<String>,
+    #[serde(default)]
+    start_time: Option<String>,
+    #[serde(default)]
+    end_time: Option<String>,
+    /// group 或 tag，指定后附带分组合计
+    #[serde(default)]
+    group_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default, schemars::JsonSchema)]
+struct ListRewardsArgs {
+    /// 是否包含不在兑换时间窗口内的奖品
+    #[serde(default)]
+    include_inactive: Option<bool>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+struct RewardListItem {
+    id: i32,
+    name: String,
+    cost_points: i32,
+    stock: Option<i32>,
+    per_student_limit: Option<i32>,
+    per_period_limit: Option<i32>,
+    active: bool,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+struct ListRewardsResult {
+    total: usize,
+    rewards: Vec<RewardListItem>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct RedeemRewardArgs {
+    #[serde(default)]
+    student_id: Option<i32>,
+    #[serde(default)]
+    student_name: Option<String>,
+    reward_id: i32,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+struct TagListItem {
+    id: i32,
+    name: String,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+struct ListTagsResult {
+    total: usize,
+    tags: Vec<TagListItem>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct SetStudentTagsArgs {
+    #[serde(default)]
+    student_id: Option<i32>,
+    #[serde(default)]
+    student_name: Option<String>,
+    /// 整体替换后的标签 ID，空数组表示清空
+    tag_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+struct SetStudentTagsResult {
+    student_id: i32,
+    student_name: String,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListSettlementsResult {
+    total: usize,
+    settlements: Vec<SettlementSummary>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct SettlementLeaderboardArgs {
+    settlement_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+struct ListAutoScoreRulesResult {
+    total: usize,
+    rules: Vec<AutoScoreRule>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct LoginArgs {
+    /// 管理员或积分口令，6 位数字
+    password: String,
+}
+
+#[derive(Debug, Serialize)]
+struct LoginResult {
+    permission: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct ToggleAutoScoreRuleArgs {
+    rule_id: i32,
+    enabled: bool,
+}
+
 static MCP_SERVER_STATE: once_cell::sync::Lazy<Arc<Mutex<McpServerState>>> =
     once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(McpServerState::default())));
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> Result<(), String> {
+    let session = window_session_key(webview.label());
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    if !permissions.require_permission(0, PermissionLevel::Admin) {
+    if !permissions.require_permission(&session, PermissionLevel::Admin) {
         return Err("Permission denied: Admin required".to_string());
     }
     Ok(())
@@ -300,9 +522,69 @@ fn mcp_log_error(app_state: &Arc<RwLock<AppState>>, message: &str, meta: Value)
     logger.error_with_meta(message, meta.clone());
 }
 
+fn structured_result<T: Serialize>(payload: &T, text: String) -> Result<CallToolResult, McpError> {
+    let structured =
+        serde_json::to_value(payload).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    let mut result = CallToolResult::structured(structured);
+    result.content = vec![Content::text(text)];
+    Ok(result)
+}
+
+/// 开启服务即视为授予积分权限；未设置口令时与窗口一样默认管理员
+fn mcp_base_permission(default: PermissionLevel) -> PermissionLevel {
+    if default.rank() > PermissionLevel::Points.rank() {
+        default
+    } else {
+        PermissionLevel::Points
+    }
+}
+
+fn require_mcp_permission(
+    app_state: &Arc<RwLock<AppState>>,
+    session: &str,
+    level: PermissionLevel,
+) -> Result<(), String> {
+    let state_guard = app_state.read();
+    let mut permissions = state_guard.permissions.write();
+    let base = mcp_base_permission(permissions.get_default_permission());
+    permissions.bind_session(session, base);
+    if permissions.require_permission(session, level) {
+        Ok(())
+    } else {
+        Err(format!(
+            "权限不足：需要 {} 权限，可先调用 login 提升权限",
+            level.as_str()
+        ))
+    }
+}
+
+/// 登录失败计数的客户端标识：优先取对端 IP，取不到时退回客户端自报的名称
+fn mcp_client_id(context: &RequestContext<RoleServer>) -> String {
+    context
+        .extensions
+        .get::<Parts>()
+        .and_then(|parts| parts.extensions.get::<ConnectInfo<SocketAddr>>())
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .or_else(|| {
+            context
+                .peer
+                .peer_info()
+                .map(|info| info.client_info.name.clone())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn flush_permission_changes(app_state: &Arc<RwLock<AppState>>) {
+    let state_guard = app_state.read();
+    let changes = state_guard.permissions.write().take_changes();
+    emit_permission_changes(&state_guard.app_handle, &changes);
+}
+
 #[derive(Clone)]
 struct SecScoreMcpServer {
     app_state: Arc<RwLock<AppState>>,
+    // 每个传输层会话各建一个实例，权限会话随实例生成，不取客户端请求头
+    session: String,
     tool_router: ToolRouter<SecScoreMcpServer>,
 }
 
@@ -311,10 +593,50 @@ impl SecScoreMcpServer {
     fn new(app_state: Arc<RwLock<AppState>>) -> Self {
         Self {
             app_state,
+            session: mcp_session_key(&uuid::Uuid::new_v4().to_string()),
             tool_router: Self::tool_router(),
         }
     }
 
+    #[tool(
+        name = "login",
+        description = "输入 6 位数字口令提升本会话权限（积分或管理员）。连续失败会被锁定一段时间。"
+    )]
+    async fn login(
+        &self,
+        Parameters(args): Parameters<LoginArgs>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        let result = AuthService::login(
+            &self.app_state,
+            &self.session,
+            &mcp_client_key(&mcp_client_id(&context)),
+            &args.password,
+        )
+        .await;
+        flush_permission_changes(&self.app_state);
+        match result.permission {
+            Some(permission) if result.success => {
+                mcp_log_info(
+                    &self.app_state,
+                    "mcp:login_succeeded",
+                    json!({ "permission": permission }),
+                );
+                let text = format!("已登录，当前权限：{}", permission);
+                structured_result(&LoginResult { permission }, text)
+            }
+            _ => Ok(CallToolResult::error(vec![Content::text(format!(
+                "登录失败: {}",
+                result.message.unwrap_or_else(|| "Login failed".to_string())
+            ))])),
+        }
+    }
+
     #[tool(
         name = "add_score",
         description = "给指定学生加分/扣分，并写入 score_events 记录。优先传入 student_id，避免同名学生误操作。"
@@ -323,6 +645,11 @@ impl SecScoreMcpServer {
         &self,
         Parameters(args): Parameters<AddScoreArgs>,
     ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::Points)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
         match mcp_add_score(&self.app_state, args).await {
             Ok(payload) => {
                 let text = format!(
@@ -360,6 +687,11 @@ impl SecScoreMcpServer {
         &self,
         Parameters(args): Parameters<ListStudentsArgs>,
     ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
         match mcp_list_students(&self.app_state, args).await {
             Ok(payload) => {
                 let text = format!("已获取 {} 名学生", payload.total);
@@ -386,14 +718,16 @@ impl SecScoreMcpServer {
         }
     }
 
-    #[tool(
-        name = "find_students",
-        description = "按姓名关键词查找学生。"
-    )]
+    #[tool(name = "find_students", description = "按姓名关键词查找学生。")]
     async fn find_students(
         &self,
         Parameters(args): Parameters<FindStudentsArgs>,
     ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
         match mcp_find_students(&self.app_state, args).await {
             Ok(payload) => {
                 let text = format!("找到 {} 名学生", payload.total);
@@ -403,7 +737,45 @@ impl SecScoreMcpServer {
                 result.content = vec![Content::text(text)];
                 Ok(result)
             }
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!("查询学生失败: {}", e))])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "查询学生失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "student_profile",
+        description = "获取学生概览：按天/周的积分与奖励积分曲线、各理由分类合计、连续加分天数、历次结算排名、兑换记录和标签。"
+    )]
+    async fn student_profile(
+        &self,
+        Parameters(args): Parameters<StudentProfileArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_student_profile(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!(
+                    "{}：当前 {} 分，奖励积分 {}，当前连续加分 {} 天",
+                    payload.student.name,
+                    payload.student.score,
+                    payload.student.reward_points,
+                    payload.streaks.current
+                );
+                let structured = serde_json::to_value(&payload)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let mut result = CallToolResult::structured(structured);
+                result.content = vec![Content::text(text)];
+                Ok(result)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取学生概览失败: {}",
+                e
+            ))])),
         }
     }
 
@@ -415,6 +787,11 @@ impl SecScoreMcpServer {
         &self,
         Parameters(args): Parameters<UndoScoreArgs>,
     ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::Points)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
         match mcp_undo_score(&self.app_state, args).await {
             Ok(payload) => {
                 let text = format!("已撤销：{} {:+} 分", payload.student_name, -payload.delta);
@@ -424,7 +801,356 @@ impl SecScoreMcpServer {
                 result.content = vec![Content::text(text)];
                 Ok(result)
             }
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!("撤销失败: {}", e))])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "撤销失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "list_reasons",
+        description = "获取加减分理由列表，可按分类或快捷组筛选。加分前先用它选取合适的理由。"
+    )]
+    async fn list_reasons(
+        &self,
+        Parameters(args): Parameters<ListReasonsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_list_reasons(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!("已获取 {} 条理由", payload.total);
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取理由失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "create_reason",
+        description = "新建一条加减分理由。需要管理员权限；category 不存在时会自动创建分类。"
+    )]
+    async fn create_reason(
+        &self,
+        Parameters(args): Parameters<CreateReasonArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::Admin)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_create_reason(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!("已新建理由：{}（{:+}）", payload.content, payload.delta);
+                structured_result(&payload, text)
+            }
+            Err(e) => {
+                mcp_log_error(
+                    &self.app_state,
+                    "mcp:tool_call_failed",
+                    json!({ "tool": "create_reason", "error": e }),
+                );
+                Ok(CallToolResult::error(vec![Content::text(format!(
+                    "新建理由失败: {}",
+                    e
+                ))]))
+            }
+        }
+    }
+
+    #[tool(
+        name = "query_events",
+        description = "按时间范围、学生或理由分页查询积分记录，默认包含已结算的历史记录。"
+    )]
+    async fn query_events(
+        &self,
+        Parameters(args): Parameters<QueryEventsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_query_events(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!(
+                    "共 {} 条记录，本页 {} 条",
+                    payload.total,
+                    payload.items.len()
+                );
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "查询积分记录失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "leaderboard",
+        description = "获取今日/本周/本月或自定义时间段的积分排行，可按小组或标签汇总。"
+    )]
+    async fn leaderboard(
+        &self,
+        Parameters(args): Parameters<LeaderboardArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_leaderboard(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!("排行榜共 {} 名学生", payload.rows.len());
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取排行榜失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "list_rewards",
+        description = "获取可兑换的奖品，包含所需奖励积分、库存和兑换限制。"
+    )]
+    async fn list_rewards(
+        &self,
+        Parameters(args): Parameters<ListRewardsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_list_rewards(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!("已获取 {} 个奖品", payload.total);
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取奖品失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "redeem_reward",
+        description = "为学生兑换奖品并扣除奖励积分。需要审批的奖品会进入待审批状态。"
+    )]
+    async fn redeem_reward(
+        &self,
+        Parameters(args): Parameters<RedeemRewardArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::Points)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_redeem_reward(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!(
+                    "已兑换（{}），剩余奖励积分 {}",
+                    payload.status, payload.remaining_reward_points
+                );
+                structured_result(&payload, text)
+            }
+            Err(e) => {
+                mcp_log_error(
+                    &self.app_state,
+                    "mcp:tool_call_failed",
+                    json!({ "tool": "redeem_reward", "error": e }),
+                );
+                Ok(CallToolResult::error(vec![Content::text(format!(
+                    "兑换失败: {}",
+                    e
+                ))]))
+            }
+        }
+    }
+
+    #[tool(name = "list_tags", description = "获取全部学生标签及其 ID。")]
+    async fn list_tags(&self) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_list_tags(&self.app_state).await {
+            Ok(payload) => {
+                let text = format!("已获取 {} 个标签", payload.total);
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取标签失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "set_student_tags",
+        description = "整体替换学生的标签。需要管理员权限；tag_ids 先用 list_tags 查询。"
+    )]
+    async fn set_student_tags(
+        &self,
+        Parameters(args): Parameters<SetStudentTagsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::Admin)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_set_student_tags(&self.app_state, args).await {
+            Ok(payload) => {
+                let text = format!(
+                    "{} 的标签已更新为：{}",
+                    payload.student_name,
+                    payload.tags.join("、")
+                );
+                structured_result(&payload, text)
+            }
+            Err(e) => {
+                mcp_log_error(
+                    &self.app_state,
+                    "mcp:tool_call_failed",
+                    json!({ "tool": "set_student_tags", "error": e }),
+                );
+                Ok(CallToolResult::error(vec![Content::text(format!(
+                    "设置标签失败: {}",
+                    e
+                ))]))
+            }
+        }
+    }
+
+    #[tool(
+        name = "list_settlements",
+        description = "获取历次结算的时间段和记录数，最新的在前。"
+    )]
+    async fn list_settlements(&self) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_list_settlements(&self.app_state).await {
+            Ok(payload) => {
+                let text = format!("共 {} 次结算", payload.total);
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取结算记录失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "settlement_leaderboard",
+        description = "获取某次结算周期内的学生积分排行。"
+    )]
+    async fn settlement_leaderboard(
+        &self,
+        Parameters(args): Parameters<SettlementLeaderboardArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match mcp_settlement_leaderboard(&self.app_state, args.settlement_id).await {
+            Ok(payload) => {
+                let text = format!("结算 #{} 排行已获取", args.settlement_id);
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取结算排行失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "list_auto_score_rules",
+        description = "获取自动加分规则及其启用状态、触发条件和上次执行时间。需要管理员权限。"
+    )]
+    async fn list_auto_score_rules(&self) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::Admin)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        match sync_cached_rules(&self.app_state).await {
+            Ok(rules) => {
+                let payload = ListAutoScoreRulesResult {
+                    total: rules.len(),
+                    rules,
+                };
+                let enabled = payload.rules.iter().filter(|rule| rule.enabled).count();
+                let text = format!("共 {} 条规则，{} 条已启用", payload.total, enabled);
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "获取自动加分规则失败: {}",
+                e
+            ))])),
+        }
+    }
+
+    #[tool(
+        name = "toggle_auto_score_rule",
+        description = "启用或停用一条自动加分规则。需要管理员权限。"
+    )]
+    async fn toggle_auto_score_rule(
+        &self,
+        Parameters(args): Parameters<ToggleAutoScoreRuleArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::Admin)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        let app_handle = { self.app_state.read().app_handle.clone() };
+        let result = set_rule_enabled(&self.app_state, &app_handle, args.rule_id, args.enabled)
+            .await
+            .and_then(|inner| inner);
+        match result {
+            Ok(rules) => {
+                mcp_log_info(
+                    &self.app_state,
+                    "mcp:tool_call_succeeded",
+                    json!({
+                        "tool": "toggle_auto_score_rule",
+                        "rule_id": args.rule_id,
+                        "enabled": args.enabled
+                    }),
+                );
+                let payload = ListAutoScoreRulesResult {
+                    total: rules.len(),
+                    rules,
+                };
+                let text = format!(
+                    "规则 #{} 已{}",
+                    args.rule_id,
+                    if args.enabled { "启用" } else { "停用" }
+                );
+                structured_result(&payload, text)
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "切换规则失败: {}",
+                e
+            ))])),
         }
     }
 }
@@ -432,9 +1158,15 @@ impl SecScoreMcpServer {
 #[tool_handler]
 impl ServerHandler for SecScoreMcpServer {
     fn get_info(&self) -> ServerInfo {
-        ServerInfo::new(ServerCapabilities::builder().enable_tools().build())
-            .with_server_info(Implementation::new("secscore-mcp", "1.0.0"))
-            .with_protocol_version(ProtocolVersion::V_2024_11_05)
+        ServerInfo::new(
+            ServerCapabilities::builder()
+                .enable_prompts()
+                .enable_resources()
+                .enable_tools()
+                .build(),
+        )
+        .with_server_info(Implementation::new("secscore-mcp", "1.0.0"))
+        .with_protocol_version(ProtocolVersion::V_2024_11_05)
     }
 
     async fn initialize(
@@ -448,6 +1180,144 @@ impl ServerHandler for SecScoreMcpServer {
         mcp_log_info(&self.app_state, "mcp:initialized", json!({}));
         Ok(self.get_info())
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let resources = MCP_RESOURCES
+            .iter()
+            .map(|(uri, name, description)| {
+                RawResource::new(*uri, *name)
+                    .with_description(*description)
+                    .with_mime_type("application/json")
+                    .no_annotation()
+            })
+            .collect();
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let templates = vec![
+            RawResourceTemplate::new("secscore://leaderboard/{range}", "leaderboard")
+                .with_description("积分排行，range 为 today、week 或 month")
+                .with_mime_type("application/json")
+                .no_annotation(),
+            RawResourceTemplate::new("secscore://students/{id}/profile", "student_profile")
+                .with_description("单个学生的积分曲线、连续加分天数和结算排名")
+                .with_mime_type("application/json")
+                .no_annotation(),
+        ];
+        Ok(ListResourceTemplatesResult::with_all_items(templates))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        if let Err(e) =
+            require_mcp_permission(&self.app_state, &self.session, PermissionLevel::View)
+        {
+            return Err(McpError::invalid_request(e, None));
+        }
+        let value = mcp_read_resource(&self.app_state, &request.uri)
+            .await
+            .map_err(|e| McpError::resource_not_found(e, Some(json!({ "uri": request.uri }))))?;
+        let text = serde_json::to_string_pretty(&value)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(ReadResourceResult::new(vec![ResourceContents::text(
+            text,
+            request.uri,
+        )
+        .with_mime_type("application/json")]))
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        Ok(ListPromptsResult::with_all_items(mcp_prompts()))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let arguments = request.arguments.unwrap_or_default();
+        let argument = |name: &str| {
+            arguments
+                .get(name)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let (description, text) = match request.name.as_str() {
+            "weekly_report" => {
+                let scope = match argument("group_name") {
+                    Some(group) => format!("「{}」小组", group),
+                    None => "全班".to_string(),
+                };
+                (
+                    "生成本周积分周报",
+                    format!(
+                        "请为{scope}生成本周积分周报。先读取 secscore://leaderboard/week（需要按小组汇总时调用 leaderboard 并传 group_by=group），\
+                         再用 query_events 查询本周一至今的积分记录。周报包括：进步最大和扣分较多的学生、最常用的加减分理由、与上次结算相比的变化（list_settlements）。\
+                         只引用工具返回的真实数据，用中文分段输出。"
+                    ),
+                )
+            }
+            "student_review" => {
+                let Some(student) = argument("student_name") else {
+                    return Err(McpError::invalid_params("缺少参数 student_name", None));
+                };
+                (
+                    "回顾单个学生的积分表现",
+                    format!(
+                        "请回顾学生「{student}」的积分表现。先用 find_students 确认学生 ID，再调用 student_profile 获取积分曲线、连续加分天数和结算排名，\
+                         必要时用 query_events 查看具体记录。总结优点、需要关注的问题和建议，语气积极、简洁。"
+                    ),
+                )
+            }
+            "record_behavior" => {
+                let (Some(student), Some(behavior)) =
+                    (argument("student_name"), argument("behavior"))
+                else {
+                    return Err(McpError::invalid_params(
+                        "缺少参数 student_name 或 behavior",
+                        None,
+                    ));
+                };
+                (
+                    "按课堂表现给学生记分",
+                    format!(
+                        "学生「{student}」的表现：{behavior}。请先用 find_students 确认学生，再用 list_reasons 选出最贴切的理由和分值；\
+                         没有合适理由时先向我确认再决定是否 create_reason。确认后调用 add_score 记录，并告诉我记录的 event_uuid 以便撤销。"
+                    ),
+                )
+            }
+            other => {
+                return Err(McpError::invalid_params(
+                    format!("未知的提示词：{}", other),
+                    None,
+                ))
+            }
+        };
+
+        Ok(
+            GetPromptResult::new(vec![PromptMessage::new_text(PromptMessageRole::User, text)])
+                .with_description(description),
+        )
+    }
 }
 
 async fn mcp_add_score(
@@ -479,73 +1349,33 @@ async fn mcp_add_score(
     }
     .ok_or_else(|| "Database not connected".to_string())?;
 
-    let student = if let Some(student_id) = args.student_id {
-        students::Entity::find_by_id(student_id)
-            .one(&db_conn)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| format!("Student not found: {}", student_id))?
-    } else {
-        let student_name = args.student_name.as_deref().unwrap_or("").trim();
-        if student_name.is_empty() {
-            return Err("student_id 或 student_name 至少提供一个".to_string());
-        }
-        students::Entity::find()
-            .filter(students::Column::Name.eq(student_name))
-            .one(&db_conn)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| format!("Student not found: {}", student_name))?
-    };
-    let student_id = student.id;
-    let student_name = student.name.clone();
-
-    let val_prev = student.score;
-    let val_curr = val_prev + args.delta;
-    let reward_curr = student.reward_points + args.delta;
-    let event_time = chrono::Utc::now()
-        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-        .to_string();
-    let event_uuid = Uuid::new_v4().to_string();
-
-    let txn = db_conn.begin().await.map_err(|e| e.to_string())?;
-
-    let new_event = score_events::ActiveModel {
-        id: sea_orm::ActiveValue::NotSet,
-        uuid: Set(event_uuid.clone()),
-        student_name: Set(student_name.clone()),
-        reason_content: Set(reason_content.clone()),
-        delta: Set(args.delta),
-        val_prev: Set(val_prev),
-        val_curr: Set(val_curr),
-        event_time: Set(event_time.clone()),
-        settlement_id: Set(None),
-    };
-
-    let inserted = new_event.insert(&txn).await.map_err(|e| e.to_string())?;
-
-    let mut student_model: students::ActiveModel = student.into();
-    student_model.score = Set(val_curr);
-    student_model.reward_points = Set(reward_curr);
-    student_model.updated_at = Set(event_time.clone());
-    student_model
-        .update(&txn)
-        .await
-        .map_err(|e| e.to_string())?;
+    let student_name = args.student_name.as_deref().unwrap_or("").trim();
+    if args.student_id.is_none() && student_name.is_empty() {
+        return Err("student_id 或 student_name 至少提供一个".to_string());
+    }
 
-    txn.commit().await.map_err(|e| e.to_string())?;
+    let local_write_lock = { app_state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let inserted = score::add_score(
+        &db_conn,
+        args.student_id,
+        student_name,
+        args.delta,
+        &reason_content,
+        None,
+    )
+    .await?;
+    let student_id = inserted.student_id.unwrap_or_default();
+    let student_name = inserted.student_name.clone();
+    let event_uuid = inserted.uuid.clone();
 
     realtime_dual_write_sync_if_legacy(app_state).await?;
-    {
-        let state_guard = app_state.read();
-        let _ = state_guard.app_handle.emit(
-            "ss:data-updated",
-            json!({
-                "category": "all",
-                "source": "mcp"
-            }),
-        );
-    }
+    app_state.read().event_bus.publish(
+        EventSource::Mcp,
+        BusPayload::ScoreEventsCreated {
+            events: vec![inserted.clone()],
+        },
+    );
 
     mcp_log_info(
         app_state,
@@ -566,10 +1396,10 @@ async fn mcp_add_score(
         student_id,
         student_name,
         delta: args.delta,
-        val_prev,
-        val_curr,
+        val_prev: inserted.val_prev,
+        val_curr: inserted.val_curr,
         reason_content,
-        event_time,
+        event_time: inserted.event_time,
     })
 }
 
@@ -610,35 +1440,22 @@ async fn mcp_undo_score(
         .await
         .map_err(|e| e.to_string())?
         .ok_or_else(|| format!("Student not found: {}", args.student_id))?;
-    if student.name != event.student_name {
+    if event.student_id != Some(student.id) {
         return Err("学生与积分事件不匹配，拒绝撤销".to_string());
     }
-
     let next_score = student.score - event.delta;
-    let next_reward_points = student.reward_points - event.delta;
-    let now = chrono::Utc::now()
-        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-        .to_string();
-    let txn = db_conn.begin().await.map_err(|e| e.to_string())?;
-    let mut active: students::ActiveModel = student.into();
-    active.score = Set(next_score);
-    active.reward_points = Set(next_reward_points);
-    active.updated_at = Set(now);
-    active.update(&txn).await.map_err(|e| e.to_string())?;
-    score_events::Entity::delete_by_id(event.id)
-        .exec(&txn)
-        .await
-        .map_err(|e| e.to_string())?;
-    txn.commit().await.map_err(|e| e.to_string())?;
+
+    let local_write_lock = { app_state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let event = score::revert_score_event(&db_conn, event_uuid).await?;
 
     realtime_dual_write_sync_if_legacy(app_state).await?;
-    {
-        let state_guard = app_state.read();
-        let _ = state_guard.app_handle.emit(
-            "ss:data-updated",
-            json!({ "category": "all", "source": "mcp" }),
-        );
-    }
+    app_state.read().event_bus.publish(
+        EventSource::Mcp,
+        BusPayload::ScoreEventsDeleted {
+            events: vec![event.clone()],
+        },
+    );
     mcp_log_info(
         app_state,
         "mcp:tool_call_succeeded",
@@ -748,12 +1565,412 @@ async fn mcp_find_students(
     Ok(ListStudentsResult { total: students.len(), students })
 }
 
+async fn mcp_student_profile(
+    app_state: &Arc<RwLock<AppState>>,
+    args: StudentProfileArgs,
+) -> Result<StudentProfile, String> {
+    let db_conn = {
+        let state_guard = app_state.read();
+        let db_guard = state_guard.db.read();
+        db_guard.clone()
+    }
+    .ok_or_else(|| "Database not connected".to_string())?;
+
+    let bucket = match args.bucket.as_deref().map(str::trim) {
+        None | Some("") | Some("day") => ProfileBucket::Day,
+        Some("week") => ProfileBucket::Week,
+        Some(other) => return Err(format!("不支持的 bucket：{}", other)),
+    };
+    let params = StudentProfileParams {
+        student_id: args.student_id,
+        student_name: args.student_name.unwrap_or_default(),
+        bucket,
+        start_date: args.start_date,
+        end_date: args.end_date,
+    };
+    build_student_profile(&db_conn, &params).await
+}
+
+fn mcp_db_conn(app_state: &Arc<RwLock<AppState>>) -> Result<DatabaseConnection, String> {
+    let state_guard = app_state.read();
+    let db_guard = state_guard.db.read();
+    db_guard
+        .clone()
+        .ok_or_else(|| "Database not connected".to_string())
+}
+
+async fn mcp_list_reasons(
+    app_state: &Arc<RwLock<AppState>>,
+    args: ListReasonsArgs,
+) -> Result<ListReasonsResult, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    let filter = ReasonFilter {
+        include_archived: args.include_archived.unwrap_or(false),
+        category_id: args.category_id,
+        set: args.set,
+    };
+    let reasons = reason::query_reasons(&db_conn, &filter)
+        .await?
+        .into_iter()
+        .map(Reason::from)
+        .collect::<Vec<_>>();
+    Ok(ListReasonsResult {
+        total: reasons.len(),
+        reasons,
+    })
+}
+
+async fn mcp_create_reason(
+    app_state: &Arc<RwLock<AppState>>,
+    args: CreateReasonArgs,
+) -> Result<Reason, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    let data = CreateReason {
+        content: args.content,
+        category: args.category.unwrap_or_default(),
+        category_id: args.category_id,
+        delta: args.delta,
+    };
+    let inserted = create_reason(&db_conn, &data).await?;
+    realtime_dual_write_sync_if_legacy(app_state).await?;
+    mcp_log_info(
+        app_state,
+        "mcp:tool_call_succeeded",
+        json!({ "tool": "create_reason", "reason_id": inserted.id }),
+    );
+    Ok(Reason::from(inserted))
+}
+
+async fn mcp_query_events(
+    app_state: &Arc<RwLock<AppState>>,
+    args: QueryEventsArgs,
+) -> Result<EventPage, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    let params = EventPageParams {
+        limit: args.limit,
+        cursor: args.cursor,
+        student_ids: args.student_id.into_iter().collect(),
+        student_names: args
+            .student_name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .into_iter()
+            .collect(),
+        reason_content: args.reason_content,
+        start_time: args.start_time,
+        end_time: args.end_time,
+        settlement_scope: if args.unsettled_only.unwrap_or(false) {
+            SettlementScope::Unsettled
+        } else {
+            SettlementScope::Any
+        },
+        ..Default::default()
+    };
+    query_events_page(&db_conn, &params).await
+}
+
+async fn mcp_leaderboard(
+    app_state: &Arc<RwLock<AppState>>,
+    args: LeaderboardArgs,
+) -> Result<LeaderboardResult, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    let group_by = match args.group_by.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some("group") => Some(LeaderboardGroupBy::Group),
+        Some("tag") => Some(LeaderboardGroupBy::Tag),
+        Some(other) => return Err(format!("不支持的 group_by：{}", other)),
+    };
+    let params = LeaderboardParams {
+        range: args.range.unwrap_or_default(),
+        start: args.start_time,
+        end: args.end_time,
+        group_by,
+    };
+    query_leaderboard(&db_conn, &params).await
+}
+
+async fn mcp_list_rewards(
+    app_state: &Arc<RwLock<AppState>>,
+    args: ListRewardsArgs,
+) -> Result<ListRewardsResult, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    let now = chrono::Utc::now();
+    let include_inactive = args.include_inactive.unwrap_or(false);
+    let rewards = reward_settings::Entity::find()
+        .order_by_asc(reward_settings::Column::Id)
+        .all(&db_conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|row| RewardListItem {
+            active: reward::is_active(&row, now),
+            id: row.id,
+            name: row.name,
+            cost_points: row.cost_points,
+            stock: row.stock,
+            per_student_limit: row.per_student_limit,
+            per_period_limit: row.per_period_limit,
+        })
+        .filter(|item| include_inactive || item.active)
+        .collect::<Vec<_>>();
+    Ok(ListRewardsResult {
+        total: rewards.len(),
+        rewards,
+    })
+}
+
+async fn mcp_redeem_reward(
+    app_state: &Arc<RwLock<AppState>>,
+    args: RedeemRewardArgs,
+) -> Result<RedeemRewardResult, String> {
+    mcp_log_info(
+        app_state,
+        "mcp:tool_call_started",
+        json!({
+            "tool": "redeem_reward",
+            "student_id": args.student_id,
+            "student_name": args.student_name.clone(),
+            "reward_id": args.reward_id
+        }),
+    );
+    let db_conn = mcp_db_conn(app_state)?;
+
+    let local_write_lock = { app_state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let (redemption, remaining) = reward::redeem(
+        &db_conn,
+        args.student_id,
+        args.student_name.as_deref().unwrap_or_default(),
+        args.reward_id,
+        None,
+    )
+    .await?;
+    realtime_dual_write_sync_if_legacy(app_state).await?;
+
+    let result = RedeemRewardResult {
+        redemption_id: redemption.id,
+        remaining_reward_points: remaining,
+        status: redemption.status.clone(),
+    };
+    app_state.read().event_bus.publish(
+        EventSource::Mcp,
+        BusPayload::RedemptionChanged {
+            redemption: Box::new(redemption),
+        },
+    );
+    mcp_log_info(
+        app_state,
+        "mcp:tool_call_succeeded",
+        json!({ "tool": "redeem_reward", "redemption_id": result.redemption_id }),
+    );
+    Ok(result)
+}
+
+async fn mcp_list_tags(app_state: &Arc<RwLock<AppState>>) -> Result<ListTagsResult, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    let tags = tags::Entity::find()
+        .order_by_asc(tags::Column::CreatedAt)
+        .all(&db_conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|row| TagListItem {
+            id: row.id,
+            name: row.name,
+        })
+        .collect::<Vec<_>>();
+    Ok(ListTagsResult {
+        total: tags.len(),
+        tags,
+    })
+}
+
+async fn mcp_set_student_tags(
+    app_state: &Arc<RwLock<AppState>>,
+    args: SetStudentTagsArgs,
+) -> Result<SetStudentTagsResult, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    let student_name = args.student_name.unwrap_or_default();
+    let student = students::find_by_reference(&db_conn, args.student_id, student_name.trim())
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Student not found".to_string())?;
+
+    let tag_rows = tags::Entity::find()
+        .filter(tags::Column::Id.is_in(args.tag_ids.clone()))
+        .all(&db_conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(missing) = args
+        .tag_ids
+        .iter()
+        .find(|id| !tag_rows.iter().any(|row| row.id == **id))
+    {
+        return Err(format!("Tag not found: {}", missing));
+    }
+
+    let added_tag_ids = {
+        let local_write_lock = { app_state.read().local_write_lock.clone() };
+        let _write_guard = local_write_lock.lock().await;
+        tag::set_student_tags(&db_conn, student.id, args.tag_ids.clone()).await?
+    };
+    realtime_dual_write_sync_if_legacy(app_state).await?;
+    app_state.read().event_bus.publish(
+        EventSource::Mcp,
+        BusPayload::StudentTagsUpdated {
+            student_id: student.id,
+            added_tag_ids,
+        },
+    );
+    mcp_log_info(
+        app_state,
+        "mcp:tool_call_succeeded",
+        json!({ "tool": "set_student_tags", "student_id": student.id, "tag_ids": args.tag_ids }),
+    );
+
+    Ok(SetStudentTagsResult {
+        student_id: student.id,
+        student_name: student.name,
+        tags: args
+            .tag_ids
+            .iter()
+            .filter_map(|id| tag_rows.iter().find(|row| row.id == *id))
+            .map(|row| row.name.clone())
+            .collect(),
+    })
+}
+
+fn mcp_settlement_repo(app_state: &Arc<RwLock<AppState>>) -> Result<SettlementRepository, String> {
+    let db_conn = mcp_db_conn(app_state)?;
+    SettlementRepository::from_connection(&db_conn)
+        .ok_or_else(|| "Settlement repository unavailable".to_string())
+}
+
+async fn mcp_list_settlements(
+    app_state: &Arc<RwLock<AppState>>,
+) -> Result<ListSettlementsResult, String> {
+    let settlements = mcp_settlement_repo(app_state)?
+        .find_all()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(ListSettlementsResult {
+        total: settlements.len(),
+        settlements,
+    })
+}
+
+async fn mcp_settlement_leaderboard(
+    app_state: &Arc<RwLock<AppState>>,
+    settlement_id: i32,
+) -> Result<SettlementLeaderboard, String> {
+    mcp_settlement_repo(app_state)?
+        .get_leaderboard(settlement_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 固定资源：(uri, name, description)
+const MCP_RESOURCES: &[(&str, &str, &str)] = &[
+    (
+        "secscore://students",
+        "students",
+        "全部学生及当前积分、奖励积分和标签",
+    ),
+    ("secscore://reasons", "reasons", "未归档的加减分理由"),
+    ("secscore://tags", "tags", "学生标签列表"),
+    ("secscore://rewards", "rewards", "当前可兑换的奖品"),
+    ("secscore://settlements", "settlements", "历次结算"),
+    (
+        "secscore://leaderboard/today",
+        "leaderboard_today",
+        "今日积分排行",
+    ),
+    (
+        "secscore://leaderboard/week",
+        "leaderboard_week",
+        "本周积分排行",
+    ),
+    (
+        "secscore://leaderboard/month",
+        "leaderboard_month",
+        "本月积分排行",
+    ),
+];
+
+fn mcp_prompts() -> Vec<Prompt> {
+    vec![
+        Prompt::new(
+            "weekly_report",
+            Some("根据本周排行和积分记录生成周报"),
+            Some(vec![PromptArgument::new("group_name")
+                .with_description("只统计该小组，留空为全班")
+                .with_required(false)]),
+        ),
+        Prompt::new(
+            "student_review",
+            Some("回顾单个学生的积分曲线、连续加分和结算排名"),
+            Some(vec![PromptArgument::new("student_name")
+                .with_description("学生姓名")
+                .with_required(true)]),
+        ),
+        Prompt::new(
+            "record_behavior",
+            Some("根据描述的课堂表现选择理由并记分"),
+            Some(vec![
+                PromptArgument::new("student_name")
+                    .with_description("学生姓名")
+                    .with_required(true),
+                PromptArgument::new("behavior")
+                    .with_description("表现描述，例如「主动帮助同学订正作业」")
+                    .with_required(true),
+            ]),
+        ),
+    ]
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+async fn mcp_read_resource(app_state: &Arc<RwLock<AppState>>, uri: &str) -> Result<Value, String> {
+    let Some(path) = uri.strip_prefix("secscore://") else {
+        return Err(format!("Unknown resource: {}", uri));
+    };
+    let segments = path.split('/').collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["students"] => to_json(mcp_list_students(app_state, ListStudentsArgs::default()).await?),
+        ["students", id, "profile"] => {
+            let student_id = id
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid student id: {}", id))?;
+            let args = StudentProfileArgs {
+                student_id: Some(student_id),
+                ..Default::default()
+            };
+            to_json(mcp_student_profile(app_state, args).await?)
+        }
+        ["reasons"] => to_json(mcp_list_reasons(app_state, ListReasonsArgs::default()).await?),
+        ["tags"] => to_json(mcp_list_tags(app_state).await?),
+        ["rewards"] => to_json(mcp_list_rewards(app_state, ListRewardsArgs::default()).await?),
+        ["settlements"] => to_json(mcp_list_settlements(app_state).await?),
+        ["leaderboard", range @ ("today" | "week" | "month")] => {
+            let args = LeaderboardArgs {
+                range: Some(range.to_string()),
+                ..Default::default()
+            };
+            to_json(mcp_leaderboard(app_state, args).await?)
+        }
+        _ => Err(format!("Unknown resource: {}", uri)),
+    }
+}
+
 #[tauri::command]
 pub async fn mcp_server_start(
     config: Option<McpServerConfig>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<McpServerStartResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
     mcp_log_info(
         state.inner(),
         "mcp:server_start_requested",
@@ -799,8 +2016,8 @@ pub async fn mcp_server_start(
         move || Ok(SecScoreMcpServer::new(app_state.clone())),
         LocalSessionManager::default().into(),
         StreamableHttpServerConfig {
-            stateful_mode: false,
-            json_response: true,
+            // 有状态模式下会话 ID 由服务端生成，登录提升的权限才能保留在会话上
+            stateful_mode: true,
             cancellation_token: cancellation_token.child_token(),
             ..Default::default()
         },
@@ -810,7 +2027,11 @@ pub async fn mcp_server_start(
 
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
     tauri::async_runtime::spawn(async move {
-        let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
             let _ = shutdown_rx.await;
         });
 
@@ -848,9 +2069,10 @@ pub async fn mcp_server_start(
 
 #[tauri::command]
 pub async fn mcp_server_stop(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
     mcp_log_info(state.inner(), "mcp:server_stop_requested", json!({}));
 
     let mut server_state = MCP_SERVER_STATE.lock().await;
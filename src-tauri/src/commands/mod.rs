@@ -1,12 +1,14 @@
 pub mod app;
 pub mod auth;
 pub mod auto_score;
+pub mod backup;
 pub mod board;
 pub mod data;
 pub mod database;
 pub mod event;
 pub mod filesystem;
 pub mod http_server;
+pub mod journal;
 pub mod log;
 pub mod mcp;
 pub mod oauth_server;
@@ -26,12 +28,14 @@ pub mod workspace;
 pub use app::*;
 pub use auth::*;
 pub use auto_score::*;
+pub use backup::*;
 pub use board::*;
 pub use data::*;
 pub use database::*;
 pub use event::*;
 pub use filesystem::*;
 pub use http_server::*;
+pub use journal::*;
 pub use log::*;
 pub use mcp::*;
 pub use oauth_server::*;
@@ -1,21 +1,21 @@
 use parking_lot::RwLock;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 
 use crate::services::plugin::{
     Plugin, PluginManifest, PluginRuntimeModule, PluginService, PluginStats,
 };
-use crate::services::PermissionLevel;
+use crate::services::{window_session_key, PermissionLevel};
 use crate::state::AppState;
 
 use super::response::IpcResponse;
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::Admin)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::Admin)
 }
 
 #[tauri::command]
@@ -56,10 +56,10 @@ pub fn plugin_get_stats(
 pub fn plugin_toggle(
     plugin_id: String,
     enabled: bool,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
     let state_guard = state.read();
@@ -72,10 +72,10 @@ pub fn plugin_toggle(
 pub fn plugin_install(
     manifest: PluginManifest,
     plugin_dir: PathBuf,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<Plugin>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
     let state_guard = state.read();
@@ -87,10 +87,10 @@ pub fn plugin_install(
 #[tauri::command]
 pub fn plugin_uninstall(
     plugin_id: String,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
     let state_guard = state.read();
@@ -102,10 +102,10 @@ pub fn plugin_uninstall(
 #[tauri::command]
 pub fn plugin_load_manifest(
     path: PathBuf,
-    sender_id: Option<u32>,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<PluginManifest>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
     let manifest = PluginService::load_plugin_manifest(&path)?;
@@ -1,11 +1,14 @@
 use parking_lot::RwLock;
-use sea_orm::{ActiveModelTrait, EntityTrait, QueryOrder, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 
-use crate::db::entities::reasons;
-use crate::services::PermissionLevel;
+use crate::db::entities::{reason_categories, reason_sets, reasons};
+use crate::services::reason::{self, ReasonFilter};
+use crate::services::{window_session_key, PermissionLevel};
 use crate::state::AppState;
 
 use super::database::realtime_dual_write_sync_if_legacy;
@@ -16,15 +19,37 @@ pub struct Reason {
     pub id: i32,
     pub content: String,
     pub category: String,
+    pub category_id: Option<i32>,
     pub delta: i32,
     pub is_system: i32,
+    pub sort_order: i32,
+    pub is_archived: bool,
     pub updated_at: String,
 }
 
+impl From<reasons::Model> for Reason {
+    fn from(r: reasons::Model) -> Self {
+        Self {
+            id: r.id,
+            content: r.content,
+            category: r.category,
+            category_id: r.category_id,
+            delta: r.delta,
+            is_system: r.is_system,
+            sort_order: r.sort_order,
+            is_archived: r.is_archived != 0,
+            updated_at: r.updated_at,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateReason {
     pub content: String,
+    #[serde(default)]
     pub category: String,
+    #[serde(default)]
+    pub category_id: Option<i32>,
     pub delta: i32,
 }
 
@@ -32,7 +57,13 @@ pub struct CreateReason {
 pub struct UpdateReason {
     pub content: Option<String>,
     pub category: Option<String>,
+    #[serde(default)]
+    pub category_id: Option<i32>,
     pub delta: Option<i32>,
+    #[serde(default)]
+    pub sort_order: Option<i32>,
+    #[serde(default)]
+    pub is_archived: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,194 +71,619 @@ pub struct DeleteResult {
     pub changes: i32,
 }
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReasonCategory {
+    pub id: i32,
+    pub name: String,
+    pub sort_order: i32,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub is_archived: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<reason_categories::Model> for ReasonCategory {
+    fn from(c: reason_categories::Model) -> Self {
+        Self {
+            id: c.id,
+            name: c.name,
+            sort_order: c.sort_order,
+            color: c.color,
+            icon: c.icon,
+            is_archived: c.is_archived != 0,
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryReasonCategoriesParams {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReasonCategory {
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateReasonCategory {
+    pub name: Option<String>,
+    // 空字符串表示清除颜色或图标
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub is_archived: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReasonSet {
+    pub id: i32,
+    pub name: String,
+    pub reason_ids: Vec<i32>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveReasonSet {
+    #[serde(default)]
+    pub id: Option<i32>,
+    pub name: String,
+    #[serde(default)]
+    pub reason_ids: Vec<i32>,
+}
+
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::Admin)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::Admin)
+}
+
+fn now_iso() -> String {
+    chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+fn optional_key(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
 }
 
 #[tauri::command]
 pub async fn reason_query(
     state: State<'_, Arc<RwLock<AppState>>>,
+    params: Option<ReasonFilter>,
 ) -> Result<IpcResponse<Vec<Reason>>, String> {
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-
-    if let Some(conn) = db_guard.as_ref() {
-        let results = reasons::Entity::find()
-            .order_by_asc(reasons::Column::Category)
-            .order_by_asc(reasons::Column::Content)
-            .all(conn)
-            .await;
-
-        match results {
-            Ok(reason_models) => {
-                let reason_list: Vec<Reason> = reason_models
-                    .into_iter()
-                    .map(|r| Reason {
-                        id: r.id,
-                        content: r.content,
-                        category: r.category,
-                        delta: r.delta,
-                        is_system: r.is_system,
-                        updated_at: r.updated_at,
-                    })
-                    .collect();
-                Ok(IpcResponse::success(reason_list))
-            }
-            Err(e) => Ok(IpcResponse::error(&format!(
-                "Failed to query reasons: {}",
-                e
-            ))),
-        }
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    match reason::query_reasons(&conn, &params.unwrap_or_default()).await {
+        Ok(rows) => Ok(IpcResponse::success(
+            rows.into_iter().map(Reason::from).collect(),
+        )),
+        Err(e) => Ok(IpcResponse::error(&format!(
+            "Failed to query reasons: {}",
+            e
+        ))),
     }
 }
 
 #[tauri::command]
 pub async fn reason_create(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     data: CreateReason,
 ) -> Result<IpcResponse<i32>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    match create_reason(&conn, &data).await {
+        Ok(inserted) => {
+            realtime_dual_write_sync_if_legacy(state.inner()).await?;
+            Ok(IpcResponse::success(inserted.id))
+        }
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
+/// 新建理由，桌面端与 MCP 共用
+pub async fn create_reason(
+    conn: &DatabaseConnection,
+    data: &CreateReason,
+) -> Result<reasons::Model, String> {
     let content = data.content.trim();
     if content.is_empty() {
-        return Ok(IpcResponse::error("Reason content cannot be empty"));
+        return Err("Reason content cannot be empty".to_string());
     }
 
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-
-    if let Some(conn) = db_guard.as_ref() {
-        let now = chrono::Utc::now()
-            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-            .to_string();
-        let new_reason = reasons::ActiveModel {
-            id: sea_orm::ActiveValue::NotSet,
-            content: Set(content.to_string()),
-            category: Set(data.category.trim().to_string()),
-            delta: Set(data.delta),
-            is_system: Set(0),
-            updated_at: Set(now),
-        };
-
-        match new_reason.insert(conn).await {
-            Ok(inserted) => {
-                realtime_dual_write_sync_if_legacy(state.inner()).await?;
-                Ok(IpcResponse::success(inserted.id))
-            }
-            Err(e) => Ok(IpcResponse::error(&format!(
-                "Failed to create reason: {}",
-                e
-            ))),
-        }
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
+    let category = match data.category_id {
+        Some(id) => reason_categories::Entity::find_by_id(id)
+            .one(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        None => reason::ensure_category(conn, &data.category).await?,
+    };
+    if data.category_id.is_some() && category.is_none() {
+        return Err("Reason category not found".to_string());
     }
+
+    let new_reason = reasons::ActiveModel {
+        id: sea_orm::ActiveValue::NotSet,
+        content: Set(content.to_string()),
+        category: Set(category
+            .as_ref()
+            .map(|c| c.name.clone())
+            .unwrap_or_else(|| data.category.trim().to_string())),
+        category_id: Set(category.map(|c| c.id)),
+        delta: Set(data.delta),
+        is_system: Set(0),
+        sort_order: Set(0),
+        is_archived: Set(0),
+        updated_at: Set(now_iso()),
+    };
+
+    new_reason
+        .insert(conn)
+        .await
+        .map_err(|e| format!("Failed to create reason: {}", e))
 }
 
 #[tauri::command]
 pub async fn reason_update(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     id: i32,
     data: UpdateReason,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-
-    if let Some(conn) = db_guard.as_ref() {
-        let existing = reasons::Entity::find_by_id(id).one(conn).await;
-
-        match existing {
-            Ok(Some(reason)) => {
-                let now = chrono::Utc::now()
-                    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-                    .to_string();
-                let mut active: reasons::ActiveModel = reason.into();
-
-                active.updated_at = Set(now);
-
-                if let Some(content) = data.content {
-                    active.content = Set(content);
-                }
-                if let Some(category) = data.category {
-                    active.category = Set(category);
-                }
-                if let Some(delta) = data.delta {
-                    active.delta = Set(delta);
-                }
-
-                match active.update(conn).await {
-                    Ok(_) => {
-                        realtime_dual_write_sync_if_legacy(state.inner()).await?;
-                        Ok(IpcResponse::success_empty())
-                    }
-                    Err(e) => Ok(IpcResponse::error(&format!(
-                        "Failed to update reason: {}",
-                        e
-                    ))),
-                }
-            }
-            Ok(None) => Ok(IpcResponse::error("Reason not found")),
-            Err(e) => Ok(IpcResponse::error(&format!("Database error: {}", e))),
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let existing = match reasons::Entity::find_by_id(id).one(&conn).await {
+        Ok(Some(existing)) => existing,
+        Ok(None) => return Ok(IpcResponse::error("Reason not found")),
+        Err(e) => return Ok(IpcResponse::error(&format!("Database error: {}", e))),
+    };
+
+    let mut active: reasons::ActiveModel = existing.into();
+    active.updated_at = Set(now_iso());
+
+    if let Some(content) = data.content {
+        active.content = Set(content);
+    }
+    // category_id 优先；只传名称时按名称查找或新建分类
+    let category = match (data.category_id, data.category) {
+        (Some(category_id), _) => {
+            let Some(category) = reason_categories::Entity::find_by_id(category_id)
+                .one(&conn)
+                .await
+                .map_err(|e| e.to_string())?
+            else {
+                return Ok(IpcResponse::error("Reason category not found"));
+            };
+            Some(Some(category))
+        }
+        (None, Some(name)) => Some(reason::ensure_category(&conn, &name).await?),
+        (None, None) => None,
+    };
+    if let Some(category) = category {
+        active.category = Set(category
+            .as_ref()
+            .map(|c| c.name.clone())
+            .unwrap_or_default());
+        active.category_id = Set(category.map(|c| c.id));
+    }
+    if let Some(delta) = data.delta {
+        active.delta = Set(delta);
+    }
+    if let Some(sort_order) = data.sort_order {
+        active.sort_order = Set(sort_order);
+    }
+    if let Some(is_archived) = data.is_archived {
+        active.is_archived = Set(i32::from(is_archived));
+    }
+
+    match active.update(&conn).await {
+        Ok(_) => {
+            realtime_dual_write_sync_if_legacy(state.inner()).await?;
+            Ok(IpcResponse::success_empty())
         }
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
+        Err(e) => Ok(IpcResponse::error(&format!(
+            "Failed to update reason: {}",
+            e
+        ))),
     }
 }
 
+/// 理由被积分记录按内容引用，删除只做归档，可通过 reason_update 恢复
 #[tauri::command]
 pub async fn reason_delete(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     id: i32,
 ) -> Result<IpcResponse<DeleteResult>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-
-    if let Some(conn) = db_guard.as_ref() {
-        let existing = reasons::Entity::find_by_id(id).one(conn).await;
-
-        match existing {
-            Ok(Some(reason)) => {
-                let delete_result = reasons::Entity::delete_by_id(reason.id).exec(conn).await;
-
-                match delete_result {
-                    Ok(result) => {
-                        if result.rows_affected == 0 {
-                            Ok(IpcResponse::error("记录不存在"))
-                        } else {
-                            realtime_dual_write_sync_if_legacy(state.inner()).await?;
-                            Ok(IpcResponse::success(DeleteResult {
-                                changes: result.rows_affected as i32,
-                            }))
-                        }
-                    }
-                    Err(e) => Ok(IpcResponse::error(&format!(
-                        "Failed to delete reason: {}",
-                        e
-                    ))),
-                }
-            }
-            Ok(None) => Ok(IpcResponse::error("记录不存在")),
-            Err(e) => Ok(IpcResponse::error(&format!("Database error: {}", e))),
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let result = reasons::Entity::update_many()
+        .col_expr(
+            reasons::Column::IsArchived,
+            sea_orm::sea_query::Expr::value(1),
+        )
+        .col_expr(
+            reasons::Column::UpdatedAt,
+            sea_orm::sea_query::Expr::value(now_iso()),
+        )
+        .filter(reasons::Column::Id.eq(id))
+        .exec(&conn)
+        .await;
+
+    match result {
+        Ok(result) if result.rows_affected == 0 => Ok(IpcResponse::error("记录不存在")),
+        Ok(result) => {
+            realtime_dual_write_sync_if_legacy(state.inner()).await?;
+            Ok(IpcResponse::success(DeleteResult {
+                changes: result.rows_affected as i32,
+            }))
         }
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
+        Err(e) => Ok(IpcResponse::error(&format!(
+            "Failed to archive reason: {}",
+            e
+        ))),
+    }
+}
+
+#[tauri::command]
+pub async fn reason_category_query(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    params: Option<QueryReasonCategoriesParams>,
+) -> Result<IpcResponse<Vec<ReasonCategory>>, String> {
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let mut query = reason_categories::Entity::find();
+    if !params.unwrap_or_default().include_archived {
+        query = query.filter(reason_categories::Column::IsArchived.eq(0));
+    }
+    let rows = query
+        .order_by_asc(reason_categories::Column::SortOrder)
+        .order_by_asc(reason_categories::Column::Name)
+        .all(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(IpcResponse::success(
+        rows.into_iter().map(ReasonCategory::from).collect(),
+    ))
+}
+
+#[tauri::command]
+pub async fn reason_category_create(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    data: CreateReasonCategory,
+) -> Result<IpcResponse<ReasonCategory>, String> {
+    if !check_admin_permission(&state, &webview) {
+        return Ok(IpcResponse::error("Permission denied: admin required"));
+    }
+
+    let name = data.name.trim();
+    if name.is_empty() {
+        return Ok(IpcResponse::error("Category name cannot be empty"));
     }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let exists = reason_categories::Entity::find()
+        .filter(reason_categories::Column::Name.eq(name))
+        .one(&conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_some();
+    if exists {
+        return Ok(IpcResponse::error("Category with this name already exists"));
+    }
+
+    let Some(category) = reason::ensure_category(&conn, name).await? else {
+        return Ok(IpcResponse::error("Category name cannot be empty"));
+    };
+    let mut active: reason_categories::ActiveModel = category.into();
+    active.color = Set(optional_key(data.color));
+    active.icon = Set(optional_key(data.icon));
+    let category = active.update(&conn).await.map_err(|e| e.to_string())?;
+
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    Ok(IpcResponse::success(ReasonCategory::from(category)))
+}
+
+#[tauri::command]
+pub async fn reason_category_update(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    id: i32,
+    data: UpdateReasonCategory,
+) -> Result<IpcResponse<ReasonCategory>, String> {
+    if !check_admin_permission(&state, &webview) {
+        return Ok(IpcResponse::error("Permission denied: admin required"));
+    }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let Some(category) = reason_categories::Entity::find_by_id(id)
+        .one(&conn)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(IpcResponse::error("Reason category not found"));
+    };
+
+    let mut renamed = None;
+    let mut active: reason_categories::ActiveModel = category.into();
+    if let Some(name) = data.name {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Ok(IpcResponse::error("Category name cannot be empty"));
+        }
+        let duplicate = reason_categories::Entity::find()
+            .filter(reason_categories::Column::Name.eq(&name))
+            .filter(reason_categories::Column::Id.ne(id))
+            .one(&conn)
+            .await
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if duplicate {
+            return Ok(IpcResponse::error("Category with this name already exists"));
+        }
+        active.name = Set(name.clone());
+        renamed = Some(name);
+    }
+    if data.color.is_some() {
+        active.color = Set(optional_key(data.color));
+    }
+    if data.icon.is_some() {
+        active.icon = Set(optional_key(data.icon));
+    }
+    if let Some(is_archived) = data.is_archived {
+        active.is_archived = Set(i32::from(is_archived));
+    }
+    active.updated_at = Set(now_iso());
+
+    let category = active.update(&conn).await.map_err(|e| e.to_string())?;
+    if let Some(name) = renamed {
+        reason::rename_category_references(&conn, id, &name).await?;
+    }
+
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    Ok(IpcResponse::success(ReasonCategory::from(category)))
+}
+
+/// 按传入顺序重排分类，未列出的分类排在其后
+#[tauri::command]
+pub async fn reason_category_reorder(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    ids: Vec<i32>,
+) -> Result<IpcResponse<()>, String> {
+    if !check_admin_permission(&state, &webview) {
+        return Ok(IpcResponse::error("Permission denied: admin required"));
+    }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let rows = reason_categories::Entity::find()
+        .order_by_asc(reason_categories::Column::SortOrder)
+        .order_by_asc(reason_categories::Column::Name)
+        .all(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (mut listed, rest): (Vec<_>, Vec<_>) = rows.into_iter().partition(|c| ids.contains(&c.id));
+    listed.sort_by_key(|c| ids.iter().position(|id| *id == c.id));
+
+    let now = now_iso();
+    for (index, category) in listed.into_iter().chain(rest).enumerate() {
+        if category.sort_order == index as i32 {
+            continue;
+        }
+        let mut active: reason_categories::ActiveModel = category.into();
+        active.sort_order = Set(index as i32);
+        active.updated_at = Set(now.clone());
+        active.update(&conn).await.map_err(|e| e.to_string())?;
+    }
+
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    Ok(IpcResponse::success_empty())
+}
+
+#[tauri::command]
+pub async fn reason_set_query(
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<Vec<ReasonSet>>, String> {
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let rows = reason_sets::Entity::find()
+        .order_by_asc(reason_sets::Column::Name)
+        .all(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut data = Vec::with_capacity(rows.len());
+    for row in rows {
+        data.push(ReasonSet {
+            reason_ids: reason::set_reason_ids(&conn, row.id).await?,
+            id: row.id,
+            name: row.name,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        });
+    }
+    Ok(IpcResponse::success(data))
+}
+
+/// 新建或整体覆盖一个快捷理由组，看板与局域网页面按名称取用
+#[tauri::command]
+pub async fn reason_set_save(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    data: SaveReasonSet,
+) -> Result<IpcResponse<ReasonSet>, String> {
+    if !check_admin_permission(&state, &webview) {
+        return Ok(IpcResponse::error("Permission denied: admin required"));
+    }
+
+    let name = data.name.trim().to_string();
+    if name.is_empty() {
+        return Ok(IpcResponse::error("Reason set name cannot be empty"));
+    }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let known = reasons::Entity::find()
+        .filter(reasons::Column::Id.is_in(data.reason_ids.clone()))
+        .all(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(missing) = data
+        .reason_ids
+        .iter()
+        .find(|id| !known.iter().any(|r| r.id == **id))
+    {
+        return Ok(IpcResponse::error(&format!(
+            "Reason not found: {}",
+            missing
+        )));
+    }
+
+    let mut duplicate = reason_sets::Entity::find().filter(reason_sets::Column::Name.eq(&name));
+    if let Some(id) = data.id {
+        duplicate = duplicate.filter(reason_sets::Column::Id.ne(id));
+    }
+    if duplicate
+        .one(&conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_some()
+    {
+        return Ok(IpcResponse::error(
+            "Reason set with this name already exists",
+        ));
+    }
+
+    let txn = sea_orm::TransactionTrait::begin(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let now = now_iso();
+    let set = match data.id {
+        Some(id) => {
+            let Some(existing) = reason_sets::Entity::find_by_id(id)
+                .one(&txn)
+                .await
+                .map_err(|e| e.to_string())?
+            else {
+                return Ok(IpcResponse::error("Reason set not found"));
+            };
+            let mut active: reason_sets::ActiveModel = existing.into();
+            active.name = Set(name);
+            active.updated_at = Set(now);
+            active.update(&txn).await.map_err(|e| e.to_string())?
+        }
+        None => reason_sets::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            name: Set(name),
+            created_at: Set(now.clone()),
+            updated_at: Set(now),
+        }
+        .insert(&txn)
+        .await
+        .map_err(|e| e.to_string())?,
+    };
+    reason::replace_set_reasons(&txn, set.id, &data.reason_ids).await?;
+    let reason_ids = reason::set_reason_ids(&txn, set.id).await?;
+    txn.commit().await.map_err(|e| e.to_string())?;
+
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    Ok(IpcResponse::success(ReasonSet {
+        id: set.id,
+        name: set.name,
+        reason_ids,
+        created_at: set.created_at,
+        updated_at: set.updated_at,
+    }))
+}
+
+#[tauri::command]
+pub async fn reason_set_delete(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    id: i32,
+) -> Result<IpcResponse<()>, String> {
+    if !check_admin_permission(&state, &webview) {
+        return Ok(IpcResponse::error("Permission denied: admin required"));
+    }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let txn = sea_orm::TransactionTrait::begin(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    reason::replace_set_reasons(&txn, id, &[]).await?;
+    let deleted = reason_sets::Entity::delete_by_id(id)
+        .exec(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if deleted.rows_affected == 0 {
+        return Ok(IpcResponse::error("Reason set not found"));
+    }
+    txn.commit().await.map_err(|e| e.to_string())?;
+
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    Ok(IpcResponse::success_empty())
 }
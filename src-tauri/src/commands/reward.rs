@@ -1,54 +1,146 @@
 use parking_lot::RwLock;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
-    TransactionTrait,
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tauri::State;
-use uuid::Uuid;
+use tauri::{State, Webview};
 
-use crate::db::entities::{reward_redemptions, reward_settings, students};
-use crate::services::PermissionLevel;
+use crate::db::entities::{reward_redemptions, reward_settings};
+use crate::services::permission::session_actor;
+use crate::services::reward;
+use crate::services::{
+    window_session_key, BusPayload, EventSource, PermissionLevel, RedemptionStatus,
+};
 use crate::state::AppState;
 
 use super::database::realtime_dual_write_sync_if_legacy;
 use super::response::IpcResponse;
 
+/// 库存、限额与有效期，均为 None 时表示不限制；requires_approval 为 true 时兑换需审核
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RewardLimits {
+    #[serde(default)]
+    pub stock: Option<i32>,
+    #[serde(default)]
+    pub per_student_limit: Option<i32>,
+    #[serde(default)]
+    pub per_period_limit: Option<i32>,
+    #[serde(default)]
+    pub active_from: Option<String>,
+    #[serde(default)]
+    pub active_until: Option<String>,
+    #[serde(default)]
+    pub requires_approval: bool,
+}
+
+impl RewardLimits {
+    fn validate(self) -> Result<Self, String> {
+        reward::validate_limit(self.stock, "Reward stock")?;
+        reward::validate_limit(self.per_student_limit, "Per-student limit")?;
+        reward::validate_limit(self.per_period_limit, "Per-period limit")?;
+        let (active_from, active_until) =
+            reward::normalize_window(self.active_from, self.active_until)?;
+        Ok(Self {
+            active_from,
+            active_until,
+            ..self
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RewardSettingDto {
     pub id: i32,
     pub name: String,
     pub cost_points: i32,
+    #[serde(flatten)]
+    pub limits: RewardLimits,
     pub created_at: String,
     pub updated_at: String,
 }
 
+impl From<reward_settings::Model> for RewardSettingDto {
+    fn from(row: reward_settings::Model) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            cost_points: row.cost_points,
+            limits: RewardLimits {
+                stock: row.stock,
+                per_student_limit: row.per_student_limit,
+                per_period_limit: row.per_period_limit,
+                active_from: row.active_from,
+                active_until: row.active_until,
+                requires_approval: row.requires_approval != 0,
+            },
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RewardRedemptionDto {
     pub id: i32,
     pub uuid: String,
+    pub student_id: Option<i32>,
     pub student_name: String,
     pub reward_id: i32,
     pub reward_name: String,
     pub cost_points: i32,
     pub redeemed_at: String,
+    pub status: String,
+    pub reviewed_at: Option<String>,
+    pub review_note: Option<String>,
+    pub cancelled_at: Option<String>,
+    pub cancelled_by: Option<String>,
+    pub cancel_reason: Option<String>,
+}
+
+impl From<reward_redemptions::Model> for RewardRedemptionDto {
+    fn from(row: reward_redemptions::Model) -> Self {
+        Self {
+            id: row.id,
+            uuid: row.uuid,
+            student_id: row.student_id,
+            student_name: row.student_name,
+            reward_id: row.reward_id,
+            reward_name: row.reward_name,
+            cost_points: row.cost_points,
+            redeemed_at: row.redeemed_at,
+            status: row.status,
+            reviewed_at: row.reviewed_at,
+            review_note: row.review_note,
+            cancelled_at: row.cancelled_at,
+            cancelled_by: row.cancelled_by,
+            cancel_reason: row.cancel_reason,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateRewardSettingData {
     pub name: String,
     pub cost_points: i32,
+    #[serde(flatten)]
+    pub limits: RewardLimits,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateRewardSettingData {
     pub name: Option<String>,
     pub cost_points: Option<i32>,
+    // 提供时整体替换库存、限额与有效期
+    #[serde(default)]
+    pub limits: Option<RewardLimits>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedeemRewardData {
+    #[serde(default)]
+    pub student_id: Option<i32>,
+    #[serde(default)]
     pub student_name: String,
     pub reward_id: i32,
     #[serde(default)]
@@ -59,31 +151,76 @@ pub struct RedeemRewardData {
 pub struct RedeemRewardResult {
     pub redemption_id: i32,
     pub remaining_reward_points: i32,
+    pub status: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryRewardRedemptionsParams {
     pub limit: Option<u64>,
+    #[serde(default)]
+    pub status: Option<RedemptionStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedemptionReviewAction {
+    Approve,
+    Reject,
+    Fulfill,
+}
+
+impl RedemptionReviewAction {
+    fn target(self) -> RedemptionStatus {
+        match self {
+            RedemptionReviewAction::Approve => RedemptionStatus::Approved,
+            RedemptionReviewAction::Reject => RedemptionStatus::Rejected,
+            RedemptionReviewAction::Fulfill => RedemptionStatus::Fulfilled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRewardRedemptionData {
+    pub action: RedemptionReviewAction,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelRewardRedemptionData {
+    pub reason: String,
 }
 
 fn require_permission(
     state: &Arc<RwLock<AppState>>,
+    webview: &Webview,
     level: PermissionLevel,
 ) -> Result<(), IpcResponse<()>> {
+    let session = window_session_key(webview.label());
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    if permissions.require_permission(0, level) {
+    if permissions.require_permission(&session, level) {
         Ok(())
     } else {
         Err(IpcResponse::error("Permission denied"))
     }
 }
 
+fn publish_redemption(state: &Arc<RwLock<AppState>>, redemption: &reward_redemptions::Model) {
+    state.read().event_bus.publish(
+        EventSource::Desktop,
+        BusPayload::RedemptionChanged {
+            redemption: Box::new(redemption.clone()),
+        },
+    );
+}
+
 #[tauri::command]
 pub async fn reward_setting_query(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<Vec<RewardSettingDto>>, String> {
-    if require_permission(&state, PermissionLevel::View).is_err() {
+    if require_permission(&state, &webview, PermissionLevel::View).is_err() {
         return Ok(IpcResponse::error("Permission denied: view required"));
     }
 
@@ -100,26 +237,18 @@ pub async fn reward_setting_query(
         .await
         .map_err(|e| e.to_string())?;
 
-    let data = rows
-        .into_iter()
-        .map(|row| RewardSettingDto {
-            id: row.id,
-            name: row.name,
-            cost_points: row.cost_points,
-            created_at: row.created_at,
-            updated_at: row.updated_at,
-        })
-        .collect();
+    let data = rows.into_iter().map(RewardSettingDto::from).collect();
 
     Ok(IpcResponse::success(data))
 }
 
 #[tauri::command]
 pub async fn reward_setting_create(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
     data: CreateRewardSettingData,
 ) -> Result<IpcResponse<i32>, String> {
-    if require_permission(&state, PermissionLevel::Admin).is_err() {
+    if require_permission(&state, &webview, PermissionLevel::Admin).is_err() {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -130,6 +259,10 @@ pub async fn reward_setting_create(
     if data.cost_points <= 0 {
         return Ok(IpcResponse::error("Reward cost points must be positive"));
     }
+    let limits = match data.limits.validate() {
+        Ok(limits) => limits,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
 
     let state_guard = state.read();
     let db_guard = state_guard.db.read();
@@ -155,6 +288,12 @@ pub async fn reward_setting_create(
         id: sea_orm::ActiveValue::NotSet,
         name: Set(name.to_string()),
         cost_points: Set(data.cost_points),
+        stock: Set(limits.stock),
+        per_student_limit: Set(limits.per_student_limit),
+        per_period_limit: Set(limits.per_period_limit),
+        active_from: Set(limits.active_from),
+        active_until: Set(limits.active_until),
+        requires_approval: Set(limits.requires_approval as i32),
         created_at: Set(now.clone()),
         updated_at: Set(now),
     }
@@ -168,11 +307,12 @@ pub async fn reward_setting_create(
 
 #[tauri::command]
 pub async fn reward_setting_update(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
     id: i32,
     data: UpdateRewardSettingData,
 ) -> Result<IpcResponse<()>, String> {
-    if require_permission(&state, PermissionLevel::Admin).is_err() {
+    if require_permission(&state, &webview, PermissionLevel::Admin).is_err() {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -216,6 +356,19 @@ pub async fn reward_setting_update(
         active.cost_points = Set(cost_points);
     }
 
+    if let Some(limits) = data.limits {
+        let limits = match limits.validate() {
+            Ok(limits) => limits,
+            Err(e) => return Ok(IpcResponse::error(&e)),
+        };
+        active.stock = Set(limits.stock);
+        active.per_student_limit = Set(limits.per_student_limit);
+        active.per_period_limit = Set(limits.per_period_limit);
+        active.active_from = Set(limits.active_from);
+        active.active_until = Set(limits.active_until);
+        active.requires_approval = Set(limits.requires_approval as i32);
+    }
+
     let now = chrono::Utc::now()
         .format("%Y-%m-%dT%H:%M:%S%.3fZ")
         .to_string();
@@ -228,10 +381,11 @@ pub async fn reward_setting_update(
 
 #[tauri::command]
 pub async fn reward_setting_delete(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
     id: i32,
 ) -> Result<IpcResponse<()>, String> {
-    if require_permission(&state, PermissionLevel::Admin).is_err() {
+    if require_permission(&state, &webview, PermissionLevel::Admin).is_err() {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -256,126 +410,212 @@ pub async fn reward_setting_delete(
 
 #[tauri::command]
 pub async fn reward_redeem(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
     data: RedeemRewardData,
 ) -> Result<IpcResponse<RedeemRewardResult>, String> {
-    if require_permission(&state, PermissionLevel::Points).is_err() {
+    if require_permission(&state, &webview, PermissionLevel::Points).is_err() {
         return Ok(IpcResponse::error("Permission denied: points required"));
     }
 
-    let student_name = data.student_name.trim();
-    if student_name.is_empty() {
-        return Ok(IpcResponse::error("Student name cannot be empty"));
-    }
-
     let local_write_lock = { state.read().local_write_lock.clone() };
     let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let (redemption, remaining) = match reward::redeem(
+        &conn,
+        data.student_id,
+        &data.student_name,
+        data.reward_id,
+        data.operation_id.as_deref(),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    publish_redemption(&state, &redemption);
+
+    Ok(IpcResponse::success(RedeemRewardResult {
+        redemption_id: redemption.id,
+        remaining_reward_points: remaining,
+        status: redemption.status,
+    }))
+}
+
+#[tauri::command]
+pub async fn reward_redemption_query(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+    params: Option<QueryRewardRedemptionsParams>,
+) -> Result<IpcResponse<Vec<RewardRedemptionDto>>, String> {
+    if require_permission(&state, &webview, PermissionLevel::View).is_err() {
+        return Ok(IpcResponse::error("Permission denied: view required"));
+    }
+
     let state_guard = state.read();
     let db_guard = state_guard.db.read();
     let Some(conn) = db_guard.as_ref() else {
         return Ok(IpcResponse::error("Database not connected"));
     };
 
-    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    let limit = params.as_ref().and_then(|p| p.limit).unwrap_or(100);
+    let status = params.and_then(|p| p.status);
 
-    let Some(student) = students::Entity::find()
-        .filter(students::Column::Name.eq(student_name))
-        .one(&txn)
+    let rows = reward::query_redemptions(conn, status, limit).await?;
+
+    let data = rows.into_iter().map(RewardRedemptionDto::from).collect();
+
+    Ok(IpcResponse::success(data))
+}
+
+#[tauri::command]
+pub async fn reward_redemption_list_pending(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+) -> Result<IpcResponse<Vec<RewardRedemptionDto>>, String> {
+    if require_permission(&state, &webview, PermissionLevel::View).is_err() {
+        return Ok(IpcResponse::error("Permission denied: view required"));
+    }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    // 按提交顺序排列，先兑换的先处理
+    let rows = reward_redemptions::Entity::find()
+        .filter(reward_redemptions::Column::Status.eq(RedemptionStatus::Pending.as_str()))
+        .order_by_asc(reward_redemptions::Column::RedeemedAt)
+        .order_by_asc(reward_redemptions::Column::Id)
+        .all(&conn)
         .await
-        .map_err(|e| e.to_string())?
-    else {
-        return Ok(IpcResponse::error("Student not found"));
+        .map_err(|e| e.to_string())?;
+
+    let data = rows.into_iter().map(RewardRedemptionDto::from).collect();
+    Ok(IpcResponse::success(data))
+}
+
+#[tauri::command]
+pub async fn reward_redemption_review(
+    webview: Webview,
+    state: State<'_, Arc<RwLock<AppState>>>,
+    id: i32,
+    data: ReviewRewardRedemptionData,
+) -> Result<IpcResponse<RewardRedemptionDto>, String> {
+    if require_permission(&state, &webview, PermissionLevel::Points).is_err() {
+        return Ok(IpcResponse::error("Permission denied: points required"));
+    }
+
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
     };
 
-    let Some(reward) = reward_settings::Entity::find_by_id(data.reward_id)
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+
+    let Some(row) = reward_redemptions::Entity::find_by_id(id)
         .one(&txn)
         .await
         .map_err(|e| e.to_string())?
     else {
-        return Ok(IpcResponse::error("Reward not found"));
+        return Ok(IpcResponse::error("Redemption not found"));
     };
 
-    if student.reward_points < reward.cost_points {
-        return Ok(IpcResponse::error("Insufficient reward points"));
+    let current = RedemptionStatus::of(&row.status);
+    let target = data.action.target();
+    if !current.can_transition_to(target) {
+        return Ok(IpcResponse::error(&format!(
+            "Cannot change redemption from {} to {}",
+            current.as_str(),
+            target.as_str()
+        )));
     }
 
-    let remaining = student.reward_points - reward.cost_points;
     let now = chrono::Utc::now()
         .format("%Y-%m-%dT%H:%M:%S%.3fZ")
         .to_string();
-    let uuid = data
-        .operation_id
-        .as_deref()
-        .and_then(|value| Uuid::parse_str(value.trim()).ok())
-        .map(|value| value.to_string())
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
-
-    let redemption = reward_redemptions::ActiveModel {
-        id: sea_orm::ActiveValue::NotSet,
-        uuid: Set(uuid),
-        student_name: Set(student_name.to_string()),
-        reward_id: Set(reward.id),
-        reward_name: Set(reward.name.clone()),
-        cost_points: Set(reward.cost_points),
-        redeemed_at: Set(now.clone()),
+
+    if target == RedemptionStatus::Rejected {
+        reward::refund_redemption(&txn, &row, &now).await?;
     }
-    .insert(&txn)
-    .await
-    .map_err(|e| e.to_string())?;
 
-    let mut active_student: students::ActiveModel = student.into();
-    active_student.reward_points = Set(remaining);
-    active_student.updated_at = Set(now);
-    active_student
-        .update(&txn)
-        .await
-        .map_err(|e| e.to_string())?;
+    let note = data
+        .note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    let mut active: reward_redemptions::ActiveModel = row.into();
+    active.status = Set(target.as_str().to_string());
+    active.reviewed_at = Set(Some(now));
+    if note.is_some() {
+        active.review_note = Set(note);
+    }
+    let updated = active.update(&txn).await.map_err(|e| e.to_string())?;
 
     txn.commit().await.map_err(|e| e.to_string())?;
     realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    publish_redemption(&state, &updated);
 
-    Ok(IpcResponse::success(RedeemRewardResult {
-        redemption_id: redemption.id,
-        remaining_reward_points: remaining,
-    }))
+    Ok(IpcResponse::success(RewardRedemptionDto::from(updated)))
 }
 
 #[tauri::command]
-pub async fn reward_redemption_query(
+pub async fn reward_redemption_cancel(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
-    params: Option<QueryRewardRedemptionsParams>,
-) -> Result<IpcResponse<Vec<RewardRedemptionDto>>, String> {
-    if require_permission(&state, PermissionLevel::View).is_err() {
-        return Ok(IpcResponse::error("Permission denied: view required"));
+    id: i32,
+    data: CancelRewardRedemptionData,
+) -> Result<IpcResponse<RewardRedemptionDto>, String> {
+    if require_permission(&state, &webview, PermissionLevel::Points).is_err() {
+        return Ok(IpcResponse::error("Permission denied: points required"));
     }
 
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-    let Some(conn) = db_guard.as_ref() else {
+    let reason = data.reason.trim();
+    if reason.is_empty() {
+        return Ok(IpcResponse::error("Cancellation reason cannot be empty"));
+    }
+    // 撤销人记录为操作端类型与权限级别，不保存会话键
+    let cancelled_by = {
+        let session = window_session_key(webview.label());
+        let state_guard = state.read();
+        let level = state_guard.permissions.write().get_permission(&session);
+        session_actor(&session, level)
+    };
+
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
         return Ok(IpcResponse::error("Database not connected"));
     };
 
-    let limit = params.and_then(|p| p.limit).unwrap_or(100);
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
 
-    let rows = reward_redemptions::Entity::find()
-        .order_by_desc(reward_redemptions::Column::RedeemedAt)
-        .limit(limit)
-        .all(conn)
+    let Some(row) = reward_redemptions::Entity::find_by_id(id)
+        .one(&txn)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(IpcResponse::error("Redemption not found"));
+    };
 
-    let data = rows
-        .into_iter()
-        .map(|row| RewardRedemptionDto {
-            id: row.id,
-            uuid: row.uuid,
-            student_name: row.student_name,
-            reward_id: row.reward_id,
-            reward_name: row.reward_name,
-            cost_points: row.cost_points,
-            redeemed_at: row.redeemed_at,
-        })
-        .collect();
+    let now = chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+    let updated = match reward::cancel_redemption(&txn, row, &cancelled_by, reason, &now).await {
+        Ok(updated) => updated,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
 
-    Ok(IpcResponse::success(data))
+    txn.commit().await.map_err(|e| e.to_string())?;
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    publish_redemption(&state, &updated);
+
+    Ok(IpcResponse::success(RewardRedemptionDto::from(updated)))
 }
@@ -5,7 +5,7 @@ use std::collections::BTreeSet;
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use std::process::Command;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, State, Webview};
 #[cfg(target_os = "windows")]
 use winreg::enums::HKEY_LOCAL_MACHINE;
 #[cfg(target_os = "windows")]
@@ -16,7 +16,7 @@ use crate::services::{
         PermissionRequirement, SettingValueKind, SettingsKey, SettingsService, SettingsSpec,
         SettingsValue,
     },
-    PermissionLevel, PermissionService,
+    window_session_key, AuthService, PermissionLevel, PermissionService,
 };
 use crate::state::AppState;
 
@@ -39,18 +39,18 @@ fn get_write_permission(key: SettingsKey) -> PermissionRequirement {
 
 fn check_write_permission(
     permissions: &mut PermissionService,
-    sender_id: Option<u32>,
+    webview: &Webview,
     requirement: PermissionRequirement,
 ) -> bool {
     match requirement {
         PermissionRequirement::Any => true,
         PermissionRequirement::Admin => {
-            let id = sender_id.unwrap_or(0);
-            permissions.require_permission(id, PermissionLevel::Admin)
+            let session = window_session_key(webview.label());
+            permissions.require_permission(&session, PermissionLevel::Admin)
         }
         PermissionRequirement::Points => {
-            let id = sender_id.unwrap_or(0);
-            permissions.require_permission(id, PermissionLevel::Points)
+            let session = window_session_key(webview.label());
+            permissions.require_permission(&session, PermissionLevel::Points)
         }
         PermissionRequirement::View => true,
     }
@@ -112,7 +112,7 @@ pub async fn settings_get(
 pub async fn settings_set(
     key: String,
     value: JsonValue,
-    sender_id: Option<u32>,
+    webview: Webview,
     app_handle: AppHandle,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
@@ -124,7 +124,7 @@ pub async fn settings_set(
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_write_permission(&mut permissions, sender_id, write_permission) {
+        if !check_write_permission(&mut permissions, &webview, write_permission) {
             return Ok(IpcResponse::error("Permission denied"));
         }
     }
@@ -156,6 +156,13 @@ pub async fn settings_set(
         let _ = app_handle.emit("auto-score:rulesChanged", auto_score.get_rules());
     }
 
+    if settings_key == SettingsKey::PermissionIdleTimeoutMinutes {
+        let state_guard = state.read();
+        let settings = state_guard.settings.read();
+        let mut permissions = state_guard.permissions.write();
+        AuthService::sync_permission_policy(&settings, &mut permissions);
+    }
+
     let change = SettingChange {
         key: key.clone(),
         value,
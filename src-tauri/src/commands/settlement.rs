@@ -1,83 +1,83 @@
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 
+use crate::db::repositories::{SettlementError, SettlementLeaderboard, SettlementRepository};
 use crate::models::{SettlementResult, SettlementSummary};
-use crate::services::permission::PermissionLevel;
+use crate::services::permission::{window_session_key, PermissionLevel};
+use crate::services::{BusPayload, EventSource};
 use crate::state::AppState;
 
+use super::database::realtime_dual_write_sync_if_legacy;
 use super::response::IpcResponse;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SettlementInfo {
-    pub id: i32,
-    pub start_time: String,
-    pub end_time: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SettlementLeaderboardRow {
-    pub name: String,
-    pub score: i64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SettlementLeaderboard {
-    pub settlement: SettlementInfo,
-    pub rows: Vec<SettlementLeaderboardRow>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettlementLeaderboardParams {
     pub settlement_id: i32,
 }
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>) -> Result<(), String> {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> Result<(), String> {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let sender_id = 0;
-    if !permissions.require_permission(sender_id, PermissionLevel::Admin) {
+    let session = window_session_key(webview.label());
+    if !permissions.require_permission(&session, PermissionLevel::Admin) {
         return Err("Permission denied: Admin required".to_string());
     }
     Ok(())
 }
 
+fn settlement_repository(state: &Arc<RwLock<AppState>>) -> Option<SettlementRepository> {
+    let state_guard = state.read();
+    let db_guard = state_guard.db.read();
+    db_guard
+        .as_ref()
+        .and_then(SettlementRepository::from_connection)
+}
+
 #[tauri::command]
 pub async fn db_settlement_query(
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<Vec<SettlementSummary>>, String> {
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-    if db_guard.is_none() {
+    let Some(repo) = settlement_repository(&state) else {
         return Ok(IpcResponse::success(Vec::new()));
-    }
-
-    let summaries: Vec<SettlementSummary> = Vec::new();
+    };
 
-    Ok(IpcResponse::success(summaries))
+    match repo.find_all().await {
+        Ok(summaries) => Ok(IpcResponse::success(summaries)),
+        Err(e) => Ok(IpcResponse::error(&e.to_string())),
+    }
 }
 
 #[tauri::command]
 pub async fn db_settlement_create(
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SettlementResult>, String> {
-    check_admin_permission(&state)?;
+    check_admin_permission(&state, &webview)?;
 
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-    if db_guard.is_none() {
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let Some(repo) = settlement_repository(&state) else {
         return Ok(IpcResponse::failure_with_type("No database connection"));
-    }
-
-    let result = SettlementResult {
-        settlement_id: 0,
-        start_time: chrono::Local::now().to_rfc3339(),
-        end_time: chrono::Local::now().to_rfc3339(),
-        event_count: 0,
     };
 
-    Ok(IpcResponse::success(result))
+    match repo.create().await {
+        Ok(result) => {
+            realtime_dual_write_sync_if_legacy(state.inner()).await?;
+            state.read().event_bus.publish(
+                EventSource::Desktop,
+                BusPayload::SettlementCreated {
+                    settlement_id: result.settlement_id,
+                },
+            );
+            Ok(IpcResponse::success(result))
+        }
+        Err(e @ SettlementError::NoEventsToSettle) => {
+            Ok(IpcResponse::failure_with_type(&e.to_string()))
+        }
+        Err(e) => Ok(IpcResponse::error(&e.to_string())),
+    }
 }
 
 #[tauri::command]
@@ -85,20 +85,12 @@ pub async fn db_settlement_leaderboard(
     params: SettlementLeaderboardParams,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<SettlementLeaderboard>, String> {
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-    if db_guard.is_none() {
+    let Some(repo) = settlement_repository(&state) else {
         return Ok(IpcResponse::failure_with_type("No database connection"));
-    }
-
-    let leaderboard = SettlementLeaderboard {
-        settlement: SettlementInfo {
-            id: params.settlement_id,
-            start_time: String::new(),
-            end_time: String::new(),
-        },
-        rows: Vec::new(),
     };
 
-    Ok(IpcResponse::success(leaderboard))
+    match repo.get_leaderboard(params.settlement_id).await {
+        Ok(leaderboard) => Ok(IpcResponse::success(leaderboard)),
+        Err(e) => Ok(IpcResponse::error(&e.to_string())),
+    }
 }
@@ -6,14 +6,17 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 use tokio::process::Command;
 use tokio::time::{timeout, Duration};
+use uuid::Uuid;
 
 use crate::db::entities::students;
 use crate::models::{StudentUpdate, StudentWithTags};
+use crate::services::journal::record_operation;
 use crate::services::logger::LogLevel;
-use crate::services::PermissionLevel;
+use crate::services::profile::{self, StudentProfile, StudentProfileParams};
+use crate::services::{window_session_key, BusPayload, EventSource, JournalOp, PermissionLevel};
 use crate::state::AppState;
 
 use super::database::realtime_dual_write_sync_if_legacy;
@@ -192,18 +195,18 @@ struct BanYouApiResponse<T> {
     pub message: Option<String>,
 }
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::Admin)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::Admin)
 }
 
-fn check_view_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
+fn check_view_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::View)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::View)
 }
 
 fn log_banyou(
@@ -321,9 +324,9 @@ async fn run_banyou_playwright_script(
 #[tauri::command]
 pub async fn student_fetch_banyou_cookie_with_browser(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
 ) -> Result<IpcResponse<BanYouBrowserCookieData>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -623,9 +626,9 @@ async fn fetch_image_as_data_url(
 #[tauri::command]
 pub async fn student_query(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
 ) -> Result<IpcResponse<Vec<StudentWithTags>>, String> {
-    if !check_view_permission(&state, sender_id) {
+    if !check_view_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: view required"));
     }
 
@@ -665,13 +668,35 @@ pub async fn student_query(
     }
 }
 
+/// 家长会等场景使用的学生概览：积分曲线、分类汇总、连续加分天数、历次结算排名、兑换与标签
+#[tauri::command]
+pub async fn student_profile_query(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    webview: Webview,
+    params: StudentProfileParams,
+) -> Result<IpcResponse<StudentProfile>, String> {
+    if !check_view_permission(&state, &webview) {
+        return Ok(IpcResponse::error("Permission denied: view required"));
+    }
+
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    match profile::build_student_profile(&conn, &params).await {
+        Ok(result) => Ok(IpcResponse::success(result)),
+        Err(e) => Ok(IpcResponse::error(&e)),
+    }
+}
+
 #[tauri::command]
 pub async fn student_create(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     data: CreateStudentData,
 ) -> Result<IpcResponse<i32>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -708,6 +733,7 @@ pub async fn student_create(
                     .map(|v| v.to_string());
                 let new_student = students::ActiveModel {
                     id: sea_orm::ActiveValue::NotSet,
+                    uuid: Set(Uuid::new_v4().to_string()),
                     name: Set(name.to_string()),
                     group_name: Set(group_name),
                     score: Set(0),
@@ -721,6 +747,12 @@ pub async fn student_create(
                 match new_student.insert(conn).await {
                     Ok(inserted) => {
                         realtime_dual_write_sync_if_legacy(state.inner()).await?;
+                        state_guard.event_bus.publish(
+                            EventSource::Desktop,
+                            BusPayload::StudentsUpdated {
+                                student_ids: vec![inserted.id],
+                            },
+                        );
                         Ok(IpcResponse::success(inserted.id))
                     }
                     Err(e) => Ok(IpcResponse::error(&format!(
@@ -739,11 +771,11 @@ pub async fn student_create(
 #[tauri::command]
 pub async fn student_update(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     id: i32,
     data: StudentUpdate,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -758,6 +790,7 @@ pub async fn student_update(
                 let now = chrono::Utc::now()
                     .format("%Y-%m-%dT%H:%M:%S%.3fZ")
                     .to_string();
+                let before = student.clone();
                 let mut active: students::ActiveModel = student.into();
 
                 active.updated_at = Set(now);
@@ -787,9 +820,33 @@ pub async fn student_update(
                     active.extra_json = Set(Some(extra_json));
                 }
 
-                match active.update(conn).await {
-                    Ok(_) => {
+                let txn = conn.begin().await.map_err(|e| e.to_string())?;
+                match active.update(&txn).await {
+                    Ok(after) => {
+                        if after.name != before.name {
+                            students::rename_references(&txn, after.id, &after.name)
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        }
+                        let student_id = after.id;
+                        let summary = format!("修改学生 {}", before.name);
+                        record_operation(
+                            &txn,
+                            &JournalOp::StudentUpdated {
+                                before: Box::new(before),
+                                after: Box::new(after),
+                            },
+                            &summary,
+                        )
+                        .await?;
+                        txn.commit().await.map_err(|e| e.to_string())?;
                         realtime_dual_write_sync_if_legacy(state.inner()).await?;
+                        state_guard.event_bus.publish(
+                            EventSource::Desktop,
+                            BusPayload::StudentsUpdated {
+                                student_ids: vec![student_id],
+                            },
+                        );
                         Ok(IpcResponse::success_empty())
                     }
                     Err(e) => Ok(IpcResponse::error(&format!(
@@ -809,10 +866,10 @@ pub async fn student_update(
 #[tauri::command]
 pub async fn student_delete(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     id: i32,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -828,6 +885,7 @@ pub async fn student_delete(
 
                 students::Entity::delete(students::ActiveModel {
                     id: sea_orm::ActiveValue::Set(student.id),
+                    uuid: sea_orm::ActiveValue::Unchanged(student.uuid),
                     name: sea_orm::ActiveValue::Unchanged(student.name),
                     group_name: sea_orm::ActiveValue::Unchanged(student.group_name),
                     score: sea_orm::ActiveValue::Unchanged(student.score),
@@ -843,6 +901,12 @@ pub async fn student_delete(
 
                 txn.commit().await.map_err(|e| e.to_string())?;
                 realtime_dual_write_sync_if_legacy(state.inner()).await?;
+                state_guard.event_bus.publish(
+                    EventSource::Desktop,
+                    BusPayload::StudentsUpdated {
+                        student_ids: vec![id],
+                    },
+                );
                 Ok(IpcResponse::success_empty())
             }
             Ok(None) => Ok(IpcResponse::error("Student not found")),
@@ -856,10 +920,10 @@ pub async fn student_delete(
 #[tauri::command]
 pub async fn student_import_from_xlsx(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     params: ImportStudentsParams,
 ) -> Result<IpcResponse<ImportResult>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -912,9 +976,11 @@ pub async fn student_import_from_xlsx(
                         .format("%Y-%m-%dT%H:%M:%S%.3fZ")
                         .to_string();
 
+                    let mut student_ids = Vec::with_capacity(to_insert.len());
                     for name in &to_insert {
                         let new_student = students::ActiveModel {
                             id: sea_orm::ActiveValue::NotSet,
+                            uuid: Set(Uuid::new_v4().to_string()),
                             name: Set(name.to_string()),
                             group_name: Set(None),
                             score: Set(0),
@@ -924,11 +990,16 @@ pub async fn student_import_from_xlsx(
                             created_at: Set(now.clone()),
                             updated_at: Set(now.clone()),
                         };
-                        new_student.insert(&txn).await.map_err(|e| e.to_string())?;
+                        let created = new_student.insert(&txn).await.map_err(|e| e.to_string())?;
+                        student_ids.push(created.id);
                     }
 
                     txn.commit().await.map_err(|e| e.to_string())?;
                     realtime_dual_write_sync_if_legacy(state.inner()).await?;
+                    state_guard.event_bus.publish(
+                        EventSource::Desktop,
+                        BusPayload::StudentsUpdated { student_ids },
+                    );
                 }
 
                 Ok(IpcResponse::success(ImportResult {
@@ -947,10 +1018,10 @@ pub async fn student_import_from_xlsx(
 #[tauri::command]
 pub async fn student_fetch_banyou_classrooms(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     params: FetchBanYouClassroomsParams,
 ) -> Result<IpcResponse<BanYouClassroomFetchData>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -1060,10 +1131,10 @@ pub async fn student_fetch_banyou_classrooms(
 #[tauri::command]
 pub async fn student_fetch_banyou_classroom_detail(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     params: FetchBanYouClassroomDetailParams,
 ) -> Result<IpcResponse<BanYouClassroomDetailData>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
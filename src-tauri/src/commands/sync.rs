@@ -1,17 +1,24 @@
 use chrono::Utc;
 use parking_lot::RwLock;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbBackend, EntityTrait, QueryFilter, Set,
-    Statement, TransactionTrait,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DbBackend, EntityTrait,
+    QueryFilter, QueryOrder, Set, Statement, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tauri::State;
+use uuid::Uuid;
 
 use crate::db::entities::{
-    reasons, reward_redemptions, reward_settings, score_events, student_tags, students, tags,
+    reason_categories, reason_sets, reasons, reward_redemptions, reward_settings, score_events,
+    student_tags, students, tags,
 };
+use crate::services::backup::backup_before_operation;
+use crate::services::reason;
+use crate::services::reward;
+use crate::services::{BusPayload, EventSource, RedemptionStatus};
 use crate::state::AppState;
 
 use super::response::IpcResponse;
@@ -38,6 +45,32 @@ fn snapshot_i32(value: &Value, key: &str) -> Option<i32> {
         .and_then(|item| i32::try_from(item).ok())
 }
 
+// 旧版快照没有这些字段时保持本地原值，而不是清空
+fn snapshot_optional_i32(value: &Value, key: &str) -> ActiveValue<Option<i32>> {
+    if value.get(key).is_some() {
+        Set(snapshot_i32(value, key))
+    } else {
+        ActiveValue::NotSet
+    }
+}
+
+fn snapshot_optional_string(value: &Value, key: &str) -> ActiveValue<Option<String>> {
+    if value.get(key).is_some() {
+        Set(snapshot_string(value, key))
+    } else {
+        ActiveValue::NotSet
+    }
+}
+
+// 快照里的布尔字段可能是 true/false，也可能是 0/1
+fn snapshot_flag(value: &Value, key: &str) -> ActiveValue<i32> {
+    match value.get(key) {
+        Some(Value::Bool(flag)) => Set(i32::from(*flag)),
+        Some(_) => Set(snapshot_i32(value, key).unwrap_or(0)),
+        None => ActiveValue::NotSet,
+    }
+}
+
 fn snapshot_array<'a>(snapshot: &'a Value, key: &str) -> &'a [Value] {
     snapshot
         .get(key)
@@ -50,33 +83,105 @@ fn now_string() -> String {
     Utc::now().to_rfc3339()
 }
 
-#[tauri::command]
-pub async fn sync_apply_snapshot(
-    state: State<'_, Arc<RwLock<AppState>>>,
-    snapshot: Value,
-) -> Result<IpcResponse<()>, String> {
-    let local_write_lock = { state.read().local_write_lock.clone() };
-    let _write_guard = local_write_lock.lock().await;
-    let state_guard = state.read();
-    let connection = state_guard
-        .local_sqlite
-        .read()
-        .clone()
-        .or_else(|| state_guard.db.read().clone());
-    let Some(connection) = connection else {
-        return Ok(IpcResponse::error("本地数据库未连接"));
-    };
-    let transaction = connection.begin().await.map_err(|e| e.to_string())?;
+// 优先按学生 uuid 匹配，旧客户端只带姓名时再按姓名匹配
+async fn find_sync_student<C: ConnectionTrait>(
+    conn: &C,
+    student_uuid: Option<&str>,
+    student_name: &str,
+) -> Result<Option<students::Model>, String> {
+    if let Some(student_uuid) = student_uuid.map(str::trim).filter(|v| !v.is_empty()) {
+        let found = students::Entity::find()
+            .filter(students::Column::Uuid.eq(student_uuid))
+            .one(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    students::Entity::find()
+        .filter(students::Column::Name.eq(student_name))
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 快照中出现过的学生 uuid，这些本地行只能按 uuid 匹配，不能被同名学生占用
+fn snapshot_student_uuids(snapshot: &Value) -> HashSet<String> {
+    snapshot_array(snapshot, "students")
+        .iter()
+        .filter_map(|value| snapshot_string(value, "uuid"))
+        .map(|uuid| uuid.trim().to_string())
+        .filter(|uuid| !uuid.is_empty())
+        .collect()
+}
 
-    for value in snapshot_array(&snapshot, "students") {
+// 先按 uuid 匹配；按姓名匹配时跳过本次已匹配的行，以及 uuid 会被快照中其他学生认领的行，
+// 否则远端改名后同名的新学生会抢走原来的本地行
+async fn match_snapshot_student<C: ConnectionTrait>(
+    conn: &C,
+    student_uuid: Option<&str>,
+    student_name: &str,
+    snapshot_uuids: &HashSet<String>,
+    claimed: &HashSet<i32>,
+) -> Result<Option<students::Model>, String> {
+    if let Some(student_uuid) = student_uuid {
+        let found = students::Entity::find()
+            .filter(students::Column::Uuid.eq(student_uuid))
+            .one(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(found) = found.filter(|row| !claimed.contains(&row.id)) {
+            return Ok(Some(found));
+        }
+    }
+    let candidates = students::Entity::find()
+        .filter(students::Column::Name.eq(student_name))
+        .order_by_asc(students::Column::Id)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(candidates.into_iter().find(|row| {
+        !claimed.contains(&row.id)
+            && (Some(row.uuid.as_str()) == student_uuid || !snapshot_uuids.contains(&row.uuid))
+    }))
+}
+
+/// uuid 有唯一索引，已被其他行占用时不能再写入
+async fn uuid_held_by_other<C: ConnectionTrait>(
+    conn: &C,
+    student_uuid: &str,
+    student_id: Option<i32>,
+) -> Result<bool, String> {
+    let mut query = students::Entity::find().filter(students::Column::Uuid.eq(student_uuid));
+    if let Some(student_id) = student_id {
+        query = query.filter(students::Column::Id.ne(student_id));
+    }
+    let holder = query.one(conn).await.map_err(|e| e.to_string())?;
+    Ok(holder.is_some())
+}
+
+async fn apply_snapshot_students<C: ConnectionTrait>(
+    conn: &C,
+    snapshot: &Value,
+) -> Result<(), String> {
+    let snapshot_uuids = snapshot_student_uuids(snapshot);
+    let mut claimed = HashSet::new();
+    for value in snapshot_array(snapshot, "students") {
         let Some(name) = snapshot_string(value, "name") else {
             continue;
         };
-        let existing = students::Entity::find()
-            .filter(students::Column::Name.eq(&name))
-            .one(&transaction)
-            .await
-            .map_err(|e| e.to_string())?;
+        let student_uuid = snapshot_string(value, "uuid")
+            .map(|uuid| uuid.trim().to_string())
+            .filter(|uuid| !uuid.is_empty());
+        let existing = match_snapshot_student(
+            conn,
+            student_uuid.as_deref(),
+            &name,
+            &snapshot_uuids,
+            &claimed,
+        )
+        .await?;
         let tags_value = value
             .get("tags")
             .cloned()
@@ -95,7 +200,15 @@ pub async fn sync_apply_snapshot(
         let updated_at = snapshot_string(value, "updated_at").unwrap_or_else(now_string);
         match existing {
             Some(student) => {
+                claimed.insert(student.id);
+                let student_id = student.id;
                 let mut active: students::ActiveModel = student.into();
+                active.name = Set(name);
+                if let Some(student_uuid) = student_uuid {
+                    if !uuid_held_by_other(conn, &student_uuid, Some(student_id)).await? {
+                        active.uuid = Set(student_uuid);
+                    }
+                }
                 active.group_name = Set(group_name);
                 active.tags = Set(tags_text);
                 active.extra_json = Set(extra_json);
@@ -108,29 +221,83 @@ pub async fn sync_apply_snapshot(
                     active.reward_points = Set(reward_points);
                 }
                 active.updated_at = Set(updated_at);
-                active
-                    .update(&transaction)
-                    .await
-                    .map_err(|e| e.to_string())?;
+                active.update(conn).await.map_err(|e| e.to_string())?;
             }
             None => {
-                students::ActiveModel {
+                let student_uuid = match student_uuid {
+                    Some(uuid) if !uuid_held_by_other(conn, &uuid, None).await? => uuid,
+                    _ => Uuid::new_v4().to_string(),
+                };
+                let created_at = snapshot_string(value, "created_at").unwrap_or_else(now_string);
+                let inserted = students::ActiveModel {
                     id: sea_orm::ActiveValue::NotSet,
+                    uuid: Set(student_uuid),
                     name: Set(name),
                     group_name: Set(group_name),
                     score: Set(score),
                     reward_points: Set(reward_points),
                     tags: Set(tags_text),
                     extra_json: Set(extra_json),
-                    created_at: Set(snapshot_string(value, "created_at").unwrap_or_else(now_string)),
+                    created_at: Set(created_at),
                     updated_at: Set(updated_at),
                 }
-                .insert(&transaction)
+                .insert(conn)
                 .await
                 .map_err(|e| e.to_string())?;
+                claimed.insert(inserted.id);
             }
         }
     }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn sync_apply_snapshot(
+    state: State<'_, Arc<RwLock<AppState>>>,
+    snapshot: Value,
+) -> Result<IpcResponse<()>, String> {
+    if let Err(e) = backup_before_operation(state.inner(), "sync_apply_snapshot").await {
+        return Ok(IpcResponse::error(&e));
+    }
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let state_guard = state.read();
+    let connection = state_guard
+        .local_sqlite
+        .read()
+        .clone()
+        .or_else(|| state_guard.db.read().clone());
+    let Some(connection) = connection else {
+        return Ok(IpcResponse::error("本地数据库未连接"));
+    };
+    let transaction = connection.begin().await.map_err(|e| e.to_string())?;
+
+    apply_snapshot_students(&transaction, &snapshot).await?;
+
+    for value in snapshot_array(&snapshot, "reason_categories") {
+        let Some(name) = snapshot_string(value, "name") else {
+            continue;
+        };
+        let Some(category) = reason::ensure_category(&transaction, &name).await? else {
+            continue;
+        };
+        let mut active: reason_categories::ActiveModel = category.into();
+        if let Some(sort_order) = snapshot_i32(value, "sort_order") {
+            active.sort_order = Set(sort_order);
+        }
+        active.color = snapshot_optional_string(value, "color");
+        active.icon = snapshot_optional_string(value, "icon");
+        active.is_archived = snapshot_flag(value, "is_archived");
+        if let Some(updated_at) = snapshot_string(value, "updated_at") {
+            active.updated_at = Set(updated_at);
+        }
+        if active.is_changed() {
+            active
+                .update(&transaction)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
 
     for value in snapshot_array(&snapshot, "reasons") {
         let Some(content) = snapshot_string(value, "content") else {
@@ -141,15 +308,25 @@ pub async fn sync_apply_snapshot(
             .one(&transaction)
             .await
             .map_err(|e| e.to_string())?;
+        // 两端分类 id 不同，按名称对应本地分类
+        let category = snapshot_string(value, "category").unwrap_or_else(|| "其他".into());
+        let category_id = reason::ensure_category(&transaction, &category)
+            .await?
+            .map(|c| c.id);
         let model = reasons::ActiveModel {
             id: existing
                 .as_ref()
                 .map(|row| Set(row.id))
                 .unwrap_or(sea_orm::ActiveValue::NotSet),
             content: Set(content),
-            category: Set(snapshot_string(value, "category").unwrap_or_else(|| "其他".into())),
+            category: Set(category),
+            category_id: Set(category_id),
             delta: Set(snapshot_i32(value, "delta").unwrap_or(0)),
             is_system: Set(snapshot_i32(value, "is_system").unwrap_or(0)),
+            sort_order: snapshot_i32(value, "sort_order")
+                .map(Set)
+                .unwrap_or(ActiveValue::NotSet),
+            is_archived: snapshot_flag(value, "is_archived"),
             updated_at: Set(snapshot_string(value, "updated_at").unwrap_or_else(now_string)),
         };
         if existing.is_some() {
@@ -165,6 +342,59 @@ pub async fn sync_apply_snapshot(
         }
     }
 
+    // 快捷理由组按名称对应，组内理由以内容引用
+    for value in snapshot_array(&snapshot, "reason_sets") {
+        let Some(name) = snapshot_string(value, "name") else {
+            continue;
+        };
+        let existing = reason_sets::Entity::find()
+            .filter(reason_sets::Column::Name.eq(&name))
+            .one(&transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        let updated_at = snapshot_string(value, "updated_at").unwrap_or_else(now_string);
+        let set = match existing {
+            Some(existing) => {
+                let mut active: reason_sets::ActiveModel = existing.into();
+                active.updated_at = Set(updated_at);
+                active
+                    .update(&transaction)
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+            None => reason_sets::ActiveModel {
+                id: sea_orm::ActiveValue::NotSet,
+                name: Set(name),
+                created_at: Set(snapshot_string(value, "created_at").unwrap_or_else(now_string)),
+                updated_at: Set(updated_at),
+            }
+            .insert(&transaction)
+            .await
+            .map_err(|e| e.to_string())?,
+        };
+        let contents = value
+            .get("reasons")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let rows = reasons::Entity::find()
+            .filter(reasons::Column::Content.is_in(contents.clone()))
+            .all(&transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        let reason_ids = contents
+            .iter()
+            .filter_map(|content| rows.iter().find(|r| &r.content == content).map(|r| r.id))
+            .collect::<Vec<_>>();
+        reason::replace_set_reasons(&transaction, set.id, &reason_ids).await?;
+    }
+
     for value in snapshot_array(&snapshot, "reward_settings") {
         let Some(name) = snapshot_string(value, "name") else {
             continue;
@@ -181,6 +411,12 @@ pub async fn sync_apply_snapshot(
                 .unwrap_or(sea_orm::ActiveValue::NotSet),
             name: Set(name),
             cost_points: Set(snapshot_i32(value, "cost_points").unwrap_or(0)),
+            stock: snapshot_optional_i32(value, "stock"),
+            per_student_limit: snapshot_optional_i32(value, "per_student_limit"),
+            per_period_limit: snapshot_optional_i32(value, "per_period_limit"),
+            active_from: snapshot_optional_string(value, "active_from"),
+            active_until: snapshot_optional_string(value, "active_until"),
+            requires_approval: Set(snapshot_i32(value, "requires_approval").unwrap_or(0)),
             created_at: Set(snapshot_string(value, "created_at").unwrap_or_else(now_string)),
             updated_at: Set(snapshot_string(value, "updated_at").unwrap_or_else(now_string)),
         };
@@ -238,10 +474,18 @@ pub async fn sync_apply_snapshot(
             .await
             .map_err(|e| e.to_string())?;
         if exists.is_none() {
+            let student_name = snapshot_string(value, "student_name").unwrap_or_default();
+            let student = find_sync_student(
+                &transaction,
+                snapshot_string(value, "student_uuid").as_deref(),
+                &student_name,
+            )
+            .await?;
             score_events::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
                 uuid: Set(uuid),
-                student_name: Set(snapshot_string(value, "student_name").unwrap_or_default()),
+                student_id: Set(student.as_ref().map(|s| s.id)),
+                student_name: Set(student.map(|s| s.name).unwrap_or(student_name)),
                 reason_content: Set(snapshot_string(value, "reason_content").unwrap_or_default()),
                 delta: Set(snapshot_i32(value, "delta").unwrap_or(0)),
                 val_prev: Set(snapshot_i32(value, "val_prev").unwrap_or(0)),
@@ -273,14 +517,30 @@ pub async fn sync_apply_snapshot(
             .await
             .map_err(|e| e.to_string())?;
         if exists.is_none() {
+            let student_name = snapshot_string(value, "student_name").unwrap_or_default();
+            let student = find_sync_student(
+                &transaction,
+                snapshot_string(value, "student_uuid").as_deref(),
+                &student_name,
+            )
+            .await?;
             reward_redemptions::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
                 uuid: Set(uuid),
-                student_name: Set(snapshot_string(value, "student_name").unwrap_or_default()),
+                student_id: Set(student.as_ref().map(|s| s.id)),
+                student_name: Set(student.map(|s| s.name).unwrap_or(student_name)),
                 reward_id: Set(local_reward_id),
                 reward_name: Set(reward_name),
                 cost_points: Set(snapshot_i32(value, "cost_points").unwrap_or(0)),
                 redeemed_at: Set(snapshot_string(value, "redeemed_at").unwrap_or_else(now_string)),
+                status: Set(snapshot_string(value, "status")
+                    .filter(|status| RedemptionStatus::parse(status).is_some())
+                    .unwrap_or_else(|| RedemptionStatus::Fulfilled.as_str().to_string())),
+                reviewed_at: Set(snapshot_string(value, "reviewed_at")),
+                review_note: Set(snapshot_string(value, "review_note")),
+                cancelled_at: Set(snapshot_string(value, "cancelled_at")),
+                cancelled_by: Set(snapshot_string(value, "cancelled_by")),
+                cancel_reason: Set(snapshot_string(value, "cancel_reason")),
             }
             .insert(&transaction)
             .await
@@ -295,11 +555,9 @@ pub async fn sync_apply_snapshot(
         let Some(tag_name) = snapshot_string(value, "tag_name") else {
             continue;
         };
-        let Some(student) = students::Entity::find()
-            .filter(students::Column::Name.eq(&student_name))
-            .one(&transaction)
-            .await
-            .map_err(|e| e.to_string())?
+        let student_uuid = snapshot_string(value, "student_uuid");
+        let Some(student) =
+            find_sync_student(&transaction, student_uuid.as_deref(), &student_name).await?
         else {
             continue;
         };
@@ -386,6 +644,10 @@ pub async fn sync_apply_snapshot(
             // 数据库连接方式和同步方式是本机策略，不能被另一端的快照覆盖。
             continue;
         }
+        if key == "auto_score_batches" {
+            // 旧版快照携带的执行批次引用的是对端的记录 id，本机无法回滚，直接丢弃
+            continue;
+        }
         let raw = serde_json::to_string(value).map_err(|e| e.to_string())?;
         let statement = Statement::from_sql_and_values(
             DbBackend::Sqlite,
@@ -399,6 +661,9 @@ pub async fn sync_apply_snapshot(
     }
 
     transaction.commit().await.map_err(|e| e.to_string())?;
+    state_guard
+        .event_bus
+        .publish(EventSource::Sync, BusPayload::DataReset);
     Ok(IpcResponse::success_empty())
 }
 
@@ -470,11 +735,12 @@ pub async fn sync_apply_remote_operation(
                 return Ok(IpcResponse::success_empty());
             }
 
-            let Some(student) = students::Entity::find()
-                .filter(students::Column::Name.eq(&student_name))
-                .one(&transaction)
-                .await
-                .map_err(|e| e.to_string())?
+            let student_uuid = operation
+                .payload
+                .get("student_uuid")
+                .and_then(Value::as_str);
+            let Some(student) =
+                find_sync_student(&transaction, student_uuid, &student_name).await?
             else {
                 return Ok(IpcResponse::error("本地找不到同步操作对应的学生"));
             };
@@ -490,7 +756,8 @@ pub async fn sync_apply_remote_operation(
             score_events::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
                 uuid: Set(operation_id.to_string()),
-                student_name: Set(student_name),
+                student_id: Set(Some(student.id)),
+                student_name: Set(student.name.clone()),
                 reason_content: Set(reason_content),
                 delta: Set(delta),
                 val_prev: Set(student.score),
@@ -529,11 +796,12 @@ pub async fn sync_apply_remote_operation(
                 return Ok(IpcResponse::success_empty());
             }
 
-            let Some(student) = students::Entity::find()
-                .filter(students::Column::Name.eq(&student_name))
-                .one(&transaction)
-                .await
-                .map_err(|e| e.to_string())?
+            let student_uuid = operation
+                .payload
+                .get("student_uuid")
+                .and_then(Value::as_str);
+            let Some(student) =
+                find_sync_student(&transaction, student_uuid, &student_name).await?
             else {
                 return Ok(IpcResponse::error("本地找不到同步操作对应的学生"));
             };
@@ -541,11 +809,18 @@ pub async fn sync_apply_remote_operation(
             reward_redemptions::ActiveModel {
                 id: sea_orm::ActiveValue::NotSet,
                 uuid: Set(operation_id.to_string()),
-                student_name: Set(student_name),
+                student_id: Set(Some(student.id)),
+                student_name: Set(student.name.clone()),
                 reward_id: Set(reward_id),
                 reward_name: Set(reward_name),
                 cost_points: Set(cost_points),
                 redeemed_at: Set(timestamp.clone()),
+                status: Set(RedemptionStatus::Pending.as_str().to_string()),
+                reviewed_at: Set(None),
+                review_note: Set(None),
+                cancelled_at: Set(None),
+                cancelled_by: Set(None),
+                cancel_reason: Set(None),
             }
             .insert(&transaction)
             .await
@@ -559,10 +834,40 @@ pub async fn sync_apply_remote_operation(
                 .await
                 .map_err(|e| e.to_string())?;
         }
+        "reward.cancel" => {
+            let redemption_uuid = text(&operation.payload, "redemption_uuid")?;
+            let reason = text(&operation.payload, "reason")?;
+            let cancelled_by =
+                text(&operation.payload, "cancelled_by").unwrap_or_else(|_| "sync".to_string());
+
+            let Some(redemption) = reward_redemptions::Entity::find()
+                .filter(reward_redemptions::Column::Uuid.eq(&redemption_uuid))
+                .one(&transaction)
+                .await
+                .map_err(|e| e.to_string())?
+            else {
+                return Ok(IpcResponse::error("本地找不到要撤销的兑换记录"));
+            };
+
+            // 已退还过的记录（重复投递或已被驳回）直接视为成功
+            if !RedemptionStatus::of(&redemption.status).is_refunded() {
+                reward::cancel_redemption(
+                    &transaction,
+                    redemption,
+                    &cancelled_by,
+                    &reason,
+                    &timestamp,
+                )
+                .await?;
+            }
+        }
         _ => return Ok(IpcResponse::error("不支持的远程同步操作")),
     }
 
     transaction.commit().await.map_err(|e| e.to_string())?;
+    state_guard
+        .event_bus
+        .publish(EventSource::Sync, BusPayload::DataReset);
     Ok(IpcResponse::success_empty())
 }
 
@@ -573,3 +878,108 @@ fn active_reward_points(active: &students::ActiveModel, delta: i32) -> i32 {
         sea_orm::ActiveValue::NotSet => delta,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::apply_snapshot_students;
+    use crate::db::connection::memory_sqlite_connection;
+    use crate::db::entities::students;
+    use crate::db::{run_migration, DatabaseType};
+    use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, QueryOrder, Set};
+    use serde_json::json;
+
+    const UUID_A: &str = "00000000-0000-4000-8000-00000000000a";
+    const UUID_B: &str = "00000000-0000-4000-8000-00000000000b";
+    const UUID_C: &str = "00000000-0000-4000-8000-00000000000c";
+
+    async fn setup(rows: &[(&str, &str)]) -> DatabaseConnection {
+        let conn = memory_sqlite_connection().await;
+        run_migration(&conn, DatabaseType::SQLite).await.unwrap();
+        for (uuid, name) in rows {
+            students::ActiveModel {
+                id: sea_orm::ActiveValue::NotSet,
+                uuid: Set(uuid.to_string()),
+                name: Set(name.to_string()),
+                group_name: Set(None),
+                score: Set(0),
+                reward_points: Set(0),
+                tags: Set("[]".to_string()),
+                extra_json: Set(None),
+                created_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+                updated_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+            }
+            .insert(&conn)
+            .await
+            .unwrap();
+        }
+        conn
+    }
+
+    async fn roster(conn: &DatabaseConnection) -> Vec<(i32, String, String, i32)> {
+        students::Entity::find()
+            .order_by_asc(students::Column::Id)
+            .all(conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.id, row.uuid, row.name, row.score))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn renamed_student_keeps_row_when_new_student_takes_old_name() {
+        // 远端把 A 改名为李四，又新建了同名的张三
+        let conn = setup(&[(UUID_A, "张三")]).await;
+        let snapshot = json!({
+            "students": [
+                { "uuid": UUID_B, "name": "张三", "score": 1 },
+                { "uuid": UUID_A, "name": "李四", "score": 2 },
+            ]
+        });
+        apply_snapshot_students(&conn, &snapshot).await.unwrap();
+        assert_eq!(
+            roster(&conn).await,
+            vec![
+                (1, UUID_A.to_string(), "李四".to_string(), 2),
+                (2, UUID_B.to_string(), "张三".to_string(), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn name_match_adopts_uuid_only_when_it_is_free() {
+        let conn = setup(&[(UUID_A, "张三"), (UUID_C, "王五")]).await;
+        let snapshot = json!({
+            "students": [
+                // 旧客户端生成的本地行按姓名认领远端 uuid
+                { "uuid": UUID_B, "name": "张三", "score": 3 },
+                // uuid 已归属王五，同名的另一条快照不能再抢
+                { "uuid": UUID_C, "name": "王五", "score": 4 },
+                { "uuid": UUID_C, "name": "赵六", "score": 5 },
+            ]
+        });
+        apply_snapshot_students(&conn, &snapshot).await.unwrap();
+        let rows = roster(&conn).await;
+        assert_eq!(rows[0], (1, UUID_B.to_string(), "张三".to_string(), 3));
+        assert_eq!(rows[1], (2, UUID_C.to_string(), "王五".to_string(), 4));
+        assert_eq!(rows.len(), 3);
+        assert_eq!((rows[2].2.as_str(), rows[2].3), ("赵六", 5));
+        assert_ne!(rows[2].1, UUID_C);
+    }
+
+    #[tokio::test]
+    async fn reapplying_the_same_snapshot_is_stable() {
+        let conn = setup(&[(UUID_A, "张三")]).await;
+        let snapshot = json!({
+            "students": [
+                { "uuid": UUID_A, "name": "张三", "score": 7 },
+                { "uuid": UUID_B, "name": "李四", "score": 8 },
+            ]
+        });
+        apply_snapshot_students(&conn, &snapshot).await.unwrap();
+        let first = roster(&conn).await;
+        apply_snapshot_students(&conn, &snapshot).await.unwrap();
+        assert_eq!(roster(&conn).await, first);
+        assert_eq!(first.len(), 2);
+    }
+}
@@ -3,35 +3,36 @@ use sea_orm::{
     ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
 };
 use std::sync::Arc;
-use tauri::State;
+use tauri::{State, Webview};
 
 use crate::db::entities::{student_tags, tags};
-use crate::services::PermissionLevel;
+use crate::services::tag;
+use crate::services::{window_session_key, BusPayload, EventSource, PermissionLevel};
 use crate::state::AppState;
 
 use super::database::realtime_dual_write_sync_if_legacy;
 use super::response::{IpcResponse, TagResponse};
 
-fn check_admin_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
+fn check_admin_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::Admin)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::Admin)
 }
 
-fn check_view_permission(state: &Arc<RwLock<AppState>>, sender_id: Option<u32>) -> bool {
+fn check_view_permission(state: &Arc<RwLock<AppState>>, webview: &Webview) -> bool {
     let state_guard = state.read();
     let mut permissions = state_guard.permissions.write();
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::View)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::View)
 }
 
 #[tauri::command]
 pub async fn tags_get_all(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
 ) -> Result<IpcResponse<Vec<TagResponse>>, String> {
-    if !check_view_permission(&state, sender_id) {
+    if !check_view_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: view required"));
     }
 
@@ -65,10 +66,10 @@ pub async fn tags_get_all(
 #[tauri::command]
 pub async fn tags_get_by_student(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     student_id: i32,
 ) -> Result<IpcResponse<Vec<TagResponse>>, String> {
-    if !check_view_permission(&state, sender_id) {
+    if !check_view_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: view required"));
     }
 
@@ -122,10 +123,10 @@ pub async fn tags_get_by_student(
 #[tauri::command]
 pub async fn tags_create(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     name: String,
 ) -> Result<IpcResponse<TagResponse>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -180,10 +181,10 @@ pub async fn tags_create(
 #[tauri::command]
 pub async fn tags_delete(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     id: i32,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
@@ -228,49 +229,32 @@ pub async fn tags_delete(
 #[tauri::command]
 pub async fn tags_update_student_tags(
     state: State<'_, Arc<RwLock<AppState>>>,
-    sender_id: Option<u32>,
+    webview: Webview,
     student_id: i32,
     tag_ids: Vec<i32>,
 ) -> Result<IpcResponse<()>, String> {
-    if !check_admin_permission(&state, sender_id) {
+    if !check_admin_permission(&state, &webview) {
         return Ok(IpcResponse::error("Permission denied: admin required"));
     }
 
-    let state_guard = state.read();
-    let db_guard = state_guard.db.read();
-
-    if let Some(conn) = db_guard.as_ref() {
-        let txn = conn.begin().await.map_err(|e| e.to_string())?;
-
-        student_tags::Entity::delete_many()
-            .filter(student_tags::Column::StudentId.eq(student_id))
-            .exec(&txn)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        if !tag_ids.is_empty() {
-            let now = chrono::Utc::now()
-                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-                .to_string();
-
-            for tag_id in tag_ids {
-                let new_student_tag = student_tags::ActiveModel {
-                    id: sea_orm::ActiveValue::NotSet,
-                    student_id: Set(student_id),
-                    tag_id: Set(tag_id),
-                    created_at: Set(now.clone()),
-                };
-                new_student_tag
-                    .insert(&txn)
-                    .await
-                    .map_err(|e| e.to_string())?;
-            }
-        }
-
-        txn.commit().await.map_err(|e| e.to_string())?;
-        realtime_dual_write_sync_if_legacy(state.inner()).await?;
-        Ok(IpcResponse::success_empty())
-    } else {
-        Ok(IpcResponse::error("Database not connected"))
-    }
+    let local_write_lock = { state.read().local_write_lock.clone() };
+    let _write_guard = local_write_lock.lock().await;
+    let db_conn = { state.read().db.read().clone() };
+    let Some(conn) = db_conn else {
+        return Ok(IpcResponse::error("Database not connected"));
+    };
+
+    let added_tag_ids = match tag::set_student_tags(&conn, student_id, tag_ids).await {
+        Ok(added_tag_ids) => added_tag_ids,
+        Err(e) => return Ok(IpcResponse::error(&e)),
+    };
+    realtime_dual_write_sync_if_legacy(state.inner()).await?;
+    state.read().event_bus.publish(
+        EventSource::Desktop,
+        BusPayload::StudentTagsUpdated {
+            student_id,
+            added_tag_ids,
+        },
+    );
+    Ok(IpcResponse::success_empty())
 }
@@ -1,18 +1,21 @@
 use parking_lot::RwLock;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{State, Webview};
 
-use crate::services::{PermissionLevel, SettingsKey, SettingsValue, ThemeConfig};
+use crate::services::{
+    window_session_key, BusPayload, EventSource, PermissionLevel, SettingsKey, SettingsValue,
+    ThemeConfig,
+};
 use crate::state::AppState;
 
 use super::response::IpcResponse;
 
 fn check_admin_permission(
     permissions: &mut crate::services::PermissionService,
-    sender_id: Option<u32>,
+    webview: &Webview,
 ) -> bool {
-    let id = sender_id.unwrap_or(0);
-    permissions.require_permission(id, PermissionLevel::Admin)
+    let session = window_session_key(webview.label());
+    permissions.require_permission(&session, PermissionLevel::Admin)
 }
 
 #[tauri::command]
@@ -40,14 +43,13 @@ pub async fn theme_current(
 #[tauri::command]
 pub async fn theme_set(
     theme_id: String,
-    sender_id: Option<u32>,
-    app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -74,7 +76,12 @@ pub async fn theme_set(
     };
 
     if let Some(theme) = current_theme {
-        let _ = app_handle.emit("theme:updated", &theme);
+        state.read().event_bus.publish(
+            EventSource::Desktop,
+            BusPayload::ThemeChanged {
+                theme: Box::new(theme),
+            },
+        );
     }
 
     Ok(IpcResponse::success(()))
@@ -83,14 +90,13 @@ pub async fn theme_set(
 #[tauri::command]
 pub async fn theme_save(
     theme: ThemeConfig,
-    sender_id: Option<u32>,
-    app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -119,7 +125,12 @@ pub async fn theme_save(
     };
 
     if let Some(theme) = current_theme {
-        let _ = app_handle.emit("theme:updated", &theme);
+        state.read().event_bus.publish(
+            EventSource::Desktop,
+            BusPayload::ThemeChanged {
+                theme: Box::new(theme),
+            },
+        );
     }
 
     Ok(IpcResponse::success(()))
@@ -128,14 +139,13 @@ pub async fn theme_save(
 #[tauri::command]
 pub async fn theme_delete(
     theme_id: String,
-    sender_id: Option<u32>,
-    app_handle: AppHandle,
+    webview: Webview,
     state: State<'_, Arc<RwLock<AppState>>>,
 ) -> Result<IpcResponse<()>, String> {
     {
         let state_guard = state.read();
         let mut permissions = state_guard.permissions.write();
-        if !check_admin_permission(&mut permissions, sender_id) {
+        if !check_admin_permission(&mut permissions, &webview) {
             return Ok(IpcResponse::error("Permission denied: admin required"));
         }
     }
@@ -172,7 +182,12 @@ pub async fn theme_delete(
     };
 
     if let Some(theme) = current_theme {
-        let _ = app_handle.emit("theme:updated", &theme);
+        state.read().event_bus.publish(
+            EventSource::Desktop,
+            BusPayload::ThemeChanged {
+                theme: Box::new(theme),
+            },
+        );
     }
 
     Ok(IpcResponse::success(()))
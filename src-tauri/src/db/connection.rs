@@ -1,4 +1,8 @@
-use sea_orm::{ConnectOptions, Database, DatabaseConnection, DbErr};
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection, DbErr,
+    Statement, Value,
+};
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -243,6 +247,17 @@ pub async fn create_sqlite_connection(path: &str) -> Result<DatabaseConnection,
     Database::connect(opt).await
 }
 
+/// 测试用内存库；单连接保证所有查询落在同一个库上
+#[cfg(test)]
+pub(crate) async fn memory_sqlite_connection() -> DatabaseConnection {
+    let mut opt = ConnectOptions::new("sqlite::memory:");
+    apply_sqlite_connect_options(&mut opt);
+    opt.sqlx_logging(false);
+    Database::connect(opt)
+        .await
+        .expect("failed to open in-memory sqlite")
+}
+
 pub async fn create_postgres_connection(url: &str) -> Result<DatabaseConnection, DbErr> {
     let mut opt = ConnectOptions::new(url);
     apply_default_connect_options(&mut opt);
@@ -264,9 +279,44 @@ pub async fn test_postgres_connection(url: &str) -> Result<bool, DbErr> {
     Ok(true)
 }
 
+/// 以 `?` 占位符书写 SQL，Postgres 下自动改写为 `$1, $2 ...`
+pub fn bind_statement(backend: DatabaseBackend, sql: &str, values: Vec<Value>) -> Statement {
+    let sql = if backend == DatabaseBackend::Postgres {
+        let mut index = 0;
+        let mut rewritten = String::with_capacity(sql.len() + 8);
+        for ch in sql.chars() {
+            if ch == '?' {
+                index += 1;
+                rewritten.push('$');
+                rewritten.push_str(&index.to_string());
+            } else {
+                rewritten.push(ch);
+            }
+        }
+        rewritten
+    } else {
+        sql.to_string()
+    };
+    Statement::from_sql_and_values(backend, sql, values)
+}
+
+/// 用 `VACUUM INTO` 生成 SQLite 数据库的一致性快照，目标文件必须不存在
+pub async fn sqlite_vacuum_into(conn: &impl ConnectionTrait, dest: &Path) -> Result<(), DbErr> {
+    let dest = dest
+        .to_str()
+        .ok_or_else(|| DbErr::Custom("Invalid snapshot path".to_string()))?;
+    conn.execute(Statement::from_sql_and_values(
+        DatabaseBackend::Sqlite,
+        "VACUUM INTO ?",
+        [dest.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::sqlite_connection_url;
+    use super::{bind_statement, sqlite_connection_url};
     use sea_orm::DbBackend;
 
     #[test]
@@ -295,4 +345,16 @@ mod tests {
         );
         assert!(DbBackend::Sqlite.is_prefix_of(&url));
     }
+
+    #[test]
+    fn bind_statement_rewrites_placeholders_for_postgres() {
+        let sql = "SELECT id FROM students WHERE name = ? AND score > ?";
+        let pg = bind_statement(DbBackend::Postgres, sql, vec!["a".into(), 1.into()]);
+        assert_eq!(
+            pg.sql,
+            "SELECT id FROM students WHERE name = $1 AND score > $2"
+        );
+        let sqlite = bind_statement(DbBackend::Sqlite, sql, vec!["a".into(), 1.into()]);
+        assert_eq!(sqlite.sql, sql);
+    }
 }
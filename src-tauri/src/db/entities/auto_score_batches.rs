@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "auto_score_batches")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub rule_id: i32,
+    pub rule_name: String,
+    pub run_at: String,
+    pub affected_students: i32,
+    pub score_delta_total: i64,
+    pub settled: i32,
+    pub rolled_back: i32,
+    pub rollback_at: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::auto_score_batch_items::Entity")]
+    Items,
+}
+
+impl Related<super::auto_score_batch_items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Items.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,3 +1,9 @@
+pub mod auto_score_batch_items;
+pub mod auto_score_batches;
+pub mod operation_journal;
+pub mod reason_categories;
+pub mod reason_set_items;
+pub mod reason_sets;
 pub mod reasons;
 pub mod reward_redemptions;
 pub mod reward_settings;
@@ -6,6 +12,12 @@ pub mod student_tags;
 pub mod students;
 pub mod tags;
 
+pub use auto_score_batch_items::Entity as AutoScoreBatchItems;
+pub use auto_score_batches::Entity as AutoScoreBatches;
+pub use operation_journal::Entity as OperationJournal;
+pub use reason_categories::Entity as ReasonCategories;
+pub use reason_set_items::Entity as ReasonSetItems;
+pub use reason_sets::Entity as ReasonSets;
 pub use reasons::Entity as Reasons;
 pub use reward_redemptions::Entity as RewardRedemptions;
 pub use reward_settings::Entity as RewardSettings;
@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "operation_journal")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub op_type: String,
+    pub summary: String,
+    pub payload: String,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "reason_categories")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    pub sort_order: i32,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub is_archived: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::reasons::Entity")]
+    Reasons,
+}
+
+impl Related<super::reasons::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Reasons.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "reason_set_items")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub set_id: i32,
+    pub reason_id: i32,
+    pub sort_order: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::reason_sets::Entity",
+        from = "Column::SetId",
+        to = "super::reason_sets::Column::Id"
+    )]
+    ReasonSet,
+    #[sea_orm(
+        belongs_to = "super::reasons::Entity",
+        from = "Column::ReasonId",
+        to = "super::reasons::Column::Id"
+    )]
+    Reason,
+}
+
+impl Related<super::reason_sets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReasonSet.def()
+    }
+}
+
+impl Related<super::reasons::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Reason.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "reason_sets")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::reason_set_items::Entity")]
+    ReasonSetItems,
+}
+
+impl Related<super::reason_set_items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReasonSetItems.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
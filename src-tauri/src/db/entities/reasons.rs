@@ -7,13 +7,33 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub content: String,
+    // 分类名称快照，供旧版客户端与按名称同步使用
     pub category: String,
+    #[serde(default)]
+    pub category_id: Option<i32>,
     pub delta: i32,
     pub is_system: i32,
+    #[serde(default)]
+    pub sort_order: i32,
+    #[serde(default)]
+    pub is_archived: i32,
     pub updated_at: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::reason_categories::Entity",
+        from = "Column::CategoryId",
+        to = "super::reason_categories::Column::Id"
+    )]
+    Category,
+}
+
+impl Related<super::reason_categories::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Category.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -7,11 +7,29 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub uuid: String,
+    pub student_id: Option<i32>,
     pub student_name: String,
     pub reward_id: i32,
     pub reward_name: String,
     pub cost_points: i32,
     pub redeemed_at: String,
+    #[serde(default = "legacy_status")]
+    pub status: String,
+    #[serde(default)]
+    pub reviewed_at: Option<String>,
+    #[serde(default)]
+    pub review_note: Option<String>,
+    #[serde(default)]
+    pub cancelled_at: Option<String>,
+    #[serde(default)]
+    pub cancelled_by: Option<String>,
+    #[serde(default)]
+    pub cancel_reason: Option<String>,
+}
+
+// 早期导出与日志中的兑换记录没有状态字段，当时兑换即发放
+fn legacy_status() -> String {
+    "fulfilled".to_string()
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -8,6 +8,19 @@ pub struct Model {
     pub id: i32,
     pub name: String,
     pub cost_points: i32,
+    #[serde(default)]
+    pub stock: Option<i32>,
+    #[serde(default)]
+    pub per_student_limit: Option<i32>,
+    #[serde(default)]
+    pub per_period_limit: Option<i32>,
+    #[serde(default)]
+    pub active_from: Option<String>,
+    #[serde(default)]
+    pub active_until: Option<String>,
+    /// 1 表示兑换后需管理员审核，0 表示直接发放
+    #[serde(default)]
+    pub requires_approval: i32,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -7,6 +7,7 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub uuid: String,
+    pub student_id: Option<i32>,
     pub student_name: String,
     pub reason_content: String,
     pub delta: i32,
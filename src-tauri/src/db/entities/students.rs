@@ -1,4 +1,5 @@
 use sea_orm::entity::prelude::*;
+use sea_orm::QueryOrder;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
@@ -6,6 +7,8 @@ use serde::{Deserialize, Serialize};
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
+    #[serde(default)]
+    pub uuid: String,
     pub name: String,
     pub group_name: Option<String>,
     pub score: i32,
@@ -29,3 +32,43 @@ impl Related<super::student_tags::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+/// 按 student_id 查找记录所属学生；旧记录没有 id 时退回按姓名匹配
+pub async fn find_by_reference<C: sea_orm::ConnectionTrait>(
+    conn: &C,
+    student_id: Option<i32>,
+    student_name: &str,
+) -> Result<Option<Model>, DbErr> {
+    match student_id {
+        Some(id) => Entity::find_by_id(id).one(conn).await,
+        None => {
+            Entity::find()
+                .filter(Column::Name.eq(student_name))
+                .order_by_asc(Column::Id)
+                .one(conn)
+                .await
+        }
+    }
+}
+
+/// 改名后同步积分记录与兑换记录中的姓名快照
+pub async fn rename_references<C: sea_orm::ConnectionTrait>(
+    conn: &C,
+    student_id: i32,
+    name: &str,
+) -> Result<(), DbErr> {
+    super::score_events::Entity::update_many()
+        .col_expr(super::score_events::Column::StudentName, Expr::value(name))
+        .filter(super::score_events::Column::StudentId.eq(student_id))
+        .exec(conn)
+        .await?;
+    super::reward_redemptions::Entity::update_many()
+        .col_expr(
+            super::reward_redemptions::Column::StudentName,
+            Expr::value(name),
+        )
+        .filter(super::reward_redemptions::Column::StudentId.eq(student_id))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
@@ -1,38 +1,230 @@
-use sea_orm::{ConnectionTrait, DbBackend, DbErr, Statement};
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, Statement, TransactionTrait};
+use serde::Serialize;
 use tracing::{info, warn};
 
-use super::connection::DatabaseType;
+use super::connection::{bind_statement, DatabaseType};
 use super::schema::*;
 
 pub struct Migration;
 
+// 多个实例同时连接同一个 PostgreSQL 时用于串行执行迁移的事务级咨询锁
+const MIGRATION_ADVISORY_LOCK_KEY: i64 = 0x5365_6353_636f_7265;
+
+/// 一条已编号的结构变更，按版本号顺序执行并登记到 schema_migrations
+pub struct MigrationStep {
+    pub version: i32,
+    pub name: &'static str,
+}
+
+// 只能在末尾追加新版本，已发布的版本不可修改或重排
+pub const MIGRATIONS: &[MigrationStep] = &[
+    MigrationStep {
+        version: 1,
+        name: "create_base_tables",
+    },
+    MigrationStep {
+        version: 2,
+        name: "add_students_reward_points",
+    },
+    MigrationStep {
+        version: 3,
+        name: "add_students_group_name",
+    },
+    MigrationStep {
+        version: 4,
+        name: "create_operation_journal",
+    },
+    MigrationStep {
+        version: 5,
+        name: "add_student_identity",
+    },
+    MigrationStep {
+        version: 6,
+        name: "add_reward_inventory_and_review",
+    },
+    MigrationStep {
+        version: 7,
+        name: "add_reward_redemption_cancellation",
+    },
+    MigrationStep {
+        version: 8,
+        name: "create_reason_categories_and_sets",
+    },
+    MigrationStep {
+        version: 9,
+        name: "create_auto_score_batches",
+    },
+];
+
+pub fn latest_schema_version() -> i32 {
+    MIGRATIONS.last().map(|step| step.version).unwrap_or(0)
+}
+
 impl Migration {
-    pub async fn run(conn: &impl ConnectionTrait, db_type: DatabaseType) -> Result<(), DbErr> {
+    pub async fn run<C>(conn: &C, db_type: DatabaseType) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
         info!("Starting database migration for {:?}", db_type);
 
         let is_sqlite = db_type == DatabaseType::SQLite;
+        let db_backend = Self::get_db_backend(is_sqlite);
 
-        Self::create_students_table(conn, is_sqlite).await?;
-        Self::create_reasons_table(conn, is_sqlite).await?;
-        Self::create_score_events_table(conn, is_sqlite).await?;
-        Self::create_settlements_table(conn, is_sqlite).await?;
-        Self::create_settings_table(conn, is_sqlite).await?;
-        Self::create_board_configs_table(conn, is_sqlite).await?;
-        Self::create_tags_table(conn, is_sqlite).await?;
-        Self::create_student_tags_table(conn, is_sqlite).await?;
-        Self::create_reward_settings_table(conn, is_sqlite).await?;
-        Self::create_reward_redemptions_table(conn, is_sqlite).await?;
-        Self::ensure_students_reward_points_column(conn, is_sqlite).await?;
-        Self::ensure_students_group_name_column(conn, is_sqlite).await?;
+        conn.execute(Statement::from_string(
+            db_backend,
+            get_create_schema_migrations_table_sql(is_sqlite),
+        ))
+        .await?;
 
-        Self::create_indexes(conn, is_sqlite).await?;
+        let applied = Self::applied_versions(conn).await?;
+        let current = applied.iter().copied().max().unwrap_or(0);
+        let latest = latest_schema_version();
+        if current > latest {
+            return Err(DbErr::Custom(format!(
+                "数据库结构版本 {} 高于当前程序支持的版本 {}，请升级客户端后再连接",
+                current, latest
+            )));
+        }
 
-        Self::insert_default_data(conn, is_sqlite).await?;
+        for step in MIGRATIONS {
+            if applied.contains(&step.version) {
+                continue;
+            }
+            if Self::apply_step_locked(conn, step, is_sqlite).await? {
+                info!("Applied migration {} ({})", step.version, step.name);
+            }
+        }
 
         info!("Database migration completed successfully");
         Ok(())
     }
 
+    /// 在事务内加锁后重新确认版本未登记再执行，另一实例已执行过时返回 false
+    async fn apply_step_locked<C>(
+        conn: &C,
+        step: &MigrationStep,
+        sqlite: bool,
+    ) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let db_backend = Self::get_db_backend(sqlite);
+        let txn = conn.begin().await?;
+        if !sqlite {
+            txn.execute(bind_statement(
+                db_backend,
+                "SELECT pg_advisory_xact_lock(?)",
+                vec![MIGRATION_ADVISORY_LOCK_KEY.into()],
+            ))
+            .await?;
+        }
+        let recorded = txn
+            .query_one(bind_statement(
+                db_backend,
+                "SELECT version FROM schema_migrations WHERE version = ?",
+                vec![step.version.into()],
+            ))
+            .await?;
+        if recorded.is_some() {
+            txn.commit().await?;
+            return Ok(false);
+        }
+
+        Self::apply_step(&txn, step.version, sqlite).await?;
+        let applied_at = chrono::Utc::now()
+            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+            .to_string();
+        txn.execute(bind_statement(
+            db_backend,
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)",
+            vec![step.version.into(), step.name.into(), applied_at.into()],
+        ))
+        .await?;
+        txn.commit().await?;
+        Ok(true)
+    }
+
+    async fn apply_step(
+        conn: &impl ConnectionTrait,
+        version: i32,
+        sqlite: bool,
+    ) -> Result<(), DbErr> {
+        match version {
+            1 => {
+                Self::create_students_table(conn, sqlite).await?;
+                Self::create_reasons_table(conn, sqlite).await?;
+                Self::create_score_events_table(conn, sqlite).await?;
+                Self::create_settlements_table(conn, sqlite).await?;
+                Self::create_settings_table(conn, sqlite).await?;
+                Self::create_board_configs_table(conn, sqlite).await?;
+                Self::create_tags_table(conn, sqlite).await?;
+                Self::create_student_tags_table(conn, sqlite).await?;
+                Self::create_reward_settings_table(conn, sqlite).await?;
+                Self::create_reward_redemptions_table(conn, sqlite).await?;
+                Self::create_indexes(
+                    conn,
+                    sqlite,
+                    vec![
+                        get_create_index_score_events_settlement_id_sql(sqlite),
+                        get_create_index_score_events_student_name_sql(sqlite),
+                        get_create_index_reasons_content_sql(sqlite),
+                        get_create_index_reward_settings_name_sql(sqlite),
+                        get_create_index_reward_redemptions_student_name_sql(sqlite),
+                        get_create_index_reward_redemptions_reward_id_sql(sqlite),
+                    ],
+                )
+                .await?;
+                Self::insert_default_data(conn, sqlite).await
+            }
+            2 => {
+                Self::ensure_column(
+                    conn,
+                    sqlite,
+                    TABLE_STUDENTS,
+                    students::REWARD_POINTS,
+                    "INTEGER DEFAULT 0",
+                )
+                .await
+            }
+            3 => {
+                Self::ensure_column(conn, sqlite, TABLE_STUDENTS, students::GROUP_NAME, "TEXT")
+                    .await
+            }
+            4 => Self::create_operation_journal_table(conn, sqlite).await,
+            5 => Self::ensure_student_identity_columns(conn, sqlite).await,
+            6 => Self::ensure_reward_inventory_columns(conn, sqlite).await,
+            7 => {
+                for column in [
+                    reward_redemptions::CANCELLED_AT,
+                    reward_redemptions::CANCELLED_BY,
+                    reward_redemptions::CANCEL_REASON,
+                ] {
+                    Self::ensure_column(conn, sqlite, TABLE_REWARD_REDEMPTIONS, column, "TEXT")
+                        .await?;
+                }
+                Ok(())
+            }
+            8 => Self::create_reason_categories_and_sets(conn, sqlite).await,
+            9 => Self::create_auto_score_batches(conn, sqlite).await,
+            _ => Err(DbErr::Custom(format!(
+                "Unknown migration version {}",
+                version
+            ))),
+        }
+    }
+
+    async fn applied_versions(conn: &impl ConnectionTrait) -> Result<Vec<i32>, DbErr> {
+        let rows = conn
+            .query_all(Statement::from_string(
+                conn.get_database_backend(),
+                "SELECT version FROM schema_migrations ORDER BY version".to_string(),
+            ))
+            .await?;
+        rows.iter()
+            .map(|row| row.try_get::<i32>("", "version"))
+            .collect()
+    }
+
     async fn create_students_table(conn: &impl ConnectionTrait, sqlite: bool) -> Result<(), DbErr> {
         let sql = get_create_students_table_sql(sqlite);
         conn.execute(Statement::from_string(Self::get_db_backend(sqlite), sql))
@@ -131,82 +323,402 @@ impl Migration {
         Ok(())
     }
 
-    async fn ensure_students_reward_points_column(
+    async fn create_operation_journal_table(
+        conn: &impl ConnectionTrait,
+        sqlite: bool,
+    ) -> Result<(), DbErr> {
+        let sql = get_create_operation_journal_table_sql(sqlite);
+        conn.execute(Statement::from_string(Self::get_db_backend(sqlite), sql))
+            .await?;
+        info!("Created operation_journal table");
+        Ok(())
+    }
+
+    // 旧版本可能已通过手写 ALTER 加过该列，先查询再添加，避免在事务内报错
+    async fn ensure_column(
+        conn: &impl ConnectionTrait,
+        sqlite: bool,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<(), DbErr> {
+        if Self::check_column_exists(conn, table, column, sqlite).await? {
+            info!("{}.{} already exists, skip alter", table, column);
+            return Ok(());
+        }
+
+        let alter_sql = format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition);
+        conn.execute(Statement::from_string(
+            Self::get_db_backend(sqlite),
+            alter_sql,
+        ))
+        .await?;
+        info!("Added {}.{} column", table, column);
+        Ok(())
+    }
+
+    // 学生 uuid 与积分/兑换记录的 student_id，旧数据按姓名回填
+    async fn ensure_student_identity_columns(
+        conn: &impl ConnectionTrait,
+        sqlite: bool,
+    ) -> Result<(), DbErr> {
+        Self::ensure_column(conn, sqlite, TABLE_STUDENTS, students::UUID, "TEXT").await?;
+        Self::ensure_column(
+            conn,
+            sqlite,
+            TABLE_SCORE_EVENTS,
+            score_events::STUDENT_ID,
+            "INTEGER",
+        )
+        .await?;
+        Self::ensure_column(
+            conn,
+            sqlite,
+            TABLE_REWARD_REDEMPTIONS,
+            reward_redemptions::STUDENT_ID,
+            "INTEGER",
+        )
+        .await?;
+
+        Self::backfill_student_uuids(conn, sqlite).await?;
+
+        let db_backend = Self::get_db_backend(sqlite);
+        for table in [TABLE_SCORE_EVENTS, TABLE_REWARD_REDEMPTIONS] {
+            let sql = format!(
+                "UPDATE {table} SET student_id = (SELECT MIN(s.id) FROM students s WHERE s.name = {table}.student_name) WHERE student_id IS NULL"
+            );
+            let result = conn
+                .execute(Statement::from_string(db_backend, sql))
+                .await?;
+            if result.rows_affected() > 0 {
+                info!(
+                    "Backfilled {}.student_id for {} rows",
+                    table,
+                    result.rows_affected()
+                );
+            }
+        }
+
+        Self::create_indexes(
+            conn,
+            sqlite,
+            vec![
+                get_create_index_students_uuid_sql(sqlite),
+                get_create_index_score_events_student_id_sql(sqlite),
+                get_create_index_reward_redemptions_student_id_sql(sqlite),
+            ],
+        )
+        .await
+    }
+
+    // 库存、限额与有效期均可为空（不限制），默认无需审核；旧兑换记录已即时扣分，视为已发放
+    async fn ensure_reward_inventory_columns(
+        conn: &impl ConnectionTrait,
+        sqlite: bool,
+    ) -> Result<(), DbErr> {
+        for column in [
+            reward_settings::STOCK,
+            reward_settings::PER_STUDENT_LIMIT,
+            reward_settings::PER_PERIOD_LIMIT,
+        ] {
+            Self::ensure_column(conn, sqlite, TABLE_REWARD_SETTINGS, column, "INTEGER").await?;
+        }
+        for column in [reward_settings::ACTIVE_FROM, reward_settings::ACTIVE_UNTIL] {
+            Self::ensure_column(conn, sqlite, TABLE_REWARD_SETTINGS, column, "TEXT").await?;
+        }
+        Self::ensure_column(
+            conn,
+            sqlite,
+            TABLE_REWARD_SETTINGS,
+            reward_settings::REQUIRES_APPROVAL,
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+
+        Self::ensure_column(
+            conn,
+            sqlite,
+            TABLE_REWARD_REDEMPTIONS,
+            reward_redemptions::STATUS,
+            "TEXT NOT NULL DEFAULT 'fulfilled'",
+        )
+        .await?;
+        for column in [
+            reward_redemptions::REVIEWED_AT,
+            reward_redemptions::REVIEW_NOTE,
+        ] {
+            Self::ensure_column(conn, sqlite, TABLE_REWARD_REDEMPTIONS, column, "TEXT").await?;
+        }
+
+        Self::create_indexes(
+            conn,
+            sqlite,
+            vec![get_create_index_reward_redemptions_status_sql(sqlite)],
+        )
+        .await
+    }
+
+    // 分类按原有文本回填，排序沿用各分类首次出现的先后；原 category 列保留为名称快照
+    async fn create_reason_categories_and_sets(
         conn: &impl ConnectionTrait,
         sqlite: bool,
     ) -> Result<(), DbErr> {
         let db_backend = Self::get_db_backend(sqlite);
-        let alter_sql = "ALTER TABLE students ADD COLUMN reward_points INTEGER DEFAULT 0";
+        for sql in [
+            get_create_reason_categories_table_sql(sqlite),
+            get_create_reason_sets_table_sql(sqlite),
+            get_create_reason_set_items_table_sql(sqlite),
+        ] {
+            conn.execute(Statement::from_string(db_backend, sql))
+                .await?;
+        }
+
+        Self::ensure_column(conn, sqlite, TABLE_REASONS, reasons::CATEGORY_ID, "INTEGER").await?;
+        Self::ensure_column(
+            conn,
+            sqlite,
+            TABLE_REASONS,
+            reasons::SORT_ORDER,
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+        Self::ensure_column(
+            conn,
+            sqlite,
+            TABLE_REASONS,
+            reasons::IS_ARCHIVED,
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+
+        let rows = conn
+            .query_all(Statement::from_string(
+                db_backend,
+                "SELECT category, MIN(id) AS first_id FROM reasons WHERE category IS NOT NULL AND category <> '' GROUP BY category ORDER BY first_id".to_string(),
+            ))
+            .await?;
+        for (index, row) in rows.iter().enumerate() {
+            let name: String = row.try_get("", "category")?;
+            conn.execute(bind_statement(
+                db_backend,
+                "INSERT INTO reason_categories (name, sort_order) VALUES (?, ?) ON CONFLICT (name) DO NOTHING",
+                vec![name.into(), (index as i32).into()],
+            ))
+            .await?;
+        }
         let result = conn
             .execute(Statement::from_string(
-                db_backend.clone(),
-                alter_sql.to_string(),
+                db_backend,
+                "UPDATE reasons SET category_id = (SELECT c.id FROM reason_categories c WHERE c.name = reasons.category) WHERE category_id IS NULL".to_string(),
             ))
-            .await;
+            .await?;
+        info!(
+            "Backfilled {} reason categories for {} reasons",
+            rows.len(),
+            result.rows_affected()
+        );
 
-        match result {
-            Ok(_) => {
-                info!("Added students.reward_points column");
+        Self::create_indexes(
+            conn,
+            sqlite,
+            vec![
+                get_create_index_reasons_category_id_sql(sqlite),
+                get_create_index_reason_set_items_set_id_sql(sqlite),
+            ],
+        )
+        .await
+    }
+
+    // 执行批次原先整体存放在 settings.auto_score_batches，迁入独立表后清空旧值
+    async fn create_auto_score_batches(
+        conn: &impl ConnectionTrait,
+        sqlite: bool,
+    ) -> Result<(), DbErr> {
+        let db_backend = Self::get_db_backend(sqlite);
+        for sql in [
+            get_create_auto_score_batches_table_sql(sqlite),
+            get_create_auto_score_batch_items_table_sql(sqlite),
+        ] {
+            conn.execute(Statement::from_string(db_backend, sql))
+                .await?;
+        }
+        Self::create_indexes(
+            conn,
+            sqlite,
+            vec![
+                get_create_index_auto_score_batches_rule_run_at_sql(sqlite),
+                get_create_index_auto_score_batches_run_at_sql(sqlite),
+                get_create_index_auto_score_batch_items_batch_id_sql(sqlite),
+            ],
+        )
+        .await?;
+
+        let row = conn
+            .query_one(Statement::from_string(
+                db_backend,
+                "SELECT value FROM settings WHERE key = 'auto_score_batches'".to_string(),
+            ))
+            .await?;
+        let raw: Option<String> = match row {
+            Some(row) => row.try_get("", "value")?,
+            None => None,
+        };
+        let legacy = raw
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+            .and_then(|value| value.as_array().cloned())
+            .unwrap_or_default();
+
+        let mut imported = 0usize;
+        for batch in &legacy {
+            let Some(id) = batch.get("id").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let Some(rule_id) = batch.get("ruleId").and_then(|v| v.as_i64()) else {
+                continue;
+            };
+            let text = |key: &str| batch.get(key).and_then(|v| v.as_str()).map(str::to_string);
+            let flag = |key: &str| batch.get(key).and_then(|v| v.as_bool()).unwrap_or(false) as i32;
+            let ids = |key: &str| -> Vec<i32> {
+                batch
+                    .get(key)
+                    .and_then(|v| v.as_array())
+                    .map(|items| {
+                        items
+                            .iter()
+                            .filter_map(|v| v.as_i64())
+                            .map(|v| v as i32)
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+
+            let inserted = conn.execute(bind_statement(
+                db_backend,
+                "INSERT INTO auto_score_batches (id, rule_id, rule_name, run_at, affected_students, score_delta_total, settled, rolled_back, rollback_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT (id) DO NOTHING",
+                vec![
+                    id.into(),
+                    (rule_id as i32).into(),
+                    text("ruleName").unwrap_or_default().into(),
+                    text("runAt").unwrap_or_default().into(),
+                    (batch
+                        .get("affectedStudents")
+                        .and_then(|v| v.as_i64())
+                        .unwrap_or(0) as i32)
+                        .into(),
+                    batch
+                        .get("scoreDeltaTotal")
+                        .and_then(|v| v.as_i64())
+                        .unwrap_or(0)
+                        .into(),
+                    flag("settled").into(),
+                    flag("rolledBack").into(),
+                    text("rollbackAt").into(),
+                ],
+            ))
+            .await?;
+            // 旧客户端可能把同一份设置写回，已导入的批次不再重复写明细
+            if inserted.rows_affected() == 0 {
+                continue;
             }
-            Err(e) => {
-                let msg = e.to_string().to_lowercase();
-                let already_exists = msg.contains("duplicate column")
-                    || msg.contains("already exists")
-                    || msg.contains("duplicate");
-                if already_exists {
-                    info!("students.reward_points already exists, skip alter");
-                } else {
-                    return Err(e);
-                }
+
+            // 旧批次可能只记录了姓名，此时 ref_id 留空
+            let names: Vec<String> = batch
+                .get("affectedStudentNames")
+                .and_then(|v| v.as_array())
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let student_ids = ids("affectedStudentIds");
+            let mut items: Vec<(&str, Option<i32>, Option<String>)> = names
+                .into_iter()
+                .enumerate()
+                .map(|(index, name)| ("student", student_ids.get(index).copied(), Some(name)))
+                .collect();
+            for (kind, key) in [
+                ("score_event", "createdEventIds"),
+                ("student_tag", "addedStudentTagIds"),
+                ("reward_redemption", "rewardRedemptionIds"),
+            ] {
+                items.extend(
+                    ids(key)
+                        .into_iter()
+                        .map(|ref_id| (kind, Some(ref_id), None)),
+                );
+            }
+            for (kind, ref_id, student_name) in items {
+                conn.execute(bind_statement(
+                    db_backend,
+                    "INSERT INTO auto_score_batch_items (batch_id, kind, ref_id, student_name) VALUES (?, ?, ?, ?)",
+                    vec![id.into(), kind.into(), ref_id.into(), student_name.into()],
+                ))
+                .await?;
             }
+            imported += 1;
         }
 
+        conn.execute(Statement::from_string(
+            db_backend,
+            "DELETE FROM settings WHERE key = 'auto_score_batches'".to_string(),
+        ))
+        .await?;
+        if imported > 0 {
+            info!("Imported {} auto score batches from settings", imported);
+        }
         Ok(())
     }
 
-    async fn ensure_students_group_name_column(
+    // 回填使用“姓名 + 同名序号”生成确定性的 v5 uuid，
+    // 保证本地 SQLite 与远端 PostgreSQL 各自迁移后得到相同的学生标识
+    async fn backfill_student_uuids(
         conn: &impl ConnectionTrait,
         sqlite: bool,
     ) -> Result<(), DbErr> {
         let db_backend = Self::get_db_backend(sqlite);
-        let alter_sql = "ALTER TABLE students ADD COLUMN group_name TEXT";
-        let result = conn
-            .execute(Statement::from_string(
-                db_backend.clone(),
-                alter_sql.to_string(),
+        let rows = conn
+            .query_all(Statement::from_string(
+                db_backend,
+                "SELECT id, name, uuid FROM students ORDER BY id".to_string(),
             ))
-            .await;
+            .await?;
 
-        match result {
-            Ok(_) => {
-                info!("Added students.group_name column");
-            }
-            Err(e) => {
-                let msg = e.to_string().to_lowercase();
-                let already_exists = msg.contains("duplicate column")
-                    || msg.contains("already exists")
-                    || msg.contains("duplicate");
-                if already_exists {
-                    info!("students.group_name already exists, skip alter");
-                } else {
-                    return Err(e);
-                }
+        let mut seen: std::collections::HashMap<String, u32> = std::collections::HashMap::new();
+        let mut filled = 0usize;
+        for row in rows {
+            let id: i32 = row.try_get("", "id")?;
+            let name: String = row.try_get("", "name")?;
+            let current: Option<String> = row.try_get("", "uuid")?;
+            let ordinal = seen.entry(name.clone()).or_insert(0);
+            *ordinal += 1;
+
+            if current.as_deref().is_some_and(|v| !v.trim().is_empty()) {
+                continue;
             }
+
+            let uuid = legacy_student_uuid(&name, *ordinal);
+            conn.execute(bind_statement(
+                db_backend,
+                "UPDATE students SET uuid = ? WHERE id = ?",
+                vec![uuid.into(), id.into()],
+            ))
+            .await?;
+            filled += 1;
         }
 
+        if filled > 0 {
+            info!("Backfilled students.uuid for {} rows", filled);
+        }
         Ok(())
     }
 
-    async fn create_indexes(conn: &impl ConnectionTrait, sqlite: bool) -> Result<(), DbErr> {
-        let indexes = vec![
-            get_create_index_score_events_settlement_id_sql(sqlite),
-            get_create_index_score_events_student_name_sql(sqlite),
-            get_create_index_reasons_content_sql(sqlite),
-            get_create_index_reward_settings_name_sql(sqlite),
-            get_create_index_reward_redemptions_student_name_sql(sqlite),
-            get_create_index_reward_redemptions_reward_id_sql(sqlite),
-        ];
-
+    async fn create_indexes(
+        conn: &impl ConnectionTrait,
+        sqlite: bool,
+        indexes: Vec<String>,
+    ) -> Result<(), DbErr> {
         for index_sql in indexes {
             conn.execute(Statement::from_string(
                 Self::get_db_backend(sqlite),
@@ -348,18 +860,52 @@ impl Migration {
         Ok(result.is_some())
     }
 
+    pub async fn check_column_exists(
+        conn: &impl ConnectionTrait,
+        table_name: &str,
+        column_name: &str,
+        sqlite: bool,
+    ) -> Result<bool, DbErr> {
+        let sql = if sqlite {
+            "SELECT COUNT(*) AS count FROM pragma_table_info(?) WHERE name = ?"
+        } else {
+            "SELECT COUNT(*) AS count FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = ? AND column_name = ?"
+        };
+
+        let result = conn
+            .query_one(bind_statement(
+                Self::get_db_backend(sqlite),
+                sql,
+                vec![table_name.into(), column_name.into()],
+            ))
+            .await?;
+
+        match result {
+            Some(row) => Ok(row.try_get::<i64>("", "count")? > 0),
+            None => Ok(false),
+        }
+    }
+
     pub async fn drop_all_tables(conn: &impl ConnectionTrait, sqlite: bool) -> Result<(), DbErr> {
         warn!("Dropping all tables...");
 
         let tables = vec![
+            TABLE_AUTO_SCORE_BATCH_ITEMS,
+            TABLE_AUTO_SCORE_BATCHES,
             TABLE_STUDENT_TAGS,
             TABLE_SCORE_EVENTS,
             TABLE_SETTLEMENTS,
+            TABLE_REASON_SET_ITEMS,
+            TABLE_REASON_SETS,
             TABLE_REASONS,
+            TABLE_REASON_CATEGORIES,
             TABLE_TAGS,
             TABLE_STUDENTS,
             TABLE_REWARD_REDEMPTIONS,
             TABLE_REWARD_SETTINGS,
+            TABLE_OPERATION_JOURNAL,
+            TABLE_SCHEMA_MIGRATIONS,
             TABLE_SETTINGS,
             TABLE_BOARD_CONFIGS,
         ];
@@ -377,7 +923,10 @@ impl Migration {
         Ok(())
     }
 
-    pub async fn reset_database(conn: &impl ConnectionTrait, sqlite: bool) -> Result<(), DbErr> {
+    pub async fn reset_database<C>(conn: &C, sqlite: bool) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
         warn!("Resetting database...");
         Self::drop_all_tables(conn, sqlite).await?;
         Self::run(
@@ -394,10 +943,15 @@ impl Migration {
     }
 }
 
-pub async fn run_migration(
-    conn: &impl ConnectionTrait,
-    db_type: DatabaseType,
-) -> Result<(), DbErr> {
+fn legacy_student_uuid(name: &str, ordinal: u32) -> String {
+    let key = format!("secscore-student:{}#{}", name, ordinal);
+    uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, key.as_bytes()).to_string()
+}
+
+pub async fn run_migration<C>(conn: &C, db_type: DatabaseType) -> Result<(), DbErr>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
     Migration::run(conn, db_type).await
 }
 
@@ -410,11 +964,20 @@ pub async fn check_migration_status(
     let tables = vec![
         TABLE_STUDENTS,
         TABLE_REASONS,
+        TABLE_REASON_CATEGORIES,
+        TABLE_REASON_SETS,
+        TABLE_REASON_SET_ITEMS,
         TABLE_SCORE_EVENTS,
         TABLE_SETTLEMENTS,
         TABLE_SETTINGS,
         TABLE_TAGS,
         TABLE_STUDENT_TAGS,
+        TABLE_REWARD_SETTINGS,
+        TABLE_REWARD_REDEMPTIONS,
+        TABLE_OPERATION_JOURNAL,
+        TABLE_AUTO_SCORE_BATCHES,
+        TABLE_AUTO_SCORE_BATCH_ITEMS,
+        TABLE_SCHEMA_MIGRATIONS,
     ];
 
     let mut existing_tables = Vec::new();
@@ -428,16 +991,255 @@ pub async fn check_migration_status(
         }
     }
 
+    let applied_versions =
+        if Migration::check_table_exists(conn, TABLE_SCHEMA_MIGRATIONS, sqlite).await? {
+            Migration::applied_versions(conn).await?
+        } else {
+            Vec::new()
+        };
+    let current_version = applied_versions.iter().copied().max().unwrap_or(0);
+    let latest_version = latest_schema_version();
+    let pending_migrations = MIGRATIONS
+        .iter()
+        .filter(|step| !applied_versions.contains(&step.version))
+        .map(|step| format!("{}_{}", step.version, step.name))
+        .collect::<Vec<_>>();
+
     Ok(MigrationStatus {
-        is_complete: missing_tables.is_empty(),
+        is_complete: missing_tables.is_empty() && pending_migrations.is_empty(),
         existing_tables,
         missing_tables,
+        current_version,
+        latest_version,
+        pending_migrations,
+        is_newer_than_binary: current_version > latest_version,
     })
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MigrationStatus {
     pub is_complete: bool,
     pub existing_tables: Vec<String>,
     pub missing_tables: Vec<String>,
+    pub current_version: i32,
+    pub latest_version: i32,
+    pub pending_migrations: Vec<String>,
+    pub is_newer_than_binary: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_migration_status, latest_schema_version, Migration, MIGRATIONS};
+    use crate::db::connection::{bind_statement, memory_sqlite_connection, DatabaseType};
+    use crate::db::schema::get_create_schema_migrations_table_sql;
+    use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+
+    const LEGACY_BATCHES: &str = r#"[{"id":"b1","ruleId":3,"ruleName":"r","runAt":"2024-01-01T00:00:00.000Z","affectedStudents":1,"scoreDeltaTotal":2,"settled":false,"rolledBack":false,"affectedStudentNames":["张三"],"affectedStudentIds":[1],"createdEventIds":[10,11]}]"#;
+
+    async fn exec(conn: &DatabaseConnection, sql: &str) {
+        conn.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await
+            .unwrap();
+    }
+
+    async fn dump(conn: &DatabaseConnection, sql: &str) -> Vec<String> {
+        conn.query_all(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.try_get::<String>("", "v").unwrap())
+            .collect()
+    }
+
+    async fn snapshot(conn: &DatabaseConnection) -> Vec<Vec<String>> {
+        let mut result = Vec::new();
+        for sql in [
+            "SELECT id || ':' || COALESCE(uuid, '') AS v FROM students ORDER BY id",
+            "SELECT id || ':' || COALESCE(student_id, '') AS v FROM score_events ORDER BY id",
+            "SELECT id || ':' || name || ':' || sort_order AS v FROM reason_categories ORDER BY id",
+            "SELECT id || ':' || COALESCE(category_id, '') AS v FROM reasons ORDER BY id",
+            "SELECT id || ':' || rule_id AS v FROM auto_score_batches ORDER BY id",
+            "SELECT kind || ':' || COALESCE(ref_id, '') AS v FROM auto_score_batch_items ORDER BY id",
+        ] {
+            result.push(dump(conn, sql).await);
+        }
+        result
+    }
+
+    // 模拟旧客户端把批次写回设置
+    async fn write_legacy_batches(conn: &DatabaseConnection) {
+        conn.execute(bind_statement(
+            DbBackend::Sqlite,
+            "INSERT INTO settings (key, value) VALUES ('auto_score_batches', ?)",
+            vec![LEGACY_BATCHES.into()],
+        ))
+        .await
+        .unwrap();
+    }
+
+    async fn applied_versions(conn: &DatabaseConnection) -> Vec<i32> {
+        Migration::applied_versions(conn).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn stale_runner_skips_steps_recorded_by_another_instance() {
+        let conn = memory_sqlite_connection().await;
+        Migration::run(&conn, DatabaseType::SQLite).await.unwrap();
+        let before = snapshot(&conn).await;
+
+        // 模拟另一实例读取版本后、加锁前已完成全部迁移
+        for step in MIGRATIONS {
+            assert!(!Migration::apply_step_locked(&conn, step, true)
+                .await
+                .unwrap());
+        }
+        assert_eq!(snapshot(&conn).await, before);
+        assert_eq!(applied_versions(&conn).await.len(), MIGRATIONS.len());
+    }
+
+    #[tokio::test]
+    async fn fresh_database_reaches_latest_version() {
+        let conn = memory_sqlite_connection().await;
+        Migration::run(&conn, DatabaseType::SQLite).await.unwrap();
+
+        let status = check_migration_status(&conn, DatabaseType::SQLite)
+            .await
+            .unwrap();
+        assert!(status.is_complete, "{:?}", status);
+        assert_eq!(status.current_version, latest_schema_version());
+        assert!(status.pending_migrations.is_empty());
+        assert!(status.missing_tables.is_empty());
+        assert!(!status.is_newer_than_binary);
+        assert_eq!(
+            applied_versions(&conn).await,
+            MIGRATIONS
+                .iter()
+                .map(|step| step.version)
+                .collect::<Vec<_>>()
+        );
+
+        // 再次运行不应重复执行任何步骤
+        Migration::run(&conn, DatabaseType::SQLite).await.unwrap();
+        assert_eq!(applied_versions(&conn).await.len(), MIGRATIONS.len());
+    }
+
+    #[tokio::test]
+    async fn partially_migrated_database_runs_only_pending_steps() {
+        let conn = memory_sqlite_connection().await;
+        exec(&conn, &get_create_schema_migrations_table_sql(true)).await;
+        for step in &MIGRATIONS[..4] {
+            Migration::apply_step(&conn, step.version, true)
+                .await
+                .unwrap();
+            conn.execute(bind_statement(
+                DbBackend::Sqlite,
+                "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, 'legacy')",
+                vec![step.version.into(), step.name.into()],
+            ))
+            .await
+            .unwrap();
+        }
+        exec(&conn, "INSERT INTO students (name) VALUES ('张三')").await;
+        exec(
+            &conn,
+            "INSERT INTO score_events (uuid, student_name, reason_content, delta, val_prev, val_curr) \
+             VALUES ('e1', '张三', '迟到', -1, 0, -1)",
+        )
+        .await;
+
+        let status = check_migration_status(&conn, DatabaseType::SQLite)
+            .await
+            .unwrap();
+        assert_eq!(status.current_version, 4);
+        assert!(!status.is_complete);
+        assert_eq!(
+            status.pending_migrations,
+            MIGRATIONS[4..]
+                .iter()
+                .map(|step| format!("{}_{}", step.version, step.name))
+                .collect::<Vec<_>>()
+        );
+
+        Migration::run(&conn, DatabaseType::SQLite).await.unwrap();
+        assert_eq!(
+            dump(
+                &conn,
+                "SELECT CAST(version AS TEXT) AS v FROM schema_migrations \
+                 WHERE applied_at = 'legacy' ORDER BY version"
+            )
+            .await,
+            vec!["1", "2", "3", "4"]
+        );
+        assert_eq!(applied_versions(&conn).await.len(), MIGRATIONS.len());
+        // 第 5 步回填学生 uuid 与记录的 student_id
+        let snapshot = snapshot(&conn).await;
+        assert!(!snapshot[0][0].ends_with(':'));
+        assert_eq!(snapshot[1], vec!["1:1"]);
+    }
+
+    #[tokio::test]
+    async fn newer_ledger_version_is_refused() {
+        let conn = memory_sqlite_connection().await;
+        Migration::run(&conn, DatabaseType::SQLite).await.unwrap();
+        let future = latest_schema_version() + 1;
+        conn.execute(bind_statement(
+            DbBackend::Sqlite,
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, 'future', 'x')",
+            vec![future.into()],
+        ))
+        .await
+        .unwrap();
+
+        assert!(Migration::run(&conn, DatabaseType::SQLite).await.is_err());
+        let status = check_migration_status(&conn, DatabaseType::SQLite)
+            .await
+            .unwrap();
+        assert!(status.is_newer_than_binary);
+        assert_eq!(status.current_version, future);
+        assert_eq!(status.latest_version, latest_schema_version());
+    }
+
+    #[tokio::test]
+    async fn backfill_steps_are_idempotent() {
+        let conn = memory_sqlite_connection().await;
+        Migration::run(&conn, DatabaseType::SQLite).await.unwrap();
+        exec(&conn, "INSERT INTO students (name) VALUES ('张三')").await;
+        exec(
+            &conn,
+            "INSERT INTO score_events (uuid, student_name, reason_content, delta, val_prev, val_curr) \
+             VALUES ('e1', '张三', '迟到', -1, 0, -1)",
+        )
+        .await;
+        exec(
+            &conn,
+            "INSERT INTO reasons (content, category, delta) VALUES ('自定义理由', '自定义', 1)",
+        )
+        .await;
+
+        write_legacy_batches(&conn).await;
+        for version in [5, 8, 9] {
+            Migration::apply_step(&conn, version, true).await.unwrap();
+        }
+        let backfilled = snapshot(&conn).await;
+        assert_eq!(backfilled[1], vec!["1:1"]);
+        assert!(backfilled[2].iter().any(|row| row.contains(":自定义:")));
+        assert!(!backfilled[3].iter().any(|row| row.ends_with(':')));
+        assert_eq!(backfilled[4], vec!["b1:3"]);
+        assert_eq!(
+            backfilled[5],
+            vec!["student:1", "score_event:10", "score_event:11"]
+        );
+
+        write_legacy_batches(&conn).await;
+        for version in [5, 8, 9] {
+            Migration::apply_step(&conn, version, true).await.unwrap();
+        }
+        assert_eq!(snapshot(&conn).await, backfilled);
+        assert!(dump(
+            &conn,
+            "SELECT value AS v FROM settings WHERE key = 'auto_score_batches'"
+        )
+        .await
+        .is_empty());
+    }
 }
@@ -1,12 +1,13 @@
 pub mod connection;
 pub mod entities;
 pub mod migration;
+pub mod repositories;
 pub mod schema;
 
 pub use connection::{
-    create_postgres_connection, create_sqlite_connection, sqlite_connection_url,
-    test_postgres_connection, test_sqlite_connection, ConnectionManager, DatabaseConfig,
-    DatabaseType,
+    bind_statement, create_postgres_connection, create_sqlite_connection, sqlite_connection_url,
+    sqlite_vacuum_into, test_postgres_connection, test_sqlite_connection, ConnectionManager,
+    DatabaseConfig, DatabaseType,
 };
 
 pub use migration::{check_migration_status, run_migration, Migration, MigrationStatus};
@@ -1,7 +1,7 @@
 use crate::models::{ScoreEvent, CreateScoreEvent};
 use sqlx::{SqlitePool, Postgres, Sqlite};
 use sqlx::postgres::PgPool;
-use chrono::Utc;
+use chrono::{Datelike, Timelike, Utc};
 use uuid::Uuid;
 
 pub struct EventRepository {
@@ -24,10 +24,6 @@ impl EventRepository {
         }
     }
 
-    fn is_postgres(&self) -> bool {
-        self.postgres_pool.is_some()
-    }
-
     pub async fn find_all(&self, limit: i32) -> Result<Vec<ScoreEvent>, sqlx::Error> {
         if let Some(pool) = &self.sqlite_pool {
             sqlx::query_as::<Sqlite, ScoreEvent>(
@@ -79,10 +75,11 @@ impl EventRepository {
             let val_curr = val_prev + delta;
             
             let result = sqlx::query(
-                r#"INSERT INTO score_events (uuid, student_name, reason_content, delta, val_prev, val_curr, event_time, settlement_id) 
-                   VALUES (?, ?, ?, ?, ?, ?, ?, NULL)"#
+                r#"INSERT INTO score_events (uuid, student_id, student_name, reason_content, delta, val_prev, val_curr, event_time, settlement_id) 
+                   VALUES (?, ?, ?, ?, ?, ?, ?, ?, NULL)"#
             )
             .bind(&uuid)
+            .bind(student.0)
             .bind(student_name)
             .bind(reason_content)
             .bind(delta)
@@ -119,11 +116,12 @@ impl EventRepository {
             let val_curr = val_prev + delta;
             
             let event_id = sqlx::query_scalar::<Postgres, i32>(
-                r#"INSERT INTO score_events (uuid, student_name, reason_content, delta, val_prev, val_curr, event_time, settlement_id) 
-                   VALUES ($1, $2, $3, $4, $5, $6, $7, NULL) 
+                r#"INSERT INTO score_events (uuid, student_id, student_name, reason_content, delta, val_prev, val_curr, event_time, settlement_id) 
+                   VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NULL) 
                    RETURNING id"#
             )
             .bind(&uuid)
+            .bind(student.0)
             .bind(student_name)
             .bind(reason_content)
             .bind(delta)
@@ -169,9 +167,9 @@ impl EventRepository {
             }
             
             let student: Option<(i32, i32)> = sqlx::query_as(
-                "SELECT id, score FROM students WHERE name = ?"
+                "SELECT s.id, s.score FROM students s JOIN score_events e ON e.student_id = s.id WHERE e.uuid = ?"
             )
-            .bind(&event.student_name)
+            .bind(uuid)
             .fetch_optional(&mut *tx)
             .await?;
             
@@ -210,9 +208,9 @@ impl EventRepository {
             }
             
             let student: Option<(i32, i32)> = sqlx::query_as(
-                "SELECT id, score FROM students WHERE name = $1"
+                "SELECT s.id, s.score FROM students s JOIN score_events e ON e.student_id = s.id WHERE e.uuid = $1"
             )
-            .bind(&event.student_name)
+            .bind(uuid)
             .fetch_optional(&mut *tx)
             .await?;
             
@@ -318,7 +316,7 @@ impl EventRepository {
             "week" => {
                 let mut s = now;
                 let day = s.weekday().num_days_from_monday() as i64;
-                s = s - chrono::Duration::days(day);
+                s -= chrono::Duration::days(day);
                 s = s.with_hour(0).unwrap_or(s);
                 s = s.with_minute(0).unwrap_or(s);
                 s = s.with_second(0).unwrap_or(s);
@@ -349,7 +347,7 @@ impl EventRepository {
             let rows = sqlx::query_as::<Sqlite, LeaderboardRow>(
                 r#"SELECT s.id, s.name, s.score, COALESCE(SUM(e.delta), 0) as range_change 
                    FROM students s 
-                   LEFT JOIN score_events e ON e.student_name = s.name 
+                   LEFT JOIN score_events e ON e.student_id = s.id 
                    AND e.settlement_id IS NULL 
                    AND julianday(e.event_time) >= julianday(?) 
                    GROUP BY s.id, s.name, s.score 
@@ -364,7 +362,7 @@ impl EventRepository {
             let rows = sqlx::query_as::<Postgres, LeaderboardRow>(
                 r#"SELECT s.id, s.name, s.score, COALESCE(SUM(e.delta), 0) as range_change 
                    FROM students s 
-                   LEFT JOIN score_events e ON e.student_name = s.name 
+                   LEFT JOIN score_events e ON e.student_id = s.id 
                    AND e.settlement_id IS NULL 
                    AND e.event_time >= $1 
                    GROUP BY s.id, s.name, s.score 
@@ -472,7 +470,7 @@ impl EventRepository {
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
 pub struct LeaderboardRow {
     pub id: i32,
     pub name: String,
@@ -7,5 +7,7 @@ mod tag_repo;
 pub use student_repo::StudentRepository;
 pub use event_repo::EventRepository;
 pub use reason_repo::ReasonRepository;
-pub use settlement_repo::SettlementRepository;
+pub use settlement_repo::{
+    SettlementError, SettlementInfo, SettlementLeaderboard, SettlementRepository,
+};
 pub use tag_repo::TagRepository;
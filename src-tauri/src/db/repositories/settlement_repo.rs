@@ -2,6 +2,7 @@ use crate::models::{Settlement, SettlementSummary, SettlementResult, SettlementL
 use sqlx::{SqlitePool, Postgres, Sqlite};
 use sqlx::postgres::PgPool;
 use chrono::Utc;
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection};
 
 pub struct SettlementRepository {
     sqlite_pool: Option<SqlitePool>,
@@ -23,8 +24,16 @@ impl SettlementRepository {
         }
     }
 
-    fn is_postgres(&self) -> bool {
-        self.postgres_pool.is_some()
+    pub fn from_connection(conn: &DatabaseConnection) -> Option<Self> {
+        match conn.get_database_backend() {
+            DatabaseBackend::Sqlite => {
+                Some(Self::new_sqlite(conn.get_sqlite_connection_pool().clone()))
+            }
+            DatabaseBackend::Postgres => {
+                Some(Self::new_postgres(conn.get_postgres_connection_pool().clone()))
+            }
+            _ => None,
+        }
     }
 
     pub async fn find_all(&self) -> Result<Vec<SettlementSummary>, sqlx::Error> {
@@ -187,10 +196,11 @@ impl SettlementRepository {
             let settlement = settlement.ok_or(SettlementError::NotFound)?;
             
             let rows = sqlx::query_as::<Sqlite, SettlementLeaderboardRow>(
-                r#"SELECT student_name as name, COALESCE(SUM(delta), 0) as score 
-                   FROM score_events 
-                   WHERE settlement_id = ? 
-                   GROUP BY student_name 
+                r#"SELECT COALESCE(s.name, e.student_name) as name, COALESCE(SUM(e.delta), 0) as score 
+                   FROM score_events e 
+                   LEFT JOIN students s ON s.id = e.student_id 
+                   WHERE e.settlement_id = ? 
+                   GROUP BY e.student_id, COALESCE(s.name, e.student_name) 
                    ORDER BY score DESC, name ASC"#
             )
             .bind(settlement_id)
@@ -216,10 +226,11 @@ impl SettlementRepository {
             let settlement = settlement.ok_or(SettlementError::NotFound)?;
             
             let rows = sqlx::query_as::<Postgres, SettlementLeaderboardRow>(
-                r#"SELECT student_name as name, COALESCE(SUM(delta), 0) as score 
-                   FROM score_events 
-                   WHERE settlement_id = $1 
-                   GROUP BY student_name 
+                r#"SELECT COALESCE(s.name, e.student_name) as name, COALESCE(SUM(e.delta), 0) as score 
+                   FROM score_events e 
+                   LEFT JOIN students s ON s.id = e.student_id 
+                   WHERE e.settlement_id = $1 
+                   GROUP BY e.student_id, COALESCE(s.name, e.student_name) 
                    ORDER BY score DESC, name ASC"#
             )
             .bind(settlement_id)
@@ -2,6 +2,7 @@ use crate::models::{Student, StudentUpdate, StudentWithTags};
 use sqlx::{SqlitePool, Postgres, Sqlite, QueryBuilder};
 use sqlx::postgres::PgPool;
 use chrono::Utc;
+use uuid::Uuid;
 
 pub struct StudentRepository {
     sqlite_pool: Option<SqlitePool>,
@@ -23,10 +24,6 @@ impl StudentRepository {
         }
     }
 
-    fn is_postgres(&self) -> bool {
-        self.postgres_pool.is_some()
-    }
-
     pub async fn find_all(&self) -> Result<Vec<StudentWithTags>, sqlx::Error> {
         if let Some(pool) = &self.sqlite_pool {
             let students = sqlx::query_as::<Sqlite, Student>(
@@ -59,9 +56,10 @@ impl StudentRepository {
         
         if let Some(pool) = &self.sqlite_pool {
             let result = sqlx::query(
-                r#"INSERT INTO students (name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) 
-                   VALUES (?, NULL, 0, 0, '[]', NULL, ?, ?)"#
+                r#"INSERT INTO students (uuid, name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) 
+                   VALUES (?, ?, NULL, 0, 0, '[]', NULL, ?, ?)"#
             )
+            .bind(Uuid::new_v4().to_string())
             .bind(name)
             .bind(&now)
             .bind(&now)
@@ -71,10 +69,11 @@ impl StudentRepository {
             Ok(result.last_insert_rowid() as i32)
         } else if let Some(pool) = &self.postgres_pool {
             let result = sqlx::query_scalar::<Postgres, i32>(
-                r#"INSERT INTO students (name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) 
-                   VALUES ($1, NULL, 0, 0, '[]', NULL, $2, $3) 
+                r#"INSERT INTO students (uuid, name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) 
+                   VALUES ($1, $2, NULL, 0, 0, '[]', NULL, $3, $4) 
                    RETURNING id"#
             )
+            .bind(Uuid::new_v4().to_string())
             .bind(name)
             .bind(&now)
             .bind(&now)
@@ -183,8 +182,8 @@ impl StudentRepository {
             if let Some(student) = student {
                 let mut tx = pool.begin().await?;
                 
-                sqlx::query("DELETE FROM score_events WHERE student_name = ?")
-                    .bind(&student.name)
+                sqlx::query("DELETE FROM score_events WHERE student_id = ?")
+                    .bind(student.id)
                     .execute(&mut *tx)
                     .await?;
                 
@@ -206,8 +205,8 @@ impl StudentRepository {
             if let Some(student) = student {
                 let mut tx = pool.begin().await?;
                 
-                sqlx::query("DELETE FROM score_events WHERE student_name = $1")
-                    .bind(&student.name)
+                sqlx::query("DELETE FROM score_events WHERE student_id = $1")
+                    .bind(student.id)
                     .execute(&mut *tx)
                     .await?;
                 
@@ -265,8 +264,9 @@ impl StudentRepository {
             
             for name in &to_insert {
                 sqlx::query(
-                    "INSERT INTO students (name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) VALUES (?, NULL, 0, 0, '[]', NULL, ?, ?)"
+                    "INSERT INTO students (uuid, name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) VALUES (?, ?, NULL, 0, 0, '[]', NULL, ?, ?)"
                 )
+                .bind(Uuid::new_v4().to_string())
                 .bind(name)
                 .bind(&now)
                 .bind(&now)
@@ -300,8 +300,9 @@ impl StudentRepository {
             
             for name in &to_insert {
                 sqlx::query(
-                    "INSERT INTO students (name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) VALUES ($1, NULL, 0, 0, '[]', NULL, $2, $3)"
+                    "INSERT INTO students (uuid, name, group_name, score, reward_points, tags, extra_json, created_at, updated_at) VALUES ($1, $2, NULL, 0, 0, '[]', NULL, $3, $4)"
                 )
+                .bind(Uuid::new_v4().to_string())
                 .bind(name)
                 .bind(&now)
                 .bind(&now)
@@ -1,4 +1,4 @@
-use crate::models::{Tag, StudentTag};
+use crate::models::Tag;
 use sqlx::{SqlitePool, Postgres, Sqlite};
 use sqlx::postgres::PgPool;
 use chrono::Utc;
@@ -77,7 +77,7 @@ impl TagRepository {
             Ok(Tag {
                 id: result.last_insert_rowid() as i32,
                 name: name.to_string(),
-                created_at: now,
+                created_at: now.clone(),
                 updated_at: now,
             })
         } else if let Some(pool) = &self.postgres_pool {
@@ -93,7 +93,7 @@ impl TagRepository {
             Ok(Tag {
                 id,
                 name: name.to_string(),
-                created_at: now,
+                created_at: now.clone(),
                 updated_at: now,
             })
         } else {
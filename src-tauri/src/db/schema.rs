@@ -1,5 +1,8 @@
 pub const TABLE_STUDENTS: &str = "students";
 pub const TABLE_REASONS: &str = "reasons";
+pub const TABLE_REASON_CATEGORIES: &str = "reason_categories";
+pub const TABLE_REASON_SETS: &str = "reason_sets";
+pub const TABLE_REASON_SET_ITEMS: &str = "reason_set_items";
 pub const TABLE_SCORE_EVENTS: &str = "score_events";
 pub const TABLE_SETTLEMENTS: &str = "settlements";
 pub const TABLE_SETTINGS: &str = "settings";
@@ -8,10 +11,15 @@ pub const TABLE_TAGS: &str = "tags";
 pub const TABLE_STUDENT_TAGS: &str = "student_tags";
 pub const TABLE_REWARD_SETTINGS: &str = "reward_settings";
 pub const TABLE_REWARD_REDEMPTIONS: &str = "reward_redemptions";
+pub const TABLE_OPERATION_JOURNAL: &str = "operation_journal";
+pub const TABLE_AUTO_SCORE_BATCHES: &str = "auto_score_batches";
+pub const TABLE_AUTO_SCORE_BATCH_ITEMS: &str = "auto_score_batch_items";
+pub const TABLE_SCHEMA_MIGRATIONS: &str = "schema_migrations";
 
 pub mod students {
     pub const TABLE: &str = "students";
     pub const ID: &str = "id";
+    pub const UUID: &str = "uuid";
     pub const NAME: &str = "name";
     pub const GROUP_NAME: &str = "group_name";
     pub const TAGS: &str = "tags";
@@ -27,15 +35,47 @@ pub mod reasons {
     pub const ID: &str = "id";
     pub const CONTENT: &str = "content";
     pub const CATEGORY: &str = "category";
+    pub const CATEGORY_ID: &str = "category_id";
     pub const DELTA: &str = "delta";
     pub const IS_SYSTEM: &str = "is_system";
+    pub const SORT_ORDER: &str = "sort_order";
+    pub const IS_ARCHIVED: &str = "is_archived";
+    pub const UPDATED_AT: &str = "updated_at";
+}
+
+pub mod reason_categories {
+    pub const TABLE: &str = "reason_categories";
+    pub const ID: &str = "id";
+    pub const NAME: &str = "name";
+    pub const SORT_ORDER: &str = "sort_order";
+    pub const COLOR: &str = "color";
+    pub const ICON: &str = "icon";
+    pub const IS_ARCHIVED: &str = "is_archived";
+    pub const CREATED_AT: &str = "created_at";
+    pub const UPDATED_AT: &str = "updated_at";
+}
+
+pub mod reason_sets {
+    pub const TABLE: &str = "reason_sets";
+    pub const ID: &str = "id";
+    pub const NAME: &str = "name";
+    pub const CREATED_AT: &str = "created_at";
     pub const UPDATED_AT: &str = "updated_at";
 }
 
+pub mod reason_set_items {
+    pub const TABLE: &str = "reason_set_items";
+    pub const ID: &str = "id";
+    pub const SET_ID: &str = "set_id";
+    pub const REASON_ID: &str = "reason_id";
+    pub const SORT_ORDER: &str = "sort_order";
+}
+
 pub mod score_events {
     pub const TABLE: &str = "score_events";
     pub const ID: &str = "id";
     pub const UUID: &str = "uuid";
+    pub const STUDENT_ID: &str = "student_id";
     pub const STUDENT_NAME: &str = "student_name";
     pub const REASON_CONTENT: &str = "reason_content";
     pub const DELTA: &str = "delta";
@@ -87,6 +127,12 @@ pub mod reward_settings {
     pub const ID: &str = "id";
     pub const NAME: &str = "name";
     pub const COST_POINTS: &str = "cost_points";
+    pub const STOCK: &str = "stock";
+    pub const PER_STUDENT_LIMIT: &str = "per_student_limit";
+    pub const PER_PERIOD_LIMIT: &str = "per_period_limit";
+    pub const ACTIVE_FROM: &str = "active_from";
+    pub const ACTIVE_UNTIL: &str = "active_until";
+    pub const REQUIRES_APPROVAL: &str = "requires_approval";
     pub const CREATED_AT: &str = "created_at";
     pub const UPDATED_AT: &str = "updated_at";
 }
@@ -95,11 +141,57 @@ pub mod reward_redemptions {
     pub const TABLE: &str = "reward_redemptions";
     pub const ID: &str = "id";
     pub const UUID: &str = "uuid";
+    pub const STUDENT_ID: &str = "student_id";
     pub const STUDENT_NAME: &str = "student_name";
     pub const REWARD_ID: &str = "reward_id";
     pub const REWARD_NAME: &str = "reward_name";
     pub const COST_POINTS: &str = "cost_points";
     pub const REDEEMED_AT: &str = "redeemed_at";
+    pub const STATUS: &str = "status";
+    pub const REVIEWED_AT: &str = "reviewed_at";
+    pub const REVIEW_NOTE: &str = "review_note";
+    pub const CANCELLED_AT: &str = "cancelled_at";
+    pub const CANCELLED_BY: &str = "cancelled_by";
+    pub const CANCEL_REASON: &str = "cancel_reason";
+}
+
+pub mod operation_journal {
+    pub const TABLE: &str = "operation_journal";
+    pub const ID: &str = "id";
+    pub const OP_TYPE: &str = "op_type";
+    pub const SUMMARY: &str = "summary";
+    pub const PAYLOAD: &str = "payload";
+    pub const CREATED_AT: &str = "created_at";
+    pub const UNDONE_AT: &str = "undone_at";
+}
+
+pub mod auto_score_batches {
+    pub const TABLE: &str = "auto_score_batches";
+    pub const ID: &str = "id";
+    pub const RULE_ID: &str = "rule_id";
+    pub const RULE_NAME: &str = "rule_name";
+    pub const RUN_AT: &str = "run_at";
+    pub const AFFECTED_STUDENTS: &str = "affected_students";
+    pub const SCORE_DELTA_TOTAL: &str = "score_delta_total";
+    pub const SETTLED: &str = "settled";
+    pub const ROLLED_BACK: &str = "rolled_back";
+    pub const ROLLBACK_AT: &str = "rollback_at";
+}
+
+pub mod auto_score_batch_items {
+    pub const TABLE: &str = "auto_score_batch_items";
+    pub const ID: &str = "id";
+    pub const BATCH_ID: &str = "batch_id";
+    pub const KIND: &str = "kind";
+    pub const REF_ID: &str = "ref_id";
+    pub const STUDENT_NAME: &str = "student_name";
+}
+
+pub mod schema_migrations {
+    pub const TABLE: &str = "schema_migrations";
+    pub const VERSION: &str = "version";
+    pub const NAME: &str = "name";
+    pub const APPLIED_AT: &str = "applied_at";
 }
 
 pub fn get_create_students_table_sql(sqlite: bool) -> String {
@@ -107,6 +199,7 @@ pub fn get_create_students_table_sql(sqlite: bool) -> String {
         r#"
         CREATE TABLE IF NOT EXISTS students (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
+            uuid TEXT,
             name TEXT NOT NULL,
             group_name TEXT,
             tags TEXT DEFAULT '[]',
@@ -122,6 +215,7 @@ pub fn get_create_students_table_sql(sqlite: bool) -> String {
         r#"
         CREATE TABLE IF NOT EXISTS students (
             id SERIAL PRIMARY KEY,
+            uuid TEXT,
             name TEXT NOT NULL,
             group_name TEXT,
             tags TEXT DEFAULT '[]',
@@ -143,6 +237,12 @@ pub fn get_create_reward_settings_table_sql(sqlite: bool) -> String {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             name TEXT NOT NULL UNIQUE,
             cost_points INTEGER NOT NULL,
+            stock INTEGER,
+            per_student_limit INTEGER,
+            per_period_limit INTEGER,
+            active_from TEXT,
+            active_until TEXT,
+            requires_approval INTEGER NOT NULL DEFAULT 0,
             created_at TEXT DEFAULT (datetime('now', 'localtime')),
             updated_at TEXT DEFAULT (datetime('now', 'localtime'))
         )
@@ -154,6 +254,12 @@ pub fn get_create_reward_settings_table_sql(sqlite: bool) -> String {
             id SERIAL PRIMARY KEY,
             name TEXT NOT NULL UNIQUE,
             cost_points INTEGER NOT NULL,
+            stock INTEGER,
+            per_student_limit INTEGER,
+            per_period_limit INTEGER,
+            active_from TEXT,
+            active_until TEXT,
+            requires_approval INTEGER NOT NULL DEFAULT 0,
             created_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"')),
             updated_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"'))
         )
@@ -168,11 +274,18 @@ pub fn get_create_reward_redemptions_table_sql(sqlite: bool) -> String {
         CREATE TABLE IF NOT EXISTS reward_redemptions (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             uuid TEXT NOT NULL UNIQUE,
+            student_id INTEGER,
             student_name TEXT NOT NULL,
             reward_id INTEGER NOT NULL,
             reward_name TEXT NOT NULL,
             cost_points INTEGER NOT NULL,
-            redeemed_at TEXT DEFAULT (datetime('now', 'localtime'))
+            redeemed_at TEXT DEFAULT (datetime('now', 'localtime')),
+            status TEXT NOT NULL DEFAULT 'fulfilled',
+            reviewed_at TEXT,
+            review_note TEXT,
+            cancelled_at TEXT,
+            cancelled_by TEXT,
+            cancel_reason TEXT
         )
         "#
         .to_string()
@@ -181,11 +294,18 @@ pub fn get_create_reward_redemptions_table_sql(sqlite: bool) -> String {
         CREATE TABLE IF NOT EXISTS reward_redemptions (
             id SERIAL PRIMARY KEY,
             uuid TEXT NOT NULL UNIQUE,
+            student_id INTEGER,
             student_name TEXT NOT NULL,
             reward_id INTEGER NOT NULL,
             reward_name TEXT NOT NULL,
             cost_points INTEGER NOT NULL,
-            redeemed_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"'))
+            redeemed_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"')),
+            status TEXT NOT NULL DEFAULT 'fulfilled',
+            reviewed_at TEXT,
+            review_note TEXT,
+            cancelled_at TEXT,
+            cancelled_by TEXT,
+            cancel_reason TEXT
         )
         "#
         .to_string()
@@ -199,8 +319,11 @@ pub fn get_create_reasons_table_sql(sqlite: bool) -> String {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             content TEXT NOT NULL UNIQUE,
             category TEXT DEFAULT '其他',
+            category_id INTEGER,
             delta INTEGER NOT NULL,
             is_system INTEGER DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            is_archived INTEGER NOT NULL DEFAULT 0,
             updated_at TEXT DEFAULT (datetime('now', 'localtime'))
         )
         "#
@@ -211,8 +334,11 @@ pub fn get_create_reasons_table_sql(sqlite: bool) -> String {
             id SERIAL PRIMARY KEY,
             content TEXT NOT NULL UNIQUE,
             category TEXT DEFAULT '其他',
+            category_id INTEGER,
             delta INTEGER NOT NULL,
             is_system INTEGER DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            is_archived INTEGER NOT NULL DEFAULT 0,
             updated_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"'))
         )
         "#
@@ -226,6 +352,7 @@ pub fn get_create_score_events_table_sql(sqlite: bool) -> String {
         CREATE TABLE IF NOT EXISTS score_events (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             uuid TEXT NOT NULL UNIQUE,
+            student_id INTEGER,
             student_name TEXT NOT NULL,
             reason_content TEXT NOT NULL,
             delta INTEGER NOT NULL,
@@ -241,6 +368,7 @@ pub fn get_create_score_events_table_sql(sqlite: bool) -> String {
         CREATE TABLE IF NOT EXISTS score_events (
             id SERIAL PRIMARY KEY,
             uuid TEXT NOT NULL UNIQUE,
+            student_id INTEGER,
             student_name TEXT NOT NULL,
             reason_content TEXT NOT NULL,
             delta INTEGER NOT NULL,
@@ -278,6 +406,92 @@ pub fn get_create_settlements_table_sql(sqlite: bool) -> String {
     }
 }
 
+pub fn get_create_reason_categories_table_sql(sqlite: bool) -> String {
+    if sqlite {
+        r#"
+        CREATE TABLE IF NOT EXISTS reason_categories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            color TEXT,
+            icon TEXT,
+            is_archived INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime'))
+        )
+        "#
+        .to_string()
+    } else {
+        r#"
+        CREATE TABLE IF NOT EXISTS reason_categories (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            color TEXT,
+            icon TEXT,
+            is_archived INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"')),
+            updated_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"'))
+        )
+        "#
+        .to_string()
+    }
+}
+
+pub fn get_create_reason_sets_table_sql(sqlite: bool) -> String {
+    if sqlite {
+        r#"
+        CREATE TABLE IF NOT EXISTS reason_sets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT DEFAULT (datetime('now', 'localtime'))
+        )
+        "#
+        .to_string()
+    } else {
+        r#"
+        CREATE TABLE IF NOT EXISTS reason_sets (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            created_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"')),
+            updated_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"'))
+        )
+        "#
+        .to_string()
+    }
+}
+
+pub fn get_create_reason_set_items_table_sql(sqlite: bool) -> String {
+    if sqlite {
+        r#"
+        CREATE TABLE IF NOT EXISTS reason_set_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            set_id INTEGER NOT NULL,
+            reason_id INTEGER NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (set_id) REFERENCES reason_sets(id) ON DELETE CASCADE,
+            FOREIGN KEY (reason_id) REFERENCES reasons(id) ON DELETE CASCADE,
+            UNIQUE (set_id, reason_id)
+        )
+        "#
+        .to_string()
+    } else {
+        r#"
+        CREATE TABLE IF NOT EXISTS reason_set_items (
+            id SERIAL PRIMARY KEY,
+            set_id INTEGER NOT NULL,
+            reason_id INTEGER NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (set_id) REFERENCES reason_sets(id) ON DELETE CASCADE,
+            FOREIGN KEY (reason_id) REFERENCES reasons(id) ON DELETE CASCADE,
+            UNIQUE (set_id, reason_id)
+        )
+        "#
+        .to_string()
+    }
+}
+
 pub fn get_create_settings_table_sql(sqlite: bool) -> String {
     if sqlite {
         r#"
@@ -374,6 +588,107 @@ pub fn get_create_student_tags_table_sql(sqlite: bool) -> String {
     }
 }
 
+pub fn get_create_operation_journal_table_sql(sqlite: bool) -> String {
+    if sqlite {
+        r#"
+        CREATE TABLE IF NOT EXISTS operation_journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            op_type TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            created_at TEXT DEFAULT (datetime('now', 'localtime')),
+            undone_at TEXT
+        )
+        "#
+        .to_string()
+    } else {
+        r#"
+        CREATE TABLE IF NOT EXISTS operation_journal (
+            id SERIAL PRIMARY KEY,
+            op_type TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            created_at TEXT DEFAULT (to_char(CURRENT_TIMESTAMP, 'YYYY-MM-DD"T"HH24:MI:SS"Z"')),
+            undone_at TEXT
+        )
+        "#
+        .to_string()
+    }
+}
+
+pub fn get_create_auto_score_batches_table_sql(sqlite: bool) -> String {
+    if sqlite {
+        r#"
+        CREATE TABLE IF NOT EXISTS auto_score_batches (
+            id TEXT PRIMARY KEY,
+            rule_id INTEGER NOT NULL,
+            rule_name TEXT NOT NULL,
+            run_at TEXT NOT NULL,
+            affected_students INTEGER NOT NULL DEFAULT 0,
+            score_delta_total INTEGER NOT NULL DEFAULT 0,
+            settled INTEGER NOT NULL DEFAULT 0,
+            rolled_back INTEGER NOT NULL DEFAULT 0,
+            rollback_at TEXT
+        )
+        "#
+        .to_string()
+    } else {
+        r#"
+        CREATE TABLE IF NOT EXISTS auto_score_batches (
+            id TEXT PRIMARY KEY,
+            rule_id INTEGER NOT NULL,
+            rule_name TEXT NOT NULL,
+            run_at TEXT NOT NULL,
+            affected_students INTEGER NOT NULL DEFAULT 0,
+            score_delta_total BIGINT NOT NULL DEFAULT 0,
+            settled INTEGER NOT NULL DEFAULT 0,
+            rolled_back INTEGER NOT NULL DEFAULT 0,
+            rollback_at TEXT
+        )
+        "#
+        .to_string()
+    }
+}
+
+pub fn get_create_auto_score_batch_items_table_sql(sqlite: bool) -> String {
+    if sqlite {
+        r#"
+        CREATE TABLE IF NOT EXISTS auto_score_batch_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            batch_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            ref_id INTEGER,
+            student_name TEXT,
+            FOREIGN KEY (batch_id) REFERENCES auto_score_batches(id) ON DELETE CASCADE
+        )
+        "#
+        .to_string()
+    } else {
+        r#"
+        CREATE TABLE IF NOT EXISTS auto_score_batch_items (
+            id SERIAL PRIMARY KEY,
+            batch_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            ref_id INTEGER,
+            student_name TEXT,
+            FOREIGN KEY (batch_id) REFERENCES auto_score_batches(id) ON DELETE CASCADE
+        )
+        "#
+        .to_string()
+    }
+}
+
+pub fn get_create_schema_migrations_table_sql(_sqlite: bool) -> String {
+    r#"
+    CREATE TABLE IF NOT EXISTS schema_migrations (
+        version INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        applied_at TEXT NOT NULL
+    )
+    "#
+    .to_string()
+}
+
 pub fn get_create_index_reward_settings_name_sql(_sqlite: bool) -> String {
     "CREATE INDEX IF NOT EXISTS idx_reward_settings_name ON reward_settings(name)".to_string()
 }
@@ -388,6 +703,47 @@ pub fn get_create_index_reward_redemptions_reward_id_sql(_sqlite: bool) -> Strin
         .to_string()
 }
 
+pub fn get_create_index_reward_redemptions_status_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_reward_redemptions_status ON reward_redemptions(status)"
+        .to_string()
+}
+
+pub fn get_create_index_reasons_category_id_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_reasons_category_id ON reasons(category_id)".to_string()
+}
+
+pub fn get_create_index_reason_set_items_set_id_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_reason_set_items_set_id ON reason_set_items(set_id)".to_string()
+}
+
+pub fn get_create_index_auto_score_batches_rule_run_at_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_auto_score_batches_rule_run_at ON auto_score_batches(rule_id, run_at)"
+        .to_string()
+}
+
+pub fn get_create_index_auto_score_batches_run_at_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_auto_score_batches_run_at ON auto_score_batches(run_at)"
+        .to_string()
+}
+
+pub fn get_create_index_auto_score_batch_items_batch_id_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_auto_score_batch_items_batch_id ON auto_score_batch_items(batch_id)"
+        .to_string()
+}
+
+pub fn get_create_index_students_uuid_sql(_sqlite: bool) -> String {
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_students_uuid ON students(uuid)".to_string()
+}
+
+pub fn get_create_index_score_events_student_id_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_score_events_student_id ON score_events(student_id)".to_string()
+}
+
+pub fn get_create_index_reward_redemptions_student_id_sql(_sqlite: bool) -> String {
+    "CREATE INDEX IF NOT EXISTS idx_reward_redemptions_student_id ON reward_redemptions(student_id)"
+        .to_string()
+}
+
 pub fn get_create_index_score_events_settlement_id_sql(sqlite: bool) -> String {
     if sqlite {
         "CREATE INDEX IF NOT EXISTS idx_score_events_settlement_id ON score_events(settlement_id)"
@@ -29,6 +29,7 @@ pub fn run() {
         .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
             let state = AppState::new(app.handle().clone());
+            crate::services::bus::spawn_tauri_forwarder(&state.event_bus, app.handle().clone());
             app.manage(Arc::new(RwLock::new(state)));
             setup_app(app)?;
             Ok(())
@@ -38,6 +39,7 @@ pub fn run() {
             student_create,
             student_update,
             student_delete,
+            student_profile_query,
             student_import_from_xlsx,
             student_fetch_banyou_cookie_with_browser,
             student_fetch_banyou_classrooms,
@@ -51,17 +53,33 @@ pub fn run() {
             reason_create,
             reason_update,
             reason_delete,
+            reason_category_query,
+            reason_category_create,
+            reason_category_update,
+            reason_category_reorder,
+            reason_set_query,
+            reason_set_save,
+            reason_set_delete,
             reward_setting_query,
             reward_setting_create,
             reward_setting_update,
             reward_setting_delete,
             reward_redeem,
             reward_redemption_query,
+            reward_redemption_list_pending,
+            reward_redemption_review,
+            reward_redemption_cancel,
             event_query,
+            event_query_page,
+            journal_query,
+            journal_undo,
+            journal_redo,
             event_create,
+            event_create_batch,
             event_delete,
             event_query_by_student,
             leaderboard_query,
+            reason_stats_query,
             db_settlement_query,
             db_settlement_create,
             db_settlement_leaderboard,
@@ -72,6 +90,7 @@ pub fn run() {
             auth_get_status,
             auth_login,
             auth_logout,
+            auth_lock,
             auth_set_passwords,
             auth_generate_recovery,
             auth_reset_by_recovery,
@@ -107,6 +126,7 @@ pub fn run() {
             auto_score_query_batches,
             auto_score_rollback_batch,
             auto_score_apply_backfill,
+            auto_score_preview_rule,
             board_get_configs,
             board_save_configs,
             board_query_sql,
@@ -126,6 +146,10 @@ pub fn run() {
             plugin_get_runtime_modules,
             data_export_json,
             data_import_json,
+            backup_list,
+            backup_create,
+            backup_restore,
+            backup_delete,
             window_minimize,
             window_maximize,
             window_close,
@@ -138,6 +162,7 @@ pub fn run() {
             db_switch_connection,
             db_use_local_sqlite,
             db_get_status,
+            db_migration_status,
             workspace_get_state,
             workspace_create_local_class,
             workspace_switch_class,
@@ -169,6 +194,9 @@ pub fn run() {
             http_server_refresh_token,
             http_server_stop,
             http_server_status,
+            http_server_device_list,
+            http_server_device_update,
+            http_server_device_revoke,
             mcp_server_start,
             mcp_server_stop,
             mcp_server_status,
@@ -326,12 +354,24 @@ fn setup_database(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
                 )
                 .await
                 .map_err(|err| format!("Failed to enable SECTL cloud sync: {}", err))?;
+            let upgrades = {
+                let security = state_guard.security.read();
+                crate::services::AuthService::legacy_secret_upgrades(&settings, &security)
+            };
+            for (key, hashed) in upgrades {
+                let _ = settings.set_raw(key, &hashed).await;
+            }
         }
 
         state_guard
             .initialize()
             .await
             .map_err(|e| format!("Failed to initialize app state: {}", e))?;
+        {
+            let settings = state_guard.settings.read();
+            let mut permissions = state_guard.permissions.write();
+            crate::services::AuthService::sync_permission_policy(&settings, &mut permissions);
+        }
 
         Ok::<_, Box<dyn std::error::Error>>(())
     });
@@ -349,6 +389,8 @@ fn setup_database(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
                 "storage_mode": "isolated_sqlite",
             }),
         );
+        crate::services::backup::spawn_scheduler(handle.clone());
+        crate::services::permission::spawn_session_sweeper(handle.clone());
     }
 
     Ok(())
@@ -8,7 +8,7 @@ pub struct Settlement {
     pub created_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct SettlementSummary {
     pub id: i32,
     pub start_time: String,
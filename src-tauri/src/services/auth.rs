@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
 
+use std::time::Duration;
+
 use super::permission::PermissionLevel;
 use super::security::SecurityService;
-use super::settings::SettingsService;
+use super::settings::{SettingsKey, SettingsService, SettingsValue};
+use crate::state::SafeAppState;
 
 pub const SETTINGS_SECURITY_ADMIN: &str = "security_admin_password";
 pub const SETTINGS_SECURITY_POINTS: &str = "security_points_password";
@@ -14,6 +17,8 @@ pub struct AuthStatus {
     pub has_admin_password: bool,
     pub has_points_password: bool,
     pub has_recovery_string: bool,
+    pub failed_attempts: u32,
+    pub lockout_remaining_seconds: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +41,53 @@ pub struct SetPasswordsResult {
     pub message: Option<String>,
 }
 
+/// 口令的存储形式；旧版密文只需对称解密，持锁时即可校验完毕
+enum StoredSecret {
+    Missing,
+    Hash(String),
+    Legacy(bool),
+}
+
+impl StoredSecret {
+    fn matches(&self, password: &str) -> bool {
+        match self {
+            StoredSecret::Missing => false,
+            StoredSecret::Hash(stored) => SecurityService::verify_password(password, stored),
+            StoredSecret::Legacy(matched) => *matched,
+        }
+    }
+}
+
+/// 从设置中复制出的口令，慢哈希在释放锁之后执行
+pub struct PendingLogin {
+    password: String,
+    admin: StoredSecret,
+    points: StoredSecret,
+    lockout: Option<Duration>,
+}
+
+pub struct LoginVerdict {
+    granted: Option<PermissionLevel>,
+    lockout: Option<Duration>,
+}
+
+impl PendingLogin {
+    /// 耗时的口令校验，应放到阻塞线程执行
+    pub fn verify(self) -> LoginVerdict {
+        let granted = if self.admin.matches(&self.password) {
+            Some(PermissionLevel::Admin)
+        } else if self.points.matches(&self.password) {
+            Some(PermissionLevel::Points)
+        } else {
+            None
+        };
+        LoginVerdict {
+            granted,
+            lockout: self.lockout,
+        }
+    }
+}
+
 pub struct AuthService;
 
 impl Default for AuthService {
@@ -49,179 +101,333 @@ impl AuthService {
         Self
     }
 
+    /// 将密码状态和空闲超时同步到权限服务
+    pub fn sync_permission_policy(
+        settings: &SettingsService,
+        permissions: &mut super::permission::PermissionService,
+    ) {
+        permissions.update_password_status(
+            settings.has_secret(SETTINGS_SECURITY_ADMIN),
+            settings.has_secret(SETTINGS_SECURITY_POINTS),
+        );
+        let minutes = match settings.get_value(SettingsKey::PermissionIdleTimeoutMinutes) {
+            SettingsValue::Number(n) if n > 0.0 => Some(n),
+            _ => None,
+        };
+        permissions.set_idle_timeout(minutes.map(|m| Duration::from_secs_f64(m * 60.0)));
+    }
+
+    /// lockout_key 为失败计数所用的键，桌面端即会话本身
     pub fn get_status(
         settings: &SettingsService,
-        sender_id: Option<u32>,
+        session: &str,
+        lockout_key: &str,
         permissions: &mut super::permission::PermissionService,
     ) -> AuthStatus {
-        let permission = if let Some(id) = sender_id {
-            permissions.get_permission(id)
-        } else {
-            permissions.get_default_permission()
-        };
+        Self::sync_permission_policy(settings, permissions);
+        let permission = permissions.get_permission(session);
 
         AuthStatus {
             permission: permission.as_str().to_string(),
             has_admin_password: settings.has_secret(SETTINGS_SECURITY_ADMIN),
             has_points_password: settings.has_secret(SETTINGS_SECURITY_POINTS),
             has_recovery_string: settings.has_secret(SETTINGS_SECURITY_RECOVERY),
+            failed_attempts: permissions.failed_login_attempts(lockout_key),
+            lockout_remaining_seconds: permissions
+                .login_lockout_remaining(lockout_key)
+                .map(|d| d.as_secs().max(1)),
         }
     }
 
-    pub async fn login(
-        settings: &mut SettingsService,
+    /// 读取口令：优先哈希，旧版密文需要保存过的 IV 才能校验
+    fn stored_secret(
+        settings: &SettingsService,
+        security: &SecurityService,
+        key: &str,
+        password: &str,
+    ) -> StoredSecret {
+        let stored = settings.get_raw(key);
+        if stored.trim().is_empty() {
+            return StoredSecret::Missing;
+        }
+        if SecurityService::is_password_hash(&stored) {
+            return StoredSecret::Hash(stored);
+        }
+        let legacy_iv = settings.get_raw(SecurityService::get_iv_key());
+        StoredSecret::Legacy(
+            !legacy_iv.is_empty() && security.verify_legacy_secret(password, &stored, &legacy_iv),
+        )
+    }
+
+    /// 找出仍是旧版可逆加密的口令，返回改写为哈希后的值，由调用方写回
+    pub fn legacy_secret_upgrades(
+        settings: &SettingsService,
+        security: &SecurityService,
+    ) -> Vec<(&'static str, String)> {
+        let legacy_iv = settings.get_raw(SecurityService::get_iv_key());
+        [
+            SETTINGS_SECURITY_ADMIN,
+            SETTINGS_SECURITY_POINTS,
+            SETTINGS_SECURITY_RECOVERY,
+        ]
+        .into_iter()
+        .filter_map(|key| {
+            let stored = settings.get_raw(key);
+            if stored.trim().is_empty() || SecurityService::is_password_hash(&stored) {
+                return None;
+            }
+            let plain = security.decrypt_secret(&stored, &legacy_iv).ok()?;
+            (!plain.is_empty()).then(|| (key, SecurityService::hash_password(&plain)))
+        })
+        .collect()
+    }
+
+    /// 登录第一步：检查锁定与格式并复制口令。尝试次数在此预先计入，
+    /// 并发请求无法绕过锁定；登录成功时再清零
+    pub fn begin_login(
+        settings: &SettingsService,
         security: &SecurityService,
         permissions: &mut super::permission::PermissionService,
-        sender_id: u32,
+        session: &str,
+        lockout_key: &str,
         password: &str,
-        iv_hex: &str,
-    ) -> LoginResult {
+    ) -> Result<PendingLogin, LoginResult> {
+        Self::sync_permission_policy(settings, permissions);
+        if let Some(remaining) = permissions.login_lockout_remaining(lockout_key) {
+            return Err(LoginResult {
+                success: false,
+                permission: None,
+                message: Some(format!(
+                    "Too many failed attempts, retry in {} seconds",
+                    remaining.as_secs().max(1)
+                )),
+            });
+        }
         if !SecurityService::is_six_digit(password) {
-            permissions.set_permission(sender_id, permissions.get_default_permission());
-            return LoginResult {
+            permissions.lock(session);
+            return Err(LoginResult {
                 success: false,
                 permission: None,
                 message: Some("Invalid password format".to_string()),
-            };
+            });
         }
 
-        let admin_cipher = settings.get_raw(SETTINGS_SECURITY_ADMIN);
-        let points_cipher = settings.get_raw(SETTINGS_SECURITY_POINTS);
-
-        let admin_plain = security
-            .decrypt_secret(&admin_cipher, iv_hex)
-            .unwrap_or_default();
-        let points_plain = security
-            .decrypt_secret(&points_cipher, iv_hex)
-            .unwrap_or_default();
-
-        if !admin_cipher.is_empty() && admin_plain == password {
-            permissions.set_permission(sender_id, PermissionLevel::Admin);
-            return LoginResult {
-                success: true,
-                permission: Some("admin".to_string()),
-                message: None,
-            };
-        }
+        Ok(PendingLogin {
+            password: password.to_string(),
+            admin: Self::stored_secret(settings, security, SETTINGS_SECURITY_ADMIN, password),
+            points: Self::stored_secret(settings, security, SETTINGS_SECURITY_POINTS, password),
+            lockout: permissions.record_login_failure(lockout_key),
+        })
+    }
 
-        if !points_cipher.is_empty() && points_plain == password {
-            permissions.set_permission(sender_id, PermissionLevel::Points);
+    pub fn finish_login(
+        permissions: &mut super::permission::PermissionService,
+        session: &str,
+        lockout_key: &str,
+        verdict: LoginVerdict,
+    ) -> LoginResult {
+        if let Some(level) = verdict.granted {
+            permissions.record_login_success(lockout_key);
+            permissions.set_permission(session, level);
             return LoginResult {
                 success: true,
-                permission: Some("points".to_string()),
+                permission: Some(level.as_str().to_string()),
                 message: None,
             };
         }
 
-        permissions.set_permission(sender_id, permissions.get_default_permission());
+        permissions.lock(session);
+        let message = match verdict.lockout {
+            Some(lockout) => format!(
+                "Password incorrect, locked for {} seconds",
+                lockout.as_secs()
+            ),
+            None => "Password incorrect".to_string(),
+        };
         LoginResult {
             success: false,
             permission: None,
-            message: Some("Password incorrect".to_string()),
+            message: Some(message),
         }
     }
 
+    /// 持锁取出口令后释放，哈希在阻塞线程中完成，最后再持锁写回结果
+    pub async fn login(
+        app_state: &SafeAppState,
+        session: &str,
+        lockout_key: &str,
+        password: &str,
+    ) -> LoginResult {
+        let pending = {
+            let state_guard = app_state.read();
+            let settings = state_guard.settings.read();
+            let security = state_guard.security.read();
+            let mut permissions = state_guard.permissions.write();
+            match Self::begin_login(
+                &settings,
+                &security,
+                &mut permissions,
+                session,
+                lockout_key,
+                password,
+            ) {
+                Ok(pending) => pending,
+                Err(result) => return result,
+            }
+        };
+
+        let verdict = match tokio::task::spawn_blocking(move || pending.verify()).await {
+            Ok(verdict) => verdict,
+            Err(e) => {
+                return LoginResult {
+                    success: false,
+                    permission: None,
+                    message: Some(format!("Login failed: {}", e)),
+                }
+            }
+        };
+
+        let state_guard = app_state.read();
+        let mut permissions = state_guard.permissions.write();
+        Self::finish_login(&mut permissions, session, lockout_key, verdict)
+    }
+
     pub fn logout(
         permissions: &mut super::permission::PermissionService,
-        sender_id: u32,
+        session: &str,
     ) -> PermissionLevel {
-        let default = permissions.get_default_permission();
-        permissions.set_permission(sender_id, default);
-        default
+        permissions.lock(session)
     }
 
-    pub async fn set_passwords(
-        settings: &mut SettingsService,
-        security: &SecurityService,
+    /// 已设置管理员口令时，只有管理员会话可以修改口令
+    fn require_secret_admin(
+        settings: &SettingsService,
         permissions: &mut super::permission::PermissionService,
-        sender_id: u32,
-        payload: SetPasswordsPayload,
-        iv_hex: &str,
-    ) -> SetPasswordsResult {
-        let has_admin = settings.has_secret(SETTINGS_SECURITY_ADMIN);
-        if has_admin && !permissions.require_permission(sender_id, PermissionLevel::Admin) {
-            return SetPasswordsResult {
-                success: false,
-                recovery_string: None,
-                message: Some("Permission denied".to_string()),
-            };
+        session: &str,
+    ) -> Result<(), SetPasswordsResult> {
+        Self::sync_permission_policy(settings, permissions);
+        if settings.has_secret(SETTINGS_SECURITY_ADMIN)
+            && !permissions.require_permission(session, PermissionLevel::Admin)
+        {
+            return Err(Self::set_failure("Permission denied"));
         }
+        Ok(())
+    }
 
-        if let Some(admin_pwd) = payload.admin_password {
-            let trimmed = admin_pwd.trim();
-            if trimmed.is_empty() {
-                let _ = settings.set_raw(SETTINGS_SECURITY_ADMIN, "").await;
-            } else {
-                if !SecurityService::is_six_digit(trimmed) {
-                    return SetPasswordsResult {
-                        success: false,
-                        recovery_string: None,
-                        message: Some("Admin password must be 6 digits".to_string()),
-                    };
-                }
-                match security.encrypt_secret(trimmed, iv_hex) {
-                    Ok(encrypted) => {
-                        let _ = settings.set_raw(SETTINGS_SECURITY_ADMIN, &encrypted).await;
-                    }
-                    Err(e) => {
-                        return SetPasswordsResult {
-                            success: false,
-                            recovery_string: None,
-                            message: Some(format!("Encryption failed: {}", e)),
-                        };
-                    }
-                }
+    fn check_secret_admin(
+        app_state: &SafeAppState,
+        session: &str,
+    ) -> Result<(), SetPasswordsResult> {
+        let state_guard = app_state.read();
+        let settings = state_guard.settings.read();
+        let mut permissions = state_guard.permissions.write();
+        Self::require_secret_admin(&settings, &mut permissions, session)
+    }
+
+    fn set_failure(message: &str) -> SetPasswordsResult {
+        SetPasswordsResult {
+            success: false,
+            recovery_string: None,
+            message: Some(message.to_string()),
+        }
+    }
+
+    /// 空字符串表示清除该口令，其余必须是 6 位数字
+    fn new_secret(
+        input: Option<String>,
+        message: &str,
+    ) -> Result<Option<String>, SetPasswordsResult> {
+        match input.map(|pwd| pwd.trim().to_string()) {
+            Some(pwd) if !pwd.is_empty() && !SecurityService::is_six_digit(&pwd) => {
+                Err(Self::set_failure(message))
             }
+            other => Ok(other),
         }
+    }
 
-        if let Some(points_pwd) = payload.points_password {
-            let trimmed = points_pwd.trim();
-            if trimmed.is_empty() {
-                let _ = settings.set_raw(SETTINGS_SECURITY_POINTS, "").await;
-            } else {
-                if !SecurityService::is_six_digit(trimmed) {
-                    return SetPasswordsResult {
-                        success: false,
-                        recovery_string: None,
-                        message: Some("Points password must be 6 digits".to_string()),
-                    };
-                }
-                match security.encrypt_secret(trimmed, iv_hex) {
-                    Ok(encrypted) => {
-                        let _ = settings.set_raw(SETTINGS_SECURITY_POINTS, &encrypted).await;
+    /// 在阻塞线程中计算口令哈希，空字符串原样保留
+    async fn hash_secrets(secrets: Vec<String>) -> Result<Vec<String>, SetPasswordsResult> {
+        tokio::task::spawn_blocking(move || {
+            secrets
+                .into_iter()
+                .map(|plain| {
+                    if plain.is_empty() {
+                        plain
+                    } else {
+                        SecurityService::hash_password(&plain)
                     }
-                    Err(e) => {
-                        return SetPasswordsResult {
-                            success: false,
-                            recovery_string: None,
-                            message: Some(format!("Encryption failed: {}", e)),
-                        };
-                    }
-                }
-            }
+                })
+                .collect()
+        })
+        .await
+        .map_err(|e| Self::set_failure(&format!("Failed to hash password: {}", e)))
+    }
+
+    /// 先持锁校验权限，释放锁后计算哈希，再持锁复核权限并写入
+    pub async fn set_passwords(
+        app_state: &SafeAppState,
+        session: &str,
+        payload: SetPasswordsPayload,
+    ) -> SetPasswordsResult {
+        if let Err(result) = Self::check_secret_admin(app_state, session) {
+            return result;
+        }
+        let admin =
+            match Self::new_secret(payload.admin_password, "Admin password must be 6 digits") {
+                Ok(admin) => admin,
+                Err(result) => return result,
+            };
+        let points =
+            match Self::new_secret(payload.points_password, "Points password must be 6 digits") {
+                Ok(points) => points,
+                Err(result) => return result,
+            };
+
+        let recovery = SecurityService::generate_recovery_string();
+        let mut hashed = match Self::hash_secrets(vec![
+            admin.clone().unwrap_or_default(),
+            points.clone().unwrap_or_default(),
+            recovery.clone(),
+        ])
+        .await
+        {
+            Ok(hashed) => hashed.into_iter(),
+            Err(result) => return result,
+        };
+        let (admin_hash, points_hash, recovery_hash) = (
+            hashed.next().unwrap_or_default(),
+            hashed.next().unwrap_or_default(),
+            hashed.next().unwrap_or_default(),
+        );
+
+        let state_guard = app_state.read();
+        let mut settings = state_guard.settings.write();
+        let mut permissions = state_guard.permissions.write();
+        if let Err(result) = Self::require_secret_admin(&settings, &mut permissions, session) {
+            return result;
+        }
+        if admin.is_some() {
+            let _ = settings.set_raw(SETTINGS_SECURITY_ADMIN, &admin_hash).await;
+        }
+        if points.is_some() {
+            let _ = settings
+                .set_raw(SETTINGS_SECURITY_POINTS, &points_hash)
+                .await;
         }
 
+        // 设置密码的会话本身已通过校验，保持管理员级别直到锁定或超时
+        Self::sync_permission_policy(&settings, &mut permissions);
+        permissions.set_permission(session, PermissionLevel::Admin);
+
         if !settings.has_secret(SETTINGS_SECURITY_RECOVERY) {
-            let recovery = SecurityService::generate_recovery_string();
-            match security.encrypt_secret(&recovery, iv_hex) {
-                Ok(encrypted) => {
-                    let _ = settings
-                        .set_raw(SETTINGS_SECURITY_RECOVERY, &encrypted)
-                        .await;
-                    return SetPasswordsResult {
-                        success: true,
-                        recovery_string: Some(recovery),
-                        message: None,
-                    };
-                }
-                Err(e) => {
-                    return SetPasswordsResult {
-                        success: false,
-                        recovery_string: None,
-                        message: Some(format!("Failed to encrypt recovery: {}", e)),
-                    };
-                }
-            }
+            let _ = settings
+                .set_raw(SETTINGS_SECURITY_RECOVERY, &recovery_hash)
+                .await;
+            return SetPasswordsResult {
+                success: true,
+                recovery_string: Some(recovery),
+                message: None,
+            };
         }
 
         SetPasswordsResult {
@@ -231,82 +437,84 @@ impl AuthService {
         }
     }
 
-    pub async fn generate_recovery(
-        settings: &mut SettingsService,
-        security: &SecurityService,
-        permissions: &mut super::permission::PermissionService,
-        sender_id: u32,
-        iv_hex: &str,
-    ) -> SetPasswordsResult {
-        if settings.has_secret(SETTINGS_SECURITY_ADMIN)
-            && !permissions.require_permission(sender_id, PermissionLevel::Admin)
-        {
-            return SetPasswordsResult {
-                success: false,
-                recovery_string: None,
-                message: Some("Permission denied".to_string()),
-            };
+    pub async fn generate_recovery(app_state: &SafeAppState, session: &str) -> SetPasswordsResult {
+        if let Err(result) = Self::check_secret_admin(app_state, session) {
+            return result;
         }
 
         let recovery = SecurityService::generate_recovery_string();
-        match security.encrypt_secret(&recovery, iv_hex) {
-            Ok(encrypted) => {
-                let _ = settings
-                    .set_raw(SETTINGS_SECURITY_RECOVERY, &encrypted)
-                    .await;
-                SetPasswordsResult {
-                    success: true,
-                    recovery_string: Some(recovery),
-                    message: None,
-                }
-            }
-            Err(e) => SetPasswordsResult {
-                success: false,
-                recovery_string: None,
-                message: Some(format!("Failed to encrypt recovery: {}", e)),
-            },
+        let recovery_hash = match Self::hash_secrets(vec![recovery.clone()]).await {
+            Ok(hashed) => hashed.into_iter().next().unwrap_or_default(),
+            Err(result) => return result,
+        };
+
+        let state_guard = app_state.read();
+        let mut settings = state_guard.settings.write();
+        let mut permissions = state_guard.permissions.write();
+        if let Err(result) = Self::require_secret_admin(&settings, &mut permissions, session) {
+            return result;
+        }
+        let _ = settings
+            .set_raw(SETTINGS_SECURITY_RECOVERY, &recovery_hash)
+            .await;
+        SetPasswordsResult {
+            success: true,
+            recovery_string: Some(recovery),
+            message: None,
         }
     }
 
+    /// 恢复字符串的校验和新字符串的哈希都在阻塞线程中完成；
+    /// 写入前确认恢复字符串在此期间未被改动
     pub async fn reset_by_recovery(
-        settings: &mut SettingsService,
-        security: &SecurityService,
-        permissions: &mut super::permission::PermissionService,
-        sender_id: u32,
+        app_state: &SafeAppState,
+        session: &str,
         recovery_string: &str,
-        iv_hex: &str,
     ) -> SetPasswordsResult {
-        let cipher = settings.get_raw(SETTINGS_SECURITY_RECOVERY);
-        let plain = security.decrypt_secret(&cipher, iv_hex).unwrap_or_default();
+        let recovery_string = recovery_string.trim().to_string();
+        let (stored_raw, stored) = {
+            let state_guard = app_state.read();
+            let settings = state_guard.settings.read();
+            let security = state_guard.security.read();
+            (
+                settings.get_raw(SETTINGS_SECURITY_RECOVERY),
+                Self::stored_secret(
+                    &settings,
+                    &security,
+                    SETTINGS_SECURITY_RECOVERY,
+                    &recovery_string,
+                ),
+            )
+        };
 
-        if plain.is_empty() || plain != recovery_string.trim() {
-            return SetPasswordsResult {
-                success: false,
-                recovery_string: None,
-                message: Some("Recovery string incorrect".to_string()),
-            };
-        }
+        let new_recovery = SecurityService::generate_recovery_string();
+        let plain = new_recovery.clone();
+        let verified = tokio::task::spawn_blocking(move || {
+            stored
+                .matches(&recovery_string)
+                .then(|| SecurityService::hash_password(&plain))
+        })
+        .await;
+        let recovery_hash = match verified {
+            Ok(Some(hash)) => hash,
+            Ok(None) => return Self::set_failure("Recovery string incorrect"),
+            Err(e) => return Self::set_failure(&format!("Recovery failed: {}", e)),
+        };
 
+        let state_guard = app_state.read();
+        let mut settings = state_guard.settings.write();
+        if settings.get_raw(SETTINGS_SECURITY_RECOVERY) != stored_raw {
+            return Self::set_failure("Recovery string incorrect");
+        }
         let _ = settings.set_raw(SETTINGS_SECURITY_ADMIN, "").await;
         let _ = settings.set_raw(SETTINGS_SECURITY_POINTS, "").await;
+        let _ = settings
+            .set_raw(SETTINGS_SECURITY_RECOVERY, &recovery_hash)
+            .await;
 
-        let new_recovery = SecurityService::generate_recovery_string();
-        match security.encrypt_secret(&new_recovery, iv_hex) {
-            Ok(encrypted) => {
-                let _ = settings
-                    .set_raw(SETTINGS_SECURITY_RECOVERY, &encrypted)
-                    .await;
-            }
-            Err(e) => {
-                return SetPasswordsResult {
-                    success: false,
-                    recovery_string: None,
-                    message: Some(format!("Failed to encrypt new recovery: {}", e)),
-                };
-            }
-        }
-
-        permissions.set_permission(sender_id, permissions.get_default_permission());
+        let mut permissions = state_guard.permissions.write();
+        Self::sync_permission_policy(&settings, &mut permissions);
+        permissions.lock(session);
         SetPasswordsResult {
             success: true,
             recovery_string: Some(new_recovery),
@@ -317,16 +525,18 @@ impl AuthService {
     pub async fn clear_all(
         settings: &mut SettingsService,
         permissions: &mut super::permission::PermissionService,
-        sender_id: u32,
+        session: &str,
     ) -> Result<(), String> {
-        if !permissions.require_permission(sender_id, PermissionLevel::Admin) {
+        Self::sync_permission_policy(settings, permissions);
+        if !permissions.require_permission(session, PermissionLevel::Admin) {
             return Err("Permission denied".to_string());
         }
 
         let _ = settings.set_raw(SETTINGS_SECURITY_ADMIN, "").await;
         let _ = settings.set_raw(SETTINGS_SECURITY_POINTS, "").await;
         let _ = settings.set_raw(SETTINGS_SECURITY_RECOVERY, "").await;
-        permissions.set_permission(sender_id, permissions.get_default_permission());
+        Self::sync_permission_policy(settings, permissions);
+        permissions.lock(session);
         Ok(())
     }
 }
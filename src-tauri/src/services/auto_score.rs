@@ -1,26 +1,78 @@
-use chrono::{DateTime, Months, Utc};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Datelike, Local, Months, TimeZone, Timelike, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
-    Set, Statement, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, Statement, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
 use std::collections::{HashMap, HashSet};
 use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::broadcast;
 use tokio::time::{interval, Duration};
+use tracing::warn;
 use uuid::Uuid;
 
+use crate::db::bind_statement;
 use crate::db::entities::{
-    reward_redemptions, reward_settings, score_events, student_tags, students, tags,
+    auto_score_batch_items, auto_score_batches, reasons, reward_redemptions, reward_settings,
+    score_events, student_tags, students, tags,
 };
+use crate::db::repositories::{SettlementError, SettlementRepository};
+use crate::services::bus::{BusEvent, BusPayload, EventBus, EventSource};
+use crate::services::reward::{self, RedemptionStatus};
 use crate::services::settings::{SettingsKey, SettingsValue};
 use crate::state::SafeAppState;
 
 const DEFAULT_INTERVAL_MINUTES: i64 = 30;
 const AUTO_SCORE_TICK_SECONDS: u64 = 15;
 const AUTO_SCORE_SQL_LIMIT: u64 = 5000;
-const AUTO_SCORE_REASON_PREFIX: &str = "自动化";
+pub const AUTO_SCORE_REASON_PREFIX: &str = "自动化";
 const AUTO_SCORE_BACKFILL_MAX_RUNS_PER_RULE: i64 = 500;
+/// 批次相关 IN 查询与批量插入的分块大小，避免超出 SQLite 参数上限
+const AUTO_SCORE_BATCH_CHUNK: usize = 200;
+const BATCH_ITEM_STUDENT: &str = "student";
+const BATCH_ITEM_SCORE_EVENT: &str = "score_event";
+const BATCH_ITEM_STUDENT_TAG: &str = "student_tag";
+const BATCH_ITEM_REWARD_REDEMPTION: &str = "reward_redemption";
+/// 写入后即时求值的触发条件，定时调度不会命中它们
+const REACTIVE_TRIGGER_EVENTS: &[&str] = &[
+    "score_event_created",
+    "score_crossed_above",
+    "score_crossed_below",
+    "reward_redeemed",
+    "tag_attached",
+];
+
+/// 条件类触发字段，树字段名与触发事件同名，取值见 ConditionTriggerValue
+const CONDITION_TRIGGER_FIELDS: &[&str] = &[
+    "reward_points",
+    "student_group",
+    "event_count",
+    "event_delta_sum",
+    "days_since_last_event",
+    "leaderboard_rank",
+    "leaderboard_percentile",
+    "extra_json",
+    "weekday",
+    "time_of_day",
+];
+const NUMBER_OPERATORS: &[&str] = &[
+    "equal",
+    "not_equal",
+    "greater",
+    "greater_or_equal",
+    "less",
+    "less_or_equal",
+    "between",
+    "not_between",
+];
+const SET_OPERATORS: &[&str] = &["in", "not_in"];
+const TIME_OF_DAY_OPERATORS: &[&str] = &["between", "not_between", "greater_or_equal", "less"];
+
+/// 定时调度与即时触发共用，避免两边同时读写执行批次
+static AUTO_SCORE_RUN_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AutoScoreTrigger {
@@ -47,6 +99,43 @@ struct RewardExchangeActionValue {
     reward_name: Option<String>,
 }
 
+/// score_event_created 的取值：理由或分类命中，可要求时间窗口内累计达到 minCount 条
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+struct ScoreEventTriggerValue {
+    #[serde(default)]
+    reasons: Vec<String>,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "min_count")]
+    min_count: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    period: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        alias = "window_days"
+    )]
+    window_days: Option<i64>,
+}
+
+/// 条件类触发的取值；days、reasons、categories 只用于积分记录统计，path 只用于 extra_json
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+struct ConditionTriggerValue {
+    operator: String,
+    #[serde(default)]
+    values: Vec<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    days: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    reasons: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    categories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct AutoScoreExecutionConfig {
@@ -67,6 +156,16 @@ pub struct AutoScoreFilterConfig {
     pub min_recent_event_count: Option<i64>,
 }
 
+impl AutoScoreFilterConfig {
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+            && self.grades.is_empty()
+            && self.min_score.is_none()
+            && self.max_score.is_none()
+            && self.recent_event_days.is_none()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct AutoScoreExecutionBatch {
@@ -76,6 +175,8 @@ pub struct AutoScoreExecutionBatch {
     pub run_at: String,
     pub affected_students: usize,
     pub affected_student_names: Vec<String>,
+    #[serde(default)]
+    pub affected_student_ids: Vec<i32>,
     pub created_event_ids: Vec<i32>,
     pub added_student_tag_ids: Vec<i32>,
     #[serde(default)]
@@ -86,6 +187,25 @@ pub struct AutoScoreExecutionBatch {
     pub rollback_at: Option<String>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoScoreBatchPageParams {
+    pub rule_id: Option<i32>,
+    /// UTC ISO 时间，按执行时间过滤
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub limit: Option<i32>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoScoreBatchPage {
+    pub items: Vec<AutoScoreExecutionBatch>,
+    pub total: u64,
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct AutoScoreBackfillItem {
@@ -103,6 +223,73 @@ pub struct AutoScoreBackfillResult {
     pub score_delta_total: i64,
 }
 
+/// 规则试运行结果，只计算不写库
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoScoreRulePreview {
+    pub rule_id: i32,
+    /// 距下次定时执行的毫秒数，没有时间触发条件时为空
+    pub next_run_in_ms: Option<i64>,
+    /// 含即时触发条件时，预览假定对应写入已经发生
+    pub has_reactive_triggers: bool,
+    pub students: Vec<AutoScorePreviewStudent>,
+    pub excluded: Vec<AutoScorePreviewExclusion>,
+    pub score_delta_total: i64,
+    pub settle: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoScorePreviewStudent {
+    pub student_id: i32,
+    pub student_name: String,
+    pub score_before: i32,
+    pub score_after: i32,
+    pub score_delta: i64,
+    pub add_tags: Vec<String>,
+    pub rewards: Vec<AutoScorePreviewReward>,
+    /// 因每日上限、积分不足等原因跳过的动作
+    pub skipped_actions: Vec<AutoScorePreviewSkip>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoScorePreviewReward {
+    pub reward_id: i32,
+    pub reward_name: String,
+    pub cost_points: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoScorePreviewSkip {
+    pub action: String,
+    pub reason: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoScorePreviewExclusion {
+    pub student_id: i32,
+    pub student_name: String,
+    /// student_names、group、grade、min_score、max_score、recent_events、trigger、
+    /// max_runs_per_day、cooldown 或 no_effect
+    pub reason: String,
+    pub detail: Option<String>,
+}
+
+impl AutoScorePreviewExclusion {
+    fn new(student: &students::Model, reason: &str, detail: Option<String>) -> Self {
+        Self {
+            student_id: student.id,
+            student_name: student.name.clone(),
+            reason: reason.to_string(),
+            detail,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 enum IntervalUnit {
@@ -150,6 +337,79 @@ enum ExecutionMode {
     Backfill,
 }
 
+/// 由事件总线上的写入转换而来，驱动即时触发条件
+#[derive(Debug, Clone)]
+enum TriggerSignal {
+    ScoreEvents(Vec<score_events::Model>),
+    Redemption(Box<reward_redemptions::Model>),
+    TagsAttached { student_id: i32, tag_ids: Vec<i32> },
+}
+
+impl TriggerSignal {
+    fn from_bus_event(event: &BusEvent) -> Option<Self> {
+        // 自动加分自身、同步和备份恢复的写入不再触发，避免循环与重放
+        if matches!(
+            event.source,
+            EventSource::AutoScore | EventSource::Sync | EventSource::Backup
+        ) {
+            return None;
+        }
+
+        match &event.payload {
+            BusPayload::ScoreEventsCreated { events } => {
+                let events = events
+                    .iter()
+                    .filter(|event| event.student_id.is_some())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (!events.is_empty()).then_some(TriggerSignal::ScoreEvents(events))
+            }
+            // 兑换创建时为 pending，之后的审核变更不算新的兑换
+            BusPayload::RedemptionChanged { redemption }
+                if redemption.student_id.is_some()
+                    && redemption.status == RedemptionStatus::Pending.as_str() =>
+            {
+                Some(TriggerSignal::Redemption(redemption.clone()))
+            }
+            BusPayload::StudentTagsUpdated {
+                student_id,
+                added_tag_ids,
+            } if !added_tag_ids.is_empty() => Some(TriggerSignal::TagsAttached {
+                student_id: *student_id,
+                tag_ids: added_tag_ids.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn supports(&self, event: &str) -> bool {
+        match self {
+            TriggerSignal::ScoreEvents(_) => matches!(
+                event,
+                "score_event_created" | "score_crossed_above" | "score_crossed_below"
+            ),
+            TriggerSignal::Redemption(_) => event == "reward_redeemed",
+            TriggerSignal::TagsAttached { .. } => event == "tag_attached",
+        }
+    }
+
+    fn is_handled_by(&self, rule: &AutoScoreRule) -> bool {
+        rule.triggers
+            .iter()
+            .any(|trigger| self.supports(&trigger.event))
+    }
+
+    fn student_ids(&self) -> HashSet<i32> {
+        match self {
+            TriggerSignal::ScoreEvents(events) => {
+                events.iter().filter_map(|event| event.student_id).collect()
+            }
+            TriggerSignal::Redemption(redemption) => redemption.student_id.into_iter().collect(),
+            TriggerSignal::TagsAttached { student_id, .. } => HashSet::from([*student_id]),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 struct StudentRefs {
     ids: HashSet<i32>,
@@ -160,6 +420,7 @@ struct StudentRefs {
 struct RuleExecutionStats {
     affected_students: usize,
     affected_student_names: Vec<String>,
+    affected_student_ids: Vec<i32>,
     created_events: usize,
     added_tags: usize,
     score_delta_total: i64,
@@ -215,6 +476,7 @@ impl AutoScoreService {
     pub async fn initialize(
         &mut self,
         app_handle: &AppHandle,
+        event_bus: &EventBus,
     ) -> Result<(), Box<dyn std::error::Error>> {
         if self.initialized {
             return Ok(());
@@ -222,6 +484,7 @@ impl AutoScoreService {
 
         self.initialized = true;
         Self::spawn_scheduler(app_handle.clone());
+        Self::spawn_reactor(app_handle.clone(), event_bus);
         Ok(())
     }
 
@@ -353,15 +616,109 @@ impl AutoScoreService {
             loop {
                 ticker.tick().await;
                 if let Err(error) = Self::run_scheduler_tick(&app_handle).await {
-                    eprintln!("auto score scheduler tick failed: {}", error);
+                    warn!("Auto score scheduler tick failed: {}", error);
+                }
+            }
+        });
+    }
+
+    /// 订阅事件总线，写入提交后立即评估即时触发条件
+    fn spawn_reactor(app_handle: AppHandle, event_bus: &EventBus) {
+        let mut receiver = event_bus.subscribe();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Auto score reactor lagged, skipped {} bus events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Some(signal) = TriggerSignal::from_bus_event(&event) else {
+                    continue;
+                };
+                if let Err(error) = Self::run_reactive(&app_handle, &signal).await {
+                    warn!("Auto score reactive run failed: {}", error);
                 }
             }
         });
     }
 
+    async fn run_reactive(app_handle: &AppHandle, signal: &TriggerSignal) -> Result<(), String> {
+        let state = app_handle.state::<SafeAppState>().inner().clone();
+        let _run_guard = AUTO_SCORE_RUN_LOCK.lock().await;
+
+        let rules_snapshot = {
+            let state_guard = state.read();
+            let auto_score = state_guard.auto_score.read();
+            auto_score.get_rules().to_vec()
+        };
+        if !rules_snapshot
+            .iter()
+            .any(|rule| rule.enabled && signal.is_handled_by(rule))
+        {
+            return Ok(());
+        }
+
+        let conn = {
+            let state_guard = state.read();
+            let db_conn = state_guard.db.read().clone();
+            db_conn
+        }
+        .ok_or_else(|| "Database not connected".to_string())?;
+
+        let mut execution_batches = load_active_batches(&conn, &rules_snapshot).await?;
+        let first_new_batch = execution_batches.len();
+        let mut next_rules = rules_snapshot.clone();
+        let mut changed = false;
+
+        for rule in next_rules
+            .iter_mut()
+            .filter(|rule| rule.enabled && signal.is_handled_by(rule))
+        {
+            match execute_rule(
+                &state,
+                &conn,
+                rule,
+                &execution_batches,
+                ExecutionMode::Normal,
+                Some(signal),
+            )
+            .await
+            {
+                Ok(stats) => {
+                    if stats.affected_students == 0 {
+                        continue;
+                    }
+                    rule.last_executed = Some(Utc::now().to_rfc3339());
+                    changed = true;
+                    Self::log_rule_executed(&state, rule, &stats);
+                    execution_batches.push(new_execution_batch(rule, &stats));
+                }
+                Err(error) => {
+                    Self::log_rule_failed(&state, rule, &error);
+                }
+            }
+        }
+
+        if !changed {
+            return Ok(());
+        }
+
+        Self::commit_run(
+            app_handle,
+            &state,
+            &conn,
+            next_rules,
+            &execution_batches[first_new_batch..],
+        )
+        .await
+    }
+
     async fn run_scheduler_tick(app_handle: &AppHandle) -> Result<(), String> {
         let state = app_handle.state::<SafeAppState>().inner().clone();
-        let mut execution_batches = load_batches_from_settings(&state).await?;
+        let _run_guard = AUTO_SCORE_RUN_LOCK.lock().await;
 
         let rules_snapshot = {
             let state_guard = state.read();
@@ -380,8 +737,10 @@ impl AutoScoreService {
         }
         .ok_or_else(|| "Database not connected".to_string())?;
 
+        let mut execution_batches = load_active_batches(&conn, &rules_snapshot).await?;
         let mut next_rules = rules_snapshot.clone();
         let mut changed = false;
+        let first_new_batch = execution_batches.len();
 
         for rule in next_rules.iter_mut().filter(|rule| rule.enabled) {
             let Some(delay_ms) = check_interval_trigger(rule) else {
@@ -391,7 +750,16 @@ impl AutoScoreService {
                 continue;
             }
 
-            match execute_rule(&conn, rule, &execution_batches, ExecutionMode::Normal).await {
+            match execute_rule(
+                &state,
+                &conn,
+                rule,
+                &execution_batches,
+                ExecutionMode::Normal,
+                None,
+            )
+            .await
+            {
                 Ok(stats) => {
                     if stats.affected_students == 0 {
                         Self::log_rule_skipped(&state, rule, "no matched students");
@@ -400,22 +768,7 @@ impl AutoScoreService {
                     rule.last_executed = Some(Utc::now().to_rfc3339());
                     changed = true;
                     Self::log_rule_executed(&state, rule, &stats);
-                    let batch = AutoScoreExecutionBatch {
-                        id: Uuid::new_v4().to_string(),
-                        rule_id: rule.id,
-                        rule_name: rule.name.clone(),
-                        run_at: now_iso(),
-                        affected_students: stats.affected_students,
-                        affected_student_names: stats.affected_student_names.clone(),
-                        created_event_ids: stats.created_event_ids.clone(),
-                        added_student_tag_ids: stats.added_student_tag_ids.clone(),
-                        reward_redemption_ids: stats.reward_redemption_ids.clone(),
-                        score_delta_total: stats.score_delta_total,
-                        settled: stats.settled,
-                        rolled_back: false,
-                        rollback_at: None,
-                    };
-                    execution_batches.push(batch);
+                    execution_batches.push(new_execution_batch(rule, &stats));
                 }
                 Err(error) => {
                     Self::log_rule_failed(&state, rule, &error);
@@ -427,8 +780,28 @@ impl AutoScoreService {
             return Ok(());
         }
 
-        persist_rules_to_settings(&state, &next_rules).await?;
-        save_batches_to_settings(&state, &execution_batches).await?;
+        Self::commit_run(
+            app_handle,
+            &state,
+            &conn,
+            next_rules,
+            &execution_batches[first_new_batch..],
+        )
+        .await
+    }
+
+    /// 保存规则与新执行批次，发布新批次并通知前端
+    async fn commit_run(
+        app_handle: &AppHandle,
+        state: &SafeAppState,
+        conn: &DatabaseConnection,
+        next_rules: Vec<AutoScoreRule>,
+        new_batches: &[AutoScoreExecutionBatch],
+    ) -> Result<(), String> {
+        persist_rules_to_settings(state, &next_rules).await?;
+        insert_batches(conn, new_batches).await?;
+        prune_expired_batches(state, conn).await?;
+        publish_batches(state, new_batches);
 
         {
             let state_guard = state.read();
@@ -497,9 +870,6 @@ fn normalize_rule(mut rule: AutoScoreRule) -> Result<AutoScoreRule, String> {
     rule.name = normalize_required_string(rule.name, "automation name")?;
     rule.student_names = dedupe_trimmed_strings(rule.student_names);
 
-    if rule.triggers.is_empty() && rule.trigger_tree.is_none() {
-        return Err("At least one trigger is required".to_string());
-    }
     if rule.actions.is_empty() {
         return Err("At least one action is required".to_string());
     }
@@ -509,25 +879,31 @@ fn normalize_rule(mut rule: AutoScoreRule) -> Result<AutoScoreRule, String> {
         .into_iter()
         .map(normalize_trigger)
         .collect::<Result<Vec<_>, _>>()?;
-    let normalized_tree = if let Some(tree) = rule.trigger_tree.take() {
-        normalize_trigger_tree(tree)?
-    } else {
-        build_trigger_tree_from_triggers(&normalized_triggers)
+    let filters = normalize_filter_config(rule.filters)?;
+    // 只有筛选条件的规则不保存触发树，按筛选结果整体命中
+    let normalized_tree = match rule.trigger_tree.take() {
+        Some(tree) if is_empty_trigger_group(&tree) => None,
+        Some(tree) => Some(normalize_trigger_tree(tree)?),
+        None if normalized_triggers.is_empty() => None,
+        None => Some(build_trigger_tree_from_triggers(&normalized_triggers)),
     };
-    let tree_triggers = collect_triggers_from_tree(&normalized_tree)?;
-    if tree_triggers.is_empty() {
-        return Err("At least one trigger is required".to_string());
+    let tree_triggers = match &normalized_tree {
+        Some(tree) => collect_triggers_from_tree(tree)?,
+        None => Vec::new(),
+    };
+    if tree_triggers.is_empty() && filters.is_empty() {
+        return Err("At least one trigger or filter is required".to_string());
     }
 
     rule.triggers = tree_triggers;
-    rule.trigger_tree = Some(normalized_tree);
+    rule.trigger_tree = normalized_tree;
+    rule.filters = filters;
     rule.actions = rule
         .actions
         .into_iter()
         .map(normalize_action)
         .collect::<Result<Vec<_>, _>>()?;
     rule.execution = normalize_execution_config(rule.execution)?;
-    rule.filters = normalize_filter_config(rule.filters)?;
     rule.last_executed = normalize_last_executed(rule.last_executed);
 
     Ok(rule)
@@ -555,9 +931,21 @@ fn normalize_filter_config(
     }
     config.recent_event_days = config.recent_event_days.filter(|value| *value > 0);
     config.min_recent_event_count = config.min_recent_event_count.filter(|value| *value >= 0);
+    if config.min_recent_event_count.is_some() && config.recent_event_days.is_none() {
+        return Err("recentEventDays is required when minRecentEventCount is set".to_string());
+    }
     Ok(config)
 }
 
+fn is_empty_trigger_group(tree: &JsonValue) -> bool {
+    tree.get("type").and_then(JsonValue::as_str) == Some("group")
+        && tree
+            .get("children1")
+            .and_then(JsonValue::as_array)
+            .map(|children| children.is_empty())
+            .unwrap_or(true)
+}
+
 fn build_trigger_tree_from_triggers(triggers: &[AutoScoreTrigger]) -> JsonValue {
     JsonValue::Object(
         [
@@ -588,6 +976,10 @@ fn build_trigger_tree_from_triggers(triggers: &[AutoScoreTrigger]) -> JsonValue
 }
 
 fn build_trigger_rule_node(trigger: &AutoScoreTrigger) -> JsonValue {
+    if let Some(properties) = build_condition_rule_properties(trigger) {
+        return trigger_rule_node(properties);
+    }
+
     let (field, operator, value) = match trigger.event.as_str() {
         "interval_time_passed" => (
             "interval_minutes",
@@ -628,6 +1020,49 @@ fn build_trigger_rule_node(trigger: &AutoScoreTrigger) -> JsonValue {
                 trigger.value.clone().unwrap_or_default(),
             )]),
         ),
+        "score_event_created" => (
+            "score_event",
+            "equal",
+            JsonValue::Array(vec![trigger
+                .value
+                .as_deref()
+                .and_then(|value| serde_json::from_str::<JsonValue>(value).ok())
+                .unwrap_or_else(|| json!({}))]),
+        ),
+        "score_crossed_above" | "score_crossed_below" => (
+            "score_crossed",
+            if trigger.event == "score_crossed_below" {
+                "less_or_equal"
+            } else {
+                "greater_or_equal"
+            },
+            JsonValue::Array(vec![JsonValue::String(
+                trigger.value.clone().unwrap_or_default(),
+            )]),
+        ),
+        "reward_redeemed" => {
+            let reward_ids = parse_reward_id_values(trigger.value.as_deref())
+                .unwrap_or_default()
+                .into_iter()
+                .map(JsonValue::from)
+                .collect::<Vec<JsonValue>>();
+            (
+                "reward_redeemed",
+                "multiselect_contains",
+                JsonValue::Array(vec![JsonValue::Array(reward_ids)]),
+            )
+        }
+        "tag_attached" => {
+            let tag_values = parse_tag_values(trigger.value.as_deref())
+                .into_iter()
+                .map(JsonValue::String)
+                .collect::<Vec<JsonValue>>();
+            (
+                "tag_attached",
+                "multiselect_contains",
+                JsonValue::Array(vec![JsonValue::Array(tag_values)]),
+            )
+        }
         _ => (
             "student_sql",
             "equal",
@@ -637,6 +1072,14 @@ fn build_trigger_rule_node(trigger: &AutoScoreTrigger) -> JsonValue {
         ),
     };
 
+    trigger_rule_node(json!({
+        "field": field,
+        "operator": operator,
+        "value": value,
+    }))
+}
+
+fn trigger_rule_node(properties: JsonValue) -> JsonValue {
     JsonValue::Object(
         [
             (
@@ -644,20 +1087,41 @@ fn build_trigger_rule_node(trigger: &AutoScoreTrigger) -> JsonValue {
                 JsonValue::String(Uuid::new_v4().to_string()),
             ),
             ("type".to_string(), JsonValue::String("rule".to_string())),
-            (
-                "properties".to_string(),
-                json!({
-                    "field": field,
-                    "operator": operator,
-                    "value": value,
-                }),
-            ),
+            ("properties".to_string(), properties),
         ]
         .into_iter()
         .collect(),
     )
 }
 
+/// 条件类触发写回树节点：in / not_in 的值放在 value[0] 数组里，其余参数放在 params
+fn build_condition_rule_properties(trigger: &AutoScoreTrigger) -> Option<JsonValue> {
+    if !CONDITION_TRIGGER_FIELDS.contains(&trigger.event.as_str()) {
+        return None;
+    }
+
+    let condition = parse_condition_trigger_value(trigger.value.as_deref()).unwrap_or_default();
+    let value = if SET_OPERATORS.contains(&condition.operator.as_str()) {
+        JsonValue::Array(vec![JsonValue::Array(condition.values.clone())])
+    } else {
+        JsonValue::Array(condition.values.clone())
+    };
+    let mut properties = json!({
+        "field": trigger.event,
+        "operator": condition.operator,
+        "value": value,
+    });
+
+    let mut params = serde_json::to_value(&condition).ok()?;
+    let params = params.as_object_mut()?;
+    params.remove("operator");
+    params.remove("values");
+    if !params.is_empty() {
+        properties["params"] = JsonValue::Object(params.clone());
+    }
+    Some(properties)
+}
+
 fn normalize_trigger_tree(tree: JsonValue) -> Result<JsonValue, String> {
     let normalized = normalize_trigger_tree_node(tree)?;
     if !matches!(
@@ -882,6 +1346,85 @@ fn trigger_from_rule_node(node: &JsonValue) -> Result<AutoScoreTrigger, String>
             };
             normalize_trigger(trigger)
         }
+        "score_event" => {
+            let value = match first_value {
+                Some(JsonValue::String(value)) => Some(value),
+                Some(value @ JsonValue::Object(_)) => Some(value.to_string()),
+                _ => None,
+            };
+            normalize_trigger(AutoScoreTrigger {
+                event: "score_event_created".to_string(),
+                value,
+            })
+        }
+        "score_crossed" => {
+            let threshold = match first_value {
+                Some(JsonValue::Number(value)) => Some(value.to_string()),
+                Some(JsonValue::String(value)) => Some(value),
+                _ => None,
+            };
+            let event = if operator.eq_ignore_ascii_case("less_or_equal") {
+                "score_crossed_below"
+            } else {
+                "score_crossed_above"
+            };
+            normalize_trigger(AutoScoreTrigger {
+                event: event.to_string(),
+                value: threshold,
+            })
+        }
+        "reward_redeemed" => {
+            let value = match first_value {
+                Some(value @ JsonValue::Array(_)) => Some(value.to_string()),
+                Some(JsonValue::Number(value)) => Some(value.to_string()),
+                Some(JsonValue::String(value)) => Some(value),
+                _ => None,
+            };
+            normalize_trigger(AutoScoreTrigger {
+                event: "reward_redeemed".to_string(),
+                value,
+            })
+        }
+        "tag_attached" => {
+            let tags = match first_value {
+                Some(JsonValue::Array(items)) => items
+                    .into_iter()
+                    .filter_map(|item| item.as_str().map(str::to_string))
+                    .collect::<Vec<String>>(),
+                Some(JsonValue::String(value)) => vec![value],
+                _ => Vec::new(),
+            };
+            normalize_trigger(AutoScoreTrigger {
+                event: "tag_attached".to_string(),
+                value: stringify_tag_values(&tags),
+            })
+        }
+        field if CONDITION_TRIGGER_FIELDS.contains(&field) => {
+            let properties = node.get("properties");
+            let operator = normalize_condition_operator(operator);
+            let mut values = properties
+                .and_then(|value| value.get("value"))
+                .and_then(JsonValue::as_array)
+                .cloned()
+                .unwrap_or_default();
+            if SET_OPERATORS.contains(&operator.as_str()) {
+                if let Some(JsonValue::Array(items)) = values.first() {
+                    values = items.clone();
+                }
+            }
+
+            let mut value = properties
+                .and_then(|value| value.get("params"))
+                .filter(|params| params.is_object())
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+            value["operator"] = JsonValue::String(operator);
+            value["values"] = JsonValue::Array(values);
+            normalize_trigger(AutoScoreTrigger {
+                event: field.to_string(),
+                value: Some(value.to_string()),
+            })
+        }
         _ => Err(format!("Unsupported trigger tree field: {}", field)),
     }
 }
@@ -934,6 +1477,48 @@ fn normalize_trigger(trigger: AutoScoreTrigger) -> Result<AutoScoreTrigger, Stri
                 value: Some(threshold),
             })
         }
+        "score_event_created" => {
+            let value = parse_score_event_trigger_value(trigger.value.as_deref())?;
+            let value = serde_json::to_string(&value).map_err(|e| e.to_string())?;
+            Ok(AutoScoreTrigger {
+                event,
+                value: Some(value),
+            })
+        }
+        "score_crossed_above" | "score_crossed_below" => {
+            let threshold = normalize_integer_string(trigger.value.as_deref())
+                .ok_or_else(|| "Score threshold trigger requires an integer value".to_string())?;
+            Ok(AutoScoreTrigger {
+                event,
+                value: Some(threshold),
+            })
+        }
+        "reward_redeemed" => {
+            let reward_ids = parse_reward_id_values(trigger.value.as_deref())
+                .ok_or_else(|| "Invalid reward redeemed trigger value".to_string())?;
+            Ok(AutoScoreTrigger {
+                event,
+                value: stringify_reward_id_values(&reward_ids),
+            })
+        }
+        "tag_attached" => {
+            let tag_values = parse_tag_values(trigger.value.as_deref());
+            let value = stringify_tag_values(&tag_values)
+                .ok_or_else(|| "Tag attached trigger requires at least one tag".to_string())?;
+            Ok(AutoScoreTrigger {
+                event,
+                value: Some(value),
+            })
+        }
+        _ if CONDITION_TRIGGER_FIELDS.contains(&event.as_str()) => {
+            let condition = parse_condition_trigger_value(trigger.value.as_deref())?;
+            let condition = normalize_condition_trigger_value(&event, condition)?;
+            let value = serde_json::to_string(&condition).map_err(|e| e.to_string())?;
+            Ok(AutoScoreTrigger {
+                event,
+                value: Some(value),
+            })
+        }
         _ => Err(format!("Unsupported trigger event: {}", event)),
     }
 }
@@ -1084,12 +1669,290 @@ fn stringify_tag_values(values: &[String]) -> Option<String> {
     serde_json::to_string(values).ok()
 }
 
-fn normalize_non_zero_integer_string(value: Option<&str>) -> Option<String> {
-    let normalized = value?.trim();
-    if normalized.is_empty() {
-        return None;
-    }
-
+/// 解析并校验 score_event_created 的取值
+fn parse_score_event_trigger_value(value: Option<&str>) -> Result<ScoreEventTriggerValue, String> {
+    let raw_value = value.map(str::trim).unwrap_or_default();
+    let mut parsed = if raw_value.is_empty() {
+        ScoreEventTriggerValue::default()
+    } else {
+        serde_json::from_str::<ScoreEventTriggerValue>(raw_value)
+            .map_err(|_| "Invalid score event trigger value".to_string())?
+    };
+
+    parsed.reasons = dedupe_trimmed_strings(parsed.reasons);
+    parsed.categories = dedupe_trimmed_strings(parsed.categories);
+    parsed.period = normalize_optional_string(parsed.period).map(|value| value.to_lowercase());
+
+    if let Some(period) = parsed.period.as_deref() {
+        if !matches!(period, "today" | "week" | "month") {
+            return Err("Score event trigger period must be today, week or month".to_string());
+        }
+    }
+    if let Some(days) = parsed.window_days {
+        if days <= 0 {
+            return Err("Score event trigger windowDays must be positive".to_string());
+        }
+        if parsed.period.is_some() {
+            return Err("Score event trigger accepts either period or windowDays".to_string());
+        }
+    }
+    match parsed.min_count {
+        Some(count) if count <= 0 => {
+            return Err("Score event trigger minCount must be positive".to_string());
+        }
+        Some(count) if count > 1 && parsed.period.is_none() && parsed.window_days.is_none() => {
+            return Err("Score event trigger minCount requires period or windowDays".to_string());
+        }
+        Some(1) => parsed.min_count = None,
+        _ => {}
+    }
+
+    Ok(parsed)
+}
+
+fn parse_condition_trigger_value(value: Option<&str>) -> Result<ConditionTriggerValue, String> {
+    let raw_value = value.map(str::trim).unwrap_or_default();
+    if raw_value.is_empty() {
+        return Err("Condition trigger requires a value".to_string());
+    }
+    serde_json::from_str::<ConditionTriggerValue>(raw_value)
+        .map_err(|_| "Invalid condition trigger value".to_string())
+}
+
+/// 查询构建器的集合运算符名称统一成 in / not_in
+fn normalize_condition_operator(operator: &str) -> String {
+    match operator.trim().to_lowercase().as_str() {
+        "select_any_in" | "multiselect_contains" => "in".to_string(),
+        "select_not_any_in" | "multiselect_not_contains" => "not_in".to_string(),
+        "select_equals" => "equal".to_string(),
+        "select_not_equals" => "not_equal".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn condition_operators(field: &str) -> Vec<&'static str> {
+    match field {
+        "student_group" | "weekday" => SET_OPERATORS.to_vec(),
+        "time_of_day" => TIME_OF_DAY_OPERATORS.to_vec(),
+        "extra_json" => [NUMBER_OPERATORS, SET_OPERATORS].concat(),
+        _ => NUMBER_OPERATORS.to_vec(),
+    }
+}
+
+fn condition_number(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(number) => number.as_f64(),
+        JsonValue::String(raw) => raw.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+    .filter(|number| number.is_finite())
+}
+
+fn condition_number_value(number: f64) -> JsonValue {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        JsonValue::from(number as i64)
+    } else {
+        JsonValue::from(number)
+    }
+}
+
+fn parse_time_of_day(value: &JsonValue) -> Option<chrono::NaiveTime> {
+    chrono::NaiveTime::parse_from_str(value.as_str()?.trim(), "%H:%M").ok()
+}
+
+fn normalize_condition_trigger_value(
+    field: &str,
+    mut condition: ConditionTriggerValue,
+) -> Result<ConditionTriggerValue, String> {
+    condition.operator = normalize_condition_operator(&condition.operator);
+    if !condition_operators(field).contains(&condition.operator.as_str()) {
+        return Err(format!(
+            "Operator {} is not supported by trigger field {}",
+            condition.operator, field
+        ));
+    }
+
+    let operator = condition.operator.as_str();
+    let is_set = SET_OPERATORS.contains(&operator);
+    let is_range = matches!(operator, "between" | "not_between");
+    let is_ordering = !is_set && !matches!(operator, "equal" | "not_equal");
+
+    condition.values = match field {
+        "student_group" => dedupe_trimmed_strings(
+            condition
+                .values
+                .iter()
+                .filter_map(|value| match value {
+                    JsonValue::String(raw) => Some(raw.clone()),
+                    JsonValue::Number(number) => Some(number.to_string()),
+                    _ => None,
+                })
+                .collect(),
+        )
+        .into_iter()
+        .map(JsonValue::String)
+        .collect(),
+        "weekday" => {
+            let mut days = Vec::new();
+            for value in &condition.values {
+                let day = condition_number(value)
+                    .filter(|day| day.fract() == 0.0 && (1.0..=7.0).contains(day))
+                    .ok_or_else(|| "Weekday trigger values must be 1 to 7".to_string())?
+                    as i64;
+                if !days.contains(&day) {
+                    days.push(day);
+                }
+            }
+            days.sort_unstable();
+            days.into_iter().map(JsonValue::from).collect()
+        }
+        "time_of_day" => condition
+            .values
+            .iter()
+            .map(|value| {
+                parse_time_of_day(value)
+                    .map(|time| JsonValue::String(time.format("%H:%M").to_string()))
+                    .ok_or_else(|| "Time of day trigger values must be HH:MM".to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        // 字符串比较只支持等于与集合运算，大小比较要求数值
+        "extra_json" if !is_ordering => condition
+            .values
+            .iter()
+            .map(|value| match value {
+                JsonValue::String(raw) => Ok(JsonValue::String(raw.trim().to_string())),
+                JsonValue::Number(_) | JsonValue::Bool(_) => Ok(value.clone()),
+                _ => Err("Extra field trigger values must be strings or numbers".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => condition
+            .values
+            .iter()
+            .map(|value| {
+                condition_number(value)
+                    .map(condition_number_value)
+                    .ok_or_else(|| format!("Trigger field {} requires numeric values", field))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let expected = if is_range {
+        Some(2)
+    } else if is_set {
+        None
+    } else {
+        Some(1)
+    };
+    match expected {
+        Some(count) if condition.values.len() != count => {
+            return Err(format!(
+                "Operator {} requires {} value(s)",
+                condition.operator, count
+            ));
+        }
+        None if condition.values.is_empty() => {
+            return Err(format!(
+                "Operator {} requires at least one value",
+                condition.operator
+            ));
+        }
+        _ => {}
+    }
+    // 时间段允许跨零点，其余区间要求下限不大于上限
+    if is_range && field != "time_of_day" {
+        let bounds = (
+            condition_number(&condition.values[0]),
+            condition_number(&condition.values[1]),
+        );
+        if let (Some(min), Some(max)) = bounds {
+            if min > max {
+                return Err("Range lower bound cannot be greater than upper bound".to_string());
+            }
+        }
+    }
+    if field == "leaderboard_percentile"
+        && condition
+            .values
+            .iter()
+            .filter_map(condition_number)
+            .any(|value| !(0.0..=100.0).contains(&value))
+    {
+        return Err("Leaderboard percentile must be between 0 and 100".to_string());
+    }
+
+    let counts_events = matches!(
+        field,
+        "event_count" | "event_delta_sum" | "days_since_last_event"
+    );
+    if counts_events {
+        condition.reasons = dedupe_trimmed_strings(condition.reasons);
+        condition.categories = dedupe_trimmed_strings(condition.categories);
+    } else {
+        condition.reasons.clear();
+        condition.categories.clear();
+    }
+    if matches!(field, "event_count" | "event_delta_sum") {
+        match condition.days {
+            Some(days) if (1..=3650).contains(&days) => {}
+            _ => {
+                return Err("Event statistics trigger requires days between 1 and 3650".to_string())
+            }
+        }
+    } else {
+        condition.days = None;
+    }
+    if field == "extra_json" {
+        let path = normalize_optional_string(condition.path.take())
+            .ok_or_else(|| "Extra field trigger requires a path".to_string())?;
+        if path.split('.').any(|segment| segment.trim().is_empty()) {
+            return Err("Invalid extra field path".to_string());
+        }
+        condition.path = Some(path);
+    } else {
+        condition.path = None;
+    }
+
+    Ok(condition)
+}
+
+/// 奖励 id 列表，空列表表示任意奖励；格式不合法时返回 None
+fn parse_reward_id_values(value: Option<&str>) -> Option<Vec<i32>> {
+    let raw_value = value.map(str::trim).unwrap_or_default();
+    if raw_value.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let ids = if raw_value.starts_with('[') {
+        serde_json::from_str::<Vec<i32>>(raw_value).ok()?
+    } else {
+        vec![raw_value.parse::<i32>().ok()?]
+    };
+    if ids.iter().any(|id| *id <= 0) {
+        return None;
+    }
+
+    let mut deduped = Vec::new();
+    for id in ids {
+        if !deduped.contains(&id) {
+            deduped.push(id);
+        }
+    }
+    Some(deduped)
+}
+
+fn stringify_reward_id_values(values: &[i32]) -> Option<String> {
+    if values.is_empty() {
+        return None;
+    }
+    serde_json::to_string(values).ok()
+}
+
+fn normalize_non_zero_integer_string(value: Option<&str>) -> Option<String> {
+    let normalized = value?.trim();
+    if normalized.is_empty() {
+        return None;
+    }
+
     let parsed = normalized.parse::<i32>().ok()?;
     if parsed == 0 {
         return None;
@@ -1434,54 +2297,307 @@ async fn persist_rules_to_settings(
     Ok(())
 }
 
-fn deserialize_batches(value: &JsonValue) -> Vec<AutoScoreExecutionBatch> {
-    let JsonValue::Array(items) = value else {
-        return Vec::new();
+#[derive(Debug, Serialize, Deserialize)]
+struct BatchCursor {
+    run_at: String,
+    id: String,
+}
+
+fn encode_batch_cursor(batch: &AutoScoreExecutionBatch) -> String {
+    let raw = serde_json::to_vec(&BatchCursor {
+        run_at: batch.run_at.clone(),
+        id: batch.id.clone(),
+    })
+    .unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+fn decode_batch_cursor(cursor: &str) -> Result<BatchCursor, String> {
+    URL_SAFE_NO_PAD
+        .decode(cursor.trim())
+        .ok()
+        .and_then(|raw| serde_json::from_slice::<BatchCursor>(&raw).ok())
+        .ok_or_else(|| "Invalid cursor".to_string())
+}
+
+fn batch_from_rows(
+    row: auto_score_batches::Model,
+    items: Vec<auto_score_batch_items::Model>,
+) -> AutoScoreExecutionBatch {
+    let mut batch = AutoScoreExecutionBatch {
+        id: row.id,
+        rule_id: row.rule_id,
+        rule_name: row.rule_name,
+        run_at: row.run_at,
+        affected_students: row.affected_students.max(0) as usize,
+        affected_student_names: Vec::new(),
+        affected_student_ids: Vec::new(),
+        created_event_ids: Vec::new(),
+        added_student_tag_ids: Vec::new(),
+        reward_redemption_ids: Vec::new(),
+        score_delta_total: row.score_delta_total,
+        settled: row.settled != 0,
+        rolled_back: row.rolled_back != 0,
+        rollback_at: row.rollback_at,
+    };
+    for item in items {
+        match (item.kind.as_str(), item.ref_id) {
+            (BATCH_ITEM_STUDENT, ref_id) => {
+                batch
+                    .affected_student_names
+                    .push(item.student_name.unwrap_or_default());
+                batch.affected_student_ids.extend(ref_id);
+            }
+            (BATCH_ITEM_SCORE_EVENT, Some(id)) => batch.created_event_ids.push(id),
+            (BATCH_ITEM_STUDENT_TAG, Some(id)) => batch.added_student_tag_ids.push(id),
+            (BATCH_ITEM_REWARD_REDEMPTION, Some(id)) => batch.reward_redemption_ids.push(id),
+            _ => {}
+        }
+    }
+    batch
+}
+
+fn batch_item_rows(batch: &AutoScoreExecutionBatch) -> Vec<auto_score_batch_items::ActiveModel> {
+    let item = |kind: &str, ref_id: Option<i32>, student_name: Option<String>| {
+        auto_score_batch_items::ActiveModel {
+            batch_id: Set(batch.id.clone()),
+            kind: Set(kind.to_string()),
+            ref_id: Set(ref_id),
+            student_name: Set(student_name),
+            ..Default::default()
+        }
     };
 
-    items
+    let mut rows: Vec<_> = batch
+        .affected_student_names
         .iter()
-        .filter_map(|item| serde_json::from_value::<AutoScoreExecutionBatch>(item.clone()).ok())
-        .collect()
+        .enumerate()
+        .map(|(index, name)| {
+            item(
+                BATCH_ITEM_STUDENT,
+                batch.affected_student_ids.get(index).copied(),
+                Some(name.clone()),
+            )
+        })
+        .collect();
+    for (kind, ids) in [
+        (BATCH_ITEM_SCORE_EVENT, &batch.created_event_ids),
+        (BATCH_ITEM_STUDENT_TAG, &batch.added_student_tag_ids),
+        (BATCH_ITEM_REWARD_REDEMPTION, &batch.reward_redemption_ids),
+    ] {
+        rows.extend(ids.iter().map(|id| item(kind, Some(*id), None)));
+    }
+    rows
 }
 
-async fn load_batches_from_settings(
-    state: &SafeAppState,
+async fn load_batch_items<C: ConnectionTrait>(
+    conn: &C,
+    rows: Vec<auto_score_batches::Model>,
 ) -> Result<Vec<AutoScoreExecutionBatch>, String> {
-    let state_guard = state.read();
-    let db_conn = state_guard.db.read().clone();
-    let mut settings = state_guard.settings.write();
-    settings.attach_db(db_conn);
-    settings.initialize().await?;
-    let raw = match settings.get_value(SettingsKey::AutoScoreBatches) {
-        SettingsValue::Json(value) => value,
-        _ => JsonValue::Array(vec![]),
-    };
-    Ok(deserialize_batches(&raw))
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ids: Vec<String> = rows.iter().map(|row| row.id.clone()).collect();
+    let mut items_by_batch: HashMap<String, Vec<auto_score_batch_items::Model>> = HashMap::new();
+    for chunk in ids.chunks(AUTO_SCORE_BATCH_CHUNK) {
+        let items = auto_score_batch_items::Entity::find()
+            .filter(auto_score_batch_items::Column::BatchId.is_in(chunk.to_vec()))
+            .order_by_asc(auto_score_batch_items::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        for item in items {
+            items_by_batch
+                .entry(item.batch_id.clone())
+                .or_default()
+                .push(item);
+        }
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let items = items_by_batch.remove(&row.id).unwrap_or_default();
+            batch_from_rows(row, items)
+        })
+        .collect())
 }
 
-async fn save_batches_to_settings(
-    state: &SafeAppState,
+/// 读取冷却和每日上限需要的近期未回滚批次，窗口取当天零点与最长冷却时间中更早者
+async fn load_active_batches<C: ConnectionTrait>(
+    conn: &C,
+    rules: &[AutoScoreRule],
+) -> Result<Vec<AutoScoreExecutionBatch>, String> {
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let now = Utc::now();
+    let day_start = now
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .map(|value| Utc.from_utc_datetime(&value))
+        .unwrap_or(now);
+    let since = rules
+        .iter()
+        .filter_map(|rule| rule.execution.cooldown_minutes)
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| now - chrono::Duration::minutes(minutes))
+        .fold(day_start, |earliest, value| earliest.min(value));
+
+    let rows = auto_score_batches::Entity::find()
+        .filter(
+            auto_score_batches::Column::RuleId
+                .is_in(rules.iter().map(|rule| rule.id).collect::<Vec<_>>()),
+        )
+        .filter(
+            auto_score_batches::Column::RunAt
+                .gte(since.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
+        )
+        .filter(auto_score_batches::Column::RolledBack.eq(0))
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    load_batch_items(conn, rows).await
+}
+
+async fn insert_batches(
+    conn: &DatabaseConnection,
     batches: &[AutoScoreExecutionBatch],
 ) -> Result<(), String> {
-    let encoded = serde_json::to_value(batches).map_err(|e| e.to_string())?;
-    let state_guard = state.read();
-    let db_conn = state_guard.db.read().clone();
-    let mut settings = state_guard.settings.write();
-    settings.attach_db(db_conn);
-    settings.initialize().await?;
-    settings
-        .set_value(SettingsKey::AutoScoreBatches, SettingsValue::Json(encoded))
-        .await?;
-    Ok(())
+    if batches.is_empty() {
+        return Ok(());
+    }
+
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    for batch in batches {
+        auto_score_batches::Entity::insert(auto_score_batches::ActiveModel {
+            id: Set(batch.id.clone()),
+            rule_id: Set(batch.rule_id),
+            rule_name: Set(batch.rule_name.clone()),
+            run_at: Set(batch.run_at.clone()),
+            affected_students: Set(batch.affected_students as i32),
+            score_delta_total: Set(batch.score_delta_total),
+            settled: Set(batch.settled as i32),
+            rolled_back: Set(batch.rolled_back as i32),
+            rollback_at: Set(batch.rollback_at.clone()),
+        })
+        .exec_without_returning(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let items = batch_item_rows(batch);
+        for chunk in items.chunks(AUTO_SCORE_BATCH_CHUNK) {
+            auto_score_batch_items::Entity::insert_many(chunk.to_vec())
+                .exec_without_returning(&txn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    txn.commit().await.map_err(|e| e.to_string())
 }
 
-pub async fn query_execution_batches(
+/// 按保留天数删除过期批次，0 表示永久保留
+async fn prune_expired_batches(
     state: &SafeAppState,
-) -> Result<Vec<AutoScoreExecutionBatch>, String> {
-    let mut batches = load_batches_from_settings(state).await?;
-    batches.sort_by(|a, b| b.run_at.cmp(&a.run_at));
-    Ok(batches)
+    conn: &DatabaseConnection,
+) -> Result<(), String> {
+    let retention_days = {
+        let state_guard = state.read();
+        let settings = state_guard.settings.read();
+        match settings.get_value(SettingsKey::AutoScoreBatchRetentionDays) {
+            SettingsValue::Number(n) => n,
+            _ => 0.0,
+        }
+    };
+    if retention_days < 1.0 {
+        return Ok(());
+    }
+    delete_batches_older_than(conn, retention_days as i64).await
+}
+
+async fn delete_batches_older_than(
+    conn: &DatabaseConnection,
+    retention_days: i64,
+) -> Result<(), String> {
+    let cutoff = (Utc::now() - chrono::Duration::days(retention_days))
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+    let backend = conn.get_database_backend();
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    txn.execute(bind_statement(
+        backend,
+        "DELETE FROM auto_score_batch_items WHERE batch_id IN (SELECT id FROM auto_score_batches WHERE run_at < ?)",
+        vec![cutoff.clone().into()],
+    ))
+    .await
+    .map_err(|e| e.to_string())?;
+    txn.execute(bind_statement(
+        backend,
+        "DELETE FROM auto_score_batches WHERE run_at < ?",
+        vec![cutoff.into()],
+    ))
+    .await
+    .map_err(|e| e.to_string())?;
+    txn.commit().await.map_err(|e| e.to_string())
+}
+
+pub async fn query_execution_batches(
+    conn: &DatabaseConnection,
+    params: &AutoScoreBatchPageParams,
+) -> Result<AutoScoreBatchPage, String> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500) as u64;
+    let mut query = auto_score_batches::Entity::find();
+    if let Some(rule_id) = params.rule_id {
+        query = query.filter(auto_score_batches::Column::RuleId.eq(rule_id));
+    }
+    if let Some(start_time) = params
+        .start_time
+        .as_deref()
+        .filter(|v| !v.trim().is_empty())
+    {
+        query = query.filter(auto_score_batches::Column::RunAt.gte(start_time));
+    }
+    if let Some(end_time) = params.end_time.as_deref().filter(|v| !v.trim().is_empty()) {
+        query = query.filter(auto_score_batches::Column::RunAt.lte(end_time));
+    }
+    let total = query.clone().count(conn).await.map_err(|e| e.to_string())?;
+
+    if let Some(cursor) = params.cursor.as_deref().filter(|v| !v.trim().is_empty()) {
+        let cursor = decode_batch_cursor(cursor)?;
+        query = query.filter(
+            Condition::any()
+                .add(auto_score_batches::Column::RunAt.lt(cursor.run_at.clone()))
+                .add(
+                    Condition::all()
+                        .add(auto_score_batches::Column::RunAt.eq(cursor.run_at))
+                        .add(auto_score_batches::Column::Id.lt(cursor.id)),
+                ),
+        );
+    }
+    // 多取一条用于判断是否还有下一页
+    let mut rows = query
+        .order_by_desc(auto_score_batches::Column::RunAt)
+        .order_by_desc(auto_score_batches::Column::Id)
+        .limit(limit + 1)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let has_more = rows.len() as u64 > limit;
+    rows.truncate(limit as usize);
+
+    let items = load_batch_items(conn, rows).await?;
+    let next_cursor = if has_more {
+        items.last().map(encode_batch_cursor)
+    } else {
+        None
+    };
+    Ok(AutoScoreBatchPage {
+        items,
+        total,
+        next_cursor,
+    })
 }
 
 pub async fn rollback_execution_batch(
@@ -1495,19 +2611,24 @@ pub async fn rollback_execution_batch(
     }
     .ok_or_else(|| "Database not connected".to_string())?;
 
-    let mut batches = load_batches_from_settings(state).await?;
-    let Some(index) = batches.iter().position(|batch| batch.id == batch_id) else {
+    let row = auto_score_batches::Entity::find_by_id(batch_id.to_string())
+        .one(&conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(mut batch) = load_batch_items(&conn, row.into_iter().collect())
+        .await?
+        .pop()
+    else {
         return Err("Execution batch not found".to_string());
     };
 
-    if batches[index].rolled_back {
+    if batch.rolled_back {
         return Err("Execution batch already rolled back".to_string());
     }
-    if batches[index].settled {
+    if batch.settled {
         return Err("Cannot rollback settled execution batch".to_string());
     }
 
-    let mut batch = batches[index].clone();
     let txn = conn.begin().await.map_err(|e| e.to_string())?;
 
     for event_id in &batch.created_event_ids {
@@ -1526,11 +2647,10 @@ pub async fn rollback_execution_batch(
             .await
             .map_err(|e| e.to_string())?;
 
-        if let Some(student) = students::Entity::find()
-            .filter(students::Column::Name.eq(event.student_name.clone()))
-            .one(&txn)
-            .await
-            .map_err(|e| e.to_string())?
+        if let Some(student) =
+            students::find_by_reference(&txn, event.student_id, &event.student_name)
+                .await
+                .map_err(|e| e.to_string())?
         {
             let current_score = student.score;
             let current_reward_points = student.reward_points;
@@ -1568,11 +2688,16 @@ pub async fn rollback_execution_batch(
             .await
             .map_err(|e| e.to_string())?;
 
-        if let Some(student) = students::Entity::find()
-            .filter(students::Column::Name.eq(redemption.student_name.clone()))
-            .one(&txn)
-            .await
-            .map_err(|e| e.to_string())?
+        // 已驳回或撤销的兑换当时已退还
+        if RedemptionStatus::of(&redemption.status).is_refunded() {
+            continue;
+        }
+        reward::restore_stock(&txn, redemption.reward_id).await?;
+
+        if let Some(student) =
+            students::find_by_reference(&txn, redemption.student_id, &redemption.student_name)
+                .await
+                .map_err(|e| e.to_string())?
         {
             let mut student_active: students::ActiveModel = student.clone().into();
             student_active.reward_points = Set(student.reward_points + redemption.cost_points);
@@ -1584,15 +2709,59 @@ pub async fn rollback_execution_batch(
         }
     }
 
-    txn.commit().await.map_err(|e| e.to_string())?;
-
     batch.rolled_back = true;
     batch.rollback_at = Some(now_iso());
-    batches[index] = batch.clone();
-    save_batches_to_settings(state, &batches).await?;
+    auto_score_batches::Entity::update_many()
+        .col_expr(auto_score_batches::Column::RolledBack, Expr::value(1))
+        .col_expr(
+            auto_score_batches::Column::RollbackAt,
+            Expr::value(batch.rollback_at.clone()),
+        )
+        .filter(auto_score_batches::Column::Id.eq(batch.id.clone()))
+        .exec(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    txn.commit().await.map_err(|e| e.to_string())?;
+    publish_batches(state, std::slice::from_ref(&batch));
     Ok(batch)
 }
 
+fn new_execution_batch(
+    rule: &AutoScoreRule,
+    stats: &RuleExecutionStats,
+) -> AutoScoreExecutionBatch {
+    AutoScoreExecutionBatch {
+        id: Uuid::new_v4().to_string(),
+        rule_id: rule.id,
+        rule_name: rule.name.clone(),
+        run_at: now_iso(),
+        affected_students: stats.affected_students,
+        affected_student_names: stats.affected_student_names.clone(),
+        affected_student_ids: stats.affected_student_ids.clone(),
+        created_event_ids: stats.created_event_ids.clone(),
+        added_student_tag_ids: stats.added_student_tag_ids.clone(),
+        reward_redemption_ids: stats.reward_redemption_ids.clone(),
+        score_delta_total: stats.score_delta_total,
+        settled: stats.settled,
+        rolled_back: false,
+        rollback_at: None,
+    }
+}
+
+/// 把新执行或回滚的批次发布到事件总线
+fn publish_batches(state: &SafeAppState, batches: &[AutoScoreExecutionBatch]) {
+    let state_guard = state.read();
+    for batch in batches {
+        state_guard.event_bus.publish(
+            EventSource::AutoScore,
+            BusPayload::AutoScoreBatch {
+                batch: Box::new(batch.clone()),
+            },
+        );
+    }
+}
+
 fn interval_base_time(rule: &AutoScoreRule) -> Option<DateTime<Utc>> {
     let last_executed = rule
         .last_executed
@@ -1694,12 +2863,14 @@ pub async fn apply_offline_backfill(
     }
     .ok_or_else(|| "Database not connected".to_string())?;
 
-    let mut execution_batches = load_batches_from_settings(state).await?;
+    let _run_guard = AUTO_SCORE_RUN_LOCK.lock().await;
     let rules_snapshot = {
         let state_guard = state.read();
         let auto_score = state_guard.auto_score.read();
         auto_score.get_rules().to_vec()
     };
+    let mut execution_batches = load_active_batches(&conn, &rules_snapshot).await?;
+    let first_new_batch = execution_batches.len();
     let mut next_rules = rules_snapshot.clone();
     let mut result = AutoScoreBackfillResult::default();
     let now = Utc::now();
@@ -1723,8 +2894,15 @@ pub async fn apply_offline_backfill(
         changed = true;
 
         for _ in 0..replay_runs {
-            let stats =
-                execute_rule(&conn, rule, &execution_batches, ExecutionMode::Backfill).await?;
+            let stats = execute_rule(
+                state,
+                &conn,
+                rule,
+                &execution_batches,
+                ExecutionMode::Backfill,
+                None,
+            )
+            .await?;
             result.applied_runs += 1;
             result.affected_students += stats.affected_students;
             result.created_events += stats.created_events;
@@ -1734,22 +2912,7 @@ pub async fn apply_offline_backfill(
                 continue;
             }
 
-            let batch = AutoScoreExecutionBatch {
-                id: Uuid::new_v4().to_string(),
-                rule_id: rule.id,
-                rule_name: rule.name.clone(),
-                run_at: now_iso(),
-                affected_students: stats.affected_students,
-                affected_student_names: stats.affected_student_names.clone(),
-                created_event_ids: stats.created_event_ids.clone(),
-                added_student_tag_ids: stats.added_student_tag_ids.clone(),
-                reward_redemption_ids: stats.reward_redemption_ids.clone(),
-                score_delta_total: stats.score_delta_total,
-                settled: stats.settled,
-                rolled_back: false,
-                rollback_at: None,
-            };
-            execution_batches.push(batch);
+            execution_batches.push(new_execution_batch(rule, &stats));
         }
 
         rule.last_executed = Some(Utc::now().to_rfc3339());
@@ -1760,7 +2923,9 @@ pub async fn apply_offline_backfill(
     }
 
     persist_rules_to_settings(state, &next_rules).await?;
-    save_batches_to_settings(state, &execution_batches).await?;
+    insert_batches(&conn, &execution_batches[first_new_batch..]).await?;
+    prune_expired_batches(state, &conn).await?;
+    publish_batches(state, &execution_batches[first_new_batch..]);
     {
         let state_guard = state.read();
         let mut auto_score = state_guard.auto_score.write();
@@ -1770,24 +2935,241 @@ pub async fn apply_offline_backfill(
     Ok(result)
 }
 
+/// 按执行时相同的筛选、触发树、冷却和每日上限计算规则会产生的动作，不写入任何数据
+pub async fn preview_rule(
+    state: &SafeAppState,
+    rule: AutoScoreRule,
+) -> Result<AutoScoreRulePreview, String> {
+    let conn = {
+        let state_guard = state.read();
+        let db_conn = state_guard.db.read().clone();
+        db_conn
+    }
+    .ok_or_else(|| "Database not connected".to_string())?;
+    build_rule_preview(&conn, rule).await
+}
+
+async fn build_rule_preview(
+    conn: &DatabaseConnection,
+    rule: AutoScoreRule,
+) -> Result<AutoScoreRulePreview, String> {
+    let rule = normalize_rule(rule)?;
+    let planned_actions = plan_actions(&rule.actions)?;
+    if planned_actions.is_empty() {
+        return Err("No executable action".to_string());
+    }
+
+    let execution_batches = load_active_batches(conn, std::slice::from_ref(&rule)).await?;
+
+    let mut preview = AutoScoreRulePreview {
+        rule_id: rule.id,
+        next_run_in_ms: check_interval_trigger(&rule),
+        has_reactive_triggers: rule
+            .triggers
+            .iter()
+            .any(|trigger| REACTIVE_TRIGGER_EVENTS.contains(&trigger.event.as_str())),
+        ..Default::default()
+    };
+
+    let candidates = resolve_rule_candidates(conn, &rule, None, true).await?;
+    preview.excluded.extend(
+        candidates
+            .excluded
+            .iter()
+            .map(|(student, reason)| AutoScorePreviewExclusion::new(student, reason, None)),
+    );
+    if candidates.matched.is_empty() {
+        return Ok(preview);
+    }
+
+    if let Some(max_runs) = rule.execution.max_runs_per_day {
+        let today_runs = today_run_count(&execution_batches, rule.id);
+        if today_runs >= max_runs {
+            let detail = format!("{} of {} runs used today", today_runs, max_runs);
+            preview
+                .excluded
+                .extend(candidates.matched.iter().map(|student| {
+                    AutoScorePreviewExclusion::new(
+                        student,
+                        "max_runs_per_day",
+                        Some(detail.clone()),
+                    )
+                }));
+            return Ok(preview);
+        }
+    }
+    preview.settle = planned_actions
+        .iter()
+        .any(|action| matches!(action, PlannedAction::SettleScore));
+
+    let student_tags_by_id = load_student_tags_by_student_id(conn).await?;
+    let mut reward_settings_by_id = HashMap::new();
+    for action in &planned_actions {
+        if let PlannedAction::RewardExchange(reward_action) = action {
+            if reward_settings_by_id.contains_key(&reward_action.reward_id) {
+                continue;
+            }
+            let reward = reward_settings::Entity::find_by_id(reward_action.reward_id)
+                .one(conn)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Reward not found: {}", reward_action.reward_id))?;
+            reward_settings_by_id.insert(reward.id, reward);
+        }
+    }
+    // 预览不扣库存，按顺序模拟每位学生兑换后的剩余库存
+    let mut remaining_stock: HashMap<i32, Option<i32>> = reward_settings_by_id
+        .values()
+        .map(|reward| (reward.id, reward.stock))
+        .collect();
+    let mut daily_score_delta_used = today_score_delta_used(&execution_batches, rule.id);
+
+    for mut student in candidates.matched {
+        if !is_student_pass_cooldown(
+            conn,
+            &execution_batches,
+            &rule,
+            student.id,
+            student.name.as_str(),
+        )
+        .await?
+        {
+            let detail = rule
+                .execution
+                .cooldown_minutes
+                .map(|minutes| format!("cooldown {} minutes", minutes));
+            preview
+                .excluded
+                .push(AutoScorePreviewExclusion::new(&student, "cooldown", detail));
+            continue;
+        }
+
+        let mut planned = AutoScorePreviewStudent {
+            student_id: student.id,
+            student_name: student.name.clone(),
+            score_before: student.score,
+            ..Default::default()
+        };
+        let mut touched = false;
+        for action in &planned_actions {
+            match action {
+                PlannedAction::AddScore(delta) => {
+                    if let Some(max_delta) = rule.execution.max_score_delta_per_day {
+                        let next = daily_score_delta_used + (*delta as i64).abs();
+                        if next > max_delta {
+                            planned.skipped_actions.push(AutoScorePreviewSkip {
+                                action: "add_score".to_string(),
+                                reason: "max_score_delta_per_day".to_string(),
+                                detail: Some(format!(
+                                    "{} of {} used today",
+                                    daily_score_delta_used, max_delta
+                                )),
+                            });
+                            continue;
+                        }
+                        daily_score_delta_used = next;
+                    }
+                    planned.score_delta += *delta as i64;
+                    student.score += delta;
+                    student.reward_points += delta;
+                    touched = true;
+                }
+                PlannedAction::AddTags(tag_names) => {
+                    let existing = student_tags_by_id.get(&student.id);
+                    for tag_name in tag_names {
+                        let attached = existing.is_some_and(|values| values.contains(tag_name));
+                        if !attached && !planned.add_tags.contains(tag_name) {
+                            planned.add_tags.push(tag_name.clone());
+                            touched = true;
+                        }
+                    }
+                }
+                PlannedAction::RewardExchange(reward_action) => {
+                    let Some(reward) = reward_settings_by_id.get(&reward_action.reward_id) else {
+                        return Err(format!("Reward not found: {}", reward_action.reward_id));
+                    };
+                    let skip_reason = if student.reward_points < reward.cost_points {
+                        Some((
+                            "insufficient_reward_points",
+                            Some(format!(
+                                "{} of {} points",
+                                student.reward_points, reward.cost_points
+                            )),
+                        ))
+                    } else if remaining_stock
+                        .get(&reward.id)
+                        .copied()
+                        .flatten()
+                        .is_some_and(|stock| stock <= 0)
+                    {
+                        Some((
+                            "reward_unavailable",
+                            Some("Reward out of stock".to_string()),
+                        ))
+                    } else {
+                        reward::ensure_redeemable(conn, reward, student.id)
+                            .await
+                            .err()
+                            .map(|error| ("reward_unavailable", Some(error)))
+                    };
+                    if let Some((reason, detail)) = skip_reason {
+                        planned.skipped_actions.push(AutoScorePreviewSkip {
+                            action: "reward_exchange".to_string(),
+                            reason: reason.to_string(),
+                            detail,
+                        });
+                        continue;
+                    }
+
+                    if let Some(Some(stock)) = remaining_stock.get_mut(&reward.id) {
+                        *stock -= 1;
+                    }
+                    student.reward_points -= reward.cost_points;
+                    planned.rewards.push(AutoScorePreviewReward {
+                        reward_id: reward.id,
+                        reward_name: reward.name.clone(),
+                        cost_points: reward.cost_points,
+                    });
+                    touched = true;
+                }
+                PlannedAction::SettleScore => {}
+            }
+        }
+
+        if !touched {
+            let detail = planned
+                .skipped_actions
+                .iter()
+                .map(|skip| skip.reason.clone())
+                .collect::<Vec<_>>()
+                .join(", ");
+            preview.excluded.push(AutoScorePreviewExclusion::new(
+                &student,
+                "no_effect",
+                (!detail.is_empty()).then_some(detail),
+            ));
+            continue;
+        }
+
+        planned.score_after = student.score;
+        preview.score_delta_total += planned.score_delta;
+        preview.students.push(planned);
+    }
+
+    Ok(preview)
+}
+
 async fn execute_rule(
+    state: &SafeAppState,
     conn: &DatabaseConnection,
     rule: &AutoScoreRule,
     execution_batches: &[AutoScoreExecutionBatch],
     mode: ExecutionMode,
+    signal: Option<&TriggerSignal>,
 ) -> Result<RuleExecutionStats, String> {
-    let mut target_students = resolve_target_students(conn, rule).await?;
-
-    if !rule.student_names.is_empty() {
-        let whitelist: HashSet<String> = rule
-            .student_names
-            .iter()
-            .map(|name| name.trim())
-            .filter(|name| !name.is_empty())
-            .map(str::to_string)
-            .collect();
-        target_students.retain(|student| whitelist.contains(&student.name));
-    }
+    let target_students = resolve_rule_candidates(conn, rule, signal, false)
+        .await?
+        .matched;
 
     if target_students.is_empty() {
         return Ok(RuleExecutionStats::default());
@@ -1795,13 +3177,7 @@ async fn execute_rule(
 
     if mode == ExecutionMode::Normal {
         if let Some(max_runs) = rule.execution.max_runs_per_day {
-            let today_runs = execution_batches
-                .iter()
-                .filter(|batch| {
-                    batch.rule_id == rule.id && !batch.rolled_back && is_same_utc_day(&batch.run_at)
-                })
-                .count() as i64;
-            if today_runs >= max_runs {
+            if today_run_count(execution_batches, rule.id) >= max_runs {
                 return Ok(RuleExecutionStats::default());
             }
         }
@@ -1814,14 +3190,12 @@ async fn execute_rule(
     let should_settle = planned_actions
         .iter()
         .any(|action| matches!(action, PlannedAction::SettleScore));
+    if should_settle {
+        // 结算会清空积分，执行前先做一次备份
+        crate::services::backup::backup_before_operation(state, "auto_score_settlement").await?;
+    }
 
-    let mut daily_score_delta_used: i64 = execution_batches
-        .iter()
-        .filter(|batch| {
-            batch.rule_id == rule.id && !batch.rolled_back && is_same_utc_day(&batch.run_at)
-        })
-        .map(|batch| batch.score_delta_total.abs())
-        .sum();
+    let mut daily_score_delta_used = today_score_delta_used(execution_batches, rule.id);
 
     let txn = conn.begin().await.map_err(|e| e.to_string())?;
     let mut stats = RuleExecutionStats::default();
@@ -1892,6 +3266,7 @@ async fn execute_rule(
                     let event = score_events::ActiveModel {
                         id: sea_orm::ActiveValue::NotSet,
                         uuid: Set(Uuid::new_v4().to_string()),
+                        student_id: Set(Some(student.id)),
                         student_name: Set(student.name.clone()),
                         reason_content: Set(build_auto_score_reason(rule.id, &rule.name, *delta)),
                         delta: Set(*delta),
@@ -1939,16 +3314,30 @@ async fn execute_rule(
                     if student.reward_points < reward.cost_points {
                         continue;
                     }
+                    if reward::ensure_redeemable(&txn, reward, student.id)
+                        .await
+                        .is_err()
+                        || !reward::take_stock(&txn, reward.id).await?
+                    {
+                        continue;
+                    }
 
                     let now = now_iso();
                     let redemption = reward_redemptions::ActiveModel {
                         id: sea_orm::ActiveValue::NotSet,
                         uuid: Set(Uuid::new_v4().to_string()),
+                        student_id: Set(Some(student.id)),
                         student_name: Set(student.name.clone()),
                         reward_id: Set(reward.id),
                         reward_name: Set(reward.name.clone()),
                         cost_points: Set(reward.cost_points),
                         redeemed_at: Set(now.clone()),
+                        status: Set(RedemptionStatus::Pending.as_str().to_string()),
+                        reviewed_at: Set(None),
+                        review_note: Set(None),
+                        cancelled_at: Set(None),
+                        cancelled_by: Set(None),
+                        cancel_reason: Set(None),
                     };
                     let inserted_redemption =
                         redemption.insert(&txn).await.map_err(|e| e.to_string())?;
@@ -1972,6 +3361,7 @@ async fn execute_rule(
         if touched {
             stats.affected_students += 1;
             stats.affected_student_names.push(student.name.clone());
+            stats.affected_student_ids.push(student.id);
         }
     }
 
@@ -2018,146 +3408,58 @@ fn plan_actions(actions: &[AutoScoreAction]) -> Result<Vec<PlannedAction>, Strin
 }
 
 async fn execute_settlement(conn: &DatabaseConnection) -> Result<(), String> {
-    let backend = conn.get_database_backend();
-    let count_stmt = Statement::from_string(
-        backend,
-        "SELECT COUNT(*) AS cnt FROM score_events WHERE settlement_id IS NULL",
-    );
-    let count_row = conn
-        .query_one(count_stmt)
-        .await
-        .map_err(|e| e.to_string())?;
-    let Some(count_row) = count_row else {
-        return Ok(());
+    let Some(repo) = SettlementRepository::from_connection(conn) else {
+        return Err("Unsupported database backend".to_string());
     };
-    let unsettled_count = try_get_i64(&count_row, "cnt").unwrap_or(0);
-    if unsettled_count <= 0 {
-        return Ok(());
+    match repo.create().await {
+        Ok(_) | Err(SettlementError::NoEventsToSettle) => Ok(()),
+        Err(e) => Err(e.to_string()),
     }
+}
 
-    let last_end_sql = match backend {
-        sea_orm::DatabaseBackend::Sqlite => {
-            "SELECT end_time AS end_time FROM settlements ORDER BY julianday(end_time) DESC LIMIT 1"
-        }
-        sea_orm::DatabaseBackend::Postgres => {
-            "SELECT end_time AS end_time FROM settlements ORDER BY end_time DESC LIMIT 1"
-        }
-        _ => "SELECT end_time AS end_time FROM settlements ORDER BY end_time DESC LIMIT 1",
-    };
-    let min_event_sql =
-        "SELECT MIN(event_time) AS min_event_time FROM score_events WHERE settlement_id IS NULL";
-
-    let last_end = conn
-        .query_one(Statement::from_string(backend, last_end_sql))
-        .await
-        .map_err(|e| e.to_string())?
-        .and_then(|row| try_get_string(&row, "end_time"));
-
-    let min_event_time = conn
-        .query_one(Statement::from_string(backend, min_event_sql))
-        .await
-        .map_err(|e| e.to_string())?
-        .and_then(|row| try_get_string(&row, "min_event_time"));
-
-    let end_time = now_iso();
-    let start_time = last_end
-        .or(min_event_time)
-        .unwrap_or_else(|| end_time.clone());
-    let created_at = end_time.clone();
-
-    match backend {
-        sea_orm::DatabaseBackend::Postgres => {
-            let insert_stmt = Statement::from_string(
-        backend,
-        format!(
-          "INSERT INTO settlements (start_time, end_time, created_at) VALUES ('{}', '{}', '{}') RETURNING id",
-          start_time.replace('\'', "''"),
-          end_time.replace('\'', "''"),
-          created_at.replace('\'', "''")
-        ),
-      );
-            let row = conn
-                .query_one(insert_stmt)
-                .await
-                .map_err(|e| e.to_string())?
-                .ok_or_else(|| "Failed to create settlement".to_string())?;
-            let settlement_id = try_get_i32(&row, "id")
-                .ok_or_else(|| "Failed to read settlement id".to_string())?;
-
-            let update_events_stmt = Statement::from_string(
-                backend,
-                format!(
-                    "UPDATE score_events SET settlement_id = {} WHERE settlement_id IS NULL",
-                    settlement_id
-                ),
-            );
-            conn.execute(update_events_stmt)
-                .await
-                .map_err(|e| e.to_string())?;
+#[derive(Debug, Default)]
+struct TriggerEvalContext {
+    student_tags_by_id: HashMap<i32, HashSet<String>>,
+    sql_refs_by_query: HashMap<String, StudentRefs>,
+    /// 即时触发条件命中的学生，键见 signal_match_key
+    signal_matches: HashMap<String, HashSet<i32>>,
+    assume_signals: bool,
+    /// 积分记录统计，键见 event_stats_key
+    event_stats: HashMap<String, EventStats>,
+    leaderboard: HashMap<i32, LeaderboardPosition>,
+    now: DateTime<Local>,
+}
 
-            let update_students_stmt = Statement::from_string(
-                backend,
-                format!(
-                    "UPDATE students SET score = 0, updated_at = '{}' ",
-                    end_time.replace('\'', "''")
-                ),
-            );
-            conn.execute(update_students_stmt)
-                .await
-                .map_err(|e| e.to_string())?;
-        }
-        _ => {
-            let insert_stmt = Statement::from_string(
-                backend,
-                format!(
-          "INSERT INTO settlements (start_time, end_time, created_at) VALUES ('{}', '{}', '{}')",
-          start_time.replace('\'', "''"),
-          end_time.replace('\'', "''"),
-          created_at.replace('\'', "''")
-        ),
-            );
-            conn.execute(insert_stmt).await.map_err(|e| e.to_string())?;
+#[derive(Debug, Clone, Default)]
+struct EventStat {
+    count: i64,
+    delta_sum: i64,
+    last_event_time: Option<String>,
+}
 
-            let id_stmt = Statement::from_string(backend, "SELECT last_insert_rowid() AS id");
-            let row = conn
-                .query_one(id_stmt)
-                .await
-                .map_err(|e| e.to_string())?
-                .ok_or_else(|| "Failed to read settlement id".to_string())?;
-            let settlement_id = try_get_i32(&row, "id")
-                .ok_or_else(|| "Failed to read settlement id".to_string())?;
-
-            let update_events_stmt = Statement::from_string(
-                backend,
-                format!(
-                    "UPDATE score_events SET settlement_id = {} WHERE settlement_id IS NULL",
-                    settlement_id
-                ),
-            );
-            conn.execute(update_events_stmt)
-                .await
-                .map_err(|e| e.to_string())?;
+#[derive(Debug, Default)]
+struct EventStats {
+    by_id: HashMap<i32, EventStat>,
+    /// 旧记录没有 student_id，按姓名统计
+    by_name: HashMap<String, EventStat>,
+}
 
-            let update_students_stmt = Statement::from_string(
-                backend,
-                format!(
-                    "UPDATE students SET score = 0, updated_at = '{}'",
-                    end_time.replace('\'', "''")
-                ),
-            );
-            conn.execute(update_students_stmt)
-                .await
-                .map_err(|e| e.to_string())?;
+impl EventStats {
+    fn for_student(&self, student: &students::Model) -> EventStat {
+        let mut stat = self.by_id.get(&student.id).cloned().unwrap_or_default();
+        if let Some(by_name) = self.by_name.get(&student.name) {
+            stat.count += by_name.count;
+            stat.delta_sum += by_name.delta_sum;
+            stat.last_event_time = stat.last_event_time.max(by_name.last_event_time.clone());
         }
+        stat
     }
-
-    Ok(())
 }
 
-#[derive(Debug, Default)]
-struct TriggerEvalContext {
-    student_tags_by_id: HashMap<i32, HashSet<String>>,
-    sql_refs_by_query: HashMap<String, StudentRefs>,
+#[derive(Debug, Clone, Copy)]
+struct LeaderboardPosition {
+    rank: usize,
+    total: usize,
 }
 
 fn collect_sql_queries_from_tree(
@@ -2322,6 +3624,16 @@ fn evaluate_trigger_tree_for_student(
                     .and_then(|value| value.trim().parse::<i32>().ok())
                     .map(|threshold| student.score < threshold)
                     .unwrap_or(false),
+                event if CONDITION_TRIGGER_FIELDS.contains(&event) => {
+                    evaluate_condition_trigger(&trigger, student, ctx)?
+                }
+                event if REACTIVE_TRIGGER_EVENTS.contains(&event) => {
+                    ctx.assume_signals
+                        || ctx
+                            .signal_matches
+                            .get(&signal_match_key(&trigger))
+                            .is_some_and(|student_ids| student_ids.contains(&student.id))
+                }
                 _ => false,
             };
             Ok(matched)
@@ -2330,20 +3642,68 @@ fn evaluate_trigger_tree_for_student(
     }
 }
 
-async fn resolve_target_students(
+/// 候选学生解析结果，excluded 记录被名单、筛选或触发树排除的学生及原因
+#[derive(Debug, Default)]
+struct RuleCandidates {
+    matched: Vec<students::Model>,
+    excluded: Vec<(students::Model, &'static str)>,
+}
+
+/// assume_signals 为 true 时即时触发条件视为命中，供预览使用
+async fn resolve_rule_candidates(
     conn: &DatabaseConnection,
     rule: &AutoScoreRule,
-) -> Result<Vec<students::Model>, String> {
-    let all_students = students::Entity::find()
+    signal: Option<&TriggerSignal>,
+    assume_signals: bool,
+) -> Result<RuleCandidates, String> {
+    let mut candidates = students::Entity::find()
         .all(conn)
         .await
         .map_err(|e| e.to_string())?;
-    if all_students.is_empty() {
-        return Ok(vec![]);
+    // 排名按全体学生计算，需在缩小候选范围之前
+    let leaderboard = leaderboard_positions(&candidates);
+    if let Some(signal) = signal {
+        // 即时触发只评估本次写入涉及的学生
+        let student_ids = signal.student_ids();
+        candidates.retain(|student| student_ids.contains(&student.id));
+    }
+
+    let mut excluded = Vec::new();
+    if !rule.student_names.is_empty() {
+        let whitelist: HashSet<String> = rule
+            .student_names
+            .iter()
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        exclude_students(&mut candidates, &mut excluded, "student_names", |student| {
+            whitelist.contains(&student.name)
+        });
     }
 
-    let fallback_tree = build_trigger_tree_from_triggers(&rule.triggers);
-    let trigger_tree = rule.trigger_tree.as_ref().unwrap_or(&fallback_tree);
+    let candidates = apply_rule_filters(conn, &rule.filters, candidates, &mut excluded).await?;
+    if candidates.is_empty() {
+        return Ok(RuleCandidates {
+            matched: Vec::new(),
+            excluded,
+        });
+    }
+
+    let fallback_tree;
+    let trigger_tree = match rule.trigger_tree.as_ref() {
+        Some(tree) => tree,
+        None if rule.triggers.is_empty() => {
+            return Ok(RuleCandidates {
+                matched: candidates,
+                excluded,
+            })
+        }
+        None => {
+            fallback_tree = build_trigger_tree_from_triggers(&rule.triggers);
+            &fallback_tree
+        }
+    };
 
     let mut sql_queries = Vec::new();
     collect_sql_queries_from_tree(trigger_tree, &mut sql_queries)?;
@@ -2355,65 +3715,682 @@ async fn resolve_target_students(
         sql_refs_by_query.insert(sql, refs);
     }
 
+    let signal_matches = match signal {
+        Some(signal) => collect_signal_matches(conn, trigger_tree, signal).await?,
+        None => HashMap::new(),
+    };
+
     let ctx = TriggerEvalContext {
         student_tags_by_id: load_student_tags_by_student_id(conn).await?,
         sql_refs_by_query,
+        signal_matches,
+        assume_signals,
+        event_stats: collect_event_stats(conn, trigger_tree).await?,
+        leaderboard,
+        now: Local::now(),
     };
 
     let mut matched = Vec::new();
-    for student in all_students {
+    for student in candidates {
         if evaluate_trigger_tree_for_student(trigger_tree, &student, rule, &ctx)? {
             matched.push(student);
+        } else {
+            excluded.push((student, "trigger"));
         }
     }
 
-    Ok(matched)
+    Ok(RuleCandidates { matched, excluded })
 }
 
-async fn is_student_pass_cooldown<C: ConnectionTrait>(
-    conn: &C,
-    execution_batches: &[AutoScoreExecutionBatch],
-    rule: &AutoScoreRule,
-    student_id: i32,
-    student_name: &str,
-) -> Result<bool, String> {
-    let Some(cooldown_minutes) = rule.execution.cooldown_minutes else {
-        return Ok(true);
-    };
-    if cooldown_minutes <= 0 {
-        return Ok(true);
+fn exclude_students(
+    candidates: &mut Vec<students::Model>,
+    excluded: &mut Vec<(students::Model, &'static str)>,
+    reason: &'static str,
+    keep: impl Fn(&students::Model) -> bool,
+) {
+    let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(candidates)
+        .into_iter()
+        .partition(|student| keep(student));
+    *candidates = kept;
+    excluded.extend(removed.into_iter().map(|student| (student, reason)));
+}
+
+fn signal_match_key(trigger: &AutoScoreTrigger) -> String {
+    format!(
+        "{}:{}",
+        trigger.event,
+        trigger.value.as_deref().unwrap_or_default()
+    )
+}
+
+/// 对树中每个即时触发条件，算出本次信号命中的学生
+async fn collect_signal_matches(
+    conn: &DatabaseConnection,
+    tree: &JsonValue,
+    signal: &TriggerSignal,
+) -> Result<HashMap<String, HashSet<i32>>, String> {
+    let mut matches = HashMap::new();
+    for trigger in collect_triggers_from_tree(tree)? {
+        if !signal.supports(&trigger.event) {
+            continue;
+        }
+        let key = signal_match_key(&trigger);
+        if matches.contains_key(&key) {
+            continue;
+        }
+        let student_ids = match_signal_trigger(conn, &trigger, signal).await?;
+        matches.insert(key, student_ids);
     }
+    Ok(matches)
+}
 
-    let _ = student_id;
-    let cutoff = Utc::now() - chrono::Duration::minutes(cooldown_minutes);
-    for batch in execution_batches
-        .iter()
-        .filter(|batch| batch.rule_id == rule.id && !batch.rolled_back)
-    {
-        let run_at = DateTime::parse_from_rfc3339(batch.run_at.as_str())
-            .map(|value| value.with_timezone(&Utc))
-            .unwrap_or_else(|_| Utc::now() - chrono::Duration::days(3650));
-        if run_at >= cutoff
-            && batch
-                .affected_student_names
+async fn match_signal_trigger(
+    conn: &DatabaseConnection,
+    trigger: &AutoScoreTrigger,
+    signal: &TriggerSignal,
+) -> Result<HashSet<i32>, String> {
+    match (trigger.event.as_str(), signal) {
+        ("score_event_created", TriggerSignal::ScoreEvents(events)) => {
+            let value = parse_score_event_trigger_value(trigger.value.as_deref())?;
+            match_score_event_trigger(conn, &value, events).await
+        }
+        ("score_crossed_above", TriggerSignal::ScoreEvents(events)) => {
+            let Some(threshold) = parse_trigger_threshold(trigger) else {
+                return Ok(HashSet::new());
+            };
+            Ok(events
                 .iter()
-                .any(|name| name == student_name)
-        {
-            return Ok(false);
+                .filter(|event| event.val_prev < threshold && event.val_curr >= threshold)
+                .filter_map(|event| event.student_id)
+                .collect())
+        }
+        ("score_crossed_below", TriggerSignal::ScoreEvents(events)) => {
+            let Some(threshold) = parse_trigger_threshold(trigger) else {
+                return Ok(HashSet::new());
+            };
+            Ok(events
+                .iter()
+                .filter(|event| event.val_prev > threshold && event.val_curr <= threshold)
+                .filter_map(|event| event.student_id)
+                .collect())
+        }
+        ("reward_redeemed", TriggerSignal::Redemption(redemption)) => {
+            let reward_ids = parse_reward_id_values(trigger.value.as_deref()).unwrap_or_default();
+            if !reward_ids.is_empty() && !reward_ids.contains(&redemption.reward_id) {
+                return Ok(HashSet::new());
+            }
+            Ok(redemption.student_id.into_iter().collect())
+        }
+        (
+            "tag_attached",
+            TriggerSignal::TagsAttached {
+                student_id,
+                tag_ids,
+            },
+        ) => {
+            let required_tags = parse_tag_values(trigger.value.as_deref());
+            let attached_tags = tags::Entity::find()
+                .filter(tags::Column::Id.is_in(tag_ids.clone()))
+                .all(conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            let matched = attached_tags
+                .iter()
+                .any(|tag| required_tags.contains(&tag.name));
+            Ok(if matched {
+                HashSet::from([*student_id])
+            } else {
+                HashSet::new()
+            })
         }
+        _ => Ok(HashSet::new()),
     }
-
-    let prefix = format!("{}#{}:", AUTO_SCORE_REASON_PREFIX, rule.id);
-    let since = cutoff.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-
-    let exists = score_events::Entity::find()
-        .filter(score_events::Column::StudentName.eq(student_name.to_string()))
-        .filter(score_events::Column::ReasonContent.like(format!("{}%", prefix)))
-        .filter(score_events::Column::EventTime.gte(since))
-        .one(conn)
-        .await
-        .map_err(|e| e.to_string())?;
-    Ok(exists.is_none())
+}
+
+/// 并列同分取相同名次，名次为分数更高的人数加一
+fn leaderboard_positions(students: &[students::Model]) -> HashMap<i32, LeaderboardPosition> {
+    let mut scores: Vec<i32> = students.iter().map(|student| student.score).collect();
+    scores.sort_unstable_by(|a, b| b.cmp(a));
+    students
+        .iter()
+        .map(|student| {
+            let rank = 1 + scores.partition_point(|score| *score > student.score);
+            (
+                student.id,
+                LeaderboardPosition {
+                    rank,
+                    total: scores.len(),
+                },
+            )
+        })
+        .collect()
+}
+
+fn event_stats_key(condition: &ConditionTriggerValue) -> String {
+    json!({
+        "days": condition.days,
+        "reasons": condition.reasons,
+        "categories": condition.categories,
+    })
+    .to_string()
+}
+
+/// 树中积分记录统计类条件按统计范围去重后各查询一次
+async fn collect_event_stats(
+    conn: &DatabaseConnection,
+    tree: &JsonValue,
+) -> Result<HashMap<String, EventStats>, String> {
+    let mut stats = HashMap::new();
+    for trigger in collect_triggers_from_tree(tree)? {
+        if !matches!(
+            trigger.event.as_str(),
+            "event_count" | "event_delta_sum" | "days_since_last_event"
+        ) {
+            continue;
+        }
+        let condition = parse_condition_trigger_value(trigger.value.as_deref())?;
+        let key = event_stats_key(&condition);
+        if stats.contains_key(&key) {
+            continue;
+        }
+        let value = query_event_stats(conn, &condition).await?;
+        stats.insert(key, value);
+    }
+    Ok(stats)
+}
+
+/// 未指定理由或分类时不统计自动加分写入的记录
+async fn query_event_stats(
+    conn: &DatabaseConnection,
+    condition: &ConditionTriggerValue,
+) -> Result<EventStats, String> {
+    let reason_contents =
+        resolve_score_event_reasons(conn, &condition.reasons, &condition.categories).await?;
+    let mut query = score_events::Entity::find()
+        .select_only()
+        .column(score_events::Column::StudentId)
+        .column(score_events::Column::StudentName)
+        .column_as(Expr::col(score_events::Column::Id).count(), "event_count")
+        .column_as(Expr::col(score_events::Column::Delta).sum(), "delta_sum")
+        .column_as(
+            Expr::col(score_events::Column::EventTime).max(),
+            "last_event_time",
+        );
+    if let Some(days) = condition.days {
+        let since = (Utc::now() - chrono::Duration::days(days))
+            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+            .to_string();
+        query = query.filter(score_events::Column::EventTime.gte(since));
+    }
+    query = match reason_contents {
+        Some(contents) => query.filter(score_events::Column::ReasonContent.is_in(contents)),
+        None => query.filter(
+            score_events::Column::ReasonContent.not_like(format!("{}#%", AUTO_SCORE_REASON_PREFIX)),
+        ),
+    };
+    let rows = query
+        .group_by(score_events::Column::StudentId)
+        .group_by(score_events::Column::StudentName)
+        .into_tuple::<(Option<i32>, String, i64, Option<i64>, Option<String>)>()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut stats = EventStats::default();
+    for (student_id, student_name, count, delta_sum, last_event_time) in rows {
+        let stat = match student_id {
+            Some(id) => stats.by_id.entry(id).or_default(),
+            None => stats.by_name.entry(student_name).or_default(),
+        };
+        stat.count += count;
+        stat.delta_sum += delta_sum.unwrap_or(0);
+        stat.last_event_time = stat.last_event_time.take().max(last_event_time);
+    }
+    Ok(stats)
+}
+
+fn evaluate_condition_trigger(
+    trigger: &AutoScoreTrigger,
+    student: &students::Model,
+    ctx: &TriggerEvalContext,
+) -> Result<bool, String> {
+    let condition = parse_condition_trigger_value(trigger.value.as_deref())?;
+    let matched = match trigger.event.as_str() {
+        "reward_points" => compare_condition_number(&condition, student.reward_points as f64),
+        "student_group" => {
+            let group = student
+                .group_name
+                .as_deref()
+                .map(str::trim)
+                .unwrap_or_default();
+            compare_condition_set(&condition, |value| value.as_str() == Some(group))
+        }
+        event @ ("event_count" | "event_delta_sum" | "days_since_last_event") => {
+            let stat = ctx
+                .event_stats
+                .get(&event_stats_key(&condition))
+                .map(|stats| stats.for_student(student))
+                .unwrap_or_default();
+            let metric = match event {
+                "event_count" => stat.count as f64,
+                "event_delta_sum" => stat.delta_sum as f64,
+                // 没有记录视为无穷久以前，只满足大于类比较
+                _ => stat
+                    .last_event_time
+                    .as_deref()
+                    .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+                    .map(|time| {
+                        (ctx.now.with_timezone(&Utc) - time.with_timezone(&Utc)).num_days() as f64
+                    })
+                    .unwrap_or(f64::INFINITY),
+            };
+            compare_condition_number(&condition, metric)
+        }
+        "leaderboard_rank" => ctx
+            .leaderboard
+            .get(&student.id)
+            .is_some_and(|position| compare_condition_number(&condition, position.rank as f64)),
+        // 百分位按“前百分之几”计算，第一名在 10 人中为 10
+        "leaderboard_percentile" => ctx.leaderboard.get(&student.id).is_some_and(|position| {
+            let percentile = position.rank as f64 * 100.0 / position.total.max(1) as f64;
+            compare_condition_number(&condition, percentile)
+        }),
+        "extra_json" => compare_extra_json(&condition, student.extra_json.as_deref()),
+        "weekday" => {
+            let weekday = ctx.now.weekday().number_from_monday() as i64;
+            compare_condition_set(&condition, |value| value.as_i64() == Some(weekday))
+        }
+        "time_of_day" => compare_time_of_day(&condition, &ctx.now),
+        _ => false,
+    };
+    Ok(matched)
+}
+
+fn compare_condition_number(condition: &ConditionTriggerValue, actual: f64) -> bool {
+    let value = |index: usize| condition.values.get(index).and_then(condition_number);
+    match (condition.operator.as_str(), value(0), value(1)) {
+        ("equal", Some(expected), _) => actual == expected,
+        ("not_equal", Some(expected), _) => actual != expected,
+        ("greater", Some(expected), _) => actual > expected,
+        ("greater_or_equal", Some(expected), _) => actual >= expected,
+        ("less", Some(expected), _) => actual < expected,
+        ("less_or_equal", Some(expected), _) => actual <= expected,
+        ("between", Some(min), Some(max)) => actual >= min && actual <= max,
+        ("not_between", Some(min), Some(max)) => actual < min || actual > max,
+        _ => false,
+    }
+}
+
+fn compare_condition_set(
+    condition: &ConditionTriggerValue,
+    is_match: impl Fn(&JsonValue) -> bool,
+) -> bool {
+    let contained = condition.values.iter().any(is_match);
+    match condition.operator.as_str() {
+        "in" => contained,
+        "not_in" => !contained,
+        _ => false,
+    }
+}
+
+/// path 用点号分隔，数组下标写成数字；字段缺失时只满足 not_equal 与 not_in
+fn compare_extra_json(condition: &ConditionTriggerValue, extra_json: Option<&str>) -> bool {
+    let root = extra_json.and_then(|raw| serde_json::from_str::<JsonValue>(raw).ok());
+    let actual = root.as_ref().and_then(|root| {
+        condition
+            .path
+            .as_deref()?
+            .split('.')
+            .try_fold(root, |current, segment| match current {
+                JsonValue::Object(map) => map.get(segment.trim()),
+                JsonValue::Array(items) => items.get(segment.trim().parse::<usize>().ok()?),
+                _ => None,
+            })
+    });
+    let Some(actual) = actual.filter(|value| !value.is_null()) else {
+        return matches!(condition.operator.as_str(), "not_equal" | "not_in");
+    };
+
+    let scalar_eq =
+        |expected: &JsonValue| match (condition_number(actual), condition_number(expected)) {
+            (Some(left), Some(right)) => left == right,
+            _ => json_scalar_text(actual) == json_scalar_text(expected),
+        };
+    match condition.operator.as_str() {
+        "equal" => condition.values.first().is_some_and(scalar_eq),
+        "not_equal" => !condition.values.first().is_some_and(scalar_eq),
+        "in" | "not_in" => compare_condition_set(condition, scalar_eq),
+        _ => condition_number(actual)
+            .is_some_and(|number| compare_condition_number(condition, number)),
+    }
+}
+
+fn json_scalar_text(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(raw) => Some(raw.trim().to_string()),
+        JsonValue::Number(number) => Some(number.to_string()),
+        JsonValue::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// 按本地时间精确到分钟比较；起点晚于终点的区间视为跨零点
+fn compare_time_of_day(condition: &ConditionTriggerValue, now: &DateTime<Local>) -> bool {
+    let Some(current) = chrono::NaiveTime::from_hms_opt(now.hour(), now.minute(), 0) else {
+        return false;
+    };
+    let value = |index: usize| condition.values.get(index).and_then(parse_time_of_day);
+    let in_range = |start: chrono::NaiveTime, end: chrono::NaiveTime| {
+        if start <= end {
+            current >= start && current <= end
+        } else {
+            current >= start || current <= end
+        }
+    };
+    match (condition.operator.as_str(), value(0), value(1)) {
+        ("between", Some(start), Some(end)) => in_range(start, end),
+        ("not_between", Some(start), Some(end)) => !in_range(start, end),
+        ("greater_or_equal", Some(start), _) => current >= start,
+        ("less", Some(end), _) => current < end,
+        _ => false,
+    }
+}
+
+fn parse_trigger_threshold(trigger: &AutoScoreTrigger) -> Option<i32> {
+    trigger
+        .value
+        .as_deref()
+        .and_then(|value| value.trim().parse::<i32>().ok())
+}
+
+/// 新记录命中理由或分类即触发；设置了 minCount 时只在本次写入让窗口内累计数跨过阈值时触发
+async fn match_score_event_trigger(
+    conn: &DatabaseConnection,
+    value: &ScoreEventTriggerValue,
+    events: &[score_events::Model],
+) -> Result<HashSet<i32>, String> {
+    let reason_contents =
+        resolve_score_event_reasons(conn, &value.reasons, &value.categories).await?;
+    let auto_reason_prefix = format!("{}#", AUTO_SCORE_REASON_PREFIX);
+    let is_matched_reason = |reason: &str| match &reason_contents {
+        Some(contents) => contents.contains(reason.trim()),
+        None => !reason.starts_with(&auto_reason_prefix),
+    };
+
+    let mut new_counts: HashMap<i32, i64> = HashMap::new();
+    for event in events {
+        let Some(student_id) = event.student_id else {
+            continue;
+        };
+        if is_matched_reason(&event.reason_content) {
+            *new_counts.entry(student_id).or_insert(0) += 1;
+        }
+    }
+
+    let min_count = value.min_count.unwrap_or(1);
+    if min_count <= 1 || new_counts.is_empty() {
+        return Ok(new_counts.into_keys().collect());
+    }
+    let Some(since) = score_event_window_start(value) else {
+        return Ok(HashSet::new());
+    };
+
+    let mut query = score_events::Entity::find()
+        .select_only()
+        .column(score_events::Column::StudentId)
+        .column_as(Expr::col(score_events::Column::Id).count(), "event_count")
+        .filter(score_events::Column::StudentId.is_in(new_counts.keys().copied()))
+        .filter(score_events::Column::EventTime.gte(since));
+    query = match &reason_contents {
+        Some(contents) => {
+            query.filter(score_events::Column::ReasonContent.is_in(contents.iter().cloned()))
+        }
+        None => query.filter(
+            score_events::Column::ReasonContent.not_like(format!("{}%", auto_reason_prefix)),
+        ),
+    };
+    let rows = query
+        .group_by(score_events::Column::StudentId)
+        .into_tuple::<(Option<i32>, i64)>()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut matched = HashSet::new();
+    for (student_id, count_after) in rows {
+        let Some(student_id) = student_id else {
+            continue;
+        };
+        let count_before = count_after - new_counts.get(&student_id).copied().unwrap_or(0);
+        if count_before < min_count && count_after >= min_count {
+            matched.insert(student_id);
+        }
+    }
+    Ok(matched)
+}
+
+/// 理由与分类展开成理由文本集合，两者都为空时返回 None 表示任意理由
+async fn resolve_score_event_reasons(
+    conn: &DatabaseConnection,
+    reason_names: &[String],
+    categories: &[String],
+) -> Result<Option<HashSet<String>>, String> {
+    if reason_names.is_empty() && categories.is_empty() {
+        return Ok(None);
+    }
+
+    let mut contents: HashSet<String> = reason_names.iter().cloned().collect();
+    if !categories.is_empty() {
+        let category_reasons = reasons::Entity::find()
+            .filter(reasons::Column::Category.is_in(categories.to_vec()))
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        contents.extend(
+            category_reasons
+                .into_iter()
+                .map(|reason| reason.content.trim().to_string()),
+        );
+    }
+    Ok(Some(contents))
+}
+
+/// 窗口起点：windowDays 为滚动天数，period 按本地时间的今天、本周、本月计算
+fn score_event_window_start(value: &ScoreEventTriggerValue) -> Option<String> {
+    let start = match (value.window_days, value.period.as_deref()) {
+        (Some(days), _) => Utc::now() - chrono::Duration::days(days),
+        (None, Some(period)) => {
+            let today = Local::now().date_naive();
+            let date = match period {
+                "today" => today,
+                "week" => {
+                    today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64)
+                }
+                "month" => today.with_day(1)?,
+                _ => return None,
+            };
+            Local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()?
+                .with_timezone(&Utc)
+        }
+        (None, None) => return None,
+    };
+    Some(start.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+}
+
+/// 触发树之前的前置筛选：小组、年级、积分区间和近期积分记录数
+async fn apply_rule_filters(
+    conn: &DatabaseConnection,
+    filters: &AutoScoreFilterConfig,
+    mut candidates: Vec<students::Model>,
+    excluded: &mut Vec<(students::Model, &'static str)>,
+) -> Result<Vec<students::Model>, String> {
+    if !filters.groups.is_empty() {
+        exclude_students(&mut candidates, excluded, "group", |student| {
+            student
+                .group_name
+                .as_deref()
+                .map(str::trim)
+                .is_some_and(|group| filters.groups.iter().any(|value| value == group))
+        });
+    }
+    if !filters.grades.is_empty() {
+        exclude_students(&mut candidates, excluded, "grade", |student| {
+            student_grade(student).is_some_and(|grade| filters.grades.contains(&grade))
+        });
+    }
+    if let Some(min_score) = filters.min_score {
+        exclude_students(&mut candidates, excluded, "min_score", |student| {
+            student.score >= min_score
+        });
+    }
+    if let Some(max_score) = filters.max_score {
+        exclude_students(&mut candidates, excluded, "max_score", |student| {
+            student.score <= max_score
+        });
+    }
+
+    if let Some(days) = filters.recent_event_days {
+        if candidates.is_empty() {
+            return Ok(candidates);
+        }
+        // 只填天数时表示近期至少有一条记录
+        let min_count = filters.min_recent_event_count.unwrap_or(1);
+        let counts = count_recent_events(conn, days).await?;
+        exclude_students(&mut candidates, excluded, "recent_events", |student| {
+            counts.for_student(student) >= min_count
+        });
+    }
+
+    Ok(candidates)
+}
+
+/// 年级存放在学生 extra_json 的 grade 字段，数字与字符串均可
+fn student_grade(student: &students::Model) -> Option<String> {
+    let extra = serde_json::from_str::<JsonValue>(student.extra_json.as_deref()?).ok()?;
+    let grade = match extra.get("grade")? {
+        JsonValue::String(value) => value.trim().to_string(),
+        JsonValue::Number(value) => value.to_string(),
+        _ => return None,
+    };
+    (!grade.is_empty()).then_some(grade)
+}
+
+#[derive(Debug, Default)]
+struct RecentEventCounts {
+    by_id: HashMap<i32, i64>,
+    /// 旧记录没有 student_id，按姓名计数
+    by_name: HashMap<String, i64>,
+}
+
+impl RecentEventCounts {
+    fn for_student(&self, student: &students::Model) -> i64 {
+        self.by_id.get(&student.id).copied().unwrap_or(0)
+            + self.by_name.get(&student.name).copied().unwrap_or(0)
+    }
+}
+
+/// 统计最近 N 天的积分记录数，不含自动加分自己写入的记录
+async fn count_recent_events(
+    conn: &DatabaseConnection,
+    days: i64,
+) -> Result<RecentEventCounts, String> {
+    let since = (Utc::now() - chrono::Duration::days(days))
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+    let rows = score_events::Entity::find()
+        .select_only()
+        .column(score_events::Column::StudentId)
+        .column(score_events::Column::StudentName)
+        .column_as(Expr::col(score_events::Column::Id).count(), "event_count")
+        .filter(score_events::Column::EventTime.gte(since))
+        .filter(
+            score_events::Column::ReasonContent.not_like(format!("{}#%", AUTO_SCORE_REASON_PREFIX)),
+        )
+        .group_by(score_events::Column::StudentId)
+        .group_by(score_events::Column::StudentName)
+        .into_tuple::<(Option<i32>, String, i64)>()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut counts = RecentEventCounts::default();
+    for (student_id, student_name, count) in rows {
+        match student_id {
+            Some(id) => *counts.by_id.entry(id).or_insert(0) += count,
+            None => *counts.by_name.entry(student_name).or_insert(0) += count,
+        }
+    }
+    Ok(counts)
+}
+
+async fn is_student_pass_cooldown<C: ConnectionTrait>(
+    conn: &C,
+    execution_batches: &[AutoScoreExecutionBatch],
+    rule: &AutoScoreRule,
+    student_id: i32,
+    student_name: &str,
+) -> Result<bool, String> {
+    let Some(cooldown_minutes) = rule.execution.cooldown_minutes else {
+        return Ok(true);
+    };
+    if cooldown_minutes <= 0 {
+        return Ok(true);
+    }
+
+    let cutoff = Utc::now() - chrono::Duration::minutes(cooldown_minutes);
+    for batch in execution_batches
+        .iter()
+        .filter(|batch| batch.rule_id == rule.id && !batch.rolled_back)
+    {
+        let run_at = DateTime::parse_from_rfc3339(batch.run_at.as_str())
+            .map(|value| value.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now() - chrono::Duration::days(3650));
+        // 旧批次只记录了姓名，没有 id 时按姓名判断
+        let affected = if batch.affected_student_ids.is_empty() {
+            batch
+                .affected_student_names
+                .iter()
+                .any(|name| name == student_name)
+        } else {
+            batch.affected_student_ids.contains(&student_id)
+        };
+        if run_at >= cutoff && affected {
+            return Ok(false);
+        }
+    }
+
+    let prefix = format!("{}#{}:", AUTO_SCORE_REASON_PREFIX, rule.id);
+    let since = cutoff.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+
+    let exists = score_events::Entity::find()
+        .filter(score_events::Column::StudentId.eq(student_id))
+        .filter(score_events::Column::ReasonContent.like(format!("{}%", prefix)))
+        .filter(score_events::Column::EventTime.gte(since))
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(exists.is_none())
+}
+
+fn today_run_count(execution_batches: &[AutoScoreExecutionBatch], rule_id: i32) -> i64 {
+    execution_batches
+        .iter()
+        .filter(|batch| {
+            batch.rule_id == rule_id && !batch.rolled_back && is_same_utc_day(&batch.run_at)
+        })
+        .count() as i64
+}
+
+fn today_score_delta_used(execution_batches: &[AutoScoreExecutionBatch], rule_id: i32) -> i64 {
+    execution_batches
+        .iter()
+        .filter(|batch| {
+            batch.rule_id == rule_id && !batch.rolled_back && is_same_utc_day(&batch.run_at)
+        })
+        .map(|batch| batch.score_delta_total.abs())
+        .sum()
 }
 
 fn is_same_utc_day(timestamp: &str) -> bool {
@@ -2465,21 +4442,6 @@ fn try_get_i32(row: &sea_orm::QueryResult, column: &str) -> Option<i32> {
         })
 }
 
-fn try_get_i64(row: &sea_orm::QueryResult, column: &str) -> Option<i64> {
-    row.try_get::<i64>("", column)
-        .ok()
-        .or_else(|| {
-            row.try_get::<i32>("", column)
-                .ok()
-                .map(|value| value as i64)
-        })
-        .or_else(|| {
-            row.try_get::<String>("", column)
-                .ok()
-                .and_then(|value| value.trim().parse::<i64>().ok())
-        })
-}
-
 fn try_get_string(row: &sea_orm::QueryResult, column: &str) -> Option<String> {
     row.try_get::<String>("", column).ok().and_then(|value| {
         let trimmed = value.trim().to_string();
@@ -2565,3 +4527,962 @@ fn build_auto_score_reason(rule_id: i32, rule_name: &str, delta: i32) -> String
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::connection::memory_sqlite_connection;
+    use crate::db::{run_migration, DatabaseType};
+    use sea_orm::{ActiveModelTrait, Set};
+
+    fn student(
+        id: i32,
+        group: Option<&str>,
+        score: i32,
+        extra_json: Option<&str>,
+    ) -> students::Model {
+        students::Model {
+            id,
+            uuid: format!("00000000-0000-4000-8000-{:012}", id),
+            name: format!("学生{}", id),
+            group_name: group.map(str::to_string),
+            score,
+            reward_points: score,
+            tags: "[]".to_string(),
+            extra_json: extra_json.map(str::to_string),
+            created_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: "2024-01-01T00:00:00.000Z".to_string(),
+        }
+    }
+
+    async fn setup() -> DatabaseConnection {
+        let conn = memory_sqlite_connection().await;
+        run_migration(&conn, DatabaseType::SQLite).await.unwrap();
+        conn
+    }
+
+    async fn insert_event(
+        conn: &DatabaseConnection,
+        student_id: Option<i32>,
+        student_name: &str,
+        reason: &str,
+        days_ago: i64,
+    ) {
+        score_events::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            uuid: Set(Uuid::new_v4().to_string()),
+            student_id: Set(student_id),
+            student_name: Set(student_name.to_string()),
+            reason_content: Set(reason.to_string()),
+            delta: Set(1),
+            val_prev: Set(0),
+            val_curr: Set(1),
+            event_time: Set((Utc::now() - chrono::Duration::days(days_ago))
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string()),
+            settlement_id: Set(None),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    async fn run_filters(
+        conn: &DatabaseConnection,
+        filters: &AutoScoreFilterConfig,
+        candidates: Vec<students::Model>,
+    ) -> (Vec<i32>, Vec<(i32, &'static str)>) {
+        let mut excluded = Vec::new();
+        let kept = apply_rule_filters(conn, filters, candidates, &mut excluded)
+            .await
+            .unwrap();
+        (
+            kept.iter().map(|student| student.id).collect(),
+            excluded
+                .iter()
+                .map(|(student, reason)| (student.id, *reason))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn rule_filters_on_student_fields() {
+        let conn = setup().await;
+        let candidates = vec![
+            student(1, Some(" 一组 "), 5, Some(r#"{"grade":"7"}"#)),
+            student(2, Some("二组"), 10, Some(r#"{"grade":7}"#)),
+            student(3, None, 15, Some(r#"{"grade":""}"#)),
+            student(4, Some("一组"), 10, None),
+        ];
+        let cases: Vec<(&str, AutoScoreFilterConfig, Vec<i32>, Vec<(i32, &str)>)> = vec![
+            (
+                "empty",
+                AutoScoreFilterConfig::default(),
+                vec![1, 2, 3, 4],
+                vec![],
+            ),
+            (
+                "group is trimmed",
+                AutoScoreFilterConfig {
+                    groups: vec!["一组".to_string()],
+                    ..Default::default()
+                },
+                vec![1, 4],
+                vec![(2, "group"), (3, "group")],
+            ),
+            (
+                "grade as string or number",
+                AutoScoreFilterConfig {
+                    grades: vec!["7".to_string()],
+                    ..Default::default()
+                },
+                vec![1, 2],
+                vec![(3, "grade"), (4, "grade")],
+            ),
+            (
+                "min equals max",
+                AutoScoreFilterConfig {
+                    min_score: Some(10),
+                    max_score: Some(10),
+                    ..Default::default()
+                },
+                vec![2, 4],
+                vec![(1, "min_score"), (3, "max_score")],
+            ),
+            (
+                "filters apply in order",
+                AutoScoreFilterConfig {
+                    groups: vec!["一组".to_string()],
+                    min_score: Some(6),
+                    ..Default::default()
+                },
+                vec![4],
+                vec![(2, "group"), (3, "group"), (1, "min_score")],
+            ),
+        ];
+        for (name, filters, kept, excluded) in cases {
+            let result = run_filters(&conn, &filters, candidates.clone()).await;
+            assert_eq!(result, (kept, excluded), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn rule_filters_on_recent_events() {
+        let conn = setup().await;
+        let candidates = vec![
+            student(1, None, 0, None),
+            student(2, None, 0, None),
+            student(3, None, 0, None),
+        ];
+        let window = |days: i64, min_count: Option<i64>| AutoScoreFilterConfig {
+            recent_event_days: Some(days),
+            min_recent_event_count: min_count,
+            ..Default::default()
+        };
+
+        // 窗口内没有任何记录
+        let (kept, excluded) = run_filters(&conn, &window(7, None), candidates.clone()).await;
+        assert!(kept.is_empty());
+        assert_eq!(excluded.len(), 3);
+        assert!(excluded
+            .iter()
+            .all(|(_, reason)| *reason == "recent_events"));
+        let (kept, _) = run_filters(&conn, &window(7, Some(0)), candidates.clone()).await;
+        assert_eq!(kept, vec![1, 2, 3]);
+
+        insert_event(&conn, Some(1), "学生1", "课堂表现优秀", 1).await;
+        insert_event(&conn, Some(1), "学生1", "帮助同学", 2).await;
+        // 旧记录只有姓名
+        insert_event(&conn, None, "学生2", "课堂表现优秀", 3).await;
+        insert_event(&conn, Some(3), "学生3", "课堂表现优秀", 30).await;
+        insert_event(&conn, Some(3), "学生3", "自动化#1: 每日签到 (+1)", 1).await;
+
+        let cases = vec![
+            ("at least one", window(7, None), vec![1, 2]),
+            ("min count", window(7, Some(2)), vec![1]),
+            ("wide window", window(60, None), vec![1, 2, 3]),
+        ];
+        for (name, filters, expected) in cases {
+            let (kept, _) = run_filters(&conn, &filters, candidates.clone()).await;
+            assert_eq!(kept, expected, "{}", name);
+        }
+
+        // 候选为空时不再查询
+        let (kept, excluded) = run_filters(&conn, &window(7, None), Vec::new()).await;
+        assert!(kept.is_empty() && excluded.is_empty());
+    }
+
+    fn condition(operator: &str, values: JsonValue) -> ConditionTriggerValue {
+        ConditionTriggerValue {
+            operator: operator.to_string(),
+            values: values.as_array().cloned().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn condition_number_operators() {
+        let cases = [
+            ("equal", json!([3]), 3.0, true),
+            ("equal", json!(["3"]), 3.0, true),
+            ("not_equal", json!([3]), 3.0, false),
+            ("greater", json!([3]), 3.0, false),
+            ("greater_or_equal", json!([3]), 3.0, true),
+            ("less", json!([3]), 2.5, true),
+            ("less_or_equal", json!([3]), 3.5, false),
+            ("between", json!([1, 3]), 1.0, true),
+            ("between", json!([1, 3]), 3.0, true),
+            ("between", json!([3, 3]), 3.0, true),
+            ("between", json!([1, 3]), 3.5, false),
+            ("not_between", json!([1, 3]), 0.0, true),
+            ("not_between", json!([1, 3]), 2.0, false),
+            ("between", json!([1]), 1.0, false),
+            ("equal", json!(["abc"]), 0.0, false),
+            ("in", json!([3]), 3.0, false),
+            // 没有记录的天数按无穷大比较
+            ("greater", json!([7]), f64::INFINITY, true),
+            ("less", json!([7]), f64::INFINITY, false),
+        ];
+        for (operator, values, actual, expected) in cases {
+            let condition = condition(operator, values.clone());
+            assert_eq!(
+                compare_condition_number(&condition, actual),
+                expected,
+                "{} {} {}",
+                operator,
+                values,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn condition_set_operators() {
+        let cases = [
+            ("in", json!(["一组", "二组"]), "二组", true),
+            ("in", json!(["一组"]), "二组", false),
+            ("not_in", json!(["一组"]), "二组", true),
+            ("not_in", json!(["一组", "二组"]), "二组", false),
+            ("in", json!([]), "二组", false),
+            ("not_in", json!([]), "二组", true),
+            ("equal", json!(["二组"]), "二组", false),
+        ];
+        for (operator, values, actual, expected) in cases {
+            let condition = condition(operator, values.clone());
+            assert_eq!(
+                compare_condition_set(&condition, |value| value.as_str() == Some(actual)),
+                expected,
+                "{} {} {}",
+                operator,
+                values,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn extra_json_conditions() {
+        let extra = r#"{"grade":7,"club":{"name":" 合唱团 ","level":"3"},"awards":["数学","英语"],"note":null}"#;
+        let cases = [
+            ("grade", "equal", json!([7]), true),
+            ("grade", "equal", json!(["7"]), true),
+            ("grade", "greater", json!([6]), true),
+            ("grade", "between", json!([8, 9]), false),
+            ("club.name", "equal", json!(["合唱团"]), true),
+            ("club.name", "not_equal", json!(["合唱团"]), false),
+            ("club.level", "less_or_equal", json!([3]), true),
+            ("club.name", "greater", json!([1]), false),
+            ("awards.1", "in", json!(["英语", "物理"]), true),
+            ("awards.1", "not_in", json!(["英语"]), false),
+            // 字段缺失或为 null 时只满足 not_equal 与 not_in
+            ("missing", "equal", json!([7]), false),
+            ("missing", "not_equal", json!([7]), true),
+            ("missing", "in", json!([7]), false),
+            ("missing", "not_in", json!([7]), true),
+            ("missing", "less", json!([7]), false),
+            ("note", "not_equal", json!(["x"]), true),
+            ("awards.5", "equal", json!(["数学"]), false),
+            ("grade.level", "not_in", json!([1]), true),
+        ];
+        for (path, operator, values, expected) in cases {
+            let condition = ConditionTriggerValue {
+                path: Some(path.to_string()),
+                ..condition(operator, values.clone())
+            };
+            assert_eq!(
+                compare_extra_json(&condition, Some(extra)),
+                expected,
+                "{} {} {}",
+                path,
+                operator,
+                values
+            );
+        }
+
+        let condition = ConditionTriggerValue {
+            path: Some("grade".to_string()),
+            ..condition("not_equal", json!([7]))
+        };
+        assert!(compare_extra_json(&condition, None));
+        assert!(compare_extra_json(&condition, Some("not json")));
+    }
+
+    #[test]
+    fn time_of_day_conditions() {
+        let at = |hour: u32, minute: u32| {
+            Local
+                .with_ymd_and_hms(2026, 3, 2, hour, minute, 30)
+                .earliest()
+                .unwrap()
+        };
+        let cases = [
+            ("between", json!(["08:00", "12:00"]), at(8, 0), true),
+            ("between", json!(["08:00", "12:00"]), at(12, 0), true),
+            ("between", json!(["08:00", "12:00"]), at(12, 1), false),
+            // 起点晚于终点表示跨零点
+            ("between", json!(["22:00", "06:00"]), at(23, 30), true),
+            ("between", json!(["22:00", "06:00"]), at(0, 0), true),
+            ("between", json!(["22:00", "06:00"]), at(6, 0), true),
+            ("between", json!(["22:00", "06:00"]), at(12, 0), false),
+            ("not_between", json!(["22:00", "06:00"]), at(12, 0), true),
+            ("not_between", json!(["22:00", "06:00"]), at(1, 0), false),
+            ("greater_or_equal", json!(["18:00"]), at(18, 0), true),
+            ("greater_or_equal", json!(["18:00"]), at(17, 59), false),
+            ("less", json!(["07:30"]), at(7, 29), true),
+            ("less", json!(["07:30"]), at(7, 30), false),
+            ("between", json!(["08:00"]), at(8, 0), false),
+            ("between", json!(["8点", "12:00"]), at(9, 0), false),
+        ];
+        for (operator, values, now, expected) in cases {
+            let condition = condition(operator, values.clone());
+            assert_eq!(
+                compare_time_of_day(&condition, &now),
+                expected,
+                "{} {} {}",
+                operator,
+                values,
+                now
+            );
+        }
+    }
+
+    #[test]
+    fn leaderboard_positions_share_ties() {
+        let students = [
+            student(1, None, 10, None),
+            student(2, None, 30, None),
+            student(3, None, 20, None),
+            student(4, None, 30, None),
+        ];
+        let positions = leaderboard_positions(&students);
+        let ranks = [1, 2, 3, 4].map(|id| {
+            let position = positions[&id];
+            (position.rank, position.total)
+        });
+        assert_eq!(ranks, [(4, 4), (1, 4), (3, 4), (1, 4)]);
+        assert!(leaderboard_positions(&[]).is_empty());
+    }
+
+    #[test]
+    fn condition_values_are_normalized() {
+        let normalized = |field: &str, value: JsonValue| {
+            let condition = serde_json::from_value::<ConditionTriggerValue>(value).unwrap();
+            normalize_condition_trigger_value(field, condition)
+        };
+
+        let group = normalized(
+            "student_group",
+            json!({ "operator": "select_any_in", "values": [" 一组 ", "一组", 2] }),
+        )
+        .unwrap();
+        assert_eq!(group.operator, "in");
+        assert_eq!(group.values, vec![json!("一组"), json!("2")]);
+
+        let weekday = normalized(
+            "weekday",
+            json!({ "operator": "in", "values": [5, "1", 5] }),
+        )
+        .unwrap();
+        assert_eq!(weekday.values, vec![json!(1), json!(5)]);
+
+        let night = normalized(
+            "time_of_day",
+            json!({ "operator": "between", "values": ["22:00", "6:30"] }),
+        )
+        .unwrap();
+        assert_eq!(night.values, vec![json!("22:00"), json!("06:30")]);
+
+        let same = normalized(
+            "reward_points",
+            json!({ "operator": "between", "values": ["10", 10] }),
+        )
+        .unwrap();
+        assert_eq!(same.values, vec![json!(10), json!(10)]);
+
+        let extra = normalized(
+            "extra_json",
+            json!({ "operator": "equal", "values": [" 合唱团 "], "path": " club.name ", "days": 3 }),
+        )
+        .unwrap();
+        assert_eq!(extra.values, vec![json!("合唱团")]);
+        assert_eq!(extra.path.as_deref(), Some("club.name"));
+        assert_eq!(extra.days, None);
+
+        let rejected = [
+            (
+                "reward_points",
+                json!({ "operator": "between", "values": [10, 1] }),
+            ),
+            ("reward_points", json!({ "operator": "in", "values": [1] })),
+            (
+                "reward_points",
+                json!({ "operator": "equal", "values": [1, 2] }),
+            ),
+            ("student_group", json!({ "operator": "in", "values": [] })),
+            ("weekday", json!({ "operator": "in", "values": [0] })),
+            (
+                "time_of_day",
+                json!({ "operator": "between", "values": ["25:00", "06:00"] }),
+            ),
+            (
+                "leaderboard_percentile",
+                json!({ "operator": "less", "values": [120] }),
+            ),
+            (
+                "event_count",
+                json!({ "operator": "greater", "values": [1] }),
+            ),
+            (
+                "event_count",
+                json!({ "operator": "greater", "values": [1], "days": 0 }),
+            ),
+            ("extra_json", json!({ "operator": "equal", "values": [1] })),
+            (
+                "extra_json",
+                json!({ "operator": "equal", "values": [1], "path": "a..b" }),
+            ),
+            (
+                "extra_json",
+                json!({ "operator": "greater", "values": ["x"], "path": "a" }),
+            ),
+        ];
+        for (field, value) in rejected {
+            assert!(
+                normalized(field, value.clone()).is_err(),
+                "{} {}",
+                field,
+                value
+            );
+        }
+    }
+
+    fn bus_event(source: EventSource, payload: BusPayload) -> BusEvent {
+        BusEvent {
+            source,
+            at: "2024-01-01T00:00:00.000Z".to_string(),
+            payload,
+        }
+    }
+
+    fn score_event(student_id: Option<i32>, val_prev: i32, val_curr: i32) -> score_events::Model {
+        score_events::Model {
+            id: 0,
+            uuid: Uuid::new_v4().to_string(),
+            student_id,
+            student_name: format!("学生{}", student_id.unwrap_or_default()),
+            reason_content: "课堂表现优秀".to_string(),
+            delta: val_curr - val_prev,
+            val_prev,
+            val_curr,
+            event_time: "2024-01-01T00:00:00.000Z".to_string(),
+            settlement_id: None,
+        }
+    }
+
+    fn redemption(student_id: i32, status: RedemptionStatus) -> Box<reward_redemptions::Model> {
+        Box::new(reward_redemptions::Model {
+            id: 1,
+            uuid: Uuid::new_v4().to_string(),
+            student_id: Some(student_id),
+            student_name: format!("学生{}", student_id),
+            reward_id: 1,
+            reward_name: "免作业卡".to_string(),
+            cost_points: 10,
+            redeemed_at: "2024-01-01T00:00:00.000Z".to_string(),
+            status: status.as_str().to_string(),
+            reviewed_at: None,
+            review_note: None,
+            cancelled_at: None,
+            cancelled_by: None,
+            cancel_reason: None,
+        })
+    }
+
+    #[test]
+    fn trigger_signal_skips_own_and_replayed_writes() {
+        let scored = || BusPayload::ScoreEventsCreated {
+            events: vec![score_event(Some(1), 0, 1), score_event(None, 0, 1)],
+        };
+        let cases: Vec<(&str, BusEvent, Option<Vec<i32>>)> = vec![
+            (
+                "desktop score events",
+                bus_event(EventSource::Desktop, scored()),
+                Some(vec![1]),
+            ),
+            (
+                "auto score writes",
+                bus_event(EventSource::AutoScore, scored()),
+                None,
+            ),
+            ("sync writes", bus_event(EventSource::Sync, scored()), None),
+            (
+                "backup restore",
+                bus_event(EventSource::Backup, scored()),
+                None,
+            ),
+            (
+                "events without student",
+                bus_event(
+                    EventSource::Lan,
+                    BusPayload::ScoreEventsCreated {
+                        events: vec![score_event(None, 0, 1)],
+                    },
+                ),
+                None,
+            ),
+            (
+                "pending redemption",
+                bus_event(
+                    EventSource::Mcp,
+                    BusPayload::RedemptionChanged {
+                        redemption: redemption(2, RedemptionStatus::Pending),
+                    },
+                ),
+                Some(vec![2]),
+            ),
+            (
+                "reviewed redemption",
+                bus_event(
+                    EventSource::Desktop,
+                    BusPayload::RedemptionChanged {
+                        redemption: redemption(2, RedemptionStatus::Fulfilled),
+                    },
+                ),
+                None,
+            ),
+            (
+                "tags attached",
+                bus_event(
+                    EventSource::Desktop,
+                    BusPayload::StudentTagsUpdated {
+                        student_id: 3,
+                        added_tag_ids: vec![1],
+                    },
+                ),
+                Some(vec![3]),
+            ),
+            (
+                "tags only removed",
+                bus_event(
+                    EventSource::Desktop,
+                    BusPayload::StudentTagsUpdated {
+                        student_id: 3,
+                        added_tag_ids: vec![],
+                    },
+                ),
+                None,
+            ),
+            (
+                "unrelated payload",
+                bus_event(
+                    EventSource::Desktop,
+                    BusPayload::StudentsUpdated {
+                        student_ids: vec![1],
+                    },
+                ),
+                None,
+            ),
+        ];
+        for (name, event, expected) in cases {
+            let student_ids = TriggerSignal::from_bus_event(&event).map(|signal| {
+                let mut ids = signal.student_ids().into_iter().collect::<Vec<_>>();
+                ids.sort_unstable();
+                ids
+            });
+            assert_eq!(student_ids, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn trigger_signal_dispatches_to_matching_rules() {
+        let rule = |events: &[&str]| AutoScoreRule {
+            triggers: events
+                .iter()
+                .map(|event| AutoScoreTrigger {
+                    event: event.to_string(),
+                    value: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let scored = TriggerSignal::ScoreEvents(vec![score_event(Some(1), 0, 1)]);
+        let redeemed = TriggerSignal::Redemption(redemption(1, RedemptionStatus::Pending));
+        let tagged = TriggerSignal::TagsAttached {
+            student_id: 1,
+            tag_ids: vec![1],
+        };
+        let cases = vec![
+            ("score event", &scored, rule(&["score_event_created"]), true),
+            (
+                "score crossed",
+                &scored,
+                rule(&["score_crossed_below"]),
+                true,
+            ),
+            (
+                "score vs redemption",
+                &scored,
+                rule(&["reward_redeemed"]),
+                false,
+            ),
+            ("redemption", &redeemed, rule(&["reward_redeemed"]), true),
+            ("tag", &tagged, rule(&["tag_attached"]), true),
+            (
+                "mixed triggers",
+                &tagged,
+                rule(&["student_score_gt", "tag_attached"]),
+                true,
+            ),
+            (
+                "scheduled only",
+                &scored,
+                rule(&["interval_time_passed"]),
+                false,
+            ),
+        ];
+        for (name, signal, rule, expected) in cases {
+            assert_eq!(signal.is_handled_by(&rule), expected, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn reactive_candidates_are_limited_to_signalled_students() {
+        let conn = setup().await;
+        let mut ids = Vec::new();
+        for score in [12, 13, 20] {
+            let inserted = students::ActiveModel {
+                uuid: Set(Uuid::new_v4().to_string()),
+                name: Set(format!("学生{}", score)),
+                tags: Set("[]".to_string()),
+                score: Set(score),
+                reward_points: Set(score),
+                created_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+                updated_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+                ..Default::default()
+            }
+            .insert(&conn)
+            .await
+            .unwrap();
+            ids.push(inserted.id);
+        }
+        let rule = AutoScoreRule {
+            enabled: true,
+            triggers: vec![AutoScoreTrigger {
+                event: "score_crossed_above".to_string(),
+                value: Some("10".to_string()),
+            }],
+            ..Default::default()
+        };
+        // 第一人本次越过阈值，第二人此前已在阈值之上，第三人不在本次写入中
+        let signal = TriggerSignal::ScoreEvents(vec![
+            score_event(Some(ids[0]), 8, 12),
+            score_event(Some(ids[1]), 11, 13),
+        ]);
+
+        let candidates = resolve_rule_candidates(&conn, &rule, Some(&signal), false)
+            .await
+            .unwrap();
+        let matched = candidates
+            .matched
+            .iter()
+            .map(|student| student.id)
+            .collect::<Vec<_>>();
+        let excluded = candidates
+            .excluded
+            .iter()
+            .map(|(student, reason)| (student.id, *reason))
+            .collect::<Vec<_>>();
+        assert_eq!(matched, vec![ids[0]]);
+        assert_eq!(excluded, vec![(ids[1], "trigger")]);
+    }
+
+    async fn insert_student(
+        conn: &DatabaseConnection,
+        name: &str,
+        group: &str,
+        score: i32,
+    ) -> students::Model {
+        students::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            name: Set(name.to_string()),
+            group_name: Set(Some(group.to_string())),
+            tags: Set("[]".to_string()),
+            score: Set(score),
+            reward_points: Set(score),
+            created_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+            updated_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn preview_lists_exclusion_reasons() {
+        let conn = setup().await;
+        let cooled = insert_student(&conn, "甲", "一组", 5).await;
+        let other_group = insert_student(&conn, "乙", "二组", 5).await;
+        let capped = insert_student(&conn, "丙", "一组", 5).await;
+        let low_score = insert_student(&conn, "丁", "一组", 1).await;
+
+        let rule = AutoScoreRule {
+            id: 1,
+            name: "课堂奖励".to_string(),
+            triggers: vec![AutoScoreTrigger {
+                event: "student_score_gt".to_string(),
+                value: Some("3".to_string()),
+            }],
+            actions: vec![AutoScoreAction {
+                event: "add_score".to_string(),
+                value: Some("2".to_string()),
+            }],
+            filters: AutoScoreFilterConfig {
+                groups: vec!["一组".to_string()],
+                ..Default::default()
+            },
+            execution: AutoScoreExecutionConfig {
+                cooldown_minutes: Some(60),
+                max_score_delta_per_day: Some(5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // 今天已执行一次，给甲加过 4 分
+        let stats = RuleExecutionStats {
+            affected_students: 1,
+            affected_student_names: vec![cooled.name.clone()],
+            affected_student_ids: vec![cooled.id],
+            score_delta_total: 4,
+            ..Default::default()
+        };
+        insert_batches(&conn, &[new_execution_batch(&rule, &stats)])
+            .await
+            .unwrap();
+
+        let reasons = |preview: &AutoScoreRulePreview| {
+            preview
+                .excluded
+                .iter()
+                .map(|exclusion| {
+                    (
+                        exclusion.student_id,
+                        exclusion.reason.clone(),
+                        exclusion.detail.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let preview = build_rule_preview(&conn, rule.clone()).await.unwrap();
+        assert!(preview.students.is_empty());
+        assert_eq!(
+            reasons(&preview),
+            vec![
+                (other_group.id, "group".to_string(), None),
+                (low_score.id, "trigger".to_string(), None),
+                (
+                    cooled.id,
+                    "cooldown".to_string(),
+                    Some("cooldown 60 minutes".to_string())
+                ),
+                (
+                    capped.id,
+                    "no_effect".to_string(),
+                    Some("max_score_delta_per_day".to_string())
+                ),
+            ]
+        );
+
+        let limited = AutoScoreRule {
+            student_names: vec!["丙".to_string()],
+            execution: AutoScoreExecutionConfig {
+                max_runs_per_day: Some(1),
+                ..Default::default()
+            },
+            ..rule.clone()
+        };
+        let preview = build_rule_preview(&conn, limited).await.unwrap();
+        assert_eq!(
+            reasons(&preview),
+            vec![
+                (cooled.id, "student_names".to_string(), None),
+                (other_group.id, "student_names".to_string(), None),
+                (low_score.id, "student_names".to_string(), None),
+                (
+                    capped.id,
+                    "max_runs_per_day".to_string(),
+                    Some("1 of 1 runs used today".to_string())
+                ),
+            ]
+        );
+
+        // 取消限制后丙正常加分，且不写入任何数据
+        let open = AutoScoreRule {
+            student_names: vec!["丙".to_string()],
+            execution: AutoScoreExecutionConfig::default(),
+            ..rule
+        };
+        let preview = build_rule_preview(&conn, open).await.unwrap();
+        assert!(preview.excluded.iter().all(|e| e.reason == "student_names"));
+        assert_eq!(preview.students.len(), 1);
+        assert_eq!(preview.students[0].score_after, 7);
+        assert_eq!(preview.score_delta_total, 2);
+        let stored = students::Entity::find_by_id(capped.id)
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.score, 5);
+    }
+
+    fn stored_batch(rule_id: i32, id: &str, days_ago: i64) -> AutoScoreExecutionBatch {
+        AutoScoreExecutionBatch {
+            id: id.to_string(),
+            rule_id,
+            rule_name: format!("规则{}", rule_id),
+            run_at: (Utc::now() - chrono::Duration::days(days_ago))
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string(),
+            affected_students: 1,
+            affected_student_names: vec!["学生1".to_string()],
+            affected_student_ids: vec![1],
+            created_event_ids: vec![1],
+            added_student_tag_ids: Vec::new(),
+            reward_redemption_ids: Vec::new(),
+            score_delta_total: 1,
+            settled: false,
+            rolled_back: false,
+            rollback_at: None,
+        }
+    }
+
+    /// 按每页两条翻到底，返回每页的总数与批次 id
+    async fn batch_pages(
+        conn: &DatabaseConnection,
+        rule_id: Option<i32>,
+        start_time: Option<String>,
+    ) -> Vec<(u64, Vec<String>)> {
+        let mut params = AutoScoreBatchPageParams {
+            rule_id,
+            start_time,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut pages = Vec::new();
+        loop {
+            let page = query_execution_batches(conn, &params).await.unwrap();
+            pages.push((
+                page.total,
+                page.items.iter().map(|batch| batch.id.clone()).collect(),
+            ));
+            match page.next_cursor {
+                Some(cursor) => params.cursor = Some(cursor),
+                None => return pages,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_pages_follow_cursor() {
+        let conn = setup().await;
+        let mut batches = vec![
+            stored_batch(1, "a", 5),
+            stored_batch(1, "b", 4),
+            stored_batch(2, "c", 3),
+            stored_batch(1, "d", 2),
+            stored_batch(1, "e", 1),
+        ];
+        // 同一时间的批次按 id 倒序，不会在翻页边界重复或遗漏
+        batches[1].run_at = batches[3].run_at.clone();
+        insert_batches(&conn, &batches).await.unwrap();
+
+        let page = |total: u64, ids: &[&str]| {
+            (
+                total,
+                ids.iter().map(|id| id.to_string()).collect::<Vec<_>>(),
+            )
+        };
+        let cases = vec![
+            (
+                "all rules",
+                batch_pages(&conn, None, None).await,
+                vec![page(5, &["e", "d"]), page(5, &["b", "c"]), page(5, &["a"])],
+            ),
+            (
+                "single rule",
+                batch_pages(&conn, Some(1), None).await,
+                vec![page(4, &["e", "d"]), page(4, &["b", "a"])],
+            ),
+            (
+                "since date",
+                batch_pages(&conn, None, Some(batches[2].run_at.clone())).await,
+                vec![page(4, &["e", "d"]), page(4, &["b", "c"])],
+            ),
+        ];
+        for (name, pages, expected) in cases {
+            assert_eq!(pages, expected, "{}", name);
+        }
+
+        let first = query_execution_batches(&conn, &AutoScoreBatchPageParams::default())
+            .await
+            .unwrap();
+        assert_eq!(first.items.len(), 5);
+        assert_eq!(first.items[0].created_event_ids, vec![1]);
+        assert!(first.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn retention_prunes_old_batches_and_items() {
+        let conn = setup().await;
+        insert_batches(
+            &conn,
+            &[
+                stored_batch(1, "old", 30),
+                stored_batch(1, "edge", 8),
+                stored_batch(1, "new", 1),
+            ],
+        )
+        .await
+        .unwrap();
+
+        delete_batches_older_than(&conn, 7).await.unwrap();
+
+        let remaining = auto_score_batches::Entity::find()
+            .all(&conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| row.id)
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, vec!["new".to_string()]);
+        let item_batches = auto_score_batch_items::Entity::find()
+            .all(&conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.batch_id)
+            .collect::<HashSet<_>>();
+        assert_eq!(item_batches, HashSet::from(["new".to_string()]));
+    }
+}
@@ -0,0 +1,689 @@
+use chrono::{DateTime, Datelike, Local, Utc};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+
+use crate::db::{
+    check_migration_status, create_sqlite_connection, run_migration, sqlite_vacuum_into,
+    DatabaseType,
+};
+use crate::services::settings::{SettingsKey, SettingsValue};
+use crate::state::SafeAppState;
+
+const BACKUP_DIR_NAME: &str = "backups";
+const CLASS_FILE_NAME: &str = "class.sql";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const BACKUP_TICK_SECONDS: u64 = 3600;
+const SCHEDULED_BACKUP_INTERVAL_HOURS: i64 = 24;
+const KEEP_DAILY_BACKUPS: usize = 7;
+const KEEP_WEEKLY_BACKUPS: usize = 4;
+const KEEP_PRE_OPERATION_BACKUPS: usize = 10;
+// 恢复前校验：缺少这些表的文件不是有效的班级数据库
+const REQUIRED_TABLES: &[&str] = &["students", "reasons", "score_events", "settings"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupKind {
+    /// 定时任务生成，按日/周保留
+    Scheduled,
+    /// 同步、重置、结算等高风险操作前自动生成
+    PreOperation,
+    /// 手动创建，不参与自动清理
+    Manual,
+}
+
+impl BackupKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BackupKind::Scheduled => "scheduled",
+            BackupKind::PreOperation => "pre_operation",
+            BackupKind::Manual => "manual",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub id: String,
+    pub kind: BackupKind,
+    pub reason: String,
+    pub class_id: String,
+    pub created_at: String,
+    pub schema_version: i32,
+    pub size_bytes: u64,
+}
+
+struct BackupContext {
+    class_id: String,
+    class_path: PathBuf,
+    backup_dir: PathBuf,
+    class_conn: DatabaseConnection,
+}
+
+async fn backup_context(state: &SafeAppState) -> Result<BackupContext, String> {
+    let (workspace, class_conn) = {
+        let state_guard = state.read();
+        let workspace = state_guard.workspace.read().clone();
+        let class_conn = state_guard.local_sqlite.read().clone();
+        (workspace, class_conn)
+    };
+    let workspace = workspace.ok_or_else(|| "工作空间尚未初始化".to_string())?;
+    let class_conn = class_conn.ok_or_else(|| "本地数据库未连接".to_string())?;
+    let class_path = PathBuf::from(workspace.current_db_path().await?);
+    let class_id = workspace.current_class_id().to_string();
+    let backup_dir = workspace.root().join(BACKUP_DIR_NAME).join(&class_id);
+
+    Ok(BackupContext {
+        class_id,
+        class_path,
+        backup_dir,
+        class_conn,
+    })
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|meta| meta.len()).unwrap_or(0)
+}
+
+fn read_manifest(dir: &Path) -> Result<BackupEntry, String> {
+    let raw = fs::read(dir.join(MANIFEST_FILE_NAME)).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&raw).map_err(|e| e.to_string())
+}
+
+fn resolve_backup_dir(backup_dir: &Path, id: &str) -> Result<PathBuf, String> {
+    let id = id.trim();
+    if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+        return Err("Invalid backup id".to_string());
+    }
+    let dir = backup_dir.join(id);
+    if !dir.join(MANIFEST_FILE_NAME).is_file() {
+        return Err("Backup not found".to_string());
+    }
+    Ok(dir)
+}
+
+const SQLITE_FILE_SUFFIXES: [&str; 4] = ["", "-wal", "-shm", "-journal"];
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut target = path.as_os_str().to_owned();
+    target.push(suffix);
+    PathBuf::from(target)
+}
+
+fn remove_sqlite_files(path: &Path) {
+    for suffix in SQLITE_FILE_SUFFIXES {
+        let _ = fs::remove_file(with_suffix(path, suffix));
+    }
+}
+
+/// 把数据库文件及其 -wal/-shm/-journal 一并改名，中途失败时撤回已移动的文件
+fn move_sqlite_files(from: &Path, to: &Path) -> Result<(), String> {
+    remove_sqlite_files(to);
+    let mut moved = Vec::new();
+    for suffix in SQLITE_FILE_SUFFIXES {
+        let source = with_suffix(from, suffix);
+        if !source.exists() {
+            continue;
+        }
+        let target = with_suffix(to, suffix);
+        if let Err(e) = fs::rename(&source, &target) {
+            for (source, target) in moved.into_iter().rev() {
+                let _ = fs::rename(target, source);
+            }
+            return Err(e.to_string());
+        }
+        moved.push((source, target));
+    }
+    Ok(())
+}
+
+/// 先把当前文件移到 `.pre-restore`，再换入副本；换入失败时把原文件移回
+fn swap_in_restored(staging: &Path, class_path: &Path, aside: &Path) -> Result<(), String> {
+    move_sqlite_files(class_path, aside)?;
+    if let Err(e) = fs::rename(staging, class_path) {
+        remove_sqlite_files(class_path);
+        if let Err(rollback) = move_sqlite_files(aside, class_path) {
+            warn!(
+                "Failed to move {} back after restore error: {}",
+                aside.display(),
+                rollback
+            );
+        }
+        return Err(e.to_string());
+    }
+    Ok(())
+}
+
+/// 读取备份清单，并确认它属于指定班级
+fn class_backup(
+    backup_dir: &Path,
+    class_id: &str,
+    id: &str,
+) -> Result<(PathBuf, BackupEntry), String> {
+    let dir = resolve_backup_dir(backup_dir, id)?;
+    let entry = read_manifest(&dir)?;
+    if entry.class_id != class_id {
+        return Err("该备份不属于当前班级".to_string());
+    }
+    Ok((dir, entry))
+}
+
+fn load_entries(backup_dir: &Path) -> Vec<BackupEntry> {
+    let Ok(dir) = fs::read_dir(backup_dir) else {
+        return Vec::new();
+    };
+    let mut entries = dir
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| match read_manifest(&entry.path()) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                warn!("Skip unreadable backup {}: {}", entry.path().display(), e);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    entries
+}
+
+// 定时备份保留最近 7 天每天最新一份、最近 4 周每周最新一份；操作前备份保留最近 10 份
+fn expired_backup_ids(entries: &[BackupEntry]) -> Vec<String> {
+    let mut keep: HashSet<&str> = HashSet::new();
+    let mut days = Vec::new();
+    let mut weeks = Vec::new();
+    let mut pre_operation = 0usize;
+
+    // entries 已按时间倒序排列，每个日/周遇到的第一份就是最新的
+    for entry in entries {
+        match entry.kind {
+            BackupKind::Manual => {
+                keep.insert(&entry.id);
+            }
+            BackupKind::PreOperation => {
+                if pre_operation < KEEP_PRE_OPERATION_BACKUPS {
+                    keep.insert(&entry.id);
+                }
+                pre_operation += 1;
+            }
+            BackupKind::Scheduled => {
+                let Ok(created_at) = DateTime::parse_from_rfc3339(&entry.created_at) else {
+                    keep.insert(&entry.id);
+                    continue;
+                };
+                let local = created_at.with_timezone(&Local);
+                let day = local.date_naive();
+                let week = (local.iso_week().year(), local.iso_week().week());
+                if !days.contains(&day) {
+                    days.push(day);
+                    if days.len() <= KEEP_DAILY_BACKUPS {
+                        keep.insert(&entry.id);
+                    }
+                }
+                if !weeks.contains(&week) {
+                    weeks.push(week);
+                    if weeks.len() <= KEEP_WEEKLY_BACKUPS {
+                        keep.insert(&entry.id);
+                    }
+                }
+            }
+        }
+    }
+
+    entries
+        .iter()
+        .filter(|entry| !keep.contains(entry.id.as_str()))
+        .map(|entry| entry.id.clone())
+        .collect()
+}
+
+fn prune_backups(backup_dir: &Path) {
+    for id in expired_backup_ids(&load_entries(backup_dir)) {
+        if let Err(e) = fs::remove_dir_all(backup_dir.join(&id)) {
+            warn!("Failed to prune backup {}: {}", id, e);
+        } else {
+            info!("Pruned backup {}", id);
+        }
+    }
+}
+
+pub async fn create_backup(
+    state: &SafeAppState,
+    kind: BackupKind,
+    reason: &str,
+) -> Result<BackupEntry, String> {
+    let ctx = backup_context(state).await?;
+    let now = Utc::now();
+    let id = format!("{}-{}", now.format("%Y%m%dT%H%M%S%3fZ"), kind.as_str());
+    let dir = ctx.backup_dir.join(&id);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let result = async {
+        let class_file = dir.join(CLASS_FILE_NAME);
+        sqlite_vacuum_into(&ctx.class_conn, &class_file)
+            .await
+            .map_err(|e| format!("Failed to snapshot class database: {}", e))?;
+        let status = check_migration_status(&ctx.class_conn, DatabaseType::SQLite)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let entry = BackupEntry {
+            id: id.clone(),
+            kind,
+            reason: reason.to_string(),
+            class_id: ctx.class_id.clone(),
+            created_at: now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            schema_version: status.current_version,
+            size_bytes: file_size(&class_file),
+        };
+        let manifest = serde_json::to_vec_pretty(&entry).map_err(|e| e.to_string())?;
+        fs::write(dir.join(MANIFEST_FILE_NAME), manifest).map_err(|e| e.to_string())?;
+        Ok::<BackupEntry, String>(entry)
+    }
+    .await;
+
+    match result {
+        Ok(entry) => {
+            info!("Created {} backup {} ({})", kind.as_str(), entry.id, reason);
+            prune_backups(&ctx.backup_dir);
+            Ok(entry)
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&dir);
+            Err(e)
+        }
+    }
+}
+
+/// 高风险操作前调用；工作空间尚未初始化时（如启动阶段）直接跳过
+pub async fn backup_before_operation(state: &SafeAppState, reason: &str) -> Result<(), String> {
+    if state.read().workspace.read().is_none() {
+        return Ok(());
+    }
+    create_backup(state, BackupKind::PreOperation, reason)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("操作前备份失败，已取消操作：{}", e))
+}
+
+pub async fn list_backups(state: &SafeAppState) -> Result<Vec<BackupEntry>, String> {
+    let ctx = backup_context(state).await?;
+    Ok(load_entries(&ctx.backup_dir))
+}
+
+pub async fn delete_backup(state: &SafeAppState, id: &str) -> Result<(), String> {
+    let ctx = backup_context(state).await?;
+    let dir = resolve_backup_dir(&ctx.backup_dir, id)?;
+    fs::remove_dir_all(dir).map_err(|e| e.to_string())
+}
+
+async fn validate_restore_candidate(path: &Path) -> Result<(), String> {
+    let conn = create_sqlite_connection(
+        path.to_str()
+            .ok_or_else(|| "Invalid restore path".to_string())?,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let result = async {
+        let integrity = conn
+            .query_one(Statement::from_string(
+                DbBackend::Sqlite,
+                "PRAGMA integrity_check".to_string(),
+            ))
+            .await
+            .map_err(|e| e.to_string())?
+            .and_then(|row| row.try_get_by_index::<String>(0).ok())
+            .unwrap_or_default();
+        if integrity != "ok" {
+            return Err(format!("备份文件已损坏：{}", integrity));
+        }
+
+        let status = check_migration_status(&conn, DatabaseType::SQLite)
+            .await
+            .map_err(|e| e.to_string())?;
+        if status.is_newer_than_binary {
+            return Err(format!(
+                "备份的数据库结构版本 {} 高于当前程序支持的版本 {}",
+                status.current_version, status.latest_version
+            ));
+        }
+        if let Some(table) = REQUIRED_TABLES
+            .iter()
+            .find(|table| status.missing_tables.iter().any(|t| t == *table))
+        {
+            return Err(format!("备份缺少数据表 {}", table));
+        }
+
+        // 旧版本备份在副本上补齐迁移，换入后即可直接使用
+        run_migration(&conn, DatabaseType::SQLite)
+            .await
+            .map_err(|e| e.to_string())
+    }
+    .await;
+
+    let _ = conn.close().await;
+    result
+}
+
+/// 校验通过后替换当前班级数据库文件，并重新打开连接；备份只包含班级数据库，不含工作空间目录
+pub async fn restore_backup(state: &SafeAppState, id: &str) -> Result<BackupEntry, String> {
+    let ctx = backup_context(state).await?;
+    let (dir, entry) = class_backup(&ctx.backup_dir, &ctx.class_id, id)?;
+
+    // 在副本上校验，备份本身保持不变
+    let mut staging = ctx.class_path.as_os_str().to_owned();
+    staging.push(".restore");
+    let staging = PathBuf::from(staging);
+    remove_sqlite_files(&staging);
+    fs::copy(dir.join(CLASS_FILE_NAME), &staging).map_err(|e| e.to_string())?;
+    if let Err(e) = validate_restore_candidate(&staging).await {
+        remove_sqlite_files(&staging);
+        return Err(e);
+    }
+
+    // 换入前再做一次快照，恢复错了还能回退
+    if let Err(e) = create_backup(state, BackupKind::PreOperation, "restore").await {
+        remove_sqlite_files(&staging);
+        return Err(format!("恢复前备份失败：{}", e));
+    }
+
+    let (old_conn, db_is_local) = {
+        let state_guard = state.read();
+        let old_conn = state_guard.local_sqlite.write().take();
+        let db_is_local = state_guard
+            .db
+            .read()
+            .as_ref()
+            .is_some_and(|conn| conn.get_database_backend() == DbBackend::Sqlite);
+        if db_is_local {
+            *state_guard.db.write() = None;
+        }
+        (old_conn, db_is_local)
+    };
+    if let Some(old_conn) = old_conn {
+        let _ = old_conn.close().await;
+    }
+
+    let aside = with_suffix(&ctx.class_path, ".pre-restore");
+    let swapped = swap_in_restored(&staging, &ctx.class_path, &aside);
+    if swapped.is_err() {
+        remove_sqlite_files(&staging);
+    }
+
+    // 无论替换是否成功都要重新打开当前班级数据库，避免应用失去连接
+    let conn = match open_class_database(&ctx.class_path).await {
+        Ok(conn) => conn,
+        Err(e) if swapped.is_ok() => {
+            // 换入的文件无法打开或迁移，退回原文件
+            remove_sqlite_files(&ctx.class_path);
+            move_sqlite_files(&aside, &ctx.class_path)?;
+            let conn = open_class_database(&ctx.class_path).await?;
+            attach_class_connection(state, conn, db_is_local);
+            return Err(format!("恢复后无法打开数据库，已退回原文件：{}", e));
+        }
+        Err(e) => return Err(e),
+    };
+    attach_class_connection(state, conn, db_is_local);
+
+    swapped?;
+    // 重新打开并迁移成功后才删除换下来的原文件
+    remove_sqlite_files(&aside);
+    info!("Restored backup {} for class {}", entry.id, entry.class_id);
+    Ok(entry)
+}
+
+async fn open_class_database(class_path: &Path) -> Result<DatabaseConnection, String> {
+    let path = class_path
+        .to_str()
+        .ok_or_else(|| "班级数据库路径无效".to_string())?;
+    let conn = create_sqlite_connection(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Err(e) = run_migration(&conn, DatabaseType::SQLite).await {
+        let _ = conn.close().await;
+        return Err(e.to_string());
+    }
+    Ok(conn)
+}
+
+fn attach_class_connection(state: &SafeAppState, conn: DatabaseConnection, db_is_local: bool) {
+    let state_guard = state.read();
+    *state_guard.local_sqlite.write() = Some(conn.clone());
+    if db_is_local {
+        *state_guard.db.write() = Some(conn.clone());
+        state_guard.settings.write().attach_db(Some(conn));
+    }
+}
+
+pub fn spawn_scheduler(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(BACKUP_TICK_SECONDS));
+        loop {
+            ticker.tick().await;
+            if let Err(error) = run_scheduled_backup(&app_handle).await {
+                eprintln!("scheduled backup failed: {}", error);
+            }
+        }
+    });
+}
+
+async fn run_scheduled_backup(app_handle: &AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<SafeAppState>().inner().clone();
+    let enabled = {
+        let state_guard = state.read();
+        if state_guard.workspace.read().is_none() {
+            return Ok(());
+        }
+        let settings = state_guard.settings.read();
+        !matches!(
+            settings.get_value(SettingsKey::AutoBackupEnabled),
+            SettingsValue::Boolean(false)
+        )
+    };
+    if !enabled {
+        return Ok(());
+    }
+
+    let latest = list_backups(&state)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.kind == BackupKind::Scheduled)
+        .filter_map(|entry| DateTime::parse_from_rfc3339(&entry.created_at).ok())
+        .max();
+    if let Some(latest) = latest {
+        let elapsed = Utc::now().signed_duration_since(latest.with_timezone(&Utc));
+        if elapsed.num_hours() < SCHEDULED_BACKUP_INTERVAL_HOURS {
+            return Ok(());
+        }
+    }
+
+    create_backup(&state, BackupKind::Scheduled, "scheduled").await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, NaiveDate, TimeZone};
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("secscore-backup-{}-{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_entry(
+        backup_dir: &Path,
+        kind: BackupKind,
+        class_id: &str,
+        at: DateTime<Utc>,
+    ) -> String {
+        let id = format!("{}-{}", at.format("%Y%m%dT%H%M%S%3fZ"), kind.as_str());
+        let entry = BackupEntry {
+            id: id.clone(),
+            kind,
+            reason: kind.as_str().to_string(),
+            class_id: class_id.to_string(),
+            created_at: at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            schema_version: 1,
+            size_bytes: 0,
+        };
+        let dir = backup_dir.join(&id);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(MANIFEST_FILE_NAME),
+            serde_json::to_vec_pretty(&entry).unwrap(),
+        )
+        .unwrap();
+        id
+    }
+
+    fn local_noon(date: NaiveDate) -> DateTime<Utc> {
+        Local
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn prune_keeps_daily_weekly_pre_operation_and_manual_backups() {
+        let backup_dir = scratch_dir("prune");
+        // 2024-06-30 是周日，最近 7 天正好是一个完整的 ISO 周
+        let sunday = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        for offset in 0..40 {
+            let day = sunday - ChronoDuration::days(offset);
+            write_entry(&backup_dir, BackupKind::Scheduled, "c1", local_noon(day));
+        }
+        for offset in 0..15 {
+            let at = local_noon(sunday) + ChronoDuration::minutes(offset);
+            write_entry(&backup_dir, BackupKind::PreOperation, "c1", at);
+        }
+        for offset in 0..3 {
+            let day = sunday - ChronoDuration::days(300 + offset);
+            write_entry(&backup_dir, BackupKind::Manual, "c1", local_noon(day));
+        }
+
+        prune_backups(&backup_dir);
+        let remaining = load_entries(&backup_dir);
+
+        let created_on = |kind: BackupKind| {
+            let mut days = remaining
+                .iter()
+                .filter(|entry| entry.kind == kind)
+                .map(|entry| {
+                    DateTime::parse_from_rfc3339(&entry.created_at)
+                        .unwrap()
+                        .with_timezone(&Local)
+                        .date_naive()
+                        .format("%m-%d")
+                        .to_string()
+                })
+                .collect::<Vec<_>>();
+            days.sort();
+            days
+        };
+        assert_eq!(
+            created_on(BackupKind::Scheduled),
+            vec![
+                "06-09", "06-16", "06-23", "06-24", "06-25", "06-26", "06-27", "06-28", "06-29",
+                "06-30"
+            ]
+        );
+        assert_eq!(created_on(BackupKind::Manual).len(), 3);
+
+        let mut pre_operation = remaining
+            .iter()
+            .filter(|entry| entry.kind == BackupKind::PreOperation)
+            .map(|entry| entry.created_at.clone())
+            .collect::<Vec<_>>();
+        pre_operation.sort();
+        let oldest_kept = (local_noon(sunday) + ChronoDuration::minutes(5))
+            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+            .to_string();
+        assert_eq!(pre_operation.len(), KEEP_PRE_OPERATION_BACKUPS);
+        assert_eq!(pre_operation[0], oldest_kept);
+
+        let _ = fs::remove_dir_all(backup_dir);
+    }
+
+    #[test]
+    fn resolve_backup_dir_rejects_path_traversal() {
+        let backup_dir = scratch_dir("resolve");
+        let id = write_entry(&backup_dir, BackupKind::Manual, "c1", Utc::now());
+
+        let cases = [
+            ("", "Invalid backup id"),
+            ("   ", "Invalid backup id"),
+            ("..", "Invalid backup id"),
+            ("../c2", "Invalid backup id"),
+            ("a/b", "Invalid backup id"),
+            ("a\\b", "Invalid backup id"),
+            ("20240101T000000000Z-manual", "Backup not found"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                resolve_backup_dir(&backup_dir, input).unwrap_err(),
+                expected,
+                "case: {:?}",
+                input
+            );
+        }
+        assert_eq!(
+            resolve_backup_dir(&backup_dir, &id).unwrap(),
+            backup_dir.join(&id)
+        );
+
+        let _ = fs::remove_dir_all(backup_dir);
+    }
+
+    #[test]
+    fn restore_rejects_backup_of_another_class() {
+        let backup_dir = scratch_dir("class");
+        let id = write_entry(&backup_dir, BackupKind::Manual, "c2", Utc::now());
+
+        assert_eq!(
+            class_backup(&backup_dir, "c1", &id).unwrap_err(),
+            "该备份不属于当前班级"
+        );
+        let (dir, entry) = class_backup(&backup_dir, "c2", &id).unwrap();
+        assert_eq!(dir, backup_dir.join(&id));
+        assert_eq!(entry.class_id, "c2");
+
+        let _ = fs::remove_dir_all(backup_dir);
+    }
+
+    #[test]
+    fn failed_swap_moves_the_original_back() {
+        let dir = scratch_dir("swap");
+        let class_path = dir.join("class.db");
+        let aside = with_suffix(&class_path, ".pre-restore");
+        fs::write(&class_path, "live").unwrap();
+        fs::write(with_suffix(&class_path, "-wal"), "live-wal").unwrap();
+
+        // 副本不存在，改名必然失败
+        let missing = dir.join("class.db.restore");
+        assert!(swap_in_restored(&missing, &class_path, &aside).is_err());
+        assert_eq!(fs::read_to_string(&class_path).unwrap(), "live");
+        assert_eq!(
+            fs::read_to_string(with_suffix(&class_path, "-wal")).unwrap(),
+            "live-wal"
+        );
+        assert!(!aside.exists());
+
+        let staging = dir.join("class.db.restore");
+        fs::write(&staging, "restored").unwrap();
+        swap_in_restored(&staging, &class_path, &aside).unwrap();
+        assert_eq!(fs::read_to_string(&class_path).unwrap(), "restored");
+        assert!(!with_suffix(&class_path, "-wal").exists());
+        assert_eq!(fs::read_to_string(&aside).unwrap(), "live");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
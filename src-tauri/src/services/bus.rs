@@ -0,0 +1,275 @@
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+
+use crate::db::entities::{reward_redemptions, score_events};
+use crate::services::auto_score::AutoScoreExecutionBatch;
+use crate::services::theme::ThemeConfig;
+
+const BUS_CAPACITY: usize = 256;
+
+/// 写入来源，桌面端据此决定是否需要再次刷新
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    Desktop,
+    Lan,
+    Mcp,
+    AutoScore,
+    Sync,
+    Backup,
+}
+
+impl EventSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventSource::Desktop => "desktop",
+            EventSource::Lan => "lan",
+            EventSource::Mcp => "mcp",
+            EventSource::AutoScore => "auto_score",
+            EventSource::Sync => "sync",
+            EventSource::Backup => "backup",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BusPayload {
+    ScoreEventsCreated {
+        events: Vec<score_events::Model>,
+    },
+    ScoreEventsDeleted {
+        events: Vec<score_events::Model>,
+    },
+    StudentsUpdated {
+        student_ids: Vec<i32>,
+    },
+    /// 整体替换学生标签，附带本次新增的标签
+    StudentTagsUpdated {
+        student_id: i32,
+        added_tag_ids: Vec<i32>,
+    },
+    RedemptionChanged {
+        redemption: Box<reward_redemptions::Model>,
+    },
+    SettlementCreated {
+        settlement_id: i32,
+    },
+    /// 自动加分执行或回滚一个批次
+    AutoScoreBatch {
+        batch: Box<AutoScoreExecutionBatch>,
+    },
+    ThemeChanged {
+        theme: Box<ThemeConfig>,
+    },
+    /// 撤销、恢复备份等影响范围不确定的写入
+    DataReset,
+}
+
+impl BusPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BusPayload::ScoreEventsCreated { .. } => "score_events_created",
+            BusPayload::ScoreEventsDeleted { .. } => "score_events_deleted",
+            BusPayload::StudentsUpdated { .. } => "students_updated",
+            BusPayload::StudentTagsUpdated { .. } => "student_tags_updated",
+            BusPayload::RedemptionChanged { .. } => "redemption_changed",
+            BusPayload::SettlementCreated { .. } => "settlement_created",
+            BusPayload::AutoScoreBatch { .. } => "auto_score_batch",
+            BusPayload::ThemeChanged { .. } => "theme_changed",
+            BusPayload::DataReset => "data_reset",
+        }
+    }
+
+    /// 对应前端 `ss:data-updated` 的刷新范围
+    fn category(&self) -> Option<&'static str> {
+        match self {
+            BusPayload::ScoreEventsCreated { .. }
+            | BusPayload::ScoreEventsDeleted { .. }
+            | BusPayload::AutoScoreBatch { .. } => Some("events"),
+            BusPayload::StudentsUpdated { .. }
+            | BusPayload::StudentTagsUpdated { .. }
+            | BusPayload::RedemptionChanged { .. } => Some("students"),
+            BusPayload::SettlementCreated { .. } | BusPayload::DataReset => Some("all"),
+            BusPayload::ThemeChanged { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BusEvent {
+    pub source: EventSource,
+    pub at: String,
+    #[serde(flatten)]
+    pub payload: BusPayload,
+}
+
+/// 进程内广播总线：所有写入路径发布到这里，Tauri 事件与局域网 SSE 都从这里订阅
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<BusEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(BUS_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, source: EventSource, payload: BusPayload) {
+        let event = BusEvent {
+            source,
+            at: chrono::Utc::now()
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string(),
+            payload,
+        };
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BusEvent> {
+        self.sender.subscribe()
+    }
+}
+
+fn forward_to_tauri(app_handle: &AppHandle, event: &BusEvent) {
+    if let BusPayload::ThemeChanged { theme } = &event.payload {
+        let _ = app_handle.emit("theme:updated", theme.as_ref());
+        return;
+    }
+    if let Some(category) = desktop_refresh_category(event) {
+        let _ = app_handle.emit(
+            "ss:data-updated",
+            json!({
+                "category": category,
+                "source": event.source.as_str(),
+            }),
+        );
+    }
+}
+
+/// 桌面端自己发起的写入（含同步）已在前端刷新，只转发其他来源的变更
+fn desktop_refresh_category(event: &BusEvent) -> Option<&'static str> {
+    if matches!(event.source, EventSource::Desktop | EventSource::Sync) {
+        return None;
+    }
+    event.payload.category()
+}
+
+/// 把总线上的事件转成桌面端的 Tauri 事件
+pub fn spawn_tauri_forwarder(bus: &EventBus, app_handle: AppHandle) {
+    let mut receiver = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => forward_to_tauri(&app_handle, &event),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn publish_fans_out_to_every_subscriber() {
+        let bus = EventBus::new();
+        // 没有订阅者时发布不报错
+        bus.publish(EventSource::Desktop, BusPayload::DataReset);
+
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+        bus.publish(
+            EventSource::Lan,
+            BusPayload::StudentsUpdated {
+                student_ids: vec![3],
+            },
+        );
+
+        for receiver in [&mut first, &mut second] {
+            let event = receiver.recv().await.unwrap();
+            assert_eq!(event.source, EventSource::Lan);
+            assert_eq!(event.payload.kind(), "students_updated");
+            assert!(receiver.try_recv().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_subscriber_lags_without_blocking_others() {
+        let bus = EventBus::new();
+        let mut slow = bus.subscribe();
+        let mut fast = bus.subscribe();
+        for id in 0..BUS_CAPACITY + 2 {
+            bus.publish(
+                EventSource::Mcp,
+                BusPayload::SettlementCreated {
+                    settlement_id: id as i32,
+                },
+            );
+            assert!(fast.recv().await.is_ok());
+        }
+
+        assert!(matches!(
+            slow.recv().await,
+            Err(broadcast::error::RecvError::Lagged(2))
+        ));
+        assert!(slow.recv().await.is_ok());
+    }
+
+    #[test]
+    fn desktop_refresh_skips_own_writes() {
+        let event = |source, payload| BusEvent {
+            source,
+            at: String::new(),
+            payload,
+        };
+        let students = || BusPayload::StudentsUpdated {
+            student_ids: vec![1],
+        };
+        let cases = [
+            (
+                "desktop write",
+                event(EventSource::Desktop, students()),
+                None,
+            ),
+            (
+                "sync write",
+                event(EventSource::Sync, BusPayload::DataReset),
+                None,
+            ),
+            (
+                "lan write",
+                event(EventSource::Lan, students()),
+                Some("students"),
+            ),
+            (
+                "auto score",
+                event(
+                    EventSource::AutoScore,
+                    BusPayload::ScoreEventsCreated { events: vec![] },
+                ),
+                Some("events"),
+            ),
+            (
+                "mcp reset",
+                event(EventSource::Mcp, BusPayload::DataReset),
+                Some("all"),
+            ),
+        ];
+        for (name, event, expected) in cases {
+            assert_eq!(desktop_refresh_category(&event), expected, "case: {}", name);
+        }
+    }
+}
@@ -1,4 +1,19 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseBackend, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder, Set, Statement, TransactionTrait,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::db::bind_statement;
+use crate::db::entities::{
+    reason_categories, reason_set_items, reason_sets, reasons, reward_redemptions, reward_settings,
+    score_events, student_tags, students, tags,
+};
+
+/// 导出文件格式版本，表结构变化时递增
+pub const EXPORT_FORMAT_VERSION: u32 = 3;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
@@ -6,39 +21,78 @@ pub struct Tag {
     pub name: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementExport {
+    pub id: i32,
+    pub start_time: String,
+    pub end_time: String,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardConfigExport {
+    pub id: i32,
+    pub config_json: String,
+    pub updated_at: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportData {
+    #[serde(default)]
+    pub format_version: u32,
     pub version: String,
     pub export_time: String,
+    #[serde(default)]
     pub settings: serde_json::Value,
-    pub students: Vec<StudentExport>,
-    pub events: Vec<EventExport>,
-    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub students: Vec<students::Model>,
+    #[serde(default)]
+    pub reason_categories: Vec<reason_categories::Model>,
+    #[serde(default)]
+    pub reasons: Vec<reasons::Model>,
+    #[serde(default)]
+    pub reason_sets: Vec<reason_sets::Model>,
+    #[serde(default)]
+    pub reason_set_items: Vec<reason_set_items::Model>,
+    #[serde(default)]
+    pub score_events: Vec<score_events::Model>,
+    #[serde(default)]
+    pub settlements: Vec<SettlementExport>,
+    #[serde(default)]
+    pub tags: Vec<tags::Model>,
+    #[serde(default)]
+    pub student_tags: Vec<student_tags::Model>,
+    #[serde(default)]
+    pub reward_settings: Vec<reward_settings::Model>,
+    #[serde(default)]
+    pub reward_redemptions: Vec<reward_redemptions::Model>,
+    #[serde(default)]
+    pub board_configs: Vec<BoardConfigExport>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StudentExport {
-    pub id: i32,
-    pub name: String,
-    pub tags: Vec<String>,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// 保留本地数据，只补充本地不存在的记录
+    #[default]
+    Merge,
+    /// 清空本地数据后按导出文件原样恢复（保留原 id）
+    Replace,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EventExport {
-    pub id: i32,
-    pub student_id: i32,
-    pub student_name: String,
-    pub score_change: i32,
-    pub reason: String,
-    pub created_at: String,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableImportCount {
+    pub table: String,
+    pub inserted: usize,
+    pub skipped: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportResult {
     pub success: bool,
     pub message: Option<String>,
-    pub students_imported: Option<usize>,
-    pub events_imported: Option<usize>,
+    pub mode: ImportMode,
+    pub tables: Vec<TableImportCount>,
 }
 
 pub struct DataService {
@@ -96,46 +150,712 @@ impl DataService {
         self.tags = tags;
     }
 
-    pub fn export_json(
-        &self,
-        settings: serde_json::Value,
-        students: Vec<StudentExport>,
-        events: Vec<EventExport>,
-    ) -> ExportData {
-        ExportData {
-            version: env!("CARGO_PKG_VERSION").to_string(),
-            export_time: chrono::Local::now().to_rfc3339(),
-            settings,
-            students,
-            events,
-            tags: self.tags.clone(),
-        }
-    }
-
-    pub fn import_json(&mut self, json: &str) -> ImportResult {
-        match serde_json::from_str::<ExportData>(json) {
-            Ok(data) => {
-                if !data.tags.is_empty() {
-                    self.tags = data.tags;
-                }
-
-                ImportResult {
-                    success: true,
-                    message: Some("Import successful".to_string()),
-                    students_imported: Some(data.students.len()),
-                    events_imported: Some(data.events.len()),
-                }
+    pub fn validate_import_data(json: &str) -> Result<ExportData, String> {
+        let data: ExportData =
+            serde_json::from_str(json).map_err(|e| format!("Invalid import data: {}", e))?;
+        if data.format_version == 0 {
+            return Err("Unsupported export format: missing format_version".to_string());
+        }
+        if data.format_version > EXPORT_FORMAT_VERSION {
+            return Err(format!(
+                "Export format version {} is newer than supported version {}",
+                data.format_version, EXPORT_FORMAT_VERSION
+            ));
+        }
+        Ok(data)
+    }
+}
+
+pub async fn export_database(
+    conn: &DatabaseConnection,
+    settings: serde_json::Value,
+) -> Result<ExportData, String> {
+    let backend = conn.get_database_backend();
+
+    let settlements = conn
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT id, start_time, end_time, created_at FROM settlements ORDER BY id",
+        ))
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|row| {
+            Ok(SettlementExport {
+                id: row.try_get("", "id")?,
+                start_time: row.try_get("", "start_time")?,
+                end_time: row.try_get("", "end_time")?,
+                created_at: row.try_get("", "created_at")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sea_orm::DbErr>>()
+        .map_err(|e| e.to_string())?;
+
+    let board_configs = conn
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT id, config_json, updated_at FROM board_configs ORDER BY id",
+        ))
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|row| {
+            Ok(BoardConfigExport {
+                id: row.try_get("", "id")?,
+                config_json: row.try_get("", "config_json")?,
+                updated_at: row.try_get("", "updated_at")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sea_orm::DbErr>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(ExportData {
+        format_version: EXPORT_FORMAT_VERSION,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        export_time: chrono::Local::now().to_rfc3339(),
+        settings,
+        students: students::Entity::find()
+            .order_by_asc(students::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        reason_categories: reason_categories::Entity::find()
+            .order_by_asc(reason_categories::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        reasons: reasons::Entity::find()
+            .order_by_asc(reasons::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        reason_sets: reason_sets::Entity::find()
+            .order_by_asc(reason_sets::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        reason_set_items: reason_set_items::Entity::find()
+            .order_by_asc(reason_set_items::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        score_events: score_events::Entity::find()
+            .order_by_asc(score_events::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        settlements,
+        tags: tags::Entity::find()
+            .order_by_asc(tags::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        student_tags: student_tags::Entity::find()
+            .order_by_asc(student_tags::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        reward_settings: reward_settings::Entity::find()
+            .order_by_asc(reward_settings::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        reward_redemptions: reward_redemptions::Entity::find()
+            .order_by_asc(reward_redemptions::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| e.to_string())?,
+        board_configs,
+    })
+}
+
+pub async fn import_database(
+    conn: &DatabaseConnection,
+    data: ExportData,
+    mode: ImportMode,
+) -> Result<ImportResult, String> {
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    let tables = import_in_transaction(&txn, data, mode).await?;
+    txn.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(ImportResult {
+        success: true,
+        message: Some("Import successful".to_string()),
+        mode,
+        tables,
+    })
+}
+
+async fn import_in_transaction(
+    txn: &DatabaseTransaction,
+    data: ExportData,
+    mode: ImportMode,
+) -> Result<Vec<TableImportCount>, String> {
+    let backend = txn.get_database_backend();
+    let replace = mode == ImportMode::Replace;
+
+    if replace {
+        // 先删子表再删父表，避免外键约束报错
+        for table in [
+            "auto_score_batch_items",
+            "auto_score_batches",
+            "student_tags",
+            "score_events",
+            "reward_redemptions",
+            "settlements",
+            "students",
+            "tags",
+            "reason_set_items",
+            "reason_sets",
+            "reasons",
+            "reason_categories",
+            "reward_settings",
+            "board_configs",
+            "operation_journal",
+        ] {
+            txn.execute(Statement::from_string(
+                backend,
+                format!("DELETE FROM {}", table),
+            ))
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let id_value = |id: i32| {
+        if replace {
+            Set(id)
+        } else {
+            sea_orm::ActiveValue::NotSet
+        }
+    };
+
+    let mut counts = Vec::new();
+
+    // 导出文件中的 id -> 本地 id
+    let mut tag_ids: HashMap<i32, i32> = HashMap::new();
+    let mut count = TableImportCount {
+        table: "tags".to_string(),
+        ..Default::default()
+    };
+    for row in data.tags {
+        let existing = if replace {
+            None
+        } else {
+            tags::Entity::find()
+                .filter(tags::Column::Name.eq(&row.name))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+        };
+        if let Some(existing) = existing {
+            tag_ids.insert(row.id, existing.id);
+            count.skipped += 1;
+            continue;
+        }
+        let inserted = tags::ActiveModel {
+            id: id_value(row.id),
+            name: Set(row.name),
+            created_at: Set(row.created_at),
+            updated_at: Set(row.updated_at),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        tag_ids.insert(row.id, inserted.id);
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut student_ids: HashMap<i32, i32> = HashMap::new();
+    // 旧版导出的记录没有 student_id，按姓名找回对应学生
+    let mut student_ids_by_name: HashMap<String, i32> = HashMap::new();
+    let mut count = TableImportCount {
+        table: "students".to_string(),
+        ..Default::default()
+    };
+    for row in data.students {
+        let uuid = if row.uuid.trim().is_empty() {
+            Uuid::new_v4().to_string()
+        } else {
+            row.uuid.clone()
+        };
+        let existing = if replace {
+            None
+        } else {
+            match students::Entity::find()
+                .filter(students::Column::Uuid.eq(&uuid))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                Some(existing) => Some(existing),
+                None => students::Entity::find()
+                    .filter(students::Column::Name.eq(&row.name))
+                    .one(txn)
+                    .await
+                    .map_err(|e| e.to_string())?,
             }
-            Err(e) => ImportResult {
-                success: false,
-                message: Some(format!("Failed to parse import data: {}", e)),
-                students_imported: None,
-                events_imported: None,
-            },
+        };
+        if let Some(existing) = existing {
+            student_ids.insert(row.id, existing.id);
+            student_ids_by_name.entry(row.name).or_insert(existing.id);
+            count.skipped += 1;
+            continue;
+        }
+        let inserted = students::ActiveModel {
+            id: id_value(row.id),
+            uuid: Set(uuid),
+            name: Set(row.name),
+            group_name: Set(row.group_name),
+            score: Set(row.score),
+            reward_points: Set(row.reward_points),
+            tags: Set(row.tags),
+            extra_json: Set(row.extra_json),
+            created_at: Set(row.created_at),
+            updated_at: Set(row.updated_at),
         }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        student_ids.insert(row.id, inserted.id);
+        student_ids_by_name
+            .entry(inserted.name)
+            .or_insert(inserted.id);
+        count.inserted += 1;
     }
+    counts.push(count);
 
-    pub fn validate_import_data(json: &str) -> Result<ExportData, String> {
-        serde_json::from_str(json).map_err(|e| format!("Invalid import data: {}", e))
+    let mut count = TableImportCount {
+        table: "student_tags".to_string(),
+        ..Default::default()
+    };
+    for row in data.student_tags {
+        let (Some(&student_id), Some(&tag_id)) =
+            (student_ids.get(&row.student_id), tag_ids.get(&row.tag_id))
+        else {
+            count.skipped += 1;
+            continue;
+        };
+        let exists = if replace {
+            false
+        } else {
+            student_tags::Entity::find()
+                .filter(student_tags::Column::StudentId.eq(student_id))
+                .filter(student_tags::Column::TagId.eq(tag_id))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+                .is_some()
+        };
+        if exists {
+            count.skipped += 1;
+            continue;
+        }
+        student_tags::ActiveModel {
+            id: id_value(row.id),
+            student_id: Set(student_id),
+            tag_id: Set(tag_id),
+            created_at: Set(row.created_at),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut category_ids: HashMap<i32, i32> = HashMap::new();
+    let mut count = TableImportCount {
+        table: "reason_categories".to_string(),
+        ..Default::default()
+    };
+    for row in data.reason_categories {
+        let existing = if replace {
+            None
+        } else {
+            reason_categories::Entity::find()
+                .filter(reason_categories::Column::Name.eq(&row.name))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+        };
+        if let Some(existing) = existing {
+            category_ids.insert(row.id, existing.id);
+            count.skipped += 1;
+            continue;
+        }
+        let inserted = reason_categories::ActiveModel {
+            id: id_value(row.id),
+            name: Set(row.name),
+            sort_order: Set(row.sort_order),
+            color: Set(row.color),
+            icon: Set(row.icon),
+            is_archived: Set(row.is_archived),
+            created_at: Set(row.created_at),
+            updated_at: Set(row.updated_at),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        category_ids.insert(row.id, inserted.id);
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut reason_ids: HashMap<i32, i32> = HashMap::new();
+    let mut count = TableImportCount {
+        table: "reasons".to_string(),
+        ..Default::default()
+    };
+    for row in data.reasons {
+        let existing = if replace {
+            None
+        } else {
+            reasons::Entity::find()
+                .filter(reasons::Column::Content.eq(&row.content))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+        };
+        if let Some(existing) = existing {
+            reason_ids.insert(row.id, existing.id);
+            count.skipped += 1;
+            continue;
+        }
+        // 旧版导出没有分类表，按名称补建分类
+        let category_id = match row.category_id.and_then(|id| category_ids.get(&id)) {
+            Some(&id) => Some(id),
+            None => crate::services::reason::ensure_category(txn, &row.category)
+                .await?
+                .map(|c| c.id),
+        };
+        let inserted = reasons::ActiveModel {
+            id: id_value(row.id),
+            content: Set(row.content),
+            category: Set(row.category),
+            category_id: Set(category_id),
+            delta: Set(row.delta),
+            is_system: Set(row.is_system),
+            sort_order: Set(row.sort_order),
+            is_archived: Set(row.is_archived),
+            updated_at: Set(row.updated_at),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        reason_ids.insert(row.id, inserted.id);
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut set_ids: HashMap<i32, i32> = HashMap::new();
+    let mut count = TableImportCount {
+        table: "reason_sets".to_string(),
+        ..Default::default()
+    };
+    for row in data.reason_sets {
+        let existing = if replace {
+            None
+        } else {
+            reason_sets::Entity::find()
+                .filter(reason_sets::Column::Name.eq(&row.name))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+        };
+        if existing.is_some() {
+            // 同名快捷组以本地为准，不合并组内理由
+            count.skipped += 1;
+            continue;
+        }
+        let inserted = reason_sets::ActiveModel {
+            id: id_value(row.id),
+            name: Set(row.name),
+            created_at: Set(row.created_at),
+            updated_at: Set(row.updated_at),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        set_ids.insert(row.id, inserted.id);
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut count = TableImportCount {
+        table: "reason_set_items".to_string(),
+        ..Default::default()
+    };
+    for row in data.reason_set_items {
+        let (Some(&set_id), Some(&reason_id)) =
+            (set_ids.get(&row.set_id), reason_ids.get(&row.reason_id))
+        else {
+            count.skipped += 1;
+            continue;
+        };
+        reason_set_items::ActiveModel {
+            id: id_value(row.id),
+            set_id: Set(set_id),
+            reason_id: Set(reason_id),
+            sort_order: Set(row.sort_order),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut settlement_ids: HashMap<i32, i32> = HashMap::new();
+    let mut count = TableImportCount {
+        table: "settlements".to_string(),
+        ..Default::default()
+    };
+    for row in data.settlements {
+        let find_sql = "SELECT id FROM settlements WHERE start_time = ? AND end_time = ? ORDER BY id DESC LIMIT 1";
+        let find_values = vec![row.start_time.clone().into(), row.end_time.clone().into()];
+        if !replace {
+            let existing = txn
+                .query_one(bind_statement(backend, find_sql, find_values.clone()))
+                .await
+                .map_err(|e| e.to_string())?;
+            if let Some(existing) = existing {
+                let id: i32 = existing.try_get("", "id").map_err(|e| e.to_string())?;
+                settlement_ids.insert(row.id, id);
+                count.skipped += 1;
+                continue;
+            }
+        }
+        let created_at = row.created_at.unwrap_or_else(|| row.end_time.clone());
+        if replace {
+            txn.execute(bind_statement(
+                backend,
+                "INSERT INTO settlements (id, start_time, end_time, created_at) VALUES (?, ?, ?, ?)",
+                vec![
+                    row.id.into(),
+                    row.start_time.into(),
+                    row.end_time.into(),
+                    created_at.into(),
+                ],
+            ))
+            .await
+            .map_err(|e| e.to_string())?;
+            settlement_ids.insert(row.id, row.id);
+        } else {
+            txn.execute(bind_statement(
+                backend,
+                "INSERT INTO settlements (start_time, end_time, created_at) VALUES (?, ?, ?)",
+                vec![
+                    row.start_time.into(),
+                    row.end_time.into(),
+                    created_at.into(),
+                ],
+            ))
+            .await
+            .map_err(|e| e.to_string())?;
+            let inserted = txn
+                .query_one(bind_statement(backend, find_sql, find_values))
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "Failed to read settlement id".to_string())?;
+            let id: i32 = inserted.try_get("", "id").map_err(|e| e.to_string())?;
+            settlement_ids.insert(row.id, id);
+        }
+        count.inserted += 1;
     }
+    counts.push(count);
+
+    let mut count = TableImportCount {
+        table: "score_events".to_string(),
+        ..Default::default()
+    };
+    for row in data.score_events {
+        let exists = if replace {
+            false
+        } else {
+            score_events::Entity::find()
+                .filter(score_events::Column::Uuid.eq(&row.uuid))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+                .is_some()
+        };
+        if exists {
+            count.skipped += 1;
+            continue;
+        }
+        score_events::ActiveModel {
+            id: id_value(row.id),
+            uuid: Set(row.uuid),
+            student_id: Set(row
+                .student_id
+                .and_then(|id| student_ids.get(&id).copied())
+                .or_else(|| student_ids_by_name.get(&row.student_name).copied())),
+            student_name: Set(row.student_name),
+            reason_content: Set(row.reason_content),
+            delta: Set(row.delta),
+            val_prev: Set(row.val_prev),
+            val_curr: Set(row.val_curr),
+            event_time: Set(row.event_time),
+            settlement_id: Set(row
+                .settlement_id
+                .and_then(|id| settlement_ids.get(&id).copied())),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut reward_ids: HashMap<i32, i32> = HashMap::new();
+    let mut count = TableImportCount {
+        table: "reward_settings".to_string(),
+        ..Default::default()
+    };
+    for row in data.reward_settings {
+        let existing = if replace {
+            None
+        } else {
+            reward_settings::Entity::find()
+                .filter(reward_settings::Column::Name.eq(&row.name))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+        };
+        if let Some(existing) = existing {
+            reward_ids.insert(row.id, existing.id);
+            count.skipped += 1;
+            continue;
+        }
+        let inserted = reward_settings::ActiveModel {
+            id: id_value(row.id),
+            name: Set(row.name),
+            cost_points: Set(row.cost_points),
+            stock: Set(row.stock),
+            per_student_limit: Set(row.per_student_limit),
+            per_period_limit: Set(row.per_period_limit),
+            active_from: Set(row.active_from),
+            active_until: Set(row.active_until),
+            requires_approval: Set(row.requires_approval),
+            created_at: Set(row.created_at),
+            updated_at: Set(row.updated_at),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        reward_ids.insert(row.id, inserted.id);
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut count = TableImportCount {
+        table: "reward_redemptions".to_string(),
+        ..Default::default()
+    };
+    for row in data.reward_redemptions {
+        let exists = if replace {
+            false
+        } else {
+            reward_redemptions::Entity::find()
+                .filter(reward_redemptions::Column::Uuid.eq(&row.uuid))
+                .one(txn)
+                .await
+                .map_err(|e| e.to_string())?
+                .is_some()
+        };
+        if exists {
+            count.skipped += 1;
+            continue;
+        }
+        reward_redemptions::ActiveModel {
+            id: id_value(row.id),
+            uuid: Set(row.uuid),
+            student_id: Set(row
+                .student_id
+                .and_then(|id| student_ids.get(&id).copied())
+                .or_else(|| student_ids_by_name.get(&row.student_name).copied())),
+            student_name: Set(row.student_name),
+            reward_id: Set(reward_ids
+                .get(&row.reward_id)
+                .copied()
+                .unwrap_or(row.reward_id)),
+            reward_name: Set(row.reward_name),
+            cost_points: Set(row.cost_points),
+            redeemed_at: Set(row.redeemed_at),
+            status: Set(row.status),
+            reviewed_at: Set(row.reviewed_at),
+            review_note: Set(row.review_note),
+            cancelled_at: Set(row.cancelled_at),
+            cancelled_by: Set(row.cancelled_by),
+            cancel_reason: Set(row.cancel_reason),
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| e.to_string())?;
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    let mut count = TableImportCount {
+        table: "board_configs".to_string(),
+        ..Default::default()
+    };
+    for row in data.board_configs {
+        let exists = if replace {
+            false
+        } else {
+            txn.query_one(bind_statement(
+                backend,
+                "SELECT id FROM board_configs WHERE id = ?",
+                vec![row.id.into()],
+            ))
+            .await
+            .map_err(|e| e.to_string())?
+            .is_some()
+        };
+        if exists {
+            count.skipped += 1;
+            continue;
+        }
+        txn.execute(bind_statement(
+            backend,
+            "INSERT INTO board_configs (id, config_json, updated_at) VALUES (?, ?, ?)",
+            vec![row.id.into(), row.config_json.into(), row.updated_at.into()],
+        ))
+        .await
+        .map_err(|e| e.to_string())?;
+        count.inserted += 1;
+    }
+    counts.push(count);
+
+    if replace && backend == DatabaseBackend::Postgres {
+        // 显式写入 id 后需要把 SERIAL 序列推进到最大 id 之后
+        for table in [
+            "students",
+            "reasons",
+            "score_events",
+            "settlements",
+            "tags",
+            "student_tags",
+            "reason_categories",
+            "reason_sets",
+            "reason_set_items",
+            "reward_settings",
+            "reward_redemptions",
+        ] {
+            txn.execute(Statement::from_string(
+                backend,
+                format!(
+                    "SELECT setval(pg_get_serial_sequence('{0}', 'id'), COALESCE((SELECT MAX(id) FROM {0}), 0) + 1, false)",
+                    table
+                ),
+            ))
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(counts)
 }
@@ -0,0 +1,644 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::{
+    operation_journal, reward_redemptions, score_events, student_tags, students, tags,
+};
+use crate::services::reward::{self, RedemptionStatus};
+
+// 只保留最近的操作记录，超出部分在写入时裁剪
+pub const JOURNAL_MAX_ENTRIES: u64 = 200;
+
+/// 可撤销的手动操作，保存足以反向执行的前后状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalOp {
+    ScoreEventsCreated {
+        events: Vec<score_events::Model>,
+    },
+    ScoreEventsDeleted {
+        events: Vec<score_events::Model>,
+    },
+    RewardRedeemed {
+        redemption: Box<reward_redemptions::Model>,
+    },
+    StudentTagsUpdated {
+        student_id: i32,
+        before: Vec<i32>,
+        after: Vec<i32>,
+    },
+    StudentUpdated {
+        before: Box<students::Model>,
+        after: Box<students::Model>,
+    },
+}
+
+impl JournalOp {
+    pub fn op_type(&self) -> &'static str {
+        match self {
+            JournalOp::ScoreEventsCreated { .. } => "score_events_created",
+            JournalOp::ScoreEventsDeleted { .. } => "score_events_deleted",
+            JournalOp::RewardRedeemed { .. } => "reward_redeemed",
+            JournalOp::StudentTagsUpdated { .. } => "student_tags_updated",
+            JournalOp::StudentUpdated { .. } => "student_updated",
+        }
+    }
+
+    // 标签与学生资料的撤销需要管理员权限
+    pub fn requires_admin(&self) -> bool {
+        matches!(
+            self,
+            JournalOp::StudentTagsUpdated { .. } | JournalOp::StudentUpdated { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: i32,
+    pub op_type: String,
+    pub summary: String,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+}
+
+impl From<operation_journal::Model> for JournalEntry {
+    fn from(model: operation_journal::Model) -> Self {
+        Self {
+            id: model.id,
+            op_type: model.op_type,
+            summary: model.summary,
+            created_at: model.created_at,
+            undone_at: model.undone_at,
+        }
+    }
+}
+
+fn now_iso() -> String {
+    chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+/// 在调用方的事务内记录一次操作；新操作会清空可重做的记录
+pub async fn record_operation(
+    conn: &impl ConnectionTrait,
+    op: &JournalOp,
+    summary: &str,
+) -> Result<(), String> {
+    operation_journal::Entity::delete_many()
+        .filter(operation_journal::Column::UndoneAt.is_not_null())
+        .exec(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let payload = serde_json::to_string(op).map_err(|e| e.to_string())?;
+    operation_journal::ActiveModel {
+        id: sea_orm::ActiveValue::NotSet,
+        op_type: Set(op.op_type().to_string()),
+        summary: Set(summary.to_string()),
+        payload: Set(payload),
+        created_at: Set(now_iso()),
+        undone_at: Set(None),
+    }
+    .insert(conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let cutoff = operation_journal::Entity::find()
+        .order_by_desc(operation_journal::Column::Id)
+        .offset(JOURNAL_MAX_ENTRIES)
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(cutoff) = cutoff {
+        operation_journal::Entity::delete_many()
+            .filter(operation_journal::Column::Id.lte(cutoff.id))
+            .exec(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+pub async fn query_journal(
+    conn: &DatabaseConnection,
+    limit: u64,
+) -> Result<Vec<JournalEntry>, String> {
+    let rows = operation_journal::Entity::find()
+        .order_by_desc(operation_journal::Column::Id)
+        .limit(limit)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().map(JournalEntry::from).collect())
+}
+
+/// 撤销最近的 count 条操作，全部成功或全部回滚
+pub async fn undo_operations(
+    conn: &DatabaseConnection,
+    count: u64,
+    allow_admin_ops: bool,
+) -> Result<Vec<JournalEntry>, String> {
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    let rows = operation_journal::Entity::find()
+        .filter(operation_journal::Column::UndoneAt.is_null())
+        .order_by_desc(operation_journal::Column::Id)
+        .limit(count)
+        .all(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if rows.is_empty() {
+        return Err("没有可撤销的操作".to_string());
+    }
+
+    let now = now_iso();
+    let mut applied = Vec::with_capacity(rows.len());
+    for row in rows {
+        let op: JournalOp = serde_json::from_str(&row.payload).map_err(|e| e.to_string())?;
+        if op.requires_admin() && !allow_admin_ops {
+            return Err("Permission denied: admin required".to_string());
+        }
+        revert_op(&txn, &op, &now)
+            .await
+            .map_err(|e| format!("撤销「{}」失败: {}", row.summary, e))?;
+
+        let mut active: operation_journal::ActiveModel = row.into();
+        active.undone_at = Set(Some(now.clone()));
+        applied.push(JournalEntry::from(
+            active.update(&txn).await.map_err(|e| e.to_string())?,
+        ));
+    }
+
+    txn.commit().await.map_err(|e| e.to_string())?;
+    Ok(applied)
+}
+
+/// 按原顺序重做最早被撤销的 count 条操作
+pub async fn redo_operations(
+    conn: &DatabaseConnection,
+    count: u64,
+    allow_admin_ops: bool,
+) -> Result<Vec<JournalEntry>, String> {
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    let rows = operation_journal::Entity::find()
+        .filter(operation_journal::Column::UndoneAt.is_not_null())
+        .order_by_asc(operation_journal::Column::Id)
+        .limit(count)
+        .all(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if rows.is_empty() {
+        return Err("没有可重做的操作".to_string());
+    }
+
+    let now = now_iso();
+    let mut applied = Vec::with_capacity(rows.len());
+    for row in rows {
+        let op: JournalOp = serde_json::from_str(&row.payload).map_err(|e| e.to_string())?;
+        if op.requires_admin() && !allow_admin_ops {
+            return Err("Permission denied: admin required".to_string());
+        }
+        apply_op(&txn, &op, &now)
+            .await
+            .map_err(|e| format!("重做「{}」失败: {}", row.summary, e))?;
+
+        let mut active: operation_journal::ActiveModel = row.into();
+        active.undone_at = Set(None);
+        applied.push(JournalEntry::from(
+            active.update(&txn).await.map_err(|e| e.to_string())?,
+        ));
+    }
+
+    txn.commit().await.map_err(|e| e.to_string())?;
+    Ok(applied)
+}
+
+async fn revert_op(conn: &impl ConnectionTrait, op: &JournalOp, now: &str) -> Result<(), String> {
+    match op {
+        JournalOp::ScoreEventsCreated { events } => remove_events(conn, events, now).await,
+        JournalOp::ScoreEventsDeleted { events } => restore_events(conn, events, now).await,
+        JournalOp::RewardRedeemed { redemption } => remove_redemption(conn, redemption, now).await,
+        JournalOp::StudentTagsUpdated {
+            student_id, before, ..
+        } => replace_student_tags(conn, *student_id, before, now).await,
+        JournalOp::StudentUpdated { before, after } => {
+            restore_student(conn, after, before, now).await
+        }
+    }
+}
+
+async fn apply_op(conn: &impl ConnectionTrait, op: &JournalOp, now: &str) -> Result<(), String> {
+    match op {
+        JournalOp::ScoreEventsCreated { events } => restore_events(conn, events, now).await,
+        JournalOp::ScoreEventsDeleted { events } => remove_events(conn, events, now).await,
+        JournalOp::RewardRedeemed { redemption } => restore_redemption(conn, redemption, now).await,
+        JournalOp::StudentTagsUpdated {
+            student_id, after, ..
+        } => replace_student_tags(conn, *student_id, after, now).await,
+        JournalOp::StudentUpdated { before, after } => {
+            restore_student(conn, before, after, now).await
+        }
+    }
+}
+
+async fn find_student(
+    conn: &impl ConnectionTrait,
+    student_id: Option<i32>,
+    name: &str,
+) -> Result<students::Model, String> {
+    students::find_by_reference(conn, student_id, name)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Student not found: {}", name))
+}
+
+async fn adjust_student_points(
+    conn: &impl ConnectionTrait,
+    student: students::Model,
+    score_delta: i32,
+    reward_delta: i32,
+    now: &str,
+) -> Result<(), String> {
+    let next_score = student.score + score_delta;
+    let next_reward_points = student.reward_points + reward_delta;
+    let mut active: students::ActiveModel = student.into();
+    active.score = Set(next_score);
+    active.reward_points = Set(next_reward_points);
+    active.updated_at = Set(now.to_string());
+    active.update(conn).await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn remove_events(
+    conn: &impl ConnectionTrait,
+    events: &[score_events::Model],
+    now: &str,
+) -> Result<(), String> {
+    for event in events.iter().rev() {
+        let current = score_events::Entity::find()
+            .filter(score_events::Column::Uuid.eq(event.uuid.as_str()))
+            .one(conn)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "积分记录已不存在".to_string())?;
+        if current.settlement_id.is_some() {
+            return Err("该记录已结算，无法撤销".to_string());
+        }
+
+        let student = find_student(conn, current.student_id, &current.student_name).await?;
+        adjust_student_points(conn, student, -current.delta, -current.delta, now).await?;
+        score_events::Entity::delete_by_id(current.id)
+            .exec(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+async fn restore_events(
+    conn: &impl ConnectionTrait,
+    events: &[score_events::Model],
+    now: &str,
+) -> Result<(), String> {
+    for event in events {
+        let student = find_student(conn, event.student_id, &event.student_name).await?;
+        let (student_id, student_name) = (student.id, student.name.clone());
+        // 结算时积分已清零，已结算记录只退回奖励积分并保留当时的分值快照
+        let (score_delta, val_prev, val_curr) = match event.settlement_id {
+            Some(_) => (0, event.val_prev, event.val_curr),
+            None => (event.delta, student.score, student.score + event.delta),
+        };
+        adjust_student_points(conn, student, score_delta, event.delta, now).await?;
+
+        // 以原 uuid 恢复记录，便于后续再次撤销
+        score_events::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            uuid: Set(event.uuid.clone()),
+            student_id: Set(Some(student_id)),
+            student_name: Set(student_name),
+            reason_content: Set(event.reason_content.clone()),
+            delta: Set(event.delta),
+            val_prev: Set(val_prev),
+            val_curr: Set(val_curr),
+            event_time: Set(event.event_time.clone()),
+            settlement_id: Set(event.settlement_id),
+        }
+        .insert(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+async fn remove_redemption(
+    conn: &impl ConnectionTrait,
+    redemption: &reward_redemptions::Model,
+    now: &str,
+) -> Result<(), String> {
+    let current = reward_redemptions::Entity::find()
+        .filter(reward_redemptions::Column::Uuid.eq(redemption.uuid.as_str()))
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "兑换记录已不存在".to_string())?;
+
+    // 已驳回或撤销的兑换当时已退还积分与库存
+    if !RedemptionStatus::of(&current.status).is_refunded() {
+        let student = find_student(conn, current.student_id, &current.student_name).await?;
+        adjust_student_points(conn, student, 0, current.cost_points, now).await?;
+        reward::restore_stock(conn, current.reward_id).await?;
+    }
+    reward_redemptions::Entity::delete_by_id(current.id)
+        .exec(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn restore_redemption(
+    conn: &impl ConnectionTrait,
+    redemption: &reward_redemptions::Model,
+    now: &str,
+) -> Result<(), String> {
+    let student = find_student(conn, redemption.student_id, &redemption.student_name).await?;
+    if student.reward_points < redemption.cost_points {
+        return Err("Insufficient reward points".to_string());
+    }
+    if !reward::take_stock(conn, redemption.reward_id).await? {
+        return Err("Reward out of stock".to_string());
+    }
+    let (student_id, student_name) = (student.id, student.name.clone());
+    adjust_student_points(conn, student, 0, -redemption.cost_points, now).await?;
+
+    reward_redemptions::ActiveModel {
+        id: sea_orm::ActiveValue::NotSet,
+        uuid: Set(redemption.uuid.clone()),
+        student_id: Set(Some(student_id)),
+        student_name: Set(student_name),
+        reward_id: Set(redemption.reward_id),
+        reward_name: Set(redemption.reward_name.clone()),
+        cost_points: Set(redemption.cost_points),
+        redeemed_at: Set(redemption.redeemed_at.clone()),
+        status: Set(redemption.status.clone()),
+        reviewed_at: Set(redemption.reviewed_at.clone()),
+        review_note: Set(redemption.review_note.clone()),
+        cancelled_at: Set(redemption.cancelled_at.clone()),
+        cancelled_by: Set(redemption.cancelled_by.clone()),
+        cancel_reason: Set(redemption.cancel_reason.clone()),
+    }
+    .insert(conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn replace_student_tags(
+    conn: &impl ConnectionTrait,
+    student_id: i32,
+    tag_ids: &[i32],
+    now: &str,
+) -> Result<(), String> {
+    if students::Entity::find_by_id(student_id)
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_none()
+    {
+        return Err("Student not found".to_string());
+    }
+
+    student_tags::Entity::delete_many()
+        .filter(student_tags::Column::StudentId.eq(student_id))
+        .exec(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if tag_ids.is_empty() {
+        return Ok(());
+    }
+
+    // 期间被删除的标签不再恢复
+    let existing: Vec<i32> = tags::Entity::find()
+        .filter(tags::Column::Id.is_in(tag_ids.to_vec()))
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|tag| tag.id)
+        .collect();
+
+    for tag_id in tag_ids.iter().filter(|id| existing.contains(id)) {
+        student_tags::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            student_id: Set(student_id),
+            tag_id: Set(*tag_id),
+            created_at: Set(now.to_string()),
+        }
+        .insert(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 积分按差值回退，以免覆盖期间由自动化等产生的变动
+async fn restore_student(
+    conn: &impl ConnectionTrait,
+    from: &students::Model,
+    to: &students::Model,
+    now: &str,
+) -> Result<(), String> {
+    let current = students::Entity::find_by_id(to.id)
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Student not found".to_string())?;
+
+    if from.name != to.name {
+        let conflict = students::Entity::find()
+            .filter(students::Column::Name.eq(to.name.as_str()))
+            .filter(students::Column::Id.ne(to.id))
+            .one(conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        if conflict.is_some() {
+            return Err("Student with this name already exists".to_string());
+        }
+    }
+
+    let next_score = current.score + (to.score - from.score);
+    let next_reward_points = current.reward_points + (to.reward_points - from.reward_points);
+    let mut active: students::ActiveModel = current.into();
+    active.name = Set(to.name.clone());
+    active.group_name = Set(to.group_name.clone());
+    active.score = Set(next_score);
+    active.reward_points = Set(next_reward_points);
+    active.tags = Set(to.tags.clone());
+    active.extra_json = Set(to.extra_json.clone());
+    active.updated_at = Set(now.to_string());
+    active.update(conn).await.map_err(|e| e.to_string())?;
+    if from.name != to.name {
+        students::rename_references(conn, to.id, &to.name)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        query_journal, record_operation, redo_operations, undo_operations, JournalOp,
+        JOURNAL_MAX_ENTRIES,
+    };
+    use crate::db::connection::memory_sqlite_connection;
+    use crate::db::entities::{operation_journal, score_events, students};
+    use crate::db::{run_migration, DatabaseType};
+    use crate::services::score::{add_score, revert_score_event};
+    use sea_orm::{
+        ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait,
+        QueryFilter, Set,
+    };
+
+    async fn setup() -> (DatabaseConnection, students::Model) {
+        let conn = memory_sqlite_connection().await;
+        run_migration(&conn, DatabaseType::SQLite).await.unwrap();
+        let student = students::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            uuid: Set("00000000-0000-4000-8000-000000000001".to_string()),
+            name: Set("张三".to_string()),
+            group_name: Set(None),
+            score: Set(10),
+            reward_points: Set(10),
+            tags: Set("[]".to_string()),
+            extra_json: Set(None),
+            created_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+            updated_at: Set("2024-01-01T00:00:00.000Z".to_string()),
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+        (conn, student)
+    }
+
+    async fn points(conn: &DatabaseConnection, id: i32) -> (i32, i32) {
+        let student = students::Entity::find_by_id(id)
+            .one(conn)
+            .await
+            .unwrap()
+            .unwrap();
+        (student.score, student.reward_points)
+    }
+
+    async fn find_event(conn: &DatabaseConnection, uuid: &str) -> Option<score_events::Model> {
+        score_events::Entity::find()
+            .filter(score_events::Column::Uuid.eq(uuid))
+            .one(conn)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_undo_redo_round_trip() {
+        let (conn, student) = setup().await;
+        let event = add_score(&conn, Some(student.id), "", 5, "课堂表现优秀", None)
+            .await
+            .unwrap();
+        assert_eq!(points(&conn, student.id).await, (15, 15));
+
+        undo_operations(&conn, 1, false).await.unwrap();
+        assert_eq!(points(&conn, student.id).await, (10, 10));
+        assert!(find_event(&conn, &event.uuid).await.is_none());
+
+        redo_operations(&conn, 1, false).await.unwrap();
+        assert_eq!(points(&conn, student.id).await, (15, 15));
+        let restored = find_event(&conn, &event.uuid).await.unwrap();
+        assert_eq!((restored.val_prev, restored.val_curr), (10, 15));
+        assert_eq!(restored.settlement_id, None);
+        assert!(redo_operations(&conn, 1, false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn undo_delete_recomputes_values_from_current_score() {
+        let (conn, student) = setup().await;
+        let first = add_score(&conn, Some(student.id), "", 5, "帮助同学", None)
+            .await
+            .unwrap();
+        add_score(&conn, Some(student.id), "", 3, "作业完成优秀", None)
+            .await
+            .unwrap();
+        revert_score_event(&conn, &first.uuid).await.unwrap();
+        assert_eq!(points(&conn, student.id).await, (13, 13));
+
+        undo_operations(&conn, 1, false).await.unwrap();
+        assert_eq!(points(&conn, student.id).await, (18, 18));
+        let restored = find_event(&conn, &first.uuid).await.unwrap();
+        assert_eq!((restored.val_prev, restored.val_curr), (13, 18));
+    }
+
+    #[tokio::test]
+    async fn undo_delete_keeps_settlement() {
+        let (conn, student) = setup().await;
+        let settled = score_events::Model {
+            id: 0,
+            uuid: "00000000-0000-4000-8000-0000000000aa".to_string(),
+            student_id: Some(student.id),
+            student_name: student.name.clone(),
+            reason_content: "帮助同学".to_string(),
+            delta: 4,
+            val_prev: 20,
+            val_curr: 24,
+            event_time: "2024-01-02T00:00:00.000Z".to_string(),
+            settlement_id: Some(7),
+        };
+        record_operation(
+            &conn,
+            &JournalOp::ScoreEventsDeleted {
+                events: vec![settled.clone()],
+            },
+            "删除",
+        )
+        .await
+        .unwrap();
+
+        undo_operations(&conn, 1, false).await.unwrap();
+        // 结算后积分已清零，只恢复奖励积分
+        assert_eq!(points(&conn, student.id).await, (10, 14));
+        let restored = find_event(&conn, &settled.uuid).await.unwrap();
+        assert_eq!(restored.settlement_id, Some(7));
+        assert_eq!((restored.val_prev, restored.val_curr), (20, 24));
+    }
+
+    #[tokio::test]
+    async fn journal_is_capped_and_new_ops_clear_redo() {
+        let (conn, _) = setup().await;
+        let op = JournalOp::ScoreEventsCreated { events: vec![] };
+        for index in 0..JOURNAL_MAX_ENTRIES + 5 {
+            record_operation(&conn, &op, &format!("op {}", index))
+                .await
+                .unwrap();
+        }
+        let total = operation_journal::Entity::find()
+            .count(&conn)
+            .await
+            .unwrap();
+        assert_eq!(total, JOURNAL_MAX_ENTRIES);
+        let newest = query_journal(&conn, 1).await.unwrap();
+        assert_eq!(newest[0].summary, format!("op {}", JOURNAL_MAX_ENTRIES + 4));
+
+        let undone = undo_operations(&conn, 2, false).await.unwrap();
+        assert_eq!(undone.len(), 2);
+        record_operation(&conn, &op, "new").await.unwrap();
+        let total = operation_journal::Entity::find()
+            .count(&conn)
+            .await
+            .unwrap();
+        assert_eq!(total, JOURNAL_MAX_ENTRIES - 1);
+        assert!(redo_operations(&conn, 1, false).await.is_err());
+    }
+}
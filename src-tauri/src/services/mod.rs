@@ -1,25 +1,39 @@
 pub mod auth;
 pub mod auto_score;
+pub mod backup;
+pub mod bus;
 pub mod data;
+pub mod journal;
 pub mod logger;
 pub mod permission;
 pub mod plugin;
+pub mod profile;
+pub mod reason;
+pub mod reward;
+pub mod score;
 pub mod security;
 pub mod settings;
+pub mod tag;
 pub mod theme;
 pub mod workspace;
 
 pub use auth::AuthService;
 pub use auto_score::{
-    apply_offline_backfill, query_execution_batches, rollback_execution_batch, AutoScoreAction,
-    AutoScoreBackfillItem, AutoScoreBackfillResult, AutoScoreExecutionBatch,
-    AutoScoreExecutionConfig, AutoScoreFilterConfig, AutoScoreRule, AutoScoreService,
-    AutoScoreTrigger,
+    apply_offline_backfill, preview_rule, query_execution_batches, rollback_execution_batch,
+    AutoScoreAction, AutoScoreBackfillItem, AutoScoreBackfillResult, AutoScoreBatchPage,
+    AutoScoreBatchPageParams, AutoScoreExecutionBatch, AutoScoreExecutionConfig,
+    AutoScoreFilterConfig, AutoScoreRule, AutoScoreRulePreview, AutoScoreService, AutoScoreTrigger,
+    AUTO_SCORE_REASON_PREFIX,
 };
+pub use backup::{BackupEntry, BackupKind};
+pub use bus::{BusPayload, EventBus, EventSource};
 pub use data::DataService;
+pub use journal::{JournalEntry, JournalOp};
 pub use logger::LoggerService;
-pub use permission::{PermissionLevel, PermissionService};
+pub use permission::{window_session_key, PermissionLevel, PermissionService};
 pub use plugin::{Plugin, PluginManifest, PluginRuntimeModule, PluginService, PluginStats};
+pub use reason::ReasonFilter;
+pub use reward::RedemptionStatus;
 pub use security::SecurityService;
 pub use settings::{SettingsKey, SettingsService, SettingsSpec, SettingsValue};
 pub use theme::{ThemeConfig, ThemeService};
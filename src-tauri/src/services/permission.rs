@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::state::SafeAppState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum PermissionLevel {
@@ -38,10 +42,85 @@ impl PermissionLevel {
 pub const SETTINGS_SECURITY_ADMIN: &str = "security_admin_password";
 pub const SETTINGS_SECURITY_POINTS: &str = "security_points_password";
 
+pub const PERMISSION_CHANGED_EVENT: &str = "ss:permission-changed";
+const DEFAULT_IDLE_TIMEOUT_SECONDS: u64 = 10 * 60;
+const SESSION_SWEEP_SECONDS: u64 = 30;
+const LOGIN_FREE_ATTEMPTS: u32 = 5;
+const LOGIN_LOCKOUT_BASE_SECONDS: u64 = 30;
+const LOGIN_LOCKOUT_MAX_SECONDS: u64 = 60 * 60;
+const TOKEN_SESSION_RETENTION_SECONDS: u64 = 60 * 60;
+
+/// 桌面端窗口会话，按 webview label 区分
+pub fn window_session_key(label: &str) -> String {
+    format!("window:{}", label)
+}
+
+/// 局域网浏览器会话，按受信任的 cookie 令牌区分
+pub fn lan_session_key(token: &str) -> String {
+    format!("lan:{}", token)
+}
+
+/// 局域网登录失败按客户端 IP 计数，重新领取设备令牌不会清零
+pub fn lan_client_key(ip: &str) -> String {
+    format!("lan-client:{}", ip)
+}
+
+/// MCP 登录失败按客户端地址计数，新建会话不会清零
+pub fn mcp_client_key(client: &str) -> String {
+    format!("mcp-client:{}", client)
+}
+
+/// MCP 客户端会话，按客户端名称区分
+pub fn mcp_session_key(client: &str) -> String {
+    format!("mcp:{}", client)
+}
+
+/// 写入记录的操作人：只保留会话类型与当时的权限级别，不落库会话令牌
+pub fn session_actor(session: &str, level: PermissionLevel) -> String {
+    let kind = match session.split_once(':').map(|(kind, _)| kind) {
+        Some("window") => "desktop",
+        Some("lan") => "lan",
+        Some("mcp") => "mcp",
+        _ => "unknown",
+    };
+    format!("{}:{}", kind, level.as_str())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionChangeReason {
+    Elevated,
+    Expired,
+    Locked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionChange {
+    pub session: String,
+    pub permission: String,
+    pub reason: PermissionChangeReason,
+}
+
+struct PermissionSession {
+    level: PermissionLevel,
+    // 令牌授予的保底级别，锁定或超时后回落到这里而不是默认级别
+    base: Option<PermissionLevel>,
+    last_active: Instant,
+}
+
+#[derive(Default)]
+struct LoginFailures {
+    count: u32,
+    locked_until: Option<Instant>,
+}
+
 pub struct PermissionService {
-    permissions_by_sender: HashMap<u32, PermissionLevel>,
+    sessions: HashMap<String, PermissionSession>,
+    login_failures: HashMap<String, LoginFailures>,
     has_admin_password: bool,
     has_points_password: bool,
+    idle_timeout: Option<Duration>,
+    pending_changes: Vec<PermissionChange>,
 }
 
 impl Default for PermissionService {
@@ -53,9 +132,12 @@ impl Default for PermissionService {
 impl PermissionService {
     pub fn new() -> Self {
         Self {
-            permissions_by_sender: HashMap::new(),
+            sessions: HashMap::new(),
+            login_failures: HashMap::new(),
             has_admin_password: false,
             has_points_password: false,
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECONDS)),
+            pending_changes: Vec::new(),
         }
     }
 
@@ -64,6 +146,11 @@ impl PermissionService {
         self.has_points_password = has_points;
     }
 
+    /// `None` 表示会话永不因空闲而过期
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
     pub fn should_protect(&self) -> bool {
         self.has_admin_password || self.has_points_password
     }
@@ -76,30 +163,256 @@ impl PermissionService {
         }
     }
 
-    pub fn get_permission(&mut self, sender_id: u32) -> PermissionLevel {
-        if let Some(&level) = self.permissions_by_sender.get(&sender_id) {
-            level
-        } else {
-            let default = self.get_default_permission();
-            self.permissions_by_sender.insert(sender_id, default);
-            default
+    fn fallback_permission(&self, session: &str) -> PermissionLevel {
+        let default = self.get_default_permission();
+        self.sessions
+            .get(session)
+            .and_then(|s| s.base)
+            .unwrap_or(default)
+    }
+
+    pub fn get_permission(&mut self, session: &str) -> PermissionLevel {
+        self.expire_if_idle(session);
+        match self.sessions.get(session) {
+            Some(s) => s.level,
+            None => self.get_default_permission(),
+        }
+    }
+
+    pub fn set_permission(&mut self, session: &str, level: PermissionLevel) {
+        let fallback = self.fallback_permission(session);
+        let previous = self.sessions.get(session).map(|s| s.level);
+        let entry = self
+            .sessions
+            .entry(session.to_string())
+            .or_insert(PermissionSession {
+                level,
+                base: None,
+                last_active: Instant::now(),
+            });
+        entry.level = level;
+        entry.last_active = Instant::now();
+        if level.rank() > fallback.rank() && previous != Some(level) {
+            self.push_change(session, level, PermissionChangeReason::Elevated);
         }
     }
 
-    pub fn set_permission(&mut self, sender_id: u32, level: PermissionLevel) {
-        self.permissions_by_sender.insert(sender_id, level);
+    /// 为令牌类会话（局域网、MCP）登记保底级别
+    pub fn bind_session(&mut self, session: &str, base: PermissionLevel) {
+        let entry = self
+            .sessions
+            .entry(session.to_string())
+            .or_insert(PermissionSession {
+                level: base,
+                base: Some(base),
+                last_active: Instant::now(),
+            });
+        let previous = entry.base.replace(base);
+        // 保底级别下调（如新设了口令）时，未额外登录的会话随之回落
+        let lowered = previous.is_some_and(|p| p.rank() > base.rank() && entry.level == p);
+        if lowered || entry.level.rank() < base.rank() {
+            entry.level = base;
+        }
     }
 
-    pub fn require_permission(&mut self, sender_id: u32, required: PermissionLevel) -> bool {
-        let current = self.get_permission(sender_id);
-        current.rank() >= required.rank()
+    pub fn require_permission(&mut self, session: &str, required: PermissionLevel) -> bool {
+        let current = self.get_permission(session);
+        let granted = current.rank() >= required.rank();
+        if granted {
+            if let Some(s) = self.sessions.get_mut(session) {
+                s.last_active = Instant::now();
+            }
+        }
+        granted
     }
 
-    pub fn clear_permission(&mut self, sender_id: u32) {
-        self.permissions_by_sender.remove(&sender_id);
+    /// 主动锁定：回落到保底级别
+    pub fn lock(&mut self, session: &str) -> PermissionLevel {
+        let fallback = self.fallback_permission(session);
+        if let Some(s) = self.sessions.get_mut(session) {
+            let changed = s.level != fallback;
+            s.level = fallback;
+            if changed {
+                self.push_change(session, fallback, PermissionChangeReason::Locked);
+            }
+        }
+        fallback
+    }
+
+    pub fn clear_permission(&mut self, session: &str) {
+        self.sessions.remove(session);
     }
 
     pub fn clear_all_permissions(&mut self) {
-        self.permissions_by_sender.clear();
+        self.sessions.clear();
+    }
+
+    pub fn failed_login_attempts(&self, session: &str) -> u32 {
+        self.login_failures
+            .get(session)
+            .map(|f| f.count)
+            .unwrap_or(0)
+    }
+
+    pub fn login_lockout_remaining(&self, session: &str) -> Option<Duration> {
+        let locked_until = self.login_failures.get(session)?.locked_until?;
+        let remaining = locked_until.saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// 记录一次失败登录，超过免罚次数后按指数退避锁定，返回锁定时长
+    pub fn record_login_failure(&mut self, session: &str) -> Option<Duration> {
+        let failures = self.login_failures.entry(session.to_string()).or_default();
+        failures.count += 1;
+        if failures.count < LOGIN_FREE_ATTEMPTS {
+            return None;
+        }
+        let exponent = (failures.count - LOGIN_FREE_ATTEMPTS).min(16);
+        let seconds = LOGIN_LOCKOUT_BASE_SECONDS
+            .saturating_mul(1u64 << exponent)
+            .min(LOGIN_LOCKOUT_MAX_SECONDS);
+        let lockout = Duration::from_secs(seconds);
+        failures.locked_until = Some(Instant::now() + lockout);
+        Some(lockout)
+    }
+
+    pub fn record_login_success(&mut self, session: &str) {
+        self.login_failures.remove(session);
+    }
+
+    pub fn sweep_expired(&mut self) {
+        let keys = self.sessions.keys().cloned().collect::<Vec<_>>();
+        for key in keys {
+            self.expire_if_idle(&key);
+        }
+        self.drop_idle_token_sessions(Instant::now());
+    }
+
+    /// 长时间无活动且已回落到保底级别的令牌会话直接移除，下次请求时会重新登记
+    fn drop_idle_token_sessions(&mut self, now: Instant) {
+        let retention = Duration::from_secs(TOKEN_SESSION_RETENTION_SECONDS);
+        self.sessions.retain(|_, s| {
+            let at_base = s.base.is_some_and(|base| s.level == base);
+            !at_base || now.saturating_duration_since(s.last_active) < retention
+        });
+    }
+
+    pub fn take_changes(&mut self) -> Vec<PermissionChange> {
+        std::mem::take(&mut self.pending_changes)
+    }
+
+    fn expire_if_idle(&mut self, session: &str) {
+        let Some(timeout) = self.idle_timeout else {
+            return;
+        };
+        let fallback = self.fallback_permission(session);
+        let Some(s) = self.sessions.get_mut(session) else {
+            return;
+        };
+        if s.level.rank() <= fallback.rank() || s.last_active.elapsed() < timeout {
+            return;
+        }
+        s.level = fallback;
+        self.push_change(session, fallback, PermissionChangeReason::Expired);
+    }
+
+    fn push_change(
+        &mut self,
+        session: &str,
+        level: PermissionLevel,
+        reason: PermissionChangeReason,
+    ) {
+        self.pending_changes.push(PermissionChange {
+            session: session.to_string(),
+            permission: level.as_str().to_string(),
+            reason,
+        });
+    }
+}
+
+pub fn emit_permission_changes(app_handle: &AppHandle, changes: &[PermissionChange]) {
+    for change in changes {
+        let _ = app_handle.emit(PERMISSION_CHANGED_EVENT, change);
+    }
+}
+
+/// 定期检查空闲会话，过期后通知前端
+pub fn spawn_session_sweeper(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(SESSION_SWEEP_SECONDS));
+        loop {
+            ticker.tick().await;
+            let state = app_handle.state::<SafeAppState>().inner().clone();
+            let changes = {
+                let state_guard = state.read();
+                let mut permissions = state_guard.permissions.write();
+                permissions.sweep_expired();
+                permissions.take_changes()
+            };
+            emit_permission_changes(&app_handle, &changes);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_actor_drops_session_tokens() {
+        let cases = [
+            (
+                window_session_key("main"),
+                PermissionLevel::Admin,
+                "desktop:admin",
+            ),
+            (
+                lan_session_key("secret-token"),
+                PermissionLevel::Points,
+                "lan:points",
+            ),
+            (mcp_session_key("8f0c"), PermissionLevel::View, "mcp:view"),
+            ("sync".to_string(), PermissionLevel::Admin, "unknown:admin"),
+        ];
+        for (session, level, expected) in cases {
+            assert_eq!(
+                session_actor(&session, level),
+                expected,
+                "case: {}",
+                session
+            );
+        }
+    }
+
+    #[test]
+    fn idle_token_sessions_are_dropped_at_base_level() {
+        let mut permissions = PermissionService::new();
+        permissions.update_password_status(true, true);
+        permissions.bind_session("mcp:idle", PermissionLevel::View);
+        permissions.bind_session("mcp:elevated", PermissionLevel::View);
+        permissions.set_permission("mcp:elevated", PermissionLevel::Admin);
+        permissions.set_permission("window:main", PermissionLevel::Points);
+
+        permissions.drop_idle_token_sessions(Instant::now());
+        assert_eq!(permissions.sessions.len(), 3);
+
+        let later = Instant::now() + Duration::from_secs(TOKEN_SESSION_RETENTION_SECONDS);
+        permissions.drop_idle_token_sessions(later);
+        assert!(!permissions.sessions.contains_key("mcp:idle"));
+        assert!(permissions.sessions.contains_key("mcp:elevated"));
+        assert!(permissions.sessions.contains_key("window:main"));
+    }
+
+    #[test]
+    fn mcp_lockout_is_per_client() {
+        let mut permissions = PermissionService::new();
+        let first = mcp_client_key("192.168.1.20");
+        let second = mcp_client_key("192.168.1.21");
+        for _ in 0..LOGIN_FREE_ATTEMPTS {
+            permissions.record_login_failure(&first);
+        }
+        assert!(permissions.login_lockout_remaining(&first).is_some());
+        assert!(permissions.login_lockout_remaining(&second).is_none());
+        assert_eq!(permissions.failed_login_attempts(&second), 0);
     }
 }
@@ -0,0 +1,651 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Statement};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::entities::{
+    reasons, reward_redemptions, score_events, student_tags, students, tags,
+};
+use crate::services::reward::RedemptionStatus;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileBucket {
+    #[default]
+    Day,
+    /// 按自然周（周一开始）汇总
+    Week,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StudentProfileParams {
+    #[serde(default, alias = "studentId")]
+    pub student_id: Option<i32>,
+    #[serde(default, alias = "studentName")]
+    pub student_name: String,
+    #[serde(default)]
+    pub bucket: ProfileBucket,
+    /// 时间序列、分类统计和连续天数只统计该日期（含）之后的记录
+    #[serde(default, alias = "startDate")]
+    pub start_date: Option<String>,
+    #[serde(default, alias = "endDate")]
+    pub end_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSeriesPoint {
+    /// 当天日期，或该周周一的日期
+    pub bucket: String,
+    pub event_count: usize,
+    pub score_delta: i64,
+    /// 该时段最后一条记录后的积分
+    pub score: i32,
+    pub reward_points_delta: i64,
+    /// 该时段结束时的奖励积分，由当前余额倒推
+    pub reward_points: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileCategoryTotal {
+    /// 找不到对应理由（自动化加分、已删除理由）时为 None
+    pub category: Option<String>,
+    pub count: usize,
+    pub total_delta: i64,
+    pub positive_delta: i64,
+    pub negative_delta: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileStreaks {
+    /// 截至今天（今天尚无记录时截至昨天）连续净加分的天数
+    pub current: u32,
+    pub longest: u32,
+    pub longest_start: Option<String>,
+    pub longest_end: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileRankEntry {
+    pub settlement_id: i32,
+    pub start_time: String,
+    pub end_time: String,
+    /// 该结算周期内没有记录时为 None
+    pub rank: Option<usize>,
+    pub participants: usize,
+    pub score: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileTag {
+    pub id: i32,
+    pub name: String,
+    pub added_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentProfile {
+    pub student: students::Model,
+    pub bucket: ProfileBucket,
+    pub series: Vec<ProfileSeriesPoint>,
+    pub categories: Vec<ProfileCategoryTotal>,
+    pub streaks: ProfileStreaks,
+    pub rank_history: Vec<ProfileRankEntry>,
+    pub redemptions: Vec<reward_redemptions::Model>,
+    pub tags: Vec<ProfileTag>,
+}
+
+/// 记录时间可能是 RFC 3339，也可能是旧版写入的本地时间
+fn event_local_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local).date_naive());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+            return Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|time| time.date_naive());
+        }
+    }
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn parse_date_param(value: Option<&str>, label: &str) -> Result<Option<NaiveDate>, String> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(value) => event_local_date(value)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid {}: {}", label, value)),
+        None => Ok(None),
+    }
+}
+
+fn bucket_key(date: NaiveDate, bucket: ProfileBucket) -> NaiveDate {
+    match bucket {
+        ProfileBucket::Day => date,
+        ProfileBucket::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+    }
+}
+
+fn compute_streaks(daily: &BTreeMap<NaiveDate, i64>, today: NaiveDate) -> ProfileStreaks {
+    let mut streaks = ProfileStreaks::default();
+    let mut run: Option<(NaiveDate, NaiveDate, u32)> = None;
+    for (date, delta) in daily {
+        if *delta <= 0 {
+            run = None;
+            continue;
+        }
+        run = match run {
+            Some((start, end, len)) if end + Duration::days(1) == *date => {
+                Some((start, *date, len + 1))
+            }
+            _ => Some((*date, *date, 1)),
+        };
+        if let Some((start, end, len)) = run {
+            if len > streaks.longest {
+                streaks.longest = len;
+                streaks.longest_start = Some(start.to_string());
+                streaks.longest_end = Some(end.to_string());
+            }
+        }
+    }
+    // 今天还没有记录不算中断
+    if let Some((_, end, len)) = run {
+        if end == today || (end + Duration::days(1) == today && !daily.contains_key(&today)) {
+            streaks.current = len;
+        }
+    }
+    streaks
+}
+
+async fn query_rank_history(
+    conn: &impl ConnectionTrait,
+    student: &students::Model,
+) -> Result<Vec<ProfileRankEntry>, String> {
+    let backend = conn.get_database_backend();
+    let settlements = conn
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT id, start_time, end_time FROM settlements ORDER BY end_time ASC, id ASC"
+                .to_string(),
+        ))
+        .await
+        .map_err(|e| format!("Failed to query settlements: {}", e))?;
+
+    // 与结算排行榜一致：按结算内积分变化之和排名，已删除学生按快照姓名计
+    let totals = conn
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT settlement_id, student_id, student_name, SUM(delta) AS total \
+             FROM score_events WHERE settlement_id IS NOT NULL \
+             GROUP BY settlement_id, student_id, student_name"
+                .to_string(),
+        ))
+        .await
+        .map_err(|e| format!("Failed to query settlement totals: {}", e))?;
+
+    let mut by_settlement: HashMap<i32, HashMap<String, i64>> = HashMap::new();
+    for row in totals {
+        let settlement_id: i32 = row
+            .try_get("", "settlement_id")
+            .map_err(|e| e.to_string())?;
+        let student_id: Option<i32> = row.try_get("", "student_id").map_err(|e| e.to_string())?;
+        let student_name: String = row.try_get("", "student_name").map_err(|e| e.to_string())?;
+        let total: Option<i64> = row.try_get("", "total").map_err(|e| e.to_string())?;
+        let key = match student_id {
+            Some(id) => format!("id:{}", id),
+            None => format!("name:{}", student_name),
+        };
+        *by_settlement
+            .entry(settlement_id)
+            .or_default()
+            .entry(key)
+            .or_insert(0) += total.unwrap_or(0);
+    }
+
+    let own_key = format!("id:{}", student.id);
+    let mut history = Vec::with_capacity(settlements.len());
+    for row in settlements {
+        let settlement_id: i32 = row.try_get("", "id").map_err(|e| e.to_string())?;
+        let scores = by_settlement.remove(&settlement_id).unwrap_or_default();
+        let own = scores.get(&own_key).copied();
+        history.push(ProfileRankEntry {
+            settlement_id,
+            start_time: row.try_get("", "start_time").map_err(|e| e.to_string())?,
+            end_time: row.try_get("", "end_time").map_err(|e| e.to_string())?,
+            rank: own.map(|own| 1 + scores.values().filter(|score| **score > own).count()),
+            participants: scores.len(),
+            score: own.unwrap_or(0),
+        });
+    }
+    Ok(history)
+}
+
+pub async fn build_student_profile(
+    conn: &impl ConnectionTrait,
+    params: &StudentProfileParams,
+) -> Result<StudentProfile, String> {
+    let student_name = params.student_name.trim();
+    if params.student_id.is_none() && student_name.is_empty() {
+        return Err("student_id or student_name is required".to_string());
+    }
+    let Some(student) = students::find_by_reference(conn, params.student_id, student_name)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Err("Student not found".to_string());
+    };
+
+    let start_date = parse_date_param(params.start_date.as_deref(), "start date")?;
+    let end_date = parse_date_param(params.end_date.as_deref(), "end date")?;
+    let in_range = |date: NaiveDate| {
+        start_date.map_or(true, |start| date >= start) && end_date.map_or(true, |end| date <= end)
+    };
+
+    let events = score_events::Entity::find()
+        .filter(score_events::Column::StudentId.eq(student.id))
+        .order_by_asc(score_events::Column::EventTime)
+        .order_by_asc(score_events::Column::Id)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut redemptions = reward_redemptions::Entity::find()
+        .filter(reward_redemptions::Column::StudentId.eq(student.id))
+        .order_by_desc(reward_redemptions::Column::RedeemedAt)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let categories_by_content = reasons::Entity::find()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|r| (r.content, r.category))
+        .collect::<HashMap<_, _>>();
+
+    // (事件数, 积分变化, 时段末积分, 奖励积分变化)
+    let mut buckets: BTreeMap<NaiveDate, (usize, i64, i32, i64)> = BTreeMap::new();
+    let mut daily: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut categories: BTreeMap<Option<String>, ProfileCategoryTotal> = BTreeMap::new();
+    for event in &events {
+        let Some(date) = event_local_date(&event.event_time) else {
+            continue;
+        };
+        let entry = buckets
+            .entry(bucket_key(date, params.bucket))
+            .or_insert((0, 0, 0, 0));
+        entry.0 += 1;
+        entry.1 += event.delta as i64;
+        entry.2 = event.val_curr;
+        entry.3 += event.delta as i64;
+
+        if !in_range(date) {
+            continue;
+        }
+        *daily.entry(date).or_insert(0) += event.delta as i64;
+        let category = categories_by_content
+            .get(&event.reason_content)
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty());
+        let total = categories
+            .entry(category.clone())
+            .or_insert(ProfileCategoryTotal {
+                category,
+                count: 0,
+                total_delta: 0,
+                positive_delta: 0,
+                negative_delta: 0,
+            });
+        total.count += 1;
+        total.total_delta += event.delta as i64;
+        if event.delta > 0 {
+            total.positive_delta += event.delta as i64;
+        } else {
+            total.negative_delta += event.delta as i64;
+        }
+    }
+    // 已退还的兑换积分净变化为零，不计入
+    for redemption in &redemptions {
+        if RedemptionStatus::of(&redemption.status).is_refunded() {
+            continue;
+        }
+        let Some(date) = event_local_date(&redemption.redeemed_at) else {
+            continue;
+        };
+        let last_score = buckets
+            .range(..=bucket_key(date, params.bucket))
+            .next_back()
+            .map_or(0, |(_, entry)| entry.2);
+        let entry = buckets
+            .entry(bucket_key(date, params.bucket))
+            .or_insert((0, 0, last_score, 0));
+        entry.3 -= redemption.cost_points as i64;
+    }
+
+    let mut reward_points = student.reward_points as i64;
+    let mut series = buckets
+        .into_iter()
+        .rev()
+        .map(
+            |(date, (event_count, score_delta, score, reward_points_delta))| {
+                let point = ProfileSeriesPoint {
+                    bucket: date.to_string(),
+                    event_count,
+                    score_delta,
+                    score,
+                    reward_points_delta,
+                    reward_points,
+                };
+                reward_points -= reward_points_delta;
+                point
+            },
+        )
+        .collect::<Vec<_>>();
+    series.reverse();
+    series.retain(|point| {
+        NaiveDate::parse_from_str(&point.bucket, "%Y-%m-%d").map_or(true, |date| {
+            // 周桶只要与范围有交集就保留
+            let last_day = match params.bucket {
+                ProfileBucket::Day => date,
+                ProfileBucket::Week => date + Duration::days(6),
+            };
+            start_date.map_or(true, |start| last_day >= start)
+                && end_date.map_or(true, |end| date <= end)
+        })
+    });
+
+    let mut categories = categories.into_values().collect::<Vec<_>>();
+    categories.sort_by(|a, b| b.count.cmp(&a.count).then(a.category.cmp(&b.category)));
+
+    redemptions.retain(|r| event_local_date(&r.redeemed_at).map_or(true, in_range));
+
+    let tags = student_tags::Entity::find()
+        .filter(student_tags::Column::StudentId.eq(student.id))
+        .find_also_related(tags::Entity)
+        .order_by_asc(student_tags::Column::CreatedAt)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter_map(|(link, tag)| {
+            tag.map(|tag| ProfileTag {
+                id: tag.id,
+                name: tag.name,
+                added_at: link.created_at,
+            })
+        })
+        .collect();
+
+    Ok(StudentProfile {
+        streaks: compute_streaks(&daily, Local::now().date_naive()),
+        rank_history: query_rank_history(conn, &student).await?,
+        student,
+        bucket: params.bucket,
+        series,
+        categories,
+        redemptions,
+        tags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::connection::memory_sqlite_connection;
+    use crate::db::{run_migration, DatabaseType};
+    use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn bucket_key_starts_weeks_on_monday() {
+        let cases = [
+            ("2026-03-02", ProfileBucket::Day, "2026-03-02"),
+            ("2026-03-08", ProfileBucket::Day, "2026-03-08"),
+            ("2026-03-02", ProfileBucket::Week, "2026-03-02"),
+            ("2026-03-04", ProfileBucket::Week, "2026-03-02"),
+            ("2026-03-08", ProfileBucket::Week, "2026-03-02"),
+            ("2026-03-09", ProfileBucket::Week, "2026-03-09"),
+        ];
+        for (input, bucket, expected) in cases {
+            assert_eq!(
+                bucket_key(date(input), bucket),
+                date(expected),
+                "{} {:?}",
+                input,
+                bucket
+            );
+        }
+    }
+
+    #[test]
+    fn streaks_count_consecutive_positive_days() {
+        let today = date("2026-03-10");
+        let cases: Vec<(&str, Vec<(&str, i64)>, u32, u32, Option<&str>, Option<&str>)> = vec![
+            ("no records", vec![], 0, 0, None, None),
+            (
+                "running through today",
+                vec![("2026-03-08", 1), ("2026-03-09", 2), ("2026-03-10", 1)],
+                3,
+                3,
+                Some("2026-03-08"),
+                Some("2026-03-10"),
+            ),
+            (
+                "no record today keeps yesterday's run",
+                vec![("2026-03-08", 1), ("2026-03-09", 2)],
+                2,
+                2,
+                Some("2026-03-08"),
+                Some("2026-03-09"),
+            ),
+            (
+                "gap before yesterday ends the run",
+                vec![("2026-03-07", 1), ("2026-03-08", 1)],
+                0,
+                2,
+                Some("2026-03-07"),
+                Some("2026-03-08"),
+            ),
+            (
+                "net zero day breaks the run",
+                vec![
+                    ("2026-03-01", 1),
+                    ("2026-03-02", 1),
+                    ("2026-03-03", 1),
+                    ("2026-03-04", 1),
+                    ("2026-03-05", 0),
+                    ("2026-03-09", 1),
+                    ("2026-03-10", 1),
+                ],
+                2,
+                4,
+                Some("2026-03-01"),
+                Some("2026-03-04"),
+            ),
+            (
+                "negative day today",
+                vec![("2026-03-08", 1), ("2026-03-09", 1), ("2026-03-10", -1)],
+                0,
+                2,
+                Some("2026-03-08"),
+                Some("2026-03-09"),
+            ),
+            (
+                "ties keep the earlier run",
+                vec![
+                    ("2026-03-01", 1),
+                    ("2026-03-02", 1),
+                    ("2026-03-05", 1),
+                    ("2026-03-06", 1),
+                ],
+                0,
+                2,
+                Some("2026-03-01"),
+                Some("2026-03-02"),
+            ),
+        ];
+
+        for (name, days, current, longest, start, end) in cases {
+            let daily = days
+                .into_iter()
+                .map(|(day, delta)| (date(day), delta))
+                .collect::<BTreeMap<_, _>>();
+            let streaks = compute_streaks(&daily, today);
+            assert_eq!(streaks.current, current, "case: {}", name);
+            assert_eq!(streaks.longest, longest, "case: {}", name);
+            assert_eq!(streaks.longest_start.as_deref(), start, "case: {}", name);
+            assert_eq!(streaks.longest_end.as_deref(), end, "case: {}", name);
+        }
+    }
+
+    async fn setup() -> (DatabaseConnection, i32) {
+        let conn = memory_sqlite_connection().await;
+        run_migration(&conn, DatabaseType::SQLite).await.unwrap();
+        let student = students::ActiveModel {
+            uuid: Set(uuid::Uuid::new_v4().to_string()),
+            name: Set("张三".to_string()),
+            tags: Set("[]".to_string()),
+            score: Set(9),
+            reward_points: Set(20),
+            created_at: Set("2026-03-01T00:00:00.000".to_string()),
+            updated_at: Set("2026-03-01T00:00:00.000".to_string()),
+            ..Default::default()
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+
+        // 周一、周三、周日属于同一周，下周一另起一周
+        for (time, delta, val_curr) in [
+            ("2026-03-02T09:00:00.000", 2, 2),
+            ("2026-03-04T09:00:00.000", 3, 5),
+            ("2026-03-08T09:00:00.000", -1, 4),
+            ("2026-03-09T09:00:00.000", 5, 9),
+        ] {
+            score_events::ActiveModel {
+                id: sea_orm::ActiveValue::NotSet,
+                uuid: Set(uuid::Uuid::new_v4().to_string()),
+                student_id: Set(Some(student.id)),
+                student_name: Set(student.name.clone()),
+                reason_content: Set("上课发言".to_string()),
+                delta: Set(delta),
+                val_prev: Set(val_curr - delta),
+                val_curr: Set(val_curr),
+                event_time: Set(time.to_string()),
+                settlement_id: Set(None),
+            }
+            .insert(&conn)
+            .await
+            .unwrap();
+        }
+
+        // 已撤销的兑换不影响奖励积分曲线
+        for (time, cost, status) in [
+            ("2026-03-10T09:00:00.000", 10, RedemptionStatus::Fulfilled),
+            ("2026-03-03T09:00:00.000", 50, RedemptionStatus::Cancelled),
+        ] {
+            reward_redemptions::ActiveModel {
+                id: sea_orm::ActiveValue::NotSet,
+                uuid: Set(uuid::Uuid::new_v4().to_string()),
+                student_id: Set(Some(student.id)),
+                student_name: Set(student.name.clone()),
+                reward_id: Set(1),
+                reward_name: Set("贴纸".to_string()),
+                cost_points: Set(cost),
+                redeemed_at: Set(time.to_string()),
+                status: Set(status.as_str().to_string()),
+                reviewed_at: Set(None),
+                review_note: Set(None),
+                cancelled_at: Set(None),
+                cancelled_by: Set(None),
+                cancel_reason: Set(None),
+            }
+            .insert(&conn)
+            .await
+            .unwrap();
+        }
+        (conn, student.id)
+    }
+
+    #[tokio::test]
+    async fn series_buckets_by_day_and_week() {
+        let (conn, student_id) = setup().await;
+
+        // (bucket, event_count, score_delta, score, reward_points_delta, reward_points)
+        type Point = (&'static str, usize, i64, i32, i64, i64);
+        let cases: Vec<(&str, ProfileBucket, Option<&str>, Vec<Point>)> = vec![
+            (
+                "daily",
+                ProfileBucket::Day,
+                None,
+                vec![
+                    ("2026-03-02", 1, 2, 2, 2, 23),
+                    ("2026-03-04", 1, 3, 5, 3, 26),
+                    ("2026-03-08", 1, -1, 4, -1, 25),
+                    ("2026-03-09", 1, 5, 9, 5, 30),
+                    ("2026-03-10", 0, 0, 9, -10, 20),
+                ],
+            ),
+            (
+                "weekly",
+                ProfileBucket::Week,
+                None,
+                vec![
+                    ("2026-03-02", 3, 4, 4, 4, 25),
+                    ("2026-03-09", 1, 5, 9, -5, 20),
+                ],
+            ),
+            (
+                "daily from start date",
+                ProfileBucket::Day,
+                Some("2026-03-05"),
+                vec![
+                    ("2026-03-08", 1, -1, 4, -1, 25),
+                    ("2026-03-09", 1, 5, 9, 5, 30),
+                    ("2026-03-10", 0, 0, 9, -10, 20),
+                ],
+            ),
+            (
+                "weekly keeps weeks overlapping the range",
+                ProfileBucket::Week,
+                Some("2026-03-05"),
+                vec![
+                    ("2026-03-02", 3, 4, 4, 4, 25),
+                    ("2026-03-09", 1, 5, 9, -5, 20),
+                ],
+            ),
+        ];
+
+        for (name, bucket, start_date, expected) in cases {
+            let profile = build_student_profile(
+                &conn,
+                &StudentProfileParams {
+                    student_id: Some(student_id),
+                    bucket,
+                    start_date: start_date.map(str::to_string),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            let series = profile
+                .series
+                .iter()
+                .map(|p| {
+                    (
+                        p.bucket.as_str(),
+                        p.event_count,
+                        p.score_delta,
+                        p.score,
+                        p.reward_points_delta,
+                        p.reward_points,
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(series, expected, "case: {}", name);
+        }
+    }
+}
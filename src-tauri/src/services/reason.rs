@@ -0,0 +1,197 @@
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::entities::{reason_categories, reason_set_items, reason_sets, reasons};
+
+/// 理由列表的筛选条件；指定快捷组时按组内顺序返回
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReasonFilter {
+    #[serde(default)]
+    pub include_archived: bool,
+    #[serde(default)]
+    pub category_id: Option<i32>,
+    #[serde(default)]
+    pub set: Option<String>,
+}
+
+fn now_iso() -> String {
+    chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+pub async fn query_reasons(
+    conn: &impl ConnectionTrait,
+    filter: &ReasonFilter,
+) -> Result<Vec<reasons::Model>, String> {
+    let categories = reason_categories::Entity::find()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|c| (c.id, c))
+        .collect::<HashMap<_, _>>();
+
+    let mut query = reasons::Entity::find();
+    if let Some(category_id) = filter.category_id {
+        query = query.filter(reasons::Column::CategoryId.eq(category_id));
+    }
+    if !filter.include_archived {
+        query = query.filter(reasons::Column::IsArchived.eq(0));
+    }
+    let rows = query.all(conn).await.map_err(|e| e.to_string())?;
+
+    // 所属分类已归档的理由同样隐藏
+    let mut rows = rows
+        .into_iter()
+        .filter(|row| {
+            filter.include_archived
+                || row
+                    .category_id
+                    .and_then(|id| categories.get(&id))
+                    .map_or(true, |c| c.is_archived == 0)
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(set_name) = filter
+        .set
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        let Some(set) = reason_sets::Entity::find()
+            .filter(reason_sets::Column::Name.eq(set_name))
+            .one(conn)
+            .await
+            .map_err(|e| e.to_string())?
+        else {
+            return Err(format!("Reason set not found: {}", set_name));
+        };
+        let order = set_reason_ids(conn, set.id)
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| (id, index))
+            .collect::<HashMap<_, _>>();
+        rows.retain(|row| order.contains_key(&row.id));
+        rows.sort_by_key(|row| order[&row.id]);
+        return Ok(rows);
+    }
+
+    rows.sort_by(|a, b| {
+        let rank = |row: &reasons::Model| {
+            row.category_id
+                .and_then(|id| categories.get(&id))
+                .map_or(i32::MAX, |c| c.sort_order)
+        };
+        rank(a)
+            .cmp(&rank(b))
+            .then_with(|| a.category.cmp(&b.category))
+            .then_with(|| a.sort_order.cmp(&b.sort_order))
+            .then_with(|| a.content.cmp(&b.content))
+    });
+    Ok(rows)
+}
+
+/// 按名称查找分类，不存在时追加到末尾；空名称返回 None
+pub async fn ensure_category(
+    conn: &impl ConnectionTrait,
+    name: &str,
+) -> Result<Option<reason_categories::Model>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if let Some(existing) = reason_categories::Entity::find()
+        .filter(reason_categories::Column::Name.eq(name))
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(Some(existing));
+    }
+
+    let last = reason_categories::Entity::find()
+        .order_by_desc(reason_categories::Column::SortOrder)
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let now = now_iso();
+    let inserted = reason_categories::ActiveModel {
+        id: sea_orm::ActiveValue::NotSet,
+        name: Set(name.to_string()),
+        sort_order: Set(last.map_or(0, |c| c.sort_order + 1)),
+        color: Set(None),
+        icon: Set(None),
+        is_archived: Set(0),
+        created_at: Set(now.clone()),
+        updated_at: Set(now),
+    }
+    .insert(conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(Some(inserted))
+}
+
+/// 分类改名后同步理由上的名称快照
+pub async fn rename_category_references(
+    conn: &impl ConnectionTrait,
+    category_id: i32,
+    name: &str,
+) -> Result<(), String> {
+    reasons::Entity::update_many()
+        .col_expr(reasons::Column::Category, Expr::value(name))
+        .filter(reasons::Column::CategoryId.eq(category_id))
+        .exec(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn set_reason_ids(conn: &impl ConnectionTrait, set_id: i32) -> Result<Vec<i32>, String> {
+    reason_set_items::Entity::find()
+        .select_only()
+        .column(reason_set_items::Column::ReasonId)
+        .filter(reason_set_items::Column::SetId.eq(set_id))
+        .order_by_asc(reason_set_items::Column::SortOrder)
+        .order_by_asc(reason_set_items::Column::Id)
+        .into_tuple::<i32>()
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 整体替换快捷组内的理由及顺序
+pub async fn replace_set_reasons(
+    conn: &impl ConnectionTrait,
+    set_id: i32,
+    reason_ids: &[i32],
+) -> Result<(), String> {
+    reason_set_items::Entity::delete_many()
+        .filter(reason_set_items::Column::SetId.eq(set_id))
+        .exec(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut seen = std::collections::HashSet::new();
+    for (index, reason_id) in reason_ids.iter().copied().enumerate() {
+        if !seen.insert(reason_id) {
+            continue;
+        }
+        reason_set_items::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            set_id: Set(set_id),
+            reason_id: Set(reason_id),
+            sort_order: Set(index as i32),
+        }
+        .insert(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,687 @@
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, Statement, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::entities::{reward_redemptions, reward_settings, students};
+use crate::services::journal::{record_operation, JournalOp};
+
+/// 兑换记录状态：待审核 → 已批准 / 已驳回 / 已发放，未驳回的记录可被撤销
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedemptionStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Fulfilled,
+    Cancelled,
+}
+
+impl RedemptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RedemptionStatus::Pending => "pending",
+            RedemptionStatus::Approved => "approved",
+            RedemptionStatus::Rejected => "rejected",
+            RedemptionStatus::Fulfilled => "fulfilled",
+            RedemptionStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(RedemptionStatus::Pending),
+            "approved" => Some(RedemptionStatus::Approved),
+            "rejected" => Some(RedemptionStatus::Rejected),
+            "fulfilled" => Some(RedemptionStatus::Fulfilled),
+            "cancelled" => Some(RedemptionStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    // 已驳回、已撤销为终态；批准后仍可驳回（例如实物缺货），发放后只能撤销
+    pub fn can_transition_to(&self, next: RedemptionStatus) -> bool {
+        matches!(
+            (self, next),
+            (RedemptionStatus::Pending, RedemptionStatus::Approved)
+                | (RedemptionStatus::Pending, RedemptionStatus::Rejected)
+                | (RedemptionStatus::Pending, RedemptionStatus::Fulfilled)
+                | (RedemptionStatus::Pending, RedemptionStatus::Cancelled)
+                | (RedemptionStatus::Approved, RedemptionStatus::Rejected)
+                | (RedemptionStatus::Approved, RedemptionStatus::Fulfilled)
+                | (RedemptionStatus::Approved, RedemptionStatus::Cancelled)
+                | (RedemptionStatus::Fulfilled, RedemptionStatus::Cancelled)
+        )
+    }
+
+    /// 积分与库存已退还的状态
+    pub fn is_refunded(&self) -> bool {
+        matches!(
+            self,
+            RedemptionStatus::Rejected | RedemptionStatus::Cancelled
+        )
+    }
+
+    /// 数据库中的状态文本，无法识别时按旧数据视为已发放
+    pub fn of(value: &str) -> Self {
+        Self::parse(value).unwrap_or(RedemptionStatus::Fulfilled)
+    }
+}
+
+/// 有效期边界既可以是日期（按本地时区整天计算）也可以是 RFC 3339 时间
+pub fn parse_window_bound(value: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let time = if end_of_day {
+        date.and_hms_milli_opt(23, 59, 59, 999)?
+    } else {
+        date.and_hms_opt(0, 0, 0)?
+    };
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|value| value.with_timezone(&Utc))
+}
+
+/// 校验有效期设置，返回规范化（去空白、空串视为不限）后的起止值
+pub fn normalize_window(
+    active_from: Option<String>,
+    active_until: Option<String>,
+) -> Result<(Option<String>, Option<String>), String> {
+    let normalize = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let active_from = normalize(active_from);
+    let active_until = normalize(active_until);
+
+    let from = match active_from.as_deref() {
+        Some(value) => Some(
+            parse_window_bound(value, false)
+                .ok_or_else(|| format!("Invalid reward start time: {}", value))?,
+        ),
+        None => None,
+    };
+    let until = match active_until.as_deref() {
+        Some(value) => Some(
+            parse_window_bound(value, true)
+                .ok_or_else(|| format!("Invalid reward end time: {}", value))?,
+        ),
+        None => None,
+    };
+    if let (Some(from), Some(until)) = (from, until) {
+        if from > until {
+            return Err("Reward start time must be before end time".to_string());
+        }
+    }
+    Ok((active_from, active_until))
+}
+
+pub fn validate_limit(value: Option<i32>, label: &str) -> Result<(), String> {
+    match value {
+        Some(value) if value < 0 => Err(format!("{} cannot be negative", label)),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_active(reward: &reward_settings::Model, now: DateTime<Utc>) -> bool {
+    let started = reward
+        .active_from
+        .as_deref()
+        .and_then(|value| parse_window_bound(value, false))
+        .map_or(true, |from| now >= from);
+    let not_ended = reward
+        .active_until
+        .as_deref()
+        .and_then(|value| parse_window_bound(value, true))
+        .map_or(true, |until| now <= until);
+    started && not_ended
+}
+
+/// 当前结算周期的起点，即最近一次结算的结束时间；从未结算时为 None
+pub async fn current_period_start(conn: &impl ConnectionTrait) -> Result<Option<String>, String> {
+    let row = conn
+        .query_one(Statement::from_string(
+            conn.get_database_backend(),
+            "SELECT MAX(end_time) AS end_time FROM settlements".to_string(),
+        ))
+        .await
+        .map_err(|e| e.to_string())?;
+    match row {
+        Some(row) => row
+            .try_get::<Option<String>>("", "end_time")
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// 检查有效期与每名学生的兑换上限（总计、本结算周期），驳回或撤销的记录不计入
+pub async fn ensure_redeemable(
+    conn: &impl ConnectionTrait,
+    reward: &reward_settings::Model,
+    student_id: i32,
+) -> Result<(), String> {
+    if !is_active(reward, Utc::now()) {
+        return Err("Reward is not available at this time".to_string());
+    }
+    if reward.stock.is_some_and(|stock| stock <= 0) {
+        return Err("Reward out of stock".to_string());
+    }
+
+    let counted = || {
+        reward_redemptions::Entity::find()
+            .filter(reward_redemptions::Column::RewardId.eq(reward.id))
+            .filter(reward_redemptions::Column::StudentId.eq(student_id))
+            .filter(reward_redemptions::Column::Status.is_not_in([
+                RedemptionStatus::Rejected.as_str(),
+                RedemptionStatus::Cancelled.as_str(),
+            ]))
+    };
+
+    if let Some(limit) = reward.per_student_limit {
+        let used = counted().count(conn).await.map_err(|e| e.to_string())?;
+        if used >= limit.max(0) as u64 {
+            return Err("Per-student redemption limit reached".to_string());
+        }
+    }
+
+    if let Some(limit) = reward.per_period_limit {
+        let mut query = counted();
+        if let Some(start) = current_period_start(conn).await? {
+            query = query.filter(reward_redemptions::Column::RedeemedAt.gte(start));
+        }
+        let used = query.count(conn).await.map_err(|e| e.to_string())?;
+        if used >= limit.max(0) as u64 {
+            return Err("Redemption limit for this settlement period reached".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// 扣减一件库存；不限库存时直接返回 true，库存不足返回 false
+pub async fn take_stock(conn: &impl ConnectionTrait, reward_id: i32) -> Result<bool, String> {
+    let Some(reward) = reward_settings::Entity::find_by_id(reward_id)
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(false);
+    };
+    if reward.stock.is_none() {
+        return Ok(true);
+    }
+
+    // 条件更新，避免与其他客户端同时兑换时超卖
+    let result = reward_settings::Entity::update_many()
+        .col_expr(
+            reward_settings::Column::Stock,
+            Expr::col(reward_settings::Column::Stock).sub(1),
+        )
+        .filter(reward_settings::Column::Id.eq(reward_id))
+        .filter(reward_settings::Column::Stock.gt(0))
+        .exec(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(result.rows_affected > 0)
+}
+
+/// 驳回、撤销兑换或回滚时退回库存
+pub async fn restore_stock(conn: &impl ConnectionTrait, reward_id: i32) -> Result<(), String> {
+    reward_settings::Entity::update_many()
+        .col_expr(
+            reward_settings::Column::Stock,
+            Expr::col(reward_settings::Column::Stock).add(1),
+        )
+        .filter(reward_settings::Column::Id.eq(reward_id))
+        .filter(reward_settings::Column::Stock.is_not_null())
+        .exec(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 退还兑换扣除的积分与库存；学生已被删除时只退库存
+pub async fn refund_redemption(
+    conn: &impl ConnectionTrait,
+    redemption: &reward_redemptions::Model,
+    now: &str,
+) -> Result<(), String> {
+    let student =
+        students::find_by_reference(conn, redemption.student_id, &redemption.student_name)
+            .await
+            .map_err(|e| e.to_string())?;
+    if let Some(student) = student {
+        let refunded = student.reward_points + redemption.cost_points;
+        let mut active: students::ActiveModel = student.into();
+        active.reward_points = Set(refunded);
+        active.updated_at = Set(now.to_string());
+        active.update(conn).await.map_err(|e| e.to_string())?;
+    }
+    restore_stock(conn, redemption.reward_id).await
+}
+
+/// 撤销一条兑换：退还积分与库存，保留记录并标记撤销人与原因
+pub async fn cancel_redemption(
+    conn: &impl ConnectionTrait,
+    redemption: reward_redemptions::Model,
+    cancelled_by: &str,
+    reason: &str,
+    now: &str,
+) -> Result<reward_redemptions::Model, String> {
+    let current = RedemptionStatus::of(&redemption.status);
+    if !current.can_transition_to(RedemptionStatus::Cancelled) {
+        return Err(format!("Cannot cancel a {} redemption", current.as_str()));
+    }
+
+    refund_redemption(conn, &redemption, now).await?;
+
+    let mut active: reward_redemptions::ActiveModel = redemption.into();
+    active.status = Set(RedemptionStatus::Cancelled.as_str().to_string());
+    active.cancelled_at = Set(Some(now.to_string()));
+    active.cancelled_by = Set(Some(cancelled_by.to_string()));
+    active.cancel_reason = Set(Some(reason.to_string()));
+    active.update(conn).await.map_err(|e| e.to_string())
+}
+
+/// 新兑换记录的初始状态
+pub fn initial_status(reward: &reward_settings::Model) -> RedemptionStatus {
+    if reward.requires_approval != 0 {
+        RedemptionStatus::Pending
+    } else {
+        RedemptionStatus::Fulfilled
+    }
+}
+
+/// 为学生兑换奖励：校验积分、限额与库存后扣减积分；需审核的奖励登记为待审核，
+/// 其余直接发放。返回记录与剩余积分
+pub async fn redeem(
+    conn: &DatabaseConnection,
+    student_id: Option<i32>,
+    student_name: &str,
+    reward_id: i32,
+    operation_id: Option<&str>,
+) -> Result<(reward_redemptions::Model, i32), String> {
+    let student_name = student_name.trim();
+    if student_id.is_none() && student_name.is_empty() {
+        return Err("Student name cannot be empty".to_string());
+    }
+
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+
+    let student = students::find_by_reference(&txn, student_id, student_name)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Student not found".to_string())?;
+    let reward = reward_settings::Entity::find_by_id(reward_id)
+        .one(&txn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Reward not found".to_string())?;
+
+    if student.reward_points < reward.cost_points {
+        return Err("Insufficient reward points".to_string());
+    }
+    ensure_redeemable(&txn, &reward, student.id).await?;
+    if !take_stock(&txn, reward.id).await? {
+        return Err("Reward out of stock".to_string());
+    }
+
+    let remaining = student.reward_points - reward.cost_points;
+    let now = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let uuid = operation_id
+        .and_then(|value| Uuid::parse_str(value.trim()).ok())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let redemption = reward_redemptions::ActiveModel {
+        id: sea_orm::ActiveValue::NotSet,
+        uuid: Set(uuid),
+        student_id: Set(Some(student.id)),
+        student_name: Set(student.name.clone()),
+        reward_id: Set(reward.id),
+        reward_name: Set(reward.name.clone()),
+        cost_points: Set(reward.cost_points),
+        redeemed_at: Set(now.clone()),
+        status: Set(initial_status(&reward).as_str().to_string()),
+        reviewed_at: Set(None),
+        review_note: Set(None),
+        cancelled_at: Set(None),
+        cancelled_by: Set(None),
+        cancel_reason: Set(None),
+    }
+    .insert(&txn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut active_student: students::ActiveModel = student.into();
+    active_student.reward_points = Set(remaining);
+    active_student.updated_at = Set(now);
+    active_student
+        .update(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    record_operation(
+        &txn,
+        &JournalOp::RewardRedeemed {
+            redemption: Box::new(redemption.clone()),
+        },
+        &format!(
+            "{} 兑换 {}",
+            redemption.student_name, redemption.reward_name
+        ),
+    )
+    .await?;
+
+    txn.commit().await.map_err(|e| e.to_string())?;
+    Ok((redemption, remaining))
+}
+
+/// 按状态查询最近的兑换记录
+pub async fn query_redemptions(
+    conn: &impl ConnectionTrait,
+    status: Option<RedemptionStatus>,
+    limit: u64,
+) -> Result<Vec<reward_redemptions::Model>, String> {
+    let mut query = reward_redemptions::Entity::find();
+    if let Some(status) = status {
+        query = query.filter(reward_redemptions::Column::Status.eq(status.as_str()));
+    }
+    query
+        .order_by_desc(reward_redemptions::Column::RedeemedAt)
+        .limit(limit)
+        .all(conn)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::connection::memory_sqlite_connection;
+    use crate::db::{run_migration, DatabaseType};
+
+    const NOW: &str = "2026-01-15T08:00:00.000Z";
+
+    async fn setup() -> (DatabaseConnection, i32) {
+        let conn = memory_sqlite_connection().await;
+        run_migration(&conn, DatabaseType::SQLite).await.unwrap();
+        let student = students::ActiveModel {
+            uuid: Set(Uuid::new_v4().to_string()),
+            name: Set("张三".to_string()),
+            tags: Set("[]".to_string()),
+            score: Set(0),
+            reward_points: Set(100),
+            created_at: Set(NOW.to_string()),
+            updated_at: Set(NOW.to_string()),
+            ..Default::default()
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+        (conn, student.id)
+    }
+
+    async fn insert_reward(
+        conn: &DatabaseConnection,
+        stock: Option<i32>,
+        per_student_limit: Option<i32>,
+        per_period_limit: Option<i32>,
+        requires_approval: bool,
+    ) -> reward_settings::Model {
+        reward_settings::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            name: Set(format!("奖励{}", Uuid::new_v4())),
+            cost_points: Set(10),
+            stock: Set(stock),
+            per_student_limit: Set(per_student_limit),
+            per_period_limit: Set(per_period_limit),
+            active_from: Set(None),
+            active_until: Set(None),
+            requires_approval: Set(requires_approval as i32),
+            created_at: Set(NOW.to_string()),
+            updated_at: Set(NOW.to_string()),
+        }
+        .insert(conn)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_redemption(
+        conn: &DatabaseConnection,
+        reward: &reward_settings::Model,
+        student_id: i32,
+        status: RedemptionStatus,
+        redeemed_at: &str,
+    ) {
+        reward_redemptions::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            uuid: Set(Uuid::new_v4().to_string()),
+            student_id: Set(Some(student_id)),
+            student_name: Set("张三".to_string()),
+            reward_id: Set(reward.id),
+            reward_name: Set(reward.name.clone()),
+            cost_points: Set(reward.cost_points),
+            redeemed_at: Set(redeemed_at.to_string()),
+            status: Set(status.as_str().to_string()),
+            reviewed_at: Set(None),
+            review_note: Set(None),
+            cancelled_at: Set(None),
+            cancelled_by: Set(None),
+            cancel_reason: Set(None),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    async fn settle(conn: &DatabaseConnection, end_time: &str) {
+        conn.execute(Statement::from_sql_and_values(
+            conn.get_database_backend(),
+            "INSERT INTO settlements (start_time, end_time) VALUES (?, ?)",
+            vec!["2026-01-01T00:00:00.000Z".into(), end_time.into()],
+        ))
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn ensure_redeemable_enforces_stock_and_limits() {
+        use RedemptionStatus::*;
+
+        struct Case {
+            name: &'static str,
+            stock: Option<i32>,
+            per_student_limit: Option<i32>,
+            per_period_limit: Option<i32>,
+            settled_at: Option<&'static str>,
+            existing: Vec<(RedemptionStatus, &'static str)>,
+            expected: Result<(), &'static str>,
+        }
+
+        let cases = vec![
+            Case {
+                name: "no limits",
+                stock: None,
+                per_student_limit: None,
+                per_period_limit: None,
+                settled_at: None,
+                existing: vec![(Fulfilled, NOW), (Fulfilled, NOW)],
+                expected: Ok(()),
+            },
+            Case {
+                name: "stock exhausted",
+                stock: Some(0),
+                per_student_limit: None,
+                per_period_limit: None,
+                settled_at: None,
+                existing: vec![],
+                expected: Err("Reward out of stock"),
+            },
+            Case {
+                name: "per-student limit reached",
+                stock: Some(5),
+                per_student_limit: Some(2),
+                per_period_limit: None,
+                settled_at: None,
+                existing: vec![(Fulfilled, NOW), (Pending, NOW)],
+                expected: Err("Per-student redemption limit reached"),
+            },
+            Case {
+                name: "rejected and cancelled do not count toward per-student limit",
+                stock: None,
+                per_student_limit: Some(2),
+                per_period_limit: None,
+                settled_at: None,
+                existing: vec![(Fulfilled, NOW), (Rejected, NOW), (Cancelled, NOW)],
+                expected: Ok(()),
+            },
+            Case {
+                name: "per-period limit only counts the current period",
+                stock: None,
+                per_student_limit: None,
+                per_period_limit: Some(1),
+                settled_at: Some("2026-01-10T00:00:00.000Z"),
+                existing: vec![(Fulfilled, "2026-01-05T08:00:00.000Z")],
+                expected: Ok(()),
+            },
+            Case {
+                name: "per-period limit reached",
+                stock: None,
+                per_student_limit: None,
+                per_period_limit: Some(1),
+                settled_at: Some("2026-01-10T00:00:00.000Z"),
+                existing: vec![(Fulfilled, "2026-01-05T08:00:00.000Z"), (Approved, NOW)],
+                expected: Err("Redemption limit for this settlement period reached"),
+            },
+            Case {
+                name: "per-period limit without settlement counts everything",
+                stock: None,
+                per_student_limit: None,
+                per_period_limit: Some(1),
+                settled_at: None,
+                existing: vec![(Fulfilled, "2026-01-05T08:00:00.000Z")],
+                expected: Err("Redemption limit for this settlement period reached"),
+            },
+        ];
+
+        for case in cases {
+            let (conn, student_id) = setup().await;
+            let reward = insert_reward(
+                &conn,
+                case.stock,
+                case.per_student_limit,
+                case.per_period_limit,
+                false,
+            )
+            .await;
+            if let Some(end_time) = case.settled_at {
+                settle(&conn, end_time).await;
+            }
+            for (status, redeemed_at) in case.existing {
+                insert_redemption(&conn, &reward, student_id, status, redeemed_at).await;
+            }
+
+            let result = ensure_redeemable(&conn, &reward, student_id).await;
+            assert_eq!(
+                result,
+                case.expected.map_err(str::to_string),
+                "case: {}",
+                case.name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn take_stock_decrements_until_empty() {
+        let (conn, _) = setup().await;
+        let unlimited = insert_reward(&conn, None, None, None, false).await;
+        let single = insert_reward(&conn, Some(1), None, None, false).await;
+        let empty = insert_reward(&conn, Some(0), None, None, false).await;
+
+        let cases = [
+            ("unlimited stock", unlimited.id, true, None),
+            ("last item", single.id, true, Some(0)),
+            ("already taken", single.id, false, Some(0)),
+            ("empty stock", empty.id, false, Some(0)),
+            ("missing reward", -1, false, None),
+        ];
+        for (name, reward_id, expected, stock_after) in cases {
+            assert_eq!(
+                take_stock(&conn, reward_id).await.unwrap(),
+                expected,
+                "case: {}",
+                name
+            );
+            let stock = reward_settings::Entity::find_by_id(reward_id)
+                .one(&conn)
+                .await
+                .unwrap()
+                .and_then(|reward| reward.stock);
+            assert_eq!(stock, stock_after, "case: {}", name);
+        }
+    }
+
+    #[test]
+    fn status_transitions() {
+        use RedemptionStatus::*;
+
+        let cases = [
+            (Pending, Approved, true),
+            (Pending, Rejected, true),
+            (Pending, Fulfilled, true),
+            (Pending, Cancelled, true),
+            (Approved, Fulfilled, true),
+            (Approved, Cancelled, true),
+            (Fulfilled, Cancelled, true),
+            (Rejected, Approved, false),
+            (Rejected, Fulfilled, false),
+            (Rejected, Cancelled, false),
+            (Cancelled, Pending, false),
+            (Fulfilled, Pending, false),
+            (Approved, Pending, false),
+        ];
+        for (from, to, allowed) in cases {
+            assert_eq!(
+                from.can_transition_to(to),
+                allowed,
+                "{} -> {}",
+                from.as_str(),
+                to.as_str()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn redeem_is_pending_only_when_approval_required() {
+        let (conn, student_id) = setup().await;
+        let direct = insert_reward(&conn, None, None, None, false).await;
+        let reviewed = insert_reward(&conn, None, None, None, true).await;
+
+        let cases = [
+            ("no approval", direct.id, RedemptionStatus::Fulfilled, 90),
+            (
+                "approval required",
+                reviewed.id,
+                RedemptionStatus::Pending,
+                80,
+            ),
+        ];
+        for (name, reward_id, expected, remaining) in cases {
+            let (redemption, left) = redeem(&conn, Some(student_id), "", reward_id, None)
+                .await
+                .unwrap();
+            assert_eq!(redemption.status, expected.as_str(), "case: {}", name);
+            assert_eq!(left, remaining, "case: {}", name);
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::entities::{score_events, student_tags, students};
+use crate::services::journal::{record_operation, JournalOp};
+
+/// 按学生 ID、姓名、分组或标签选取学生，结果取并集
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StudentSelector {
+    #[serde(default, alias = "studentIds")]
+    pub student_ids: Vec<i32>,
+    #[serde(default, alias = "studentNames")]
+    pub student_names: Vec<String>,
+    #[serde(default, alias = "groupName")]
+    pub group_name: Option<String>,
+    #[serde(default, alias = "tagId")]
+    pub tag_id: Option<i32>,
+}
+
+fn now_iso() -> String {
+    chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+/// 客户端传入合法 UUID 时沿用，便于离线重放时去重
+fn event_uuid(operation_id: Option<&str>) -> String {
+    operation_id
+        .and_then(|value| Uuid::parse_str(value.trim()).ok())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+pub async fn resolve_students(
+    conn: &impl ConnectionTrait,
+    selector: &StudentSelector,
+) -> Result<Vec<students::Model>, sea_orm::DbErr> {
+    let mut condition = Condition::any();
+    if !selector.student_ids.is_empty() {
+        condition = condition.add(students::Column::Id.is_in(selector.student_ids.clone()));
+    }
+    let names: Vec<String> = selector
+        .student_names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if !names.is_empty() {
+        condition = condition.add(students::Column::Name.is_in(names));
+    }
+    if let Some(group_name) = selector
+        .group_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        condition = condition.add(students::Column::GroupName.eq(group_name));
+    }
+    if let Some(tag_id) = selector.tag_id {
+        let tagged: Vec<i32> = student_tags::Entity::find()
+            .filter(student_tags::Column::TagId.eq(tag_id))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|link| link.student_id)
+            .collect();
+        condition = condition.add(students::Column::Id.is_in(tagged));
+    }
+
+    if condition.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    students::Entity::find()
+        .filter(condition)
+        .order_by_asc(students::Column::Id)
+        .all(conn)
+        .await
+}
+
+/// 写入一条积分记录并同步学生的积分与奖励积分，不记录操作日志
+pub async fn insert_score_event(
+    conn: &impl ConnectionTrait,
+    student: students::Model,
+    delta: i32,
+    reason_content: &str,
+    uuid: String,
+    now: &str,
+) -> Result<score_events::Model, String> {
+    let val_prev = student.score;
+    let val_curr = val_prev + delta;
+    let reward_points_next = student.reward_points + delta;
+
+    let inserted = score_events::ActiveModel {
+        id: sea_orm::ActiveValue::NotSet,
+        uuid: Set(uuid),
+        student_id: Set(Some(student.id)),
+        student_name: Set(student.name.clone()),
+        reason_content: Set(reason_content.trim().to_string()),
+        delta: Set(delta),
+        val_prev: Set(val_prev),
+        val_curr: Set(val_curr),
+        event_time: Set(now.to_string()),
+        settlement_id: Set(None),
+    }
+    .insert(conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut active: students::ActiveModel = student.into();
+    active.score = Set(val_curr);
+    active.reward_points = Set(reward_points_next);
+    active.updated_at = Set(now.to_string());
+    active.update(conn).await.map_err(|e| e.to_string())?;
+
+    Ok(inserted)
+}
+
+/// 给单个学生加减分，桌面端、局域网和 MCP 共用
+pub async fn add_score(
+    conn: &DatabaseConnection,
+    student_id: Option<i32>,
+    student_name: &str,
+    delta: i32,
+    reason_content: &str,
+    operation_id: Option<&str>,
+) -> Result<score_events::Model, String> {
+    let student_name = student_name.trim();
+    if student_id.is_none() && student_name.is_empty() {
+        return Err("Student name cannot be empty".to_string());
+    }
+
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    let student = students::find_by_reference(&txn, student_id, student_name)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Student not found".to_string())?;
+
+    let inserted = insert_score_event(
+        &txn,
+        student,
+        delta,
+        reason_content,
+        event_uuid(operation_id),
+        &now_iso(),
+    )
+    .await?;
+    record_operation(
+        &txn,
+        &JournalOp::ScoreEventsCreated {
+            events: vec![inserted.clone()],
+        },
+        &format!(
+            "{} {:+} {}",
+            inserted.student_name, inserted.delta, inserted.reason_content
+        ),
+    )
+    .await?;
+
+    txn.commit().await.map_err(|e| e.to_string())?;
+    Ok(inserted)
+}
+
+/// 批量加减分，整批作为一条操作记录，撤销时一并回退
+pub async fn add_score_batch(
+    conn: &DatabaseConnection,
+    selector: &StudentSelector,
+    delta: i32,
+    reason_content: &str,
+) -> Result<Vec<score_events::Model>, String> {
+    let reason_content = reason_content.trim();
+    if reason_content.is_empty() {
+        return Err("Reason cannot be empty".to_string());
+    }
+
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    let targets = resolve_students(&txn, selector)
+        .await
+        .map_err(|e| e.to_string())?;
+    if targets.is_empty() {
+        return Err("No students matched".to_string());
+    }
+
+    let now = now_iso();
+    let mut created = Vec::with_capacity(targets.len());
+    for student in targets {
+        created.push(
+            insert_score_event(
+                &txn,
+                student,
+                delta,
+                reason_content,
+                Uuid::new_v4().to_string(),
+                &now,
+            )
+            .await?,
+        );
+    }
+
+    record_operation(
+        &txn,
+        &JournalOp::ScoreEventsCreated {
+            events: created.clone(),
+        },
+        &format!("批量 {} 人 {:+} {}", created.len(), delta, reason_content),
+    )
+    .await?;
+
+    txn.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+/// 撤销一条未结算的积分记录并回退学生积分
+pub async fn revert_score_event(
+    conn: &DatabaseConnection,
+    uuid: &str,
+) -> Result<score_events::Model, String> {
+    let event = score_events::Entity::find()
+        .filter(score_events::Column::Uuid.eq(uuid.trim()))
+        .one(conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Event not found".to_string())?;
+
+    if event.settlement_id.is_some() {
+        return Err("该记录已结算，无法撤销".to_string());
+    }
+
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+    if let Some(student) = students::find_by_reference(&txn, event.student_id, &event.student_name)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        let next_score = student.score - event.delta;
+        let next_reward_points = student.reward_points - event.delta;
+        let mut active: students::ActiveModel = student.into();
+        active.score = Set(next_score);
+        active.reward_points = Set(next_reward_points);
+        active.updated_at = Set(now_iso());
+        active.update(&txn).await.map_err(|e| e.to_string())?;
+    }
+
+    score_events::Entity::delete_by_id(event.id)
+        .exec(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let summary = format!(
+        "删除 {} {:+} {}",
+        event.student_name, event.delta, event.reason_content
+    );
+    record_operation(
+        &txn,
+        &JournalOp::ScoreEventsDeleted {
+            events: vec![event.clone()],
+        },
+        &summary,
+    )
+    .await?;
+    txn.commit().await.map_err(|e| e.to_string())?;
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_score_batch, StudentSelector};
+    use crate::db::connection::memory_sqlite_connection;
+    use crate::db::entities::{operation_journal, score_events, student_tags, students, tags};
+    use crate::db::{run_migration, DatabaseType};
+    use crate::services::journal::undo_operations;
+    use sea_orm::{
+        ActiveModelTrait, ConnectionTrait, DatabaseConnection, EntityTrait, PaginatorTrait,
+        QueryOrder, Set,
+    };
+
+    const NOW: &str = "2024-01-01T00:00:00.000Z";
+
+    async fn setup() -> DatabaseConnection {
+        let conn = memory_sqlite_connection().await;
+        run_migration(&conn, DatabaseType::SQLite).await.unwrap();
+        let roster = [
+            ("张三", Some("一组")),
+            ("李四", Some("一组")),
+            ("王五", Some("二组")),
+            ("赵六", None),
+        ];
+        for (index, (name, group)) in roster.into_iter().enumerate() {
+            students::ActiveModel {
+                id: sea_orm::ActiveValue::NotSet,
+                uuid: Set(format!("00000000-0000-4000-8000-{:012}", index + 1)),
+                name: Set(name.to_string()),
+                group_name: Set(group.map(str::to_string)),
+                score: Set(10),
+                reward_points: Set(10),
+                tags: Set("[]".to_string()),
+                extra_json: Set(None),
+                created_at: Set(NOW.to_string()),
+                updated_at: Set(NOW.to_string()),
+            }
+            .insert(&conn)
+            .await
+            .unwrap();
+        }
+        let tag = tags::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            name: Set("值日生".to_string()),
+            created_at: Set(NOW.to_string()),
+            updated_at: Set(NOW.to_string()),
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+        student_tags::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            student_id: Set(4),
+            tag_id: Set(tag.id),
+            created_at: Set(NOW.to_string()),
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+        conn
+    }
+
+    async fn scores(conn: &DatabaseConnection) -> Vec<(i32, i32)> {
+        students::Entity::find()
+            .order_by_asc(students::Column::Id)
+            .all(conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|student| (student.score, student.reward_points))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn batch_selector_is_a_union() {
+        let conn = setup().await;
+        let cases = [
+            (
+                "ids",
+                StudentSelector {
+                    student_ids: vec![1, 3, 99],
+                    ..Default::default()
+                },
+                vec![1, 3],
+            ),
+            (
+                "names and group overlap",
+                StudentSelector {
+                    student_names: vec![" 王五 ".to_string(), String::new()],
+                    group_name: Some("一组".to_string()),
+                    ..Default::default()
+                },
+                vec![1, 2, 3],
+            ),
+            (
+                "tag",
+                StudentSelector {
+                    tag_id: Some(1),
+                    student_ids: vec![2],
+                    ..Default::default()
+                },
+                vec![2, 4],
+            ),
+        ];
+        for (name, selector, expected) in cases {
+            let resolved = super::resolve_students(&conn, &selector).await.unwrap();
+            let ids = resolved.iter().map(|s| s.id).collect::<Vec<_>>();
+            assert_eq!(ids, expected, "{}", name);
+        }
+        let empty = super::resolve_students(&conn, &StudentSelector::default())
+            .await
+            .unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[tokio::test]
+    async fn batch_writes_one_journal_entry_and_undoes_together() {
+        let conn = setup().await;
+        let selector = StudentSelector {
+            group_name: Some("一组".to_string()),
+            student_ids: vec![4],
+            ..Default::default()
+        };
+        let created = add_score_batch(&conn, &selector, 2, " 集体表扬 ")
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 3);
+        assert!(created
+            .iter()
+            .all(|event| event.reason_content == "集体表扬"));
+        assert_eq!(
+            created
+                .iter()
+                .map(|event| (event.student_id, event.val_prev, event.val_curr))
+                .collect::<Vec<_>>(),
+            vec![(Some(1), 10, 12), (Some(2), 10, 12), (Some(4), 10, 12)]
+        );
+        assert_eq!(
+            scores(&conn).await,
+            vec![(12, 12), (12, 12), (10, 10), (12, 12)]
+        );
+
+        let journal = operation_journal::Entity::find().all(&conn).await.unwrap();
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal[0].summary, "批量 3 人 +2 集体表扬");
+
+        undo_operations(&conn, 1, false).await.unwrap();
+        assert_eq!(scores(&conn).await, vec![(10, 10); 4]);
+        assert_eq!(score_events::Entity::find().count(&conn).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn batch_rejects_bad_input_without_writing() {
+        let conn = setup().await;
+        let everyone = StudentSelector {
+            student_ids: vec![1, 2, 3, 4],
+            ..Default::default()
+        };
+        assert!(add_score_batch(&conn, &everyone, 1, "  ").await.is_err());
+        let nobody = StudentSelector {
+            group_name: Some("三组".to_string()),
+            ..Default::default()
+        };
+        assert!(add_score_batch(&conn, &nobody, 1, "表扬").await.is_err());
+        assert_eq!(score_events::Entity::find().count(&conn).await.unwrap(), 0);
+        assert_eq!(
+            operation_journal::Entity::find()
+                .count(&conn)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_rolls_back_when_one_student_fails() {
+        let conn = setup().await;
+        // 第三个学生写入时失败，前面已写入的记录必须一起回滚
+        conn.execute_unprepared(
+            "CREATE TRIGGER fail_third BEFORE INSERT ON score_events \
+             WHEN NEW.student_id = 3 BEGIN SELECT RAISE(ABORT, 'boom'); END",
+        )
+        .await
+        .unwrap();
+        let everyone = StudentSelector {
+            student_ids: vec![1, 2, 3, 4],
+            ..Default::default()
+        };
+        assert!(add_score_batch(&conn, &everyone, 5, "表扬").await.is_err());
+        assert_eq!(scores(&conn).await, vec![(10, 10); 4]);
+        assert_eq!(score_events::Entity::find().count(&conn).await.unwrap(), 0);
+        assert_eq!(
+            operation_journal::Entity::find()
+                .count(&conn)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
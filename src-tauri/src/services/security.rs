@@ -7,6 +7,10 @@ type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
 
 const SALT: &[u8] = b"secscore-salt";
 const IV_KEY: &str = "security_crypto_iv";
+const HASH_SCHEME: &str = "pbkdf2_sha256";
+// 6 位数字口令空间很小，只能靠慢哈希拖慢离线穷举
+const HASH_ITERATIONS: u32 = 210_000;
+const HASH_SALT_LEN: usize = 16;
 
 pub struct SecurityService {
     app_data_dir: Option<String>,
@@ -95,6 +99,70 @@ impl SecurityService {
         String::from_utf8(plaintext.to_vec()).map_err(|e| e.to_string())
     }
 
+    fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+        let mut output = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations, &mut output);
+        output
+    }
+
+    fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    /// 生成 `pbkdf2_sha256$迭代次数$盐$摘要` 格式的口令哈希
+    pub fn hash_password(password: &str) -> String {
+        use rand::RngCore;
+        let mut salt = [0u8; HASH_SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let digest = Self::pbkdf2_sha256(password.as_bytes(), &salt, HASH_ITERATIONS);
+        format!(
+            "{}${}${}${}",
+            HASH_SCHEME,
+            HASH_ITERATIONS,
+            hex::encode(salt),
+            hex::encode(digest)
+        )
+    }
+
+    pub fn is_password_hash(stored: &str) -> bool {
+        stored.starts_with(&format!("{}$", HASH_SCHEME))
+    }
+
+    pub fn verify_password(password: &str, stored: &str) -> bool {
+        let mut parts = stored.split('$');
+        let (Some(HASH_SCHEME), Some(iterations), Some(salt), Some(digest), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return false;
+        };
+        let (Ok(iterations), Ok(salt), Ok(digest)) = (
+            iterations.parse::<u32>(),
+            hex::decode(salt),
+            hex::decode(digest),
+        ) else {
+            return false;
+        };
+        if iterations == 0 {
+            return false;
+        }
+        let computed = Self::pbkdf2_sha256(password.as_bytes(), &salt, iterations);
+        Self::constant_time_eq(&computed, &digest)
+    }
+
+    /// 校验旧版可逆加密的口令，仅用于升级到哈希存储
+    pub fn verify_legacy_secret(&self, password: &str, cipher_text: &str, iv_hex: &str) -> bool {
+        match self.decrypt_secret(cipher_text, iv_hex) {
+            Ok(plain) => {
+                !plain.is_empty() && Self::constant_time_eq(plain.as_bytes(), password.as_bytes())
+            }
+            Err(_) => false,
+        }
+    }
+
     pub fn is_six_digit(s: &str) -> bool {
         s.len() == 6 && s.chars().all(|c| c.is_ascii_digit())
     }
@@ -115,3 +183,120 @@ impl SecurityService {
         IV_KEY
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SecurityService;
+
+    // RFC 7914 第 11 节及常用 PBKDF2-HMAC-SHA256 测试向量，取前 32 字节
+    #[test]
+    fn pbkdf2_sha256_matches_known_vectors() {
+        let cases: [(&[u8], &[u8], u32, &str); 5] = [
+            (
+                b"password",
+                b"salt",
+                1,
+                "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+            ),
+            (
+                b"password",
+                b"salt",
+                2,
+                "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43",
+            ),
+            (
+                b"password",
+                b"salt",
+                4096,
+                "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a",
+            ),
+            (
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1",
+            ),
+            (
+                b"passwd",
+                b"salt",
+                1,
+                "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc",
+            ),
+        ];
+        for (password, salt, iterations, expected) in cases {
+            assert_eq!(
+                hex::encode(SecurityService::pbkdf2_sha256(password, salt, iterations)),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn constant_time_eq_compares_length_and_content() {
+        assert!(SecurityService::constant_time_eq(b"", b""));
+        assert!(SecurityService::constant_time_eq(b"123456", b"123456"));
+        assert!(!SecurityService::constant_time_eq(b"123456", b"123457"));
+        assert!(!SecurityService::constant_time_eq(b"123456", b"1234567"));
+        assert!(!SecurityService::constant_time_eq(b"123456", b""));
+    }
+
+    #[test]
+    fn verify_password_checks_stored_hash() {
+        let stored = format!(
+            "pbkdf2_sha256$210000${}$e6b5c316862785131e1195ad7acb3aa9b9dd4e3fadf5dced6c3193577f408686",
+            "00".repeat(16)
+        );
+        assert!(SecurityService::verify_password("123456", &stored));
+        assert!(!SecurityService::verify_password("123457", &stored));
+
+        let hashed = SecurityService::hash_password("654321");
+        assert!(SecurityService::is_password_hash(&hashed));
+        assert!(SecurityService::verify_password("654321", &hashed));
+        assert!(!SecurityService::verify_password("123456", &hashed));
+    }
+
+    #[test]
+    fn verify_password_rejects_malformed_hashes() {
+        for stored in [
+            "",
+            "123456",
+            "pbkdf2_sha256$1$73616c74",
+            "pbkdf2_sha256$0$73616c74$00",
+            "pbkdf2_sha256$x$73616c74$00",
+            "pbkdf2_sha256$1$zz$00",
+            "pbkdf2_sha256$1$73616c74$120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b$extra",
+            "sha1$1$73616c74$120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+        ] {
+            assert!(!SecurityService::verify_password("password", stored), "{}", stored);
+        }
+        // 同一摘要格式正确时应通过，确认上面的失败来自格式而非口令
+        assert!(SecurityService::verify_password(
+            "password",
+            "pbkdf2_sha256$1$73616c74$120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        ));
+    }
+
+    #[test]
+    fn verify_legacy_secret_decrypts_with_saved_iv() {
+        let mut security = SecurityService::new();
+        security.set_app_data_dir("/tmp/secscore-test");
+        let iv = SecurityService::generate_iv_hex();
+        let cipher = security.encrypt_secret("123456", &iv).unwrap();
+
+        assert!(security.verify_legacy_secret("123456", &cipher, &iv));
+        assert!(!security.verify_legacy_secret("654321", &cipher, &iv));
+        assert!(!security.verify_legacy_secret(
+            "123456",
+            &cipher,
+            &SecurityService::generate_iv_hex()
+        ));
+        assert!(!security.verify_legacy_secret("123456", "not-hex", &iv));
+
+        // 其他数据目录派生的密钥不同
+        let other = SecurityService::new();
+        assert!(!other.verify_legacy_secret("123456", &cipher, &iv));
+
+        let empty = security.encrypt_secret("", &iv).unwrap();
+        assert!(!security.verify_legacy_secret("", &empty, &iv));
+    }
+}
@@ -14,7 +14,7 @@ pub struct SettingsSpec {
     pub themes_custom: JsonValue,
     pub auto_score_enabled: bool,
     pub auto_score_rules: JsonValue,
-    pub auto_score_batches: JsonValue,
+    pub auto_score_batch_retention_days: f64,
     pub current_theme_id: String,
     pub dashboards_config: JsonValue,
     pub pg_connection_string: String,
@@ -22,6 +22,8 @@ pub struct SettingsSpec {
     pub sync_method: String,
     pub mobile_bottom_nav_items: JsonValue,
     pub lan_access_enabled: bool,
+    pub auto_backup_enabled: bool,
+    pub permission_idle_timeout_minutes: f64,
 }
 
 impl Default for SettingsSpec {
@@ -36,7 +38,7 @@ impl Default for SettingsSpec {
             themes_custom: JsonValue::Array(vec![]),
             auto_score_enabled: false,
             auto_score_rules: JsonValue::Array(vec![]),
-            auto_score_batches: JsonValue::Array(vec![]),
+            auto_score_batch_retention_days: 90.0,
             current_theme_id: "light-default".to_string(),
             dashboards_config: JsonValue::Array(vec![]),
             pg_connection_string: String::new(),
@@ -55,6 +57,8 @@ impl Default for SettingsSpec {
                 "settings"
             ]),
             lan_access_enabled: false,
+            auto_backup_enabled: true,
+            permission_idle_timeout_minutes: 10.0,
         }
     }
 }
@@ -70,7 +74,7 @@ pub enum SettingsKey {
     ThemesCustom,
     AutoScoreEnabled,
     AutoScoreRules,
-    AutoScoreBatches,
+    AutoScoreBatchRetentionDays,
     CurrentThemeId,
     DashboardsConfig,
     PgConnectionString,
@@ -78,6 +82,8 @@ pub enum SettingsKey {
     SyncMethod,
     MobileBottomNavItems,
     LanAccessEnabled,
+    AutoBackupEnabled,
+    PermissionIdleTimeoutMinutes,
 }
 
 impl SettingsKey {
@@ -92,7 +98,7 @@ impl SettingsKey {
             SettingsKey::ThemesCustom => "themes_custom",
             SettingsKey::AutoScoreEnabled => "auto_score_enabled",
             SettingsKey::AutoScoreRules => "auto_score_rules",
-            SettingsKey::AutoScoreBatches => "auto_score_batches",
+            SettingsKey::AutoScoreBatchRetentionDays => "auto_score_batch_retention_days",
             SettingsKey::CurrentThemeId => "current_theme_id",
             SettingsKey::DashboardsConfig => "dashboards_config",
             SettingsKey::PgConnectionString => "pg_connection_string",
@@ -100,6 +106,8 @@ impl SettingsKey {
             SettingsKey::SyncMethod => "sync_method",
             SettingsKey::MobileBottomNavItems => "mobile_bottom_nav_items",
             SettingsKey::LanAccessEnabled => "lan_access_enabled",
+            SettingsKey::AutoBackupEnabled => "auto_backup_enabled",
+            SettingsKey::PermissionIdleTimeoutMinutes => "permission_idle_timeout_minutes",
         }
     }
 
@@ -114,7 +122,7 @@ impl SettingsKey {
             "themes_custom" => Some(SettingsKey::ThemesCustom),
             "auto_score_enabled" => Some(SettingsKey::AutoScoreEnabled),
             "auto_score_rules" => Some(SettingsKey::AutoScoreRules),
-            "auto_score_batches" => Some(SettingsKey::AutoScoreBatches),
+            "auto_score_batch_retention_days" => Some(SettingsKey::AutoScoreBatchRetentionDays),
             "current_theme_id" => Some(SettingsKey::CurrentThemeId),
             "dashboards_config" => Some(SettingsKey::DashboardsConfig),
             "pg_connection_string" => Some(SettingsKey::PgConnectionString),
@@ -122,6 +130,8 @@ impl SettingsKey {
             "sync_method" => Some(SettingsKey::SyncMethod),
             "mobile_bottom_nav_items" => Some(SettingsKey::MobileBottomNavItems),
             "lan_access_enabled" => Some(SettingsKey::LanAccessEnabled),
+            "auto_backup_enabled" => Some(SettingsKey::AutoBackupEnabled),
+            "permission_idle_timeout_minutes" => Some(SettingsKey::PermissionIdleTimeoutMinutes),
             _ => None,
         }
     }
@@ -400,12 +410,19 @@ impl SettingsService {
         );
 
         defs.insert(
-            SettingsKey::AutoScoreBatches,
+            SettingsKey::AutoScoreBatchRetentionDays,
             SettingDefinition {
-                kind: SettingValueKind::Json,
-                default_value: SettingsValue::Json(JsonValue::Array(vec![])),
+                kind: SettingValueKind::Number,
+                default_value: SettingsValue::Number(90.0),
                 write_permission: PermissionRequirement::Admin,
-                validate: None,
+                validate: Some(|v| {
+                    if let SettingsValue::Number(n) = v {
+                        // 0 表示永久保留
+                        n.is_finite() && *n >= 0.0 && *n <= 3650.0
+                    } else {
+                        false
+                    }
+                }),
             },
         );
 
@@ -517,6 +534,33 @@ impl SettingsService {
             },
         );
 
+        defs.insert(
+            SettingsKey::AutoBackupEnabled,
+            SettingDefinition {
+                kind: SettingValueKind::Boolean,
+                default_value: SettingsValue::Boolean(true),
+                write_permission: PermissionRequirement::Admin,
+                validate: None,
+            },
+        );
+
+        defs.insert(
+            SettingsKey::PermissionIdleTimeoutMinutes,
+            SettingDefinition {
+                kind: SettingValueKind::Number,
+                default_value: SettingsValue::Number(10.0),
+                write_permission: PermissionRequirement::Admin,
+                validate: Some(|v| {
+                    if let SettingsValue::Number(n) = v {
+                        // 0 表示不自动锁定
+                        n.is_finite() && *n >= 0.0 && *n <= 24.0 * 60.0
+                    } else {
+                        false
+                    }
+                }),
+            },
+        );
+
         defs
     }
 
@@ -643,9 +687,11 @@ impl SettingsService {
                 SettingsValue::Json(j) => j,
                 _ => JsonValue::Array(vec![]),
             },
-            auto_score_batches: match self.get_value(SettingsKey::AutoScoreBatches) {
-                SettingsValue::Json(j) => j,
-                _ => JsonValue::Array(vec![]),
+            auto_score_batch_retention_days: match self
+                .get_value(SettingsKey::AutoScoreBatchRetentionDays)
+            {
+                SettingsValue::Number(n) => n,
+                _ => 90.0,
             },
             current_theme_id: match self.get_value(SettingsKey::CurrentThemeId) {
                 SettingsValue::String(s) => s,
@@ -686,6 +732,16 @@ impl SettingsService {
                 SettingsValue::Boolean(b) => b,
                 _ => false,
             },
+            auto_backup_enabled: match self.get_value(SettingsKey::AutoBackupEnabled) {
+                SettingsValue::Boolean(b) => b,
+                _ => true,
+            },
+            permission_idle_timeout_minutes: match self
+                .get_value(SettingsKey::PermissionIdleTimeoutMinutes)
+            {
+                SettingsValue::Number(n) => n,
+                _ => 10.0,
+            },
         }
     }
 
@@ -0,0 +1,70 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+
+use crate::db::entities::student_tags;
+use crate::services::journal::{record_operation, JournalOp};
+
+/// 整体替换学生的标签，记录修改前后的标签以便撤销，返回新增的标签 id
+pub async fn set_student_tags(
+    conn: &DatabaseConnection,
+    student_id: i32,
+    tag_ids: Vec<i32>,
+) -> Result<Vec<i32>, String> {
+    let txn = conn.begin().await.map_err(|e| e.to_string())?;
+
+    let before: Vec<i32> = student_tags::Entity::find()
+        .filter(student_tags::Column::StudentId.eq(student_id))
+        .order_by_asc(student_tags::Column::Id)
+        .all(&txn)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|link| link.tag_id)
+        .collect();
+
+    student_tags::Entity::delete_many()
+        .filter(student_tags::Column::StudentId.eq(student_id))
+        .exec(&txn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !tag_ids.is_empty() {
+        let now = chrono::Utc::now()
+            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+            .to_string();
+
+        for tag_id in tag_ids.iter().copied() {
+            student_tags::ActiveModel {
+                id: sea_orm::ActiveValue::NotSet,
+                student_id: Set(student_id),
+                tag_id: Set(tag_id),
+                created_at: Set(now.clone()),
+            }
+            .insert(&txn)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let added: Vec<i32> = tag_ids
+        .iter()
+        .copied()
+        .filter(|tag_id| !before.contains(tag_id))
+        .collect();
+
+    record_operation(
+        &txn,
+        &JournalOp::StudentTagsUpdated {
+            student_id,
+            before,
+            after: tag_ids,
+        },
+        &format!("修改学生 #{} 的标签", student_id),
+    )
+    .await?;
+
+    txn.commit().await.map_err(|e| e.to_string())?;
+    Ok(added)
+}
@@ -760,6 +760,18 @@ impl WorkspaceService {
         })
     }
 
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn catalog(&self) -> &DatabaseConnection {
+        &self.catalog
+    }
+
+    pub fn current_class_id(&self) -> &str {
+        &self.current_class_id
+    }
+
     pub async fn current_db_path(&self) -> Result<String, String> {
         Ok(self
             .current_class_path()
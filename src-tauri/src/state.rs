@@ -6,10 +6,10 @@ use tauri::AppHandle;
 use tokio::sync::Mutex;
 
 use crate::services::{
-    auth::AuthService, auto_score::AutoScoreService, data::DataService, logger::LoggerService,
-    permission::PermissionService, plugin::PluginService, security::SecurityService,
-    settings::SettingsService, theme::ThemeService, workspace::WorkspaceService, SettingsKey,
-    SettingsValue,
+    auth::AuthService, auto_score::AutoScoreService, bus::EventBus, data::DataService,
+    logger::LoggerService, permission::PermissionService, plugin::PluginService,
+    security::SecurityService, settings::SettingsService, theme::ThemeService,
+    workspace::WorkspaceService, SettingsKey, SettingsValue,
 };
 
 pub struct AppState {
@@ -31,6 +31,8 @@ pub struct AppState {
     pub http_client: Client,
     pub app_handle: AppHandle,
     pub workspace: Arc<RwLock<Option<WorkspaceService>>>,
+    /// 数据变更广播，桌面端事件与局域网推送共用
+    pub event_bus: EventBus,
 }
 
 impl AppState {
@@ -70,6 +72,7 @@ impl AppState {
             http_client,
             app_handle,
             workspace,
+            event_bus: EventBus::new(),
         }
     }
 
@@ -102,7 +105,9 @@ impl AppState {
         {
             let mut auto_score = self.auto_score.write();
             auto_score.load_rules(auto_score_rules);
-            auto_score.initialize(&self.app_handle).await?;
+            auto_score
+                .initialize(&self.app_handle, &self.event_bus)
+                .await?;
         }
 
         {
@@ -38,6 +38,7 @@ export interface AutoScoreExecutionBatch {
   runAt: string
   affectedStudents: number
   affectedStudentNames: string[]
+  affectedStudentIds?: number[]
   createdEventIds: number[]
   addedStudentTagIds: number[]
   rewardRedemptionIds?: number[]
@@ -75,6 +75,7 @@ interface AutoScoreManagerProps {
 
 const getRuleFileRelativePath = (ruleId: number) => `auto-score/rule-${ruleId}.json`
 const AUTO_SCORE_BACKFILL_MAX_RUNS_PER_RULE = 500
+const BATCH_FETCH_LIMIT = 100
 
 interface BackfillPlanItem {
   ruleId: number
@@ -191,6 +192,9 @@ function AutoScoreManager({ canEdit }: AutoScoreManagerProps): React.JSX.Element
   const [students, setStudents] = useState<StudentItem[]>([])
   const [rules, setRules] = useState<AutoScoreRule[]>([])
   const [batches, setBatches] = useState<AutoScoreExecutionBatch[]>([])
+  const [batchTotal, setBatchTotal] = useState(0)
+  const [batchNextCursor, setBatchNextCursor] = useState<string | null>(null)
+  const [loadingMoreBatches, setLoadingMoreBatches] = useState(false)
   const [loading, setLoading] = useState(false)
   const [saving, setSaving] = useState(false)
   const [rollingBackBatchId, setRollingBackBatchId] = useState<string | null>(null)
@@ -343,15 +347,38 @@ function AutoScoreManager({ canEdit }: AutoScoreManagerProps): React.JSX.Element
     const api = (window as any).api
     if (!api || !canEdit) return
     try {
-      const res = await api.autoScoreQueryBatches()
-      if (res.success && Array.isArray(res.data)) {
-        setBatches(res.data)
+      const res = await api.autoScoreQueryBatches({ limit: BATCH_FETCH_LIMIT })
+      if (res.success && res.data) {
+        setBatches(res.data.items)
+        setBatchTotal(res.data.total)
+        setBatchNextCursor(res.data.nextCursor)
       }
     } catch {
       void 0
     }
   }, [canEdit])
 
+  const loadMoreBatches = useCallback(async () => {
+    const api = (window as any).api
+    if (!api || !canEdit || !batchNextCursor) return
+    setLoadingMoreBatches(true)
+    try {
+      const res = await api.autoScoreQueryBatches({
+        limit: BATCH_FETCH_LIMIT,
+        cursor: batchNextCursor,
+      })
+      if (res.success && res.data) {
+        setBatches((prev) => [...prev, ...res.data.items])
+        setBatchTotal(res.data.total)
+        setBatchNextCursor(res.data.nextCursor)
+      }
+    } catch {
+      void 0
+    } finally {
+      setLoadingMoreBatches(false)
+    }
+  }, [batchNextCursor, canEdit])
+
   useEffect(() => {
     if (!canEdit) return
     fetchTags().catch(() => void 0)
@@ -1014,12 +1041,23 @@ function AutoScoreManager({ canEdit }: AutoScoreManagerProps): React.JSX.Element
             pageSize={batchPageSize}
             total={batches.length}
             showSizeChanger
-            showTotal={(total) => t("common.total", { count: total })}
+            showTotal={() => t("common.total", { count: batchTotal })}
             onChange={(page, size) => {
               setBatchCurrentPage(page)
               setBatchPageSize(size)
             }}
           />
+          {batchNextCursor && (
+            <Button
+              style={{ marginTop: 12 }}
+              loading={loadingMoreBatches}
+              onClick={() => {
+                loadMoreBatches().catch(() => void 0)
+              }}
+            >
+              {t("autoScore.batchLoadMore")}
+            </Button>
+          )}
         </div>
       </Card>
     </div>
@@ -254,6 +254,12 @@ export const Settings: React.FC<{
   const [pgTestLoading, setPgTestLoading] = useState(false)
   const [pgSwitchLoading, setPgSwitchLoading] = useState(false)
   const [pgUploadLoading, setPgUploadLoading] = useState(false)
+  const [migrationStatus, setMigrationStatus] = useState<{
+    current_version: number
+    latest_version: number
+    pending_migrations: string[]
+    is_newer_than_binary: boolean
+  } | null>(null)
 
   const [oauthLoginVisible, setOAuthLoginVisible] = useState(false)
   const [oauthUserInfo, setOAuthUserInfo] = useState<{
@@ -421,6 +427,13 @@ export const Settings: React.FC<{
       }
     }
 
+    try {
+      const migrationRes = await api.dbMigrationStatus()
+      setMigrationStatus(migrationRes?.success && migrationRes.data ? migrationRes.data : null)
+    } catch {
+      setMigrationStatus(null)
+    }
+
     if (api.checkUrlProtocolStatus) {
       try {
         const urlRes = await api.checkUrlProtocolStatus()
@@ -1922,6 +1935,34 @@ export const Settings: React.FC<{
             </div>
           </Card>
 
+          {migrationStatus && (
+            <Card
+              title={t("settings.data.schemaVersion")}
+              style={{
+                backgroundColor: "var(--ss-card-bg)",
+                color: "var(--ss-text-main)",
+                marginBottom: "16px",
+              }}
+            >
+              <Space wrap>
+                <Tag>
+                  v{migrationStatus.current_version} / v{migrationStatus.latest_version}
+                </Tag>
+                {migrationStatus.is_newer_than_binary ? (
+                  <Tag color="error">{t("settings.data.schemaTooNew")}</Tag>
+                ) : migrationStatus.pending_migrations.length > 0 ? (
+                  <Tag color="warning">
+                    {t("settings.data.schemaPending", {
+                      migrations: migrationStatus.pending_migrations.join(", "),
+                    })}
+                  </Tag>
+                ) : (
+                  <Tag color="success">{t("settings.data.schemaUpToDate")}</Tag>
+                )}
+              </Space>
+            </Card>
+          )}
+
           <Card
             title={t("settings.data.logs")}
             style={{ backgroundColor: "var(--ss-card-bg)", color: "var(--ss-text-main)" }}
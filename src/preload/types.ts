@@ -52,6 +52,7 @@ export interface autoScoreExecutionBatch {
   runAt: string
   affectedStudents: number
   affectedStudentNames: string[]
+  affectedStudentIds?: number[]
   createdEventIds: number[]
   addedStudentTagIds: number[]
   rewardRedemptionIds?: number[]
@@ -61,6 +62,12 @@ export interface autoScoreExecutionBatch {
   rollbackAt?: string | null
 }
 
+export interface autoScoreBatchPage {
+  items: autoScoreExecutionBatch[]
+  total: number
+  nextCursor: string | null
+}
+
 export interface autoScoreBackfillItem {
   ruleId: number
   runs: number
@@ -74,6 +81,50 @@ export interface autoScoreBackfillResult {
   scoreDeltaTotal: number
 }
 
+export interface autoScorePreviewSkip {
+  action: string
+  reason: string
+  detail?: string | null
+}
+
+export interface autoScorePreviewStudent {
+  studentId: number
+  studentName: string
+  scoreBefore: number
+  scoreAfter: number
+  scoreDelta: number
+  addTags: string[]
+  rewards: { rewardId: number; rewardName: string; costPoints: number }[]
+  skippedActions: autoScorePreviewSkip[]
+}
+
+export interface autoScorePreviewExclusion {
+  studentId: number
+  studentName: string
+  reason:
+    | "student_names"
+    | "group"
+    | "grade"
+    | "min_score"
+    | "max_score"
+    | "recent_events"
+    | "trigger"
+    | "max_runs_per_day"
+    | "cooldown"
+    | "no_effect"
+  detail?: string | null
+}
+
+export interface autoScoreRulePreview {
+  ruleId: number
+  nextRunInMs?: number | null
+  hasReactiveTriggers: boolean
+  students: autoScorePreviewStudent[]
+  excluded: autoScorePreviewExclusion[]
+  scoreDeltaTotal: number
+  settle: boolean
+}
+
 export interface autoScoreRule {
   id: number
   name: string
@@ -86,6 +137,168 @@ export interface autoScoreRule {
   lastExecuted?: string | null
 }
 
+export interface journalEntry {
+  id: number
+  op_type:
+    | "score_events_created"
+    | "score_events_deleted"
+    | "reward_redeemed"
+    | "student_tags_updated"
+    | "student_updated"
+  summary: string
+  created_at: string
+  undone_at: string | null
+}
+
+export interface backupEntry {
+  id: string
+  kind: "scheduled" | "pre_operation" | "manual"
+  reason: string
+  class_id: string
+  created_at: string
+  schema_version: number
+  size_bytes: number
+}
+
+export interface rewardLimits {
+  stock?: number | null
+  per_student_limit?: number | null
+  per_period_limit?: number | null
+  active_from?: string | null
+  active_until?: string | null
+  requires_approval?: boolean
+}
+
+export interface rewardSetting extends rewardLimits {
+  id: number
+  name: string
+  cost_points: number
+  created_at: string
+  updated_at: string
+}
+
+export type rewardRedemptionStatus =
+  | "pending"
+  | "approved"
+  | "rejected"
+  | "fulfilled"
+  | "cancelled"
+
+export interface rewardRedemption {
+  id: number
+  uuid: string
+  student_id: number | null
+  student_name: string
+  reward_id: number
+  reward_name: string
+  cost_points: number
+  redeemed_at: string
+  status: rewardRedemptionStatus
+  reviewed_at: string | null
+  review_note: string | null
+  cancelled_at: string | null
+  cancelled_by: string | null
+  cancel_reason: string | null
+}
+
+export interface reasonCategory {
+  id: number
+  name: string
+  sort_order: number
+  color: string | null
+  icon: string | null
+  is_archived: boolean
+  created_at: string
+  updated_at: string
+}
+
+export interface reasonSet {
+  id: number
+  name: string
+  reason_ids: number[]
+  created_at: string
+  updated_at: string
+}
+
+export interface studentProfileParams {
+  student_id?: number
+  student_name?: string
+  bucket?: "day" | "week"
+  start_date?: string
+  end_date?: string
+}
+
+export interface studentProfile {
+  student: {
+    id: number
+    uuid: string
+    name: string
+    group_name: string | null
+    score: number
+    reward_points: number
+    tags: string
+    extra_json: string | null
+    created_at: string
+    updated_at: string
+  }
+  bucket: "day" | "week"
+  series: {
+    bucket: string
+    event_count: number
+    score_delta: number
+    score: number
+    reward_points_delta: number
+    reward_points: number
+  }[]
+  categories: {
+    category: string | null
+    count: number
+    total_delta: number
+    positive_delta: number
+    negative_delta: number
+  }[]
+  streaks: {
+    current: number
+    longest: number
+    longest_start: string | null
+    longest_end: string | null
+  }
+  rank_history: {
+    settlement_id: number
+    start_time: string
+    end_time: string
+    rank: number | null
+    participants: number
+    score: number
+  }[]
+  redemptions: rewardRedemption[]
+  tags: { id: number; name: string; added_at: string }[]
+}
+
+export interface reasonStatRow {
+  reason_id: number | null
+  reason_content: string
+  category: string | null
+  count: number
+  total_delta: number
+  distinct_students: number
+  last_used_at: string | null
+}
+
+export interface reasonStatsResult {
+  start_time: string | null
+  end_time: string | null
+  settlement_id: number | null
+  rows: reasonStatRow[]
+  unused: reasonStatRow[]
+}
+
+export interface reasonFilter {
+  include_archived?: boolean
+  category_id?: number
+  set?: string
+}
+
 export type settingsKey =
   | "is_wizard_completed"
   | "log_level"
@@ -96,13 +309,21 @@ export type settingsKey =
   | "themes_custom"
   | "auto_score_enabled"
   | "auto_score_rules"
-  | "auto_score_batches"
+  | "auto_score_batch_retention_days"
   | "current_theme_id"
   | "dashboards_config"
   | "pg_connection_string"
   | "pg_connection_status"
   | "mobile_bottom_nav_items"
   | "lan_access_enabled"
+  | "auto_backup_enabled"
+  | "permission_idle_timeout_minutes"
+
+export interface permissionChangedEvent {
+  session: string
+  permission: "view" | "points" | "admin"
+  reason: "elevated" | "expired" | "locked"
+}
 
 export interface settingsSpec {
   is_wizard_completed: boolean
@@ -114,7 +335,7 @@ export interface settingsSpec {
   themes_custom: themeConfig[]
   auto_score_enabled: boolean
   auto_score_rules: autoScoreRule[]
-  auto_score_batches: autoScoreExecutionBatch[]
+  auto_score_batch_retention_days: number
   current_theme_id: string
   dashboards_config: any[]
   pg_connection_string: string
@@ -126,6 +347,8 @@ export interface settingsSpec {
   sync_method: "postgresql" | "sectl_cloud_v2"
   mobile_bottom_nav_items: string[]
   lan_access_enabled: boolean
+  auto_backup_enabled: boolean
+  permission_idle_timeout_minutes: number
 }
 
 export interface pluginRuntimeModule {
@@ -146,6 +369,19 @@ export interface httpServerShareUrl {
   is_192_168?: boolean
 }
 
+export type lanDeviceLevel = "view" | "points" | "admin"
+
+export interface lanDevice {
+  id: string
+  name: string
+  level: lanDeviceLevel
+  created_at: string
+  last_seen_at?: string | null
+  last_ip?: string | null
+  expires_at?: string | null
+  is_expired: boolean
+}
+
 export interface AccountRecord {
   id: string
   kind: "local" | "sectl"
@@ -268,6 +504,10 @@ const api = {
   updateStudent: (id: number, data: any): Promise<{ success: boolean }> =>
     invoke("student_update", { id, data }),
   deleteStudent: (id: number): Promise<{ success: boolean }> => invoke("student_delete", { id }),
+  studentProfileQuery: (
+    params: studentProfileParams
+  ): Promise<{ success: boolean; data?: studentProfile; message?: string }> =>
+    invoke("student_profile_query", { params }),
   importStudentsFromXlsx: (params: {
     names: string[]
   }): Promise<{ success: boolean; data: { inserted: number; skipped: number; total: number } }> =>
@@ -366,7 +606,8 @@ const api = {
     ),
 
   // DB - Reason
-  queryReasons: (): Promise<{ success: boolean; data: any[] }> => invoke("reason_query"),
+  queryReasons: (params?: reasonFilter): Promise<{ success: boolean; data: any[] }> =>
+    invoke("reason_query", { params }),
   createReason: (data: any): Promise<{ success: boolean; data?: number; message?: string }> =>
     invoke<{ success: boolean; data?: number; message?: string }>("reason_create", { data }).then(
       requestSnapshotOnSuccess
@@ -375,14 +616,55 @@ const api = {
     invoke<{ success: boolean }>("reason_update", { id, data }).then(requestSnapshotOnSuccess),
   deleteReason: (id: number): Promise<{ success: boolean }> =>
     invoke<{ success: boolean }>("reason_delete", { id }).then(requestSnapshotOnSuccess),
+  reasonCategoryQuery: (params?: {
+    include_archived?: boolean
+  }): Promise<{ success: boolean; data?: reasonCategory[]; message?: string }> =>
+    invoke("reason_category_query", { params }),
+  reasonCategoryCreate: (data: {
+    name: string
+    color?: string | null
+    icon?: string | null
+  }): Promise<{ success: boolean; data?: reasonCategory; message?: string }> =>
+    invoke<{ success: boolean; data?: reasonCategory; message?: string }>(
+      "reason_category_create",
+      { data }
+    ).then(requestSnapshotOnSuccess),
+  reasonCategoryUpdate: (
+    id: number,
+    data: { name?: string; color?: string | null; icon?: string | null; is_archived?: boolean }
+  ): Promise<{ success: boolean; data?: reasonCategory; message?: string }> =>
+    invoke<{ success: boolean; data?: reasonCategory; message?: string }>(
+      "reason_category_update",
+      { id, data }
+    ).then(requestSnapshotOnSuccess),
+  reasonCategoryReorder: (ids: number[]): Promise<{ success: boolean; message?: string }> =>
+    invoke<{ success: boolean; message?: string }>("reason_category_reorder", { ids }).then(
+      requestSnapshotOnSuccess
+    ),
+  reasonSetQuery: (): Promise<{ success: boolean; data?: reasonSet[]; message?: string }> =>
+    invoke("reason_set_query"),
+  reasonSetSave: (data: {
+    id?: number
+    name: string
+    reason_ids: number[]
+  }): Promise<{ success: boolean; data?: reasonSet; message?: string }> =>
+    invoke<{ success: boolean; data?: reasonSet; message?: string }>("reason_set_save", {
+      data,
+    }).then(requestSnapshotOnSuccess),
+  reasonSetDelete: (id: number): Promise<{ success: boolean; message?: string }> =>
+    invoke<{ success: boolean; message?: string }>("reason_set_delete", { id }).then(
+      requestSnapshotOnSuccess
+    ),
 
   // DB - Reward
-  rewardSettingQuery: (): Promise<{ success: boolean; data: any[] }> =>
+  rewardSettingQuery: (): Promise<{ success: boolean; data: rewardSetting[] }> =>
     invoke("reward_setting_query"),
-  rewardSettingCreate: (data: {
-    name: string
-    cost_points: number
-  }): Promise<{ success: boolean; data?: number; message?: string }> =>
+  rewardSettingCreate: (
+    data: {
+      name: string
+      cost_points: number
+    } & rewardLimits
+  ): Promise<{ success: boolean; data?: number; message?: string }> =>
     invoke<{ success: boolean; data?: number; message?: string }>("reward_setting_create", {
       data,
     }).then(requestSnapshotOnSuccess),
@@ -395,17 +677,26 @@ const api = {
       requestSnapshotOnSuccess
     ),
   rewardRedeem: async (data: {
+    student_id?: number
     student_name: string
     reward_id: number
   }): Promise<{
     success: boolean
-    data?: { redemption_id: number; remaining_reward_points: number }
+    data?: {
+      redemption_id: number
+      remaining_reward_points: number
+      status: rewardRedemptionStatus
+    }
     message?: string
   }> => {
     const operationId = syncClient.createOperationId()
     const result = await invoke<{
       success: boolean
-      data?: { redemption_id: number; remaining_reward_points: number }
+      data?: {
+        redemption_id: number
+        remaining_reward_points: number
+        status: rewardRedemptionStatus
+      }
       message?: string
     }>("reward_redeem", { data: { ...data, operation_id: operationId } })
     if (result.success) {
@@ -419,12 +710,65 @@ const api = {
   },
   rewardRedemptionQuery: (params?: {
     limit?: number
-  }): Promise<{ success: boolean; data: any[] }> => invoke("reward_redemption_query", { params }),
+    status?: rewardRedemptionStatus
+  }): Promise<{ success: boolean; data: rewardRedemption[] }> =>
+    invoke("reward_redemption_query", { params }),
+  rewardRedemptionListPending: (): Promise<{
+    success: boolean
+    data?: rewardRedemption[]
+    message?: string
+  }> => invoke("reward_redemption_list_pending"),
+  rewardRedemptionReview: (
+    id: number,
+    data: { action: "approve" | "reject" | "fulfill"; note?: string }
+  ): Promise<{ success: boolean; data?: rewardRedemption; message?: string }> =>
+    invoke<{ success: boolean; data?: rewardRedemption; message?: string }>(
+      "reward_redemption_review",
+      { id, data }
+    ).then(requestSnapshotOnSuccess),
+  rewardRedemptionCancel: async (
+    id: number,
+    data: { reason: string }
+  ): Promise<{ success: boolean; data?: rewardRedemption; message?: string }> => {
+    const result = await invoke<{ success: boolean; data?: rewardRedemption; message?: string }>(
+      "reward_redemption_cancel",
+      { id, data }
+    )
+    if (result.success && result.data) {
+      void syncClient.enqueueRewardCancellation({
+        student_name: result.data.student_name,
+        redemption_uuid: result.data.uuid,
+        reason: data.reason,
+        cancelled_by: result.data.cancelled_by,
+      })
+    }
+    return result
+  },
 
   // DB - Event
   queryEvents: (params?: { limit?: number }): Promise<{ success: boolean; data: any[] }> =>
     invoke("event_query", { params }),
+  queryEventsPage: (params?: {
+    limit?: number
+    cursor?: string | null
+    student_ids?: number[]
+    student_names?: string[]
+    reason_content?: string
+    reason_category?: string
+    reason_category_id?: number
+    delta_sign?: "positive" | "negative" | "zero"
+    start_time?: string
+    end_time?: string
+    settlement_scope?: "unsettled" | "settled" | "any"
+    settlement_id?: number
+    auto_score?: boolean
+  }): Promise<{
+    success: boolean
+    data?: { items: any[]; total: number; next_cursor: string | null }
+    message?: string
+  }> => invoke("event_query_page", { params }),
   createEvent: async (data: {
+    student_id?: number
     student_name?: string
     reason_content?: string
     delta: number
@@ -432,6 +776,7 @@ const api = {
     reasonContent?: string
   }): Promise<{ success: boolean; data?: number; message?: string }> => {
     const normalized = {
+      student_id: data.student_id,
       student_name: String(data.student_name ?? data.studentName ?? "").trim(),
       reason_content: String(data.reason_content ?? data.reasonContent ?? "").trim(),
       delta: Number(data.delta),
@@ -451,6 +796,35 @@ const api = {
     }
     return result
   },
+  createEventBatch: async (data: {
+    student_ids?: number[]
+    student_names?: string[]
+    group_name?: string
+    tag_id?: number
+    reason_content: string
+    delta: number
+  }): Promise<{
+    success: boolean
+    data?: { uuids: string[]; events: any[] }
+    message?: string
+  }> => {
+    const result = await invoke<{
+      success: boolean
+      data?: { uuids: string[]; events: any[] }
+      message?: string
+    }>("event_create_batch", { data })
+    if (result.success && result.data) {
+      for (const event of result.data.events) {
+        void syncClient.enqueueScoreAdjustment({
+          student_name: event.student_name,
+          reason_content: event.reason_content,
+          delta: event.delta,
+          operation_id: event.uuid,
+        })
+      }
+    }
+    return result
+  },
   syncApplyRemoteOperation: (operation: {
     operation_id: string
     operation_type: string
@@ -462,6 +836,7 @@ const api = {
     invoke("sync_apply_snapshot", { snapshot }),
   deleteEvent: (uuid: string): Promise<{ success: boolean }> => invoke("event_delete", { uuid }),
   queryEventsByStudent: (params: {
+    student_id?: number
     student_name?: string
     limit?: number
     start_time?: string
@@ -470,6 +845,7 @@ const api = {
   }): Promise<{ success: boolean; data: any[] }> =>
     invoke("event_query_by_student", {
       params: {
+        student_id: params.student_id,
         student_name: String(params.student_name ?? params.studentName ?? "").trim(),
         limit: params.limit,
         start_time: params.start_time ?? params.startTime,
@@ -477,8 +853,24 @@ const api = {
     }),
   queryLeaderboard: (params: {
     range: "today" | "week" | "month"
-  }): Promise<{ success: boolean; data: { startTime: string; rows: any[] } }> =>
+    start?: string
+    end?: string
+    group_by?: "group" | "tag"
+  }): Promise<{
+    success: boolean
+    data: { startTime: string; endTime?: string | null; rows: any[]; groups?: any[] | null }
+  }> =>
     invoke("leaderboard_query", { params }),
+  queryReasonStats: (params?: {
+    range?: "today" | "week" | "month"
+    start?: string
+    end?: string
+    settlement_id?: number
+    group_name?: string
+    tag_id?: number
+    limit?: number
+  }): Promise<{ success: boolean; data?: reasonStatsResult; message?: string }> =>
+    invoke("reason_stats_query", { params }),
   boardQuerySql: (params: {
     sql: string
     limit?: number
@@ -491,6 +883,29 @@ const api = {
       requestSnapshotOnSuccess
     ),
 
+  // Journal
+  journalQuery: (params?: { limit?: number }): Promise<{
+    success: boolean
+    data?: journalEntry[]
+    message?: string
+  }> => invoke("journal_query", { params }),
+  journalUndo: (params?: { count?: number }): Promise<{
+    success: boolean
+    data?: journalEntry[]
+    message?: string
+  }> =>
+    invoke<{ success: boolean; data?: journalEntry[]; message?: string }>("journal_undo", {
+      params,
+    }).then(requestSnapshotOnSuccess),
+  journalRedo: (params?: { count?: number }): Promise<{
+    success: boolean
+    data?: journalEntry[]
+    message?: string
+  }> =>
+    invoke<{ success: boolean; data?: journalEntry[]; message?: string }>("journal_redo", {
+      params,
+    }).then(requestSnapshotOnSuccess),
+
   // Settlement
   querySettlements: (): Promise<{ success: boolean; data: any[] }> => invoke("db_settlement_query"),
   createSettlement: (): Promise<{ success: boolean; data: any }> => invoke("db_settlement_create"),
@@ -555,11 +970,17 @@ const api = {
     invoke<{ success: boolean; data?: boolean; message?: string }>("auto_score_sort_rules", {
       ruleIds,
     }).then(requestSnapshotOnSuccess),
-  autoScoreQueryBatches: (): Promise<{
+  autoScoreQueryBatches: (params?: {
+    ruleId?: number
+    startTime?: string
+    endTime?: string
+    limit?: number
+    cursor?: string
+  }): Promise<{
     success: boolean
-    data?: autoScoreExecutionBatch[]
+    data?: autoScoreBatchPage
     message?: string
-  }> => invoke("auto_score_query_batches"),
+  }> => invoke("auto_score_query_batches", { params }),
   autoScoreRollbackBatch: (params: {
     batchId: string
   }): Promise<{ success: boolean; data?: autoScoreExecutionBatch; message?: string }> =>
@@ -574,6 +995,19 @@ const api = {
       "auto_score_apply_backfill",
       { params }
     ).then(requestSnapshotOnSuccess),
+  autoScorePreviewRule: (params: {
+    ruleId?: number
+    rule?: {
+      name: string
+      enabled: boolean
+      studentNames: string[]
+      triggers: autoScoreTrigger[]
+      triggerTree?: any | null
+      actions: autoScoreAction[]
+      execution?: autoScoreExecutionConfig
+    }
+  }): Promise<{ success: boolean; data?: autoScoreRulePreview; message?: string }> =>
+    invoke("auto_score_preview_rule", { params }),
 
   // Settings & Sync
   getAllSettings: (): Promise<{ success: boolean; data: settingsSpec }> =>
@@ -605,6 +1039,8 @@ const api = {
       hasAdminPassword: boolean
       hasPointsPassword: boolean
       hasRecoveryString: boolean
+      failedAttempts: number
+      lockoutRemainingSeconds?: number
     }
   }> => invoke("auth_get_status"),
   authLogin: (
@@ -613,6 +1049,15 @@ const api = {
     invoke("auth_login", { password }),
   authLogout: (): Promise<{ success: boolean; data: { permission: string } }> =>
     invoke("auth_logout"),
+  authLock: (): Promise<{ success: boolean; data: { permission: string } }> =>
+    invoke("auth_lock"),
+  onPermissionChanged: (
+    callback: (payload: permissionChangedEvent) => void
+  ): Promise<UnlistenFn> => {
+    return listen<permissionChangedEvent>("ss:permission-changed", (event) => {
+      callback(event.payload)
+    })
+  },
   authSetPasswords: (payload: {
     adminPassword?: string | null
     pointsPassword?: string | null
@@ -822,8 +1267,31 @@ const api = {
 
   // Data import/export
   exportDataJson: (): Promise<{ success: boolean; data: string }> => invoke("data_export_json"),
-  importDataJson: (jsonText: string): Promise<{ success: boolean }> =>
-    invoke("data_import_json", { jsonText }),
+  importDataJson: (
+    jsonText: string,
+    mode?: "merge" | "replace"
+  ): Promise<{
+    success: boolean
+    data?: {
+      mode: "merge" | "replace"
+      tables: { table: string; inserted: number; skipped: number }[]
+    }
+    message?: string
+  }> => invoke("data_import_json", { jsonText, mode }),
+
+  // Backups
+  backupList: (): Promise<{ success: boolean; data?: backupEntry[]; message?: string }> =>
+    invoke("backup_list"),
+  backupCreate: (
+    reason?: string
+  ): Promise<{ success: boolean; data?: backupEntry; message?: string }> =>
+    invoke("backup_create", { reason }),
+  backupRestore: (id: string): Promise<{ success: boolean; data?: backupEntry; message?: string }> =>
+    invoke<{ success: boolean; data?: backupEntry; message?: string }>("backup_restore", {
+      id,
+    }).then(requestSnapshotOnSuccess),
+  backupDelete: (id: string): Promise<{ success: boolean; message?: string }> =>
+    invoke("backup_delete", { id }),
 
   // Window
   windowMinimize: (): Promise<void> => invoke("window_minimize"),
@@ -891,6 +1359,19 @@ const api = {
     success: boolean
     data: { type: string; connected: boolean; error?: string }
   }> => invoke("db_get_status"),
+  dbMigrationStatus: (): Promise<{
+    success: boolean
+    data?: {
+      is_complete: boolean
+      existing_tables: string[]
+      missing_tables: string[]
+      current_version: number
+      latest_version: number
+      pending_migrations: string[]
+      is_newer_than_binary: boolean
+    }
+    message?: string
+  }> => invoke("db_migration_status"),
   dbSync: (): Promise<{ success: boolean; data: { success: boolean; message?: string } }> =>
     invoke("db_sync"),
   dbSyncPreview: (): Promise<{
@@ -960,6 +1441,15 @@ const api = {
       token?: string
     }
   }> => invoke("http_server_status"),
+  httpServerDeviceList: (): Promise<{ success: boolean; data?: lanDevice[]; message?: string }> =>
+    invoke("http_server_device_list"),
+  httpServerDeviceUpdate: (
+    id: string,
+    data: { name?: string; level?: lanDeviceLevel; expires_at?: string }
+  ): Promise<{ success: boolean; data?: lanDevice; message?: string }> =>
+    invoke("http_server_device_update", { id, data }),
+  httpServerDeviceRevoke: (id: string): Promise<{ success: boolean; message?: string }> =>
+    invoke("http_server_device_revoke", { id }),
 
   // MCP Server
   mcpServerStart: (config?: {
@@ -57,9 +57,38 @@ const lanApiBase = {
 
   queryStudents: async () =>
     request<{ success: boolean; data: any[]; message?: string }>("/api/students"),
-  queryReasons: async () =>
-    request<{ success: boolean; data: any[]; message?: string }>("/api/reasons"),
+  studentProfileQuery: async (params: {
+    student_id: number
+    bucket?: "day" | "week"
+    start_date?: string
+    end_date?: string
+  }) => {
+    const query = new URLSearchParams()
+    if (params.bucket) query.set("bucket", params.bucket)
+    if (params.start_date) query.set("start_date", params.start_date)
+    if (params.end_date) query.set("end_date", params.end_date)
+    const suffix = query.toString()
+    return request<{ success: boolean; data?: any; message?: string }>(
+      `/api/students/${params.student_id}/profile${suffix ? `?${suffix}` : ""}`
+    )
+  },
+  queryReasons: async (params?: { set?: string; category_id?: number }) => {
+    const query = new URLSearchParams()
+    if (params?.set) query.set("set", params.set)
+    if (params?.category_id != null) query.set("category_id", String(params.category_id))
+    const suffix = query.toString()
+    return request<{ success: boolean; data: any[]; message?: string }>(
+      suffix ? `/api/reasons?${suffix}` : "/api/reasons"
+    )
+  },
   updateStudent: async () => ({ success: false, message: "LAN 模式不支持修改学生信息" }),
+  tagsGetAll: async () =>
+    request<{ success: boolean; data: any[]; message?: string }>("/api/tags"),
+  tagsUpdateStudentTags: async (studentId: number, tagIds: number[]) =>
+    request<{ success: boolean; message?: string }>(`/api/students/${studentId}/tags`, {
+      method: "PUT",
+      body: JSON.stringify({ tag_ids: tagIds }),
+    }),
   rewardSettingQuery: async () =>
     request<{ success: boolean; data: any[]; message?: string }>("/api/rewards"),
   queryEvents: async (params?: { limit?: number }) => {
@@ -77,7 +106,48 @@ const lanApiBase = {
     request<{ success: boolean; message?: string }>(`/api/events/${encodeURIComponent(uuid)}`, {
       method: "DELETE",
     }),
-  rewardRedeem: async () => ({ success: false, message: "LAN 模式仅支持加分和扣分" }),
+  createEventBatch: async (data: any) =>
+    request<{ success: boolean; data?: { uuids: string[]; events: any[] }; message?: string }>(
+      "/api/events/batch",
+      {
+        method: "POST",
+        body: JSON.stringify(data),
+      }
+    ),
+  rewardRedeem: async (data: { student_id?: number; student_name: string; reward_id: number }) =>
+    request<{ success: boolean; data?: any; message?: string }>("/api/rewards/redeem", {
+      method: "POST",
+      body: JSON.stringify(data),
+    }),
+  rewardRedemptionQuery: async (params?: { limit?: number; status?: string }) => {
+    const query = new URLSearchParams()
+    if (params?.limit != null) query.set("limit", String(params.limit))
+    if (params?.status) query.set("status", params.status)
+    const suffix = query.toString()
+    return request<{ success: boolean; data: any[]; message?: string }>(
+      suffix ? `/api/rewards/redemptions?${suffix}` : "/api/rewards/redemptions"
+    )
+  },
+  queryLeaderboard: async (params: {
+    range: string
+    start?: string
+    end?: string
+    group_by?: "group" | "tag"
+  }) => {
+    const query = new URLSearchParams({ range: params.range })
+    if (params.start) query.set("start", params.start)
+    if (params.end) query.set("end", params.end)
+    if (params.group_by) query.set("group_by", params.group_by)
+    return request<{ success: boolean; data?: any; message?: string }>(
+      `/api/leaderboard?${query.toString()}`
+    )
+  },
+  querySettlements: async () =>
+    request<{ success: boolean; data: any[]; message?: string }>("/api/settlements"),
+  querySettlementLeaderboard: async (params: { settlementId: number }) =>
+    request<{ success: boolean; data?: any; message?: string }>(
+      `/api/settlements/${params.settlementId}/leaderboard`
+    ),
 
   getAllSettings: async () => ({
     success: true,
@@ -125,17 +195,41 @@ const lanApiBase = {
     },
   }),
 
-  authGetStatus: async () => ({
-    success: true,
-    data: {
-      permission: "points",
-      hasAdminPassword: false,
-      hasPointsPassword: false,
-      hasRecoveryString: false,
-    },
-  }),
-  authLogin: async () => ({ success: true, data: { permission: "points" } }),
-  authLogout: async () => ({ success: true, data: { permission: "points" } }),
+  authGetStatus: async () => {
+    const res = await request<{ success: boolean; data?: any; message?: string }>(
+      "/api/auth/status"
+    )
+    if (!res.success || !res.data) return res
+    return {
+      success: true,
+      data: {
+        permission: res.data.permission,
+        hasAdminPassword: Boolean(res.data.has_admin_password),
+        hasPointsPassword: Boolean(res.data.has_points_password),
+        hasRecoveryString: Boolean(res.data.has_recovery_string),
+        failedAttempts: Number(res.data.failed_attempts || 0),
+        lockoutRemainingSeconds: res.data.lockout_remaining_seconds,
+      },
+    }
+  },
+  authLogin: async (password: string) =>
+    request<{ success: boolean; data?: { permission: string }; message?: string }>(
+      "/api/auth/login",
+      {
+        method: "POST",
+        body: JSON.stringify({ password }),
+      }
+    ),
+  authLogout: async () =>
+    request<{ success: boolean; data?: { permission: string }; message?: string }>(
+      "/api/auth/lock",
+      { method: "POST" }
+    ),
+  authLock: async () =>
+    request<{ success: boolean; data?: { permission: string }; message?: string }>(
+      "/api/auth/lock",
+      { method: "POST" }
+    ),
   authSetPasswords: async () => ({ success: false, message: "LAN 模式不支持权限设置" }),
   authGenerateRecovery: async () => ({ success: false, message: "LAN 模式不支持权限设置" }),
   authResetByRecovery: async () => ({ success: false, message: "LAN 模式不支持权限设置" }),
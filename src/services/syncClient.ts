@@ -88,7 +88,7 @@ interface PendingOperation {
   lamport: number
   entity_type: "student"
   entity_id: string
-  operation_type: "score.adjust" | "reward.redeem"
+  operation_type: "score.adjust" | "reward.redeem" | "reward.cancel"
   payload: Record<string, unknown>
   client_created_at: string
 }
@@ -380,11 +380,38 @@ class SyncClient {
     }
   }
 
+  async enqueueRewardCancellation(input: {
+    student_name: string
+    redemption_uuid: string
+    reason: string
+    cancelled_by: string | null
+  }): Promise<void> {
+    const operation = this.createOperation("reward.cancel", input.student_name, {
+      student_name: input.student_name,
+      redemption_uuid: input.redemption_uuid,
+      reason: input.reason,
+      cancelled_by: input.cancelled_by,
+    })
+    try {
+      this.appendOperation(operation)
+      void this.syncNow()
+    } catch (error) {
+      syncLog("error", "兑换撤销加入待同步队列失败", {
+        operation_type: operation.operation_type,
+        student_name: input.student_name,
+        redemption_uuid: input.redemption_uuid,
+        error: String(error),
+      })
+    }
+  }
+
   private async buildSnapshot(): Promise<Record<string, unknown>> {
     const api = (window as any).api
     const [
       students,
       reasons,
+      reasonCategories,
+      reasonSets,
       rewards,
       tags,
       events,
@@ -393,10 +420,11 @@ class SyncClient {
       boards,
       settings,
       rules,
-      batches,
     ] = await Promise.all([
       api.queryStudents(),
-      api.queryReasons(),
+      api.queryReasons({ include_archived: true }),
+      api.reasonCategoryQuery({ include_archived: true }),
+      api.reasonSetQuery(),
       api.rewardSettingQuery(),
       api.tagsGetAll(),
       api.queryEvents({ limit: 100000 }),
@@ -405,7 +433,6 @@ class SyncClient {
       api.boardGetConfigs(),
       api.getAllSettings(),
       api.autoScoreGetRules(),
-      api.autoScoreQueryBatches(),
     ])
 
     const studentRows = Array.isArray(students?.data) ? students.data : []
@@ -422,10 +449,28 @@ class SyncClient {
       )
     ).flat()
 
+    // 快捷理由组跨设备按理由内容对应，本地 id 不可用
+    const reasonRows = Array.isArray(reasons?.data) ? reasons.data : []
+    const reasonContentById = new Map<number, string>(
+      reasonRows.map((reason: any) => [Number(reason.id), String(reason.content)])
+    )
+    const reasonSetRows = (Array.isArray(reasonSets?.data) ? reasonSets.data : []).map(
+      (set: any) => ({
+        name: set.name,
+        reasons: (Array.isArray(set.reason_ids) ? set.reason_ids : [])
+          .map((id: number) => reasonContentById.get(Number(id)))
+          .filter((content: string | undefined) => content !== undefined),
+        created_at: set.created_at,
+        updated_at: set.updated_at,
+      })
+    )
+
     return {
       version: 1,
       students: studentRows,
-      reasons: Array.isArray(reasons?.data) ? reasons.data : [],
+      reason_categories: Array.isArray(reasonCategories?.data) ? reasonCategories.data : [],
+      reasons: reasonRows,
+      reason_sets: reasonSetRows,
       reward_settings: Array.isArray(rewards?.data) ? rewards.data : [],
       tags: Array.isArray(tags?.data) ? tags.data : [],
       student_tags: studentTags,
@@ -436,7 +481,6 @@ class SyncClient {
       settings: {
         ...(settings?.data || {}),
         auto_score_rules: rules?.data || [],
-        auto_score_batches: batches?.data || [],
       },
     }
   }
@@ -453,7 +497,9 @@ class SyncClient {
     const counts = Object.fromEntries(
       [
         "students",
+        "reason_categories",
         "reasons",
+        "reason_sets",
         "reward_settings",
         "tags",
         "student_tags",